@@ -46,6 +46,9 @@ const rawMode = !emptyMode && !templateMode && (slug.includes('/') || slug.inclu
 let templateContext = null;
 let isDirty = false;
 let autoSaveTimer = null;
+// ETag of the file as last loaded or saved, sent as If-Match on save
+let currentEtag = null;
+let conflictOpen = false;
 
 const themeCompartment = new Compartment();
 const fontSizeCompartment = new Compartment();
@@ -197,7 +200,7 @@ function connectLiveReload() {
   ws.addEventListener('message', (event) => {
     try {
       const payload = JSON.parse(event.data);
-      if (payload.event === 'reload') {
+      if (payload.event === 'reload' || payload.event === 'css') {
         const iframe = $('#me-preview-frame');
         if (iframe && iframe.src) {
           iframe.src = iframe.src.split('?')[0] + '?t=' + Date.now();
@@ -655,12 +658,104 @@ function scheduleAutoSave() {
   if (!templateMode) {
     if (autoSaveTimer) clearTimeout(autoSaveTimer);
     autoSaveTimer = setTimeout(() => {
-      if (!editorView) return;
+      if (!editorView || hasConflictMarkers(editorView.state.doc.toString())) return;
       saveContent();
     }, 1500);
   }
 }
 
+// --- Save conflicts ---
+// Saves send the ETag of the loaded file. When someone else saved it in the
+// meantime the server answers 409 with its version and the user decides.
+const CONFLICT_START = '<<<<<<< your version';
+const CONFLICT_SEPARATOR = '=======';
+const CONFLICT_END = '>>>>>>> saved version';
+
+function hasConflictMarkers(content) {
+  return content.split('\n').includes(CONFLICT_START);
+}
+
+function setEditorContent(content) {
+  editorView.dispatch({
+    changes: { from: 0, to: editorView.state.doc.length, insert: content },
+  });
+}
+
+// Wraps the lines that differ in conflict markers, keeping the common start and end
+function mergeWithMarkers(mine, theirs) {
+  const a = mine.split('\n');
+  const b = theirs.split('\n');
+  let start = 0;
+  while (start < a.length && start < b.length && a[start] === b[start]) start++;
+  let end = 0;
+  while (end < a.length - start && end < b.length - start
+    && a[a.length - 1 - end] === b[b.length - 1 - end]) end++;
+  return [
+    ...a.slice(0, start),
+    CONFLICT_START,
+    ...a.slice(start, a.length - end),
+    CONFLICT_SEPARATOR,
+    ...b.slice(start, b.length - end),
+    CONFLICT_END,
+    ...a.slice(a.length - end),
+  ].join('\n');
+}
+
+function mergeDialog(mine, theirs) {
+  return new Promise((resolve) => {
+    const overlay = document.createElement('div');
+    overlay.className = 'me-confirm-overlay';
+    overlay.innerHTML = `
+      <div class="me-confirm-box me-merge-box">
+        <h4>This file was changed by someone else</h4>
+        <p>It was saved after you opened it. Choose how to continue.</p>
+        <div class="me-merge-panes">
+          <div><h5>Your version</h5><pre id="me-merge-mine"></pre></div>
+          <div><h5>Saved version</h5><pre id="me-merge-theirs"></pre></div>
+        </div>
+        <div class="me-confirm-actions">
+          <button class="me-btn" data-choice="cancel">Cancel</button>
+          <button class="me-btn" data-choice="theirs">Use saved version</button>
+          <button class="me-btn" data-choice="merge">Merge manually</button>
+          <button class="me-btn me-btn-primary" data-choice="mine">Overwrite with mine</button>
+        </div>
+      </div>`;
+    overlay.querySelector('#me-merge-mine').textContent = mine;
+    overlay.querySelector('#me-merge-theirs').textContent = theirs ?? '(the file was deleted)';
+    if (theirs == null) {
+      overlay.querySelector('[data-choice="theirs"]').remove();
+      overlay.querySelector('[data-choice="merge"]').remove();
+    }
+    overlay.querySelectorAll('[data-choice]').forEach(btn => {
+      btn.onclick = () => { overlay.remove(); resolve(btn.dataset.choice); };
+    });
+    document.body.appendChild(overlay);
+  });
+}
+
+async function resolveConflict(mine, current) {
+  if (conflictOpen) return;
+  conflictOpen = true;
+  const choice = await mergeDialog(mine, current.content);
+  conflictOpen = false;
+  if (choice === 'mine') {
+    currentEtag = current.etag || null;
+    await saveContent();
+  } else if (choice === 'theirs') {
+    setEditorContent(current.content);
+    if (autoSaveTimer) clearTimeout(autoSaveTimer);
+    currentEtag = current.etag;
+    originalBody = current.content;
+    isDirty = false;
+    updateDirtyIndicator();
+    toast('Loaded the saved version');
+  } else if (choice === 'merge') {
+    setEditorContent(mergeWithMarkers(mine, current.content));
+    currentEtag = current.etag;
+    toast('Resolve the conflict markers, then save');
+  }
+}
+
 // --- Frontmatter parsing from editor content ---
 function parseFrontmatterFromContent(content) {
   const fm = {};
@@ -728,14 +823,27 @@ async function saveContent() {
     }
   }
 
+  if (conflictOpen) return;
+  if (hasConflictMarkers(content)) {
+    toast('Resolve the conflict markers before saving', true);
+    return;
+  }
+
   try {
-    await fetch(`${API}/file/${savePath}`, {
+    const headers = { 'Content-Type': 'application/json' };
+    if (currentEtag) headers['If-Match'] = currentEtag;
+    const r = await fetch(`${API}/file/${savePath}`, {
       method: 'PUT',
-      headers: { 'Content-Type': 'application/json' },
+      headers,
       body: JSON.stringify({ content }),
-    }).then(async r => {
-      if (!r.ok) { const d = await r.json(); throw new Error(d.error || r.statusText); }
     });
+    const d = await r.json();
+    if (r.status === 409) {
+      await resolveConflict(content, d);
+      return;
+    }
+    if (!r.ok) throw new Error(d.error || r.statusText);
+    currentEtag = d.etag || null;
 
     if (!rawMode && !templateMode) {
       frontmatter = parseFrontmatterFromContent(content);
@@ -1344,6 +1452,9 @@ function showMediaDialog() {
       <div class="me-media-header">
         <h4>Insert Media</h4>
         <div style="display:flex;gap:6px">
+          <label class="me-media-slug-folder" title="Store uploaded images in media/${slug}/">
+            <input type="checkbox" id="me-media-slug-folder"> In post folder
+          </label>
           <button class="me-btn me-btn-sm" id="me-media-upload">Upload</button>
           <input type="file" id="me-media-upload-input" hidden accept="image/*,video/*,audio/*,.pdf,.svg" multiple>
           <button class="me-btn me-btn-sm" id="me-media-close">Close</button>
@@ -1394,6 +1505,9 @@ function showMediaDialog() {
     if (e.target === overlay) overlay.remove();
   });
 
+  const slugFolder = overlay.querySelector('#me-media-slug-folder');
+  if (rawMode || templateMode || emptyMode) slugFolder.parentElement.remove();
+
   const mediaUploadInput = overlay.querySelector('#me-media-upload-input');
   overlay.querySelector('#me-media-upload').addEventListener('click', () => mediaUploadInput.click());
   mediaUploadInput.addEventListener('change', async () => {
@@ -1403,7 +1517,25 @@ function showMediaDialog() {
       ? `${siteData.config.content_path}/${siteData?.config?.media_path || 'media'}`
       : `content/${siteData?.config?.media_path || 'media'}`;
     let uploaded = 0;
+    const snippets = [];
     for (const file of files) {
+      // Images go through the media endpoint, which resizes them and
+      // answers with the markdown to insert
+      if (file.type.startsWith('image/')) {
+        const form = new FormData();
+        form.append('file', file);
+        if (slugFolder.isConnected && slugFolder.checked) form.append('slug', slug);
+        try {
+          const r = await fetch(`${API}/media`, { method: 'POST', body: form });
+          const d = await r.json();
+          if (!r.ok) throw new Error(d.error || r.statusText);
+          snippets.push(...d.files.map(f => f.markdown));
+          uploaded++;
+        } catch (e) {
+          toast(`Failed to upload ${file.name}: ${e.message}`, true);
+        }
+        continue;
+      }
       const fullPath = `${mediaPath}/${file.name}`;
       try {
         const data = await file.arrayBuffer();
@@ -1418,6 +1550,12 @@ function showMediaDialog() {
         toast(`Failed to upload ${file.name}: ${e.message}`, true);
       }
     }
+    if (snippets.length > 0 && editorView) {
+      insertAtCursor(snippets.join('\n'));
+      overlay.remove();
+      toast(`${snippets.length} image${snippets.length > 1 ? 's' : ''} uploaded and inserted`);
+      return;
+    }
     if (uploaded > 0) {
       toast(`${uploaded} file${uploaded > 1 ? 's' : ''} uploaded to ${mediaPath}/. Waiting for rebuild...`);
       // Wait for rebuild to copy media to output, then refresh grid
@@ -1843,6 +1981,7 @@ function showConfigDialog() {
 
         <div class="me-config-pane" data-cpanel="feeds">
           <div class="me-field me-check"><label><input type="checkbox" data-key="json_feed" data-bool${chk(cfg.json_feed)}> JSON Feed</label></div>
+          <div class="me-field me-check"><label><input type="checkbox" data-key="atom_feed" data-bool${chk(cfg.atom_feed)}> Atom Feed</label></div>
           <div class="me-field me-check"><label><input type="checkbox" data-key="build_sitemap" data-bool${chk(cfg.build_sitemap, true)}> Sitemap</label></div>
           <div class="me-field me-check"><label><input type="checkbox" data-key="publish_urls_json" data-bool${chk(cfg.publish_urls_json, true)}> URLs JSON</label></div>
           <div class="me-field me-check"><label><input type="checkbox" data-key="publish_md" data-bool${chk(cfg.publish_md)}> Publish Markdown Source</label></div>
@@ -2078,6 +2217,7 @@ async function init() {
       body = data.content || '';
       sourcePath = slug;
       originalBody = body;
+      currentEtag = data.etag || null;
     } catch (e) {
       toast('Failed to load file: ' + e.message, true);
       return;
@@ -2104,6 +2244,7 @@ async function init() {
       if (data.error) throw new Error(data.error);
       body = data.content || '';
       originalBody = body;
+      currentEtag = data.etag || null;
     } catch (e) {
       toast('Failed to load file: ' + e.message, true);
       return;
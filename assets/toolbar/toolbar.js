@@ -78,11 +78,20 @@
   // resolves, call redirectAfterRebuild(slug) or reloadAfterRebuild() to set the
   // final destination. If live-reload already fired while held, navigates immediately.
   // If not, waits for the next live-reload (or falls back to polling after 8s).
+  // Live-reload only reloads the pages a rebuild changed, so while held every
+  // rebuild event counts, even the ones that do not touch the current page.
   const _origReload = window.location.reload.bind(window.location);
   let _held = false;
   let _reloadFiredWhileHeld = false;
   let _pendingPoll = null;
 
+  // Build errors are left to the live-reload overlay, the next rebuild navigates.
+  window.addEventListener('marmite:livereload', (event) => {
+    if (!_held || event.detail.event === 'build-error') return;
+    event.preventDefault();
+    window.location.reload();
+  });
+
   function holdReload() {
     _held = true;
     _reloadFiredWhileHeld = false;
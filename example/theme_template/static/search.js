@@ -1,83 +1,109 @@
-import Fuse from "./vendor/fuse.basic.min.mjs";
-
-function buildSnippet(text, start, len) {
-    const end = start + len;
-    const snippetStart = Math.max(0, start - 40);
-    const snippetEnd = Math.min(text.length, end + 40);
-    const prefix = snippetStart > 0 ? '...' : '';
-    const suffix = snippetEnd < text.length ? '...' : '';
-    const before = escapeHtml(text.substring(snippetStart, start));
-    const matched = escapeHtml(text.substring(start, end));
-    const after = escapeHtml(text.substring(end, snippetEnd));
-    return `${prefix}${before}<mark>${matched}</mark>${after}${suffix}`;
+// Client for the search index generated by marmite at build time.
+// The index is split per language in `static/search/{language}.json`,
+// each shard maps stemmed terms to `[document, score, ...]` postings.
+
+function normalize(word) {
+    return word.toLowerCase().normalize("NFD").replace(/\p{M}/gu, "");
 }
 
-function getMatchSnippets(result, searchPattern, maxCount) {
-    if (!result.matches?.length) return [];
-    const match = result.matches.find(m => m.key === "html")
-                  || result.matches.find(m => m.key === "description");
-    if (!match) return [];
-    const text = match.value;
-    const textLower = text.toLowerCase();
-
-    const candidates = [searchPattern, ...searchPattern.split(/\s+/)].filter(t => t.length > 2);
-    candidates.sort((a, b) => b.length - a.length);
-
-    const snippets = [];
-    const usedRanges = [];
-
-    for (const term of candidates) {
-        const termLower = term.toLowerCase();
-        let searchFrom = 0;
-        while (snippets.length < maxCount) {
-            const idx = textLower.indexOf(termLower, searchFrom);
-            if (idx === -1) break;
-            searchFrom = idx + termLower.length;
-            const overlaps = usedRanges.some(([s, e]) =>
-                idx < e + 40 && idx + termLower.length > s - 40
-            );
-            if (overlaps) continue;
-            usedRanges.push([idx, idx + termLower.length]);
-            snippets.push(buildSnippet(text, idx, termLower.length));
-        }
-        if (snippets.length >= maxCount) break;
+function tokenize(text) {
+    return text
+        .split(/[^\p{L}\p{N}]+/u)
+        .filter(word => word.length > 1)
+        .map(normalize);
+}
+
+function escapeHtml(str) {
+    return str.replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;');
+}
+
+async function loadShard() {
+    const manifest = await (await fetch('./static/search/index.json')).json();
+    const pageLanguage = document.documentElement.lang;
+    const language = manifest.languages[pageLanguage] ? pageLanguage : manifest.default_language;
+    const file = manifest.languages[language] || Object.values(manifest.languages)[0];
+    if (!file) return null;
+    const shard = await (await fetch(`./static/search/${file}`)).json();
+    shard.vocabulary = Object.keys(shard.terms).sort();
+    return shard;
+}
+
+// Terms of the index matching a query word with a weight for each:
+// the word itself, the stem recorded for it, the longest stem that is a
+// prefix of it ("searching" -> "search") and, as the last word may still
+// be typed, the terms starting with it.
+function matchTerms(shard, word) {
+    const alias = shard.aliases[word];
+    if (alias) return [[alias, 1]];
+    if (shard.terms[word]) return [[word, 1]];
+    for (let end = word.length - 1; end >= 3; end--) {
+        const prefix = word.slice(0, end);
+        if (shard.terms[prefix]) return [[prefix, 1]];
     }
+    if (word.length < 3) return [];
+    const matches = [];
+    let low = 0;
+    let high = shard.vocabulary.length;
+    while (low < high) {
+        const mid = (low + high) >> 1;
+        if (shard.vocabulary[mid] < word) low = mid + 1; else high = mid;
+    }
+    for (let i = low; i < shard.vocabulary.length && matches.length < 20; i++) {
+        if (!shard.vocabulary[i].startsWith(word)) break;
+        matches.push([shard.vocabulary[i], 0.5]);
+    }
+    return matches;
+}
 
-    // Fallback to longest Fuse.js index ranges
-    if (snippets.length === 0 && match.indices?.length) {
-        const sorted = [...match.indices].sort((a, b) => (b[1] - b[0]) - (a[1] - a[0]));
-        for (const idx of sorted) {
-            if (snippets.length >= maxCount) break;
-            snippets.push(buildSnippet(text, idx[0], idx[1] - idx[0] + 1));
+function search(shard, query) {
+    const scores = new Map();
+    const matched = new Map();
+    const words = [...new Set(tokenize(query))];
+    const terms = [];
+    for (const word of words) {
+        const wordScores = new Map();
+        for (const [term, weight] of matchTerms(shard, word)) {
+            terms.push(term);
+            const postings = shard.terms[term];
+            for (let i = 0; i < postings.length; i += 2) {
+                const score = postings[i + 1] * weight;
+                wordScores.set(postings[i], Math.max(wordScores.get(postings[i]) || 0, score));
+            }
+        }
+        for (const [doc, score] of wordScores) {
+            scores.set(doc, (scores.get(doc) || 0) + score);
+            matched.set(doc, (matched.get(doc) || 0) + 1);
         }
     }
-
-    return snippets;
+    // documents matching every word of the query rank first
+    const results = [...scores.entries()]
+        .map(([doc, score]) => ({ doc: shard.documents[doc], score: score * matched.get(doc) }))
+        .sort((a, b) => b.score - a.score);
+    return { results, terms };
 }
 
-function escapeHtml(str) {
-    return str.replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;');
+function highlight(shard, text, terms) {
+    return text
+        .split(/([\p{L}\p{N}]+)/u)
+        .map((part, i) => {
+            const escaped = escapeHtml(part);
+            const word = normalize(part);
+            const stem = shard.aliases[word] || word;
+            if (i % 2 === 1 && terms.some(term => stem.startsWith(term))) {
+                return `<mark>${escaped}</mark>`;
+            }
+            return escaped;
+        })
+        .join("");
 }
 
 (async () => {
     const searchInput = document.getElementById("marmite-search-input");
     const showMatches = searchInput?.dataset.showMatches === "true";
-    const matchCount = parseInt(searchInput?.dataset.matchCount, 10) || 3;
-
-    const fuseOptions = {
-        threshold: 0.25,
-        findAllMatches: true,
-        shouldSort: true,
-        minMatchCharLength: 3,
-        ignoreLocation: true,
-        includeMatches: showMatches,
-        keys: ["title", "description", "tags", "html"]
-    };
 
     try {
-        const response = await fetch('./static/search_index.json');
-        const data = await response.json();
-        const fuse = new Fuse(data, fuseOptions);
+        const shard = await loadShard();
+        if (!shard) return;
         searchInput.addEventListener("input", (event) => {
             event.preventDefault();
 
@@ -91,22 +117,20 @@ function escapeHtml(str) {
             // Search for results
             const searchPattern = event.target.value;
             if (searchPattern?.length > 2) {
-                const results = fuse.search(searchPattern);
+                const { results, terms } = search(shard, searchPattern);
                 if(results?.length > 0) {
                     // Build the results list, limiting here to 10 items
                     results.slice(0, 10).forEach((result) => {
                         const elementList = document.createElement("li");
                         const resultElement = document.createElement("a");
-                        resultElement.href = `${result.item.slug}.html`;
-                        resultElement.innerText = result.item.title;
+                        resultElement.href = `${result.doc.slug}.html`;
+                        resultElement.innerText = result.doc.title;
                         elementList.appendChild(resultElement);
-                        if (showMatches) {
-                            for (const snippet of getMatchSnippets(result, searchPattern, matchCount)) {
-                                const snippetEl = document.createElement("p");
-                                snippetEl.className = "search-match-snippet";
-                                snippetEl.innerHTML = snippet;
-                                elementList.appendChild(snippetEl);
-                            }
+                        if (showMatches && result.doc.excerpt) {
+                            const snippetEl = document.createElement("p");
+                            snippetEl.className = "search-match-snippet";
+                            snippetEl.innerHTML = highlight(shard, result.doc.excerpt, terms);
+                            elementList.appendChild(snippetEl);
                         }
                         resultsElement.appendChild(elementList);
                     });
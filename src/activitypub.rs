@@ -0,0 +1,1094 @@
+//! ActivityPub documents written with the site, so Mastodon and the rest of
+//! the fediverse can find and follow its authors.
+//!
+//! With an `activitypub` section the build writes, for each published author:
+//!
+//! - `.well-known/webfinger`: the JRD of the main actor, and
+//!   `{path}/webfinger/{username}.json` for every actor
+//! - `{path}/{username}.json`: the `Person` actor with its public key
+//! - `{path}/{username}/outbox.json`: the latest posts as `Create` activities
+//! - `{path}/{username}/followers.json`: the number of followers
+//! - `{path}/objects/{slug}.json`: the `Note` or `Article` of each post
+//!
+//! The actors share one RSA key, kept in `.marmite-activitypub-key.pem` next
+//! to `marmite.yaml` or read from `MARMITE_ACTIVITYPUB_KEY`. With
+//! `activitypub.inbox` the built-in server answers WebFinger for every actor
+//! and accepts signed `Follow` and `Undo` activities in the inboxes, saving
+//! the followers to `.marmite/activitypub-followers.json`.
+use crate::config::{ActivityPubConfig, ActivityPubObject, Author, Marmite};
+use crate::content::Content;
+use crate::feed::site_url;
+use crate::parser::escape_html;
+use crate::re;
+use crate::search::{html_to_text, truncate_text};
+use crate::site::Data;
+use crate::slugify::slugify;
+use crate::tera_functions::UrlFor;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::{error, info, warn};
+use regex::Regex;
+use ring::signature::{RsaKeyPair, UnparsedPublicKey, RSA_PKCS1_2048_8192_SHA256};
+use rsa::pkcs1::EncodeRsaPublicKey;
+use rsa::pkcs8::{
+    DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::net::IpAddr;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response};
+use ureq::unversioned::resolver::{DefaultResolver, ResolvedSocketAddrs, Resolver};
+use ureq::unversioned::transport::{DefaultConnector, NextTimeout};
+
+const KEY_FILE: &str = ".marmite-activitypub-key.pem";
+const KEY_ENV: &str = "MARMITE_ACTIVITYPUB_KEY";
+/// Inside the `.marmite` folder, which the watcher of `--watch` skips
+const FOLLOWERS_FILE: &str = ".marmite/activitypub-followers.json";
+const ACTIVITY_JSON: &str = "application/activity+json";
+const JRD_JSON: &str = "application/jrd+json";
+const AS_CONTEXT: &str = "https://www.w3.org/ns/activitystreams";
+const SECURITY_CONTEXT: &str = "https://w3id.org/security/v1";
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+/// Largest activity read from an inbox request
+const MAX_ACTIVITY_SIZE: u64 = 1024 * 1024;
+/// Seconds the `Date` of a signed request can be away from now
+const MAX_CLOCK_SKEW: i64 = 12 * 60 * 60;
+/// Seconds to wait for the servers of the followers
+const REQUEST_TIMEOUT: u64 = 10;
+
+/// RSA key of the actors, signing the requests of the inbox
+pub struct ActorKey {
+    pair: RsaKeyPair,
+    /// SPKI PEM published in the actors
+    public_pem: String,
+}
+
+impl ActorKey {
+    fn from_pem(pem: &str) -> Result<Self, String> {
+        let key = rsa::RsaPrivateKey::from_pkcs8_pem(pem)
+            .map_err(|e| format!("Invalid ActivityPub key: {e}"))?;
+        let der = key.to_pkcs8_der().map_err(|e| e.to_string())?;
+        let pair = RsaKeyPair::from_pkcs8(der.as_bytes())
+            .map_err(|e| format!("Invalid ActivityPub key: {e}"))?;
+        let public_pem = key
+            .to_public_key()
+            .to_public_key_pem(LineEnding::LF)
+            .map_err(|e| e.to_string())?;
+        Ok(Self { pair, public_pem })
+    }
+
+    /// Key from `MARMITE_ACTIVITYPUB_KEY` or from the key file, which is
+    /// generated on first use
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the key is invalid or can not be saved.
+    pub fn load(input_folder: &Path) -> Result<Self, String> {
+        if let Ok(pem) = std::env::var(KEY_ENV) {
+            return Self::from_pem(&pem);
+        }
+        let path = input_folder.join(KEY_FILE);
+        if let Ok(pem) = fs::read_to_string(&path) {
+            return Self::from_pem(&pem);
+        }
+        info!("Generating the ActivityPub key in {}", path.display());
+        let key = rsa::RsaPrivateKey::new(&mut rsa::rand_core::OsRng, 2048)
+            .map_err(|e| format!("Could not generate the ActivityPub key: {e}"))?;
+        let pem = key
+            .to_pkcs8_pem(LineEnding::LF)
+            .map_err(|e| e.to_string())?;
+        fs::write(&path, pem.as_bytes()).map_err(|e| format!("Could not save the key: {e}"))?;
+        #[cfg(unix)]
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Could not save the key: {e}"))?;
+        Self::from_pem(&pem)
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, String> {
+        let mut signature = vec![0; self.pair.public().modulus_len()];
+        self.pair
+            .sign(
+                &ring::signature::RSA_PKCS1_SHA256,
+                &ring::rand::SystemRandom::new(),
+                message,
+                &mut signature,
+            )
+            .map_err(|_| "Could not sign the request".to_string())?;
+        Ok(signature)
+    }
+}
+
+/// A configured author published as an actor
+struct Actor<'a> {
+    username: String,
+    author: &'a Author,
+}
+
+/// Authors with an actor, the main one first: the first of
+/// `activitypub.authors`, or the default author when every author is published
+fn actors<'a>(site: &'a Marmite, config: &ActivityPubConfig) -> Vec<Actor<'a>> {
+    let names: Vec<&String> = if config.authors.is_empty() {
+        let mut names: Vec<&String> = site.authors.keys().collect();
+        names.sort_by_key(|name| (**name != site.default_author, *name));
+        names
+    } else {
+        config.authors.iter().collect()
+    };
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let Some(author) = site.authors.get(name) else {
+                warn!("activitypub.authors: '{name}' is not in the authors of marmite.yaml");
+                return None;
+            };
+            Some(Actor {
+                username: slugify(name),
+                author,
+            })
+        })
+        .collect()
+}
+
+/// URLs of the ActivityPub documents
+struct Urls {
+    url_for: UrlFor,
+    /// Folder of the documents inside the output folder
+    path: String,
+}
+
+impl Urls {
+    fn new(site: &Marmite, path_prefix: &str) -> Self {
+        let path = site
+            .activitypub
+            .as_ref()
+            .map(|config| config.path.trim_matches('/').to_string())
+            .unwrap_or_default();
+        Self {
+            url_for: UrlFor {
+                base_url: site_url(site),
+                path_prefix: path_prefix.to_string(),
+                ..Default::default()
+            },
+            path,
+        }
+    }
+
+    fn abs(&self, path: &str) -> String {
+        self.url_for.resolve(path, true)
+    }
+
+    fn actor(&self, username: &str) -> String {
+        self.abs(&format!("{}/{username}.json", self.path))
+    }
+
+    fn inbox(&self, username: &str) -> String {
+        self.abs(&format!("{}/{username}/inbox", self.path))
+    }
+
+    fn shared_inbox(&self) -> String {
+        self.abs(&format!("{}/inbox", self.path))
+    }
+
+    fn outbox(&self, username: &str) -> String {
+        self.abs(&format!("{}/{username}/outbox.json", self.path))
+    }
+
+    fn followers(&self, username: &str) -> String {
+        self.abs(&format!("{}/{username}/followers.json", self.path))
+    }
+
+    fn object(&self, slug: &str) -> String {
+        self.abs(&format!("{}/objects/{slug}.json", self.path))
+    }
+}
+
+/// `host[:port]` of the site, the domain of the `acct:` URIs
+fn host(site_url: &str) -> Option<String> {
+    let url = url::Url::parse(site_url).ok()?;
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    })
+}
+
+fn webfinger(urls: &Urls, host: &str, username: &str) -> Value {
+    let actor = urls.actor(username);
+    let profile = urls.abs(&format!("author-{username}.html"));
+    json!({
+        "subject": format!("acct:{username}@{host}"),
+        "aliases": [actor, profile],
+        "links": [
+            {"rel": "self", "type": ACTIVITY_JSON, "href": actor},
+            {"rel": "http://webfinger.net/rel/profile-page", "type": "text/html", "href": profile},
+        ],
+    })
+}
+
+fn actor_document(urls: &Urls, actor: &Actor, public_pem: &str) -> Value {
+    let id = urls.actor(&actor.username);
+    let mut document = json!({
+        "@context": [AS_CONTEXT, SECURITY_CONTEXT],
+        "id": id,
+        "type": "Person",
+        "preferredUsername": actor.username,
+        "name": actor.author.name,
+        "summary": actor
+            .author
+            .bio
+            .as_deref()
+            .map(|bio| format!("<p>{}</p>", escape_html(bio)))
+            .unwrap_or_default(),
+        "url": urls.abs(&format!("author-{}.html", actor.username)),
+        "inbox": urls.inbox(&actor.username),
+        "outbox": urls.outbox(&actor.username),
+        "followers": urls.followers(&actor.username),
+        "endpoints": {"sharedInbox": urls.shared_inbox()},
+        "manuallyApprovesFollowers": false,
+        "discoverable": true,
+        "publicKey": {
+            "id": format!("{id}#main-key"),
+            "owner": id,
+            "publicKeyPem": public_pem,
+        },
+    });
+    if let Some(avatar) = &actor.author.avatar {
+        document["icon"] = json!({"type": "Image", "url": urls.abs(avatar)});
+    }
+    if let Some(links) = &actor.author.links {
+        document["attachment"] = links
+            .iter()
+            .map(|(name, link)| {
+                let link = escape_html(&urls.abs(link));
+                json!({
+                    "type": "PropertyValue",
+                    "name": name,
+                    "value": format!("<a href=\"{link}\" rel=\"me\">{link}</a>"),
+                })
+            })
+            .collect();
+    }
+    document
+}
+
+/// Mastodon hashtags are letters, digits and underscores
+fn hashtag(tag: &str) -> String {
+    tag.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '_')
+        .collect()
+}
+
+/// The `Note` or `Article` of a post, attributed to `username`
+fn object(site: &Marmite, urls: &Urls, content: &Content, username: &str) -> Value {
+    let object_type = site
+        .activitypub
+        .as_ref()
+        .map(|config| config.object_type)
+        .unwrap_or_default();
+    let url = urls.abs(&format!("{}.html", content.slug));
+    let tags: Vec<Value> = content
+        .tags
+        .iter()
+        .map(|tag| {
+            json!({
+                "type": "Hashtag",
+                "name": format!("#{}", hashtag(tag)),
+                "href": urls.abs(&format!("tag-{}.html", slugify(tag))),
+            })
+        })
+        .collect();
+    let summary = content
+        .description
+        .clone()
+        .unwrap_or_else(|| truncate_text(&html_to_text(&content.html), site.feed.excerpt_length));
+    let mut object = json!({
+        "id": urls.object(&content.slug),
+        "attributedTo": urls.actor(username),
+        "url": url,
+        "published": content
+            .date
+            .map(|date| date.and_utc().to_rfc3339_opts(SecondsFormat::Secs, true)),
+        "to": [PUBLIC],
+        "cc": [urls.followers(username)],
+        "tag": tags,
+    });
+    match object_type {
+        ActivityPubObject::Note => {
+            let hashtags: Vec<String> = content
+                .tags
+                .iter()
+                .map(|tag| {
+                    format!(
+                        "<a href=\"{}\" class=\"mention hashtag\" rel=\"tag\">#{}</a>",
+                        escape_html(&urls.abs(&format!("tag-{}.html", slugify(tag)))),
+                        escape_html(&hashtag(tag))
+                    )
+                })
+                .collect();
+            let mut html = format!(
+                "<p><strong>{}</strong></p><p>{}</p><p><a href=\"{url}\">{url}</a></p>",
+                escape_html(&content.title),
+                escape_html(&summary),
+                url = escape_html(&url)
+            );
+            if !hashtags.is_empty() {
+                let _ = write!(html, "<p>{}</p>", hashtags.join(" "));
+            }
+            object["type"] = json!("Note");
+            object["content"] = json!(html);
+        }
+        ActivityPubObject::Article => {
+            object["type"] = json!("Article");
+            object["name"] = json!(content.title);
+            object["summary"] = json!(escape_html(&summary));
+            object["content"] = json!(content.html);
+        }
+    }
+    if let Some(image) = content
+        .card_image
+        .as_ref()
+        .or(content.banner_image.as_ref())
+    {
+        object["image"] = json!({"type": "Image", "url": urls.abs(image)});
+    }
+    object
+}
+
+fn create(object: &Value) -> Value {
+    json!({
+        "id": format!("{}#create", object["id"].as_str().unwrap_or_default()),
+        "type": "Create",
+        "actor": object["attributedTo"],
+        "published": object["published"],
+        "to": object["to"],
+        "cc": object["cc"],
+        "object": object,
+    })
+}
+
+/// A remote actor following one of the actors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Follower {
+    /// ID of the remote actor
+    pub actor: String,
+    pub inbox: String,
+    /// Username of the followed actor
+    pub following: String,
+    /// Unix timestamp
+    pub followed_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Followers {
+    followers: Vec<Follower>,
+}
+
+fn load_followers(input_folder: &Path) -> Followers {
+    fs::read_to_string(input_folder.join(FOLLOWERS_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_followers(input_folder: &Path, followers: &Followers) -> Result<(), String> {
+    let path = input_folder.join(FOLLOWERS_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(followers).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+fn write_json(path: &Path, value: &Value) -> bool {
+    if let Some(parent) = path.parent() {
+        if let Err(e) = fs::create_dir_all(parent) {
+            error!("Failed to create {}: {e:?}", parent.display());
+            return false;
+        }
+    }
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => match fs::write(path, json) {
+            Ok(()) => true,
+            Err(e) => {
+                error!("Failed to write {}: {e:?}", path.display());
+                false
+            }
+        },
+        Err(e) => {
+            error!("Failed to serialize {}: {e:?}", path.display());
+            false
+        }
+    }
+}
+
+/// Writes the WebFinger, actor, outbox, followers and object documents
+#[allow(clippy::too_many_lines)]
+pub fn generate(site_data: &Data, input_folder: &Path, output_path: &Path, path_prefix: &str) {
+    let site = &site_data.site;
+    let Some(config) = &site.activitypub else {
+        return;
+    };
+    let path = config.path.trim_matches('/');
+    if path.is_empty() || path.split('/').any(|part| part == "..") {
+        error!("Invalid activitypub.path '{}'", config.path);
+        return;
+    }
+    let Some(host) = host(&site_url(site)).filter(|_| !site.url.is_empty()) else {
+        error!("Set url in marmite.yaml to publish the ActivityPub actors");
+        return;
+    };
+    let actors = actors(site, config);
+    if actors.is_empty() {
+        warn!("No author to publish with ActivityPub, add authors to marmite.yaml");
+        return;
+    }
+    let key = match ActorKey::load(input_folder) {
+        Ok(key) => key,
+        Err(e) => {
+            error!("{e}");
+            return;
+        }
+    };
+    let dir = output_path.join(path);
+    if dir.exists() {
+        if let Err(e) = fs::remove_dir_all(&dir) {
+            error!("Failed to clean the ActivityPub directory: {e:?}");
+            return;
+        }
+    }
+
+    let urls = Urls::new(site, path_prefix);
+    let followers = load_followers(input_folder);
+    let posts: Vec<&Content> = site_data
+        .posts
+        .iter()
+        .filter(|post| post.stream.as_deref() != Some("draft") && post.date.is_some())
+        .collect();
+    for actor in &actors {
+        let username = &actor.username;
+        write_json(
+            &dir.join("webfinger").join(format!("{username}.json")),
+            &webfinger(&urls, &host, username),
+        );
+        write_json(
+            &dir.join(format!("{username}.json")),
+            &actor_document(&urls, actor, &key.public_pem),
+        );
+        let count = followers
+            .followers
+            .iter()
+            .filter(|follower| &follower.following == username)
+            .count();
+        write_json(
+            &dir.join(username).join("followers.json"),
+            &json!({
+                "@context": AS_CONTEXT,
+                "id": urls.followers(username),
+                "type": "OrderedCollection",
+                "totalItems": count,
+            }),
+        );
+    }
+
+    // Each post belongs to its first author with an actor
+    let mut outboxes: HashMap<&str, Vec<Value>> = HashMap::new();
+    for post in posts {
+        let Some(actor) = actors.iter().find(|actor| {
+            post.authors
+                .iter()
+                .any(|author| slugify(author) == actor.username)
+        }) else {
+            continue;
+        };
+        let object = object(site, &urls, post, &actor.username);
+        write_json(
+            &dir.join("objects").join(format!("{}.json", post.slug)),
+            &object,
+        );
+        outboxes
+            .entry(actor.username.as_str())
+            .or_default()
+            .push(create(&object));
+    }
+    for actor in &actors {
+        let items = outboxes.remove(actor.username.as_str()).unwrap_or_default();
+        write_json(
+            &dir.join(&actor.username).join("outbox.json"),
+            &json!({
+                "@context": AS_CONTEXT,
+                "id": urls.outbox(&actor.username),
+                "type": "OrderedCollection",
+                "totalItems": items.len(),
+                "orderedItems": items.into_iter().take(config.outbox_items).collect::<Vec<_>>(),
+            }),
+        );
+    }
+
+    let main = &actors[0].username;
+    if write_json(
+        &output_path.join(".well-known").join("webfinger"),
+        &webfinger(&urls, &host, main),
+    ) {
+        info!(
+            "Generated ActivityPub actors for {} author(s), follow @{main}@{host}",
+            actors.len()
+        );
+    }
+}
+
+/// Parameters of a `Signature` header
+fn parse_signature(header: &str) -> HashMap<String, String> {
+    Regex::new(re::CAPTURE_NAME_VALUE_FROM_SIGNATURE_HEADER)
+        .expect("Signature regex should compile")
+        .captures_iter(header)
+        .map(|cap| (cap[1].to_string(), cap[2].to_string()))
+        .collect()
+}
+
+fn digest(body: &[u8]) -> String {
+    format!("SHA-256={}", BASE64_STANDARD.encode(Sha256::digest(body)))
+}
+
+/// The `Signature` header of a request, checked against the other headers
+/// and to verify with the key of `key_id`
+#[derive(Debug)]
+struct SignedRequest {
+    key_id: String,
+    signature: String,
+    signing_string: String,
+}
+
+/// Checks the HTTP signature of an inbox request covers its target, host,
+/// date and body, `headers` are keyed by lowercase name
+fn check_signature(
+    headers: &HashMap<String, String>,
+    request_target: &str,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Result<SignedRequest, String> {
+    let params = parse_signature(headers.get("signature").ok_or("Missing signature")?);
+    let signed: Vec<&str> = params
+        .get("headers")
+        .map_or("date", String::as_str)
+        .split_whitespace()
+        .collect();
+    for required in ["(request-target)", "host", "date", "digest"] {
+        if !signed.contains(&required) {
+            return Err(format!("The signature does not cover {required}"));
+        }
+    }
+    if headers.get("digest").map(String::as_str) != Some(digest(body).as_str()) {
+        return Err("The digest does not match the body".to_string());
+    }
+    let date = headers
+        .get("date")
+        .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+        .ok_or("Invalid date")?;
+    if (now - date.with_timezone(&Utc)).num_seconds().abs() > MAX_CLOCK_SKEW {
+        return Err("The date is too far from now".to_string());
+    }
+    let mut lines = Vec::new();
+    for name in signed {
+        if name == "(request-target)" {
+            lines.push(format!("(request-target): {request_target}"));
+        } else {
+            let value = headers
+                .get(name)
+                .ok_or_else(|| format!("Missing signed header {name}"))?;
+            lines.push(format!("{name}: {value}"));
+        }
+    }
+    Ok(SignedRequest {
+        key_id: params.get("keyId").ok_or("Missing keyId")?.clone(),
+        signature: params.get("signature").ok_or("Missing signature")?.clone(),
+        signing_string: lines.join("\n"),
+    })
+}
+
+/// Verifies a signature made by the key of `public_pem`
+fn verify(public_pem: &str, signing_string: &str, signature: &str) -> Result<(), String> {
+    let key = rsa::RsaPublicKey::from_public_key_pem(public_pem)
+        .map_err(|e| format!("Invalid public key: {e}"))?;
+    let der = key.to_pkcs1_der().map_err(|e| e.to_string())?;
+    let signature = BASE64_STANDARD
+        .decode(signature)
+        .map_err(|_| "Invalid signature")?;
+    UnparsedPublicKey::new(&RSA_PKCS1_2048_8192_SHA256, der.as_bytes())
+        .verify(signing_string.as_bytes(), &signature)
+        .map_err(|_| "The signature does not match".to_string())
+}
+
+/// Headers signing a request with the key `key_id`
+fn signed_headers(
+    key: &ActorKey,
+    key_id: &str,
+    method: &str,
+    url: &str,
+    body: Option<&[u8]>,
+) -> Result<Vec<(&'static str, String)>, String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL {url}: {e}"))?;
+    let host = host(url).ok_or_else(|| format!("Invalid URL {url}"))?;
+    let target = format!("{method} {}", &parsed[url::Position::BeforePath..]);
+    let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    let mut names = "(request-target) host date".to_string();
+    let mut lines = vec![
+        format!("(request-target): {target}"),
+        format!("host: {host}"),
+        format!("date: {date}"),
+    ];
+    let mut headers = vec![("Date", date)];
+    if let Some(body) = body {
+        let digest = digest(body);
+        names.push_str(" digest");
+        lines.push(format!("digest: {digest}"));
+        headers.push(("Digest", digest));
+    }
+    let signature = BASE64_STANDARD.encode(key.sign(lines.join("\n").as_bytes())?);
+    headers.push((
+        "Signature",
+        format!(
+            "keyId=\"{key_id}\",algorithm=\"rsa-sha256\",headers=\"{names}\",signature=\"{signature}\""
+        ),
+    ));
+    Ok(headers)
+}
+
+/// Public unicast addresses, so the URLs sent to the inbox can not reach
+/// the local network of the server
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || first == 0
+                // Shared address space of carrier-grade NAT
+                || (first == 100 && second & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local and link-local addresses
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+/// Checks `url` is an https URL whose host only resolves to public addresses
+fn check_remote(url: &str) -> Result<(), String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL {url}: {e}"))?;
+    if parsed.scheme() != "https" {
+        return Err(format!("{url} is not an https URL"));
+    }
+    let addresses = parsed
+        .socket_addrs(|| None)
+        .map_err(|e| format!("Could not resolve {url}: {e}"))?;
+    if addresses.is_empty() || !addresses.iter().all(|address| is_public_ip(address.ip())) {
+        return Err(format!("{url} is not a public address"));
+    }
+    Ok(())
+}
+
+/// Resolves hosts like ureq does, failing when any address is not public.
+/// The request connects to the addresses checked here, so a host can not
+/// pass [`check_remote`] and then resolve to the local network.
+#[derive(Debug)]
+struct PublicResolver {
+    allow_local: bool,
+}
+
+impl Resolver for PublicResolver {
+    fn resolve(
+        &self,
+        uri: &ureq::http::Uri,
+        config: &ureq::config::Config,
+        timeout: NextTimeout,
+    ) -> Result<ResolvedSocketAddrs, ureq::Error> {
+        let addresses = DefaultResolver::default().resolve(uri, config, timeout)?;
+        if self.allow_local || addresses.iter().all(|address| is_public_ip(address.ip())) {
+            Ok(addresses)
+        } else {
+            Err(ureq::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("{uri} is not a public address"),
+            )))
+        }
+    }
+}
+
+/// Agent for the requests to other servers, only connecting to public
+/// addresses unless `allow_local`
+fn remote_agent(allow_local: bool) -> ureq::Agent {
+    let config = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(REQUEST_TIMEOUT)))
+        .http_status_as_error(false)
+        .max_redirects(0)
+        .user_agent(concat!("marmite/", env!("CARGO_PKG_VERSION")))
+        .build();
+    ureq::Agent::with_parts(
+        config,
+        DefaultConnector::default(),
+        PublicResolver { allow_local },
+    )
+}
+
+/// Same scheme, host and port
+fn same_origin(a: &str, b: &str) -> bool {
+    match (url::Url::parse(a), url::Url::parse(b)) {
+        (Ok(a), Ok(b)) => a.origin() == b.origin(),
+        _ => false,
+    }
+}
+
+/// Posts a signed activity to an inbox
+fn deliver(
+    agent: &ureq::Agent,
+    key: &ActorKey,
+    key_id: &str,
+    inbox: &str,
+    activity: &Value,
+) -> Result<(), String> {
+    let body = activity.to_string();
+    let mut request = agent.post(inbox).header("Content-Type", ACTIVITY_JSON);
+    for (name, value) in signed_headers(key, key_id, "post", inbox, Some(body.as_bytes()))? {
+        request = request.header(name, value);
+    }
+    let response = request.send(body.as_bytes()).map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("{inbox} answered {}", response.status()))
+    }
+}
+
+fn reply(status: u16, body: &Value, content_type: &str) -> Response<Cursor<Vec<u8>>> {
+    let mut response = Response::from_string(body.to_string()).with_status_code(status);
+    if let Ok(header) = Header::from_bytes("Content-Type", content_type) {
+        response.add_header(header);
+    }
+    response
+}
+
+fn error_reply(status: u16, message: &str) -> Response<Cursor<Vec<u8>>> {
+    reply(status, &json!({"error": message}), "application/json")
+}
+
+/// ActivityPub requests answered by the built-in server
+pub struct Service {
+    urls: Urls,
+    host: String,
+    /// Usernames of the actors
+    actors: Vec<String>,
+    /// Signs the requests of the inbox, `None` when the inbox is off
+    key: Option<Arc<ActorKey>>,
+    input_folder: PathBuf,
+    agent: ureq::Agent,
+    /// Lets the tests reach actors over http on the loopback address, with
+    /// an agent made by `remote_agent(true)`
+    allow_local: bool,
+}
+
+impl Service {
+    /// The service of a site with an `activitypub` section and an url
+    #[must_use]
+    pub fn new(site: &Marmite, input_folder: &Path) -> Option<Self> {
+        let config = site.activitypub.as_ref()?;
+        let host = host(&site_url(site)).filter(|_| !site.url.is_empty())?;
+        let urls = Urls::new(site, "");
+        let key = if config.inbox {
+            match ActorKey::load(input_folder) {
+                Ok(key) => {
+                    info!(
+                        "ActivityPub inbox accepting follows at {}",
+                        urls.shared_inbox()
+                    );
+                    Some(Arc::new(key))
+                }
+                Err(e) => {
+                    error!("ActivityPub inbox disabled: {e}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Some(Self {
+            actors: actors(site, config)
+                .into_iter()
+                .map(|actor| actor.username)
+                .collect(),
+            urls,
+            host,
+            key,
+            input_folder: input_folder.to_path_buf(),
+            agent: remote_agent(false),
+            allow_local: false,
+        })
+    }
+
+    /// `Content-Type` of the ActivityPub documents of the output folder
+    #[must_use]
+    pub fn content_type(&self, request_path: &str) -> Option<&'static str> {
+        let path = &self.urls.path;
+        if request_path == ".well-known/webfinger"
+            || request_path.starts_with(&format!("{path}/webfinger/"))
+        {
+            return Some(JRD_JSON);
+        }
+        (request_path.starts_with(&format!("{path}/"))
+            && Path::new(request_path)
+                .extension()
+                .is_some_and(|ext| ext == "json"))
+        .then_some(ACTIVITY_JSON)
+    }
+
+    /// Answers WebFinger queries and inbox requests, `None` for every
+    /// other request
+    pub fn handle(&self, request: &mut Request) -> Option<Response<Cursor<Vec<u8>>>> {
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        if path == "/.well-known/webfinger" && request.method() == &Method::Get {
+            let resource = url::form_urlencoded::parse(query.as_bytes())
+                .find(|(name, _)| name == "resource")?
+                .1
+                .into_owned();
+            return Some(self.webfinger(&resource));
+        }
+        let key = self.key.as_ref()?;
+        let inboxes = format!("/{}/", self.urls.path);
+        let rest = path.strip_prefix(&inboxes)?;
+        let is_inbox = rest == "inbox"
+            || rest
+                .strip_suffix("/inbox")
+                .is_some_and(|username| self.actors.iter().any(|actor| actor == username));
+        if !is_inbox {
+            return None;
+        }
+        if request.method() != &Method::Post {
+            return Some(error_reply(405, "The inbox only accepts POST"));
+        }
+        Some(self.receive(request, key))
+    }
+
+    fn webfinger(&self, resource: &str) -> Response<Cursor<Vec<u8>>> {
+        let username = self.actors.iter().find(|username| {
+            resource.eq_ignore_ascii_case(&format!("acct:{username}@{}", self.host))
+                || resource == self.urls.actor(username)
+        });
+        match username {
+            Some(username) => reply(200, &webfinger(&self.urls, &self.host, username), JRD_JSON),
+            None => error_reply(404, "Unknown resource"),
+        }
+    }
+
+    fn check_remote(&self, url: &str) -> Result<(), String> {
+        if self.allow_local {
+            return Ok(());
+        }
+        check_remote(url)
+    }
+
+    /// Fetches a document of another server with a signed request, as
+    /// servers with authorized fetch require
+    fn fetch(&self, url: &str, key: &ActorKey) -> Result<Value, String> {
+        self.check_remote(url)?;
+        let key_id = format!("{}#main-key", self.urls.actor(&self.actors[0]));
+        let mut request = self.agent.get(url).header("Accept", ACTIVITY_JSON);
+        for (name, value) in signed_headers(key, &key_id, "get", url, None)? {
+            request = request.header(name, value);
+        }
+        let mut response = request
+            .call()
+            .map_err(|e| format!("Could not fetch {url}: {e}"))?;
+        if !response.status().is_success() {
+            return Err(format!("Could not fetch {url}: {}", response.status()));
+        }
+        response
+            .body_mut()
+            .with_config()
+            .limit(MAX_ACTIVITY_SIZE)
+            .read_json()
+            .map_err(|e| format!("Invalid document at {url}: {e}"))
+    }
+
+    /// The actor that signed the request: the signature must be made with
+    /// the key published in the actor document, on the server of the actor
+    fn signer(
+        &self,
+        request: &Request,
+        body: &[u8],
+        actor_id: &str,
+        key: &ActorKey,
+    ) -> Result<Value, String> {
+        let headers: HashMap<String, String> = request
+            .headers()
+            .iter()
+            .map(|header| {
+                (
+                    header.field.as_str().as_str().to_ascii_lowercase(),
+                    header.value.as_str().to_string(),
+                )
+            })
+            .collect();
+        let target = format!("post {}", request.url());
+        let signature = check_signature(&headers, &target, body, Utc::now())?;
+        let key_id = &signature.key_id;
+        if actor_id.starts_with(&self.urls.abs(&self.urls.path)) {
+            return Err("The actors of the site do not post to its inboxes".to_string());
+        }
+        if !same_origin(key_id, actor_id) {
+            return Err(format!(
+                "The key {key_id} is not on the server of {actor_id}"
+            ));
+        }
+        let actor = self.fetch(actor_id, key)?;
+        if actor["id"].as_str() != Some(actor_id) {
+            return Err(format!("The document at {actor_id} is another actor"));
+        }
+        let public_key = &actor["publicKey"];
+        if public_key["id"].as_str() != Some(key_id) {
+            return Err(format!("The key {key_id} does not belong to {actor_id}"));
+        }
+        verify(
+            public_key["publicKeyPem"].as_str().unwrap_or_default(),
+            &signature.signing_string,
+            &signature.signature,
+        )?;
+        Ok(actor)
+    }
+
+    /// Sends the `Accept` of a follow from another thread, so a slow
+    /// server does not hold the request loop
+    fn accept(&self, username: &str, inbox: &str, follow: &Value, key: &Arc<ActorKey>) {
+        let actor = self.urls.actor(username);
+        let accept = json!({
+            "@context": AS_CONTEXT,
+            "id": format!("{actor}#accept-{}", Utc::now().timestamp_millis()),
+            "type": "Accept",
+            "actor": actor,
+            "object": follow,
+        });
+        let agent = self.agent.clone();
+        let key = Arc::clone(key);
+        let key_id = format!("{actor}#main-key");
+        let inbox = inbox.to_string();
+        let follower = follow["actor"].as_str().unwrap_or_default().to_string();
+        std::thread::spawn(move || {
+            if let Err(e) = deliver(&agent, &key, &key_id, &inbox, &accept) {
+                warn!("Could not accept the follow of {follower}: {e}");
+            }
+        });
+    }
+
+    fn receive(&self, request: &mut Request, key: &Arc<ActorKey>) -> Response<Cursor<Vec<u8>>> {
+        let mut body = Vec::new();
+        if request
+            .as_reader()
+            .take(MAX_ACTIVITY_SIZE)
+            .read_to_end(&mut body)
+            .is_err()
+        {
+            return error_reply(400, "Could not read the activity");
+        }
+        let Ok(activity) = serde_json::from_slice::<Value>(&body) else {
+            return error_reply(400, "Invalid activity");
+        };
+        let actor_id = activity["actor"].as_str().unwrap_or_default();
+        let remote = match self.signer(request, &body, actor_id, key) {
+            Ok(remote) => remote,
+            Err(e) => {
+                warn!("Rejected an activity from {actor_id}: {e}");
+                return error_reply(401, &e);
+            }
+        };
+        let object_id = |object: &Value| {
+            object
+                .as_str()
+                .or(object["id"].as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let username = |object: &Value| {
+            let id = object_id(object);
+            self.actors
+                .iter()
+                .find(|username| self.urls.actor(username) == id)
+                .cloned()
+        };
+        match activity["type"].as_str() {
+            Some("Follow") => {
+                let Some(username) = username(&activity["object"]) else {
+                    return error_reply(404, "Unknown actor");
+                };
+                let Some(inbox) = remote["inbox"].as_str() else {
+                    return error_reply(400, "The follower has no inbox");
+                };
+                if let Err(e) = self.check_remote(inbox) {
+                    return error_reply(400, &e);
+                }
+                let mut followers = load_followers(&self.input_folder);
+                followers
+                    .followers
+                    .retain(|f| !(f.actor == actor_id && f.following == username));
+                followers.followers.push(Follower {
+                    actor: actor_id.to_string(),
+                    inbox: inbox.to_string(),
+                    following: username.clone(),
+                    followed_at: Utc::now().timestamp(),
+                });
+                if let Err(e) = save_followers(&self.input_folder, &followers) {
+                    error!("Failed to save the followers: {e}");
+                    return error_reply(500, "Could not save the follower");
+                }
+                info!("{actor_id} follows @{username}");
+                self.accept(&username, inbox, &activity, key);
+                reply(202, &json!({}), "application/json")
+            }
+            Some("Undo") if activity["object"]["type"] == "Follow" => {
+                let follow = &activity["object"];
+                if follow["actor"].as_str() != Some(actor_id) {
+                    return error_reply(400, "Only the follower can undo a follow");
+                }
+                let Some(username) = username(&follow["object"]) else {
+                    return error_reply(404, "Unknown actor");
+                };
+                let mut followers = load_followers(&self.input_folder);
+                followers
+                    .followers
+                    .retain(|f| !(f.actor == actor_id && f.following == username));
+                if let Err(e) = save_followers(&self.input_folder, &followers) {
+                    error!("Failed to save the followers: {e}");
+                    return error_reply(500, "Could not remove the follower");
+                }
+                info!("{actor_id} unfollowed @{username}");
+                reply(202, &json!({}), "application/json")
+            }
+            _ => reply(202, &json!({}), "application/json"),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/activitypub.rs"]
+mod tests;
@@ -0,0 +1,189 @@
+//! Library entry point to build sites from Rust code.
+//!
+//! ```no_run
+//! use marmite::{BuildOptions, Site};
+//!
+//! let site = Site::load("mysite")?;
+//! let options = BuildOptions {
+//!     output_folder: Some("public".into()),
+//!     ..Default::default()
+//! };
+//! let report = site.build(&options)?;
+//! for diagnostic in &report.diagnostics {
+//!     eprintln!("{diagnostic}");
+//! }
+//! # Ok::<(), marmite::Error>(())
+//! ```
+use crate::cli::Cli;
+use crate::config::Marmite;
+use crate::diagnostics::Diagnostic;
+use crate::error::Error;
+use crate::site::{self, Data, UrlCollection};
+use clap::Parser;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Name of the configuration file read from the input folder
+const CONFIG_FILE: &str = "marmite.yaml";
+
+/// A site folder with its loaded configuration
+#[derive(Debug, Clone)]
+pub struct Site {
+    input_folder: PathBuf,
+    config_path: PathBuf,
+    config: Marmite,
+}
+
+/// Options of a single build, the same as the matching command line flags
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// Where to write the site [default: `input_folder/site`]
+    pub output_folder: Option<PathBuf>,
+    /// Render every page even if no changes were detected
+    pub force: bool,
+    /// Only parse changed files and render the outputs that depend on them
+    pub incremental: bool,
+    /// Publish content scheduled for the future
+    pub include_future: bool,
+    /// Fail with [`Error::Denied`] when diagnostics with these codes are
+    /// found, `warnings` denies all of them
+    pub deny: Vec<String>,
+}
+
+/// What a successful build produced
+#[derive(Debug)]
+pub struct BuildReport {
+    /// Posts, pages and groups of the built site
+    pub data: Data,
+    /// Every URL generated by the build
+    pub urls: UrlCollection,
+    /// Problems that did not stop the build, like broken links
+    pub diagnostics: Vec<Diagnostic>,
+    /// Time spent building
+    pub elapsed: Duration,
+    /// Folder the site was written to
+    pub output_folder: PathBuf,
+}
+
+impl Site {
+    /// Loads `marmite.yaml` from `input_folder`, using the defaults when it
+    /// does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] when the folder does not exist or the config can
+    /// not be read, [`Error::Config`] when it is not valid and
+    /// [`Error::ThemeNotFound`] when the configured theme is missing.
+    pub fn load(input_folder: impl AsRef<Path>) -> Result<Self, Error> {
+        let input_folder = input_folder.as_ref().to_path_buf();
+        Self::load_with_config(&input_folder, &input_folder.join(CONFIG_FILE))
+    }
+
+    /// Same as [`Site::load`] with the configuration read from `config_path`.
+    ///
+    /// # Errors
+    ///
+    /// See [`Site::load`].
+    pub fn load_with_config(input_folder: &Path, config_path: &Path) -> Result<Self, Error> {
+        if !input_folder.is_dir() {
+            return Err(Error::Io {
+                path: input_folder.to_path_buf(),
+                message: "Input folder does not exist".to_string(),
+            });
+        }
+
+        let config_str = if config_path.exists() {
+            fs::read_to_string(config_path).map_err(|e| Error::Io {
+                path: config_path.to_path_buf(),
+                message: e.to_string(),
+            })?
+        } else {
+            String::new()
+        };
+        let config: Marmite = serde_yaml::from_str(&config_str).map_err(|e| Error::Config {
+            path: config_path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        config
+            .validate_taxonomies()
+            .map_err(|message| Error::Config {
+                path: config_path.to_path_buf(),
+                message,
+            })?;
+
+        if let Some(theme) = &config.theme {
+            let theme_path = input_folder.join(theme);
+            if !theme_path.is_dir() {
+                return Err(Error::ThemeNotFound(theme_path));
+            }
+        }
+
+        Ok(Self {
+            input_folder: input_folder.to_path_buf(),
+            config_path: config_path.to_path_buf(),
+            config,
+        })
+    }
+
+    #[must_use]
+    pub fn input_folder(&self) -> &Path {
+        &self.input_folder
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &Marmite {
+        &self.config
+    }
+
+    /// Builds the site, the same as running `marmite` without `--watch`/`--serve`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] when templates fail to render, files can not be
+    /// written, broken links are found with `strict_internal_links` or
+    /// diagnostics listed in `deny` are found.
+    pub fn build(&self, options: &BuildOptions) -> Result<BuildReport, Error> {
+        let start = Instant::now();
+        let output_folder = options
+            .output_folder
+            .clone()
+            .unwrap_or_else(|| self.input_folder.join("site"));
+
+        let mut cli = Cli::parse_from(["marmite"]);
+        cli.input_folder = Some(self.input_folder.clone());
+        cli.output_folder = Some(output_folder.clone());
+        cli.config = self
+            .config_path
+            .strip_prefix(&self.input_folder)
+            .unwrap_or(&self.config_path)
+            .to_string_lossy()
+            .to_string();
+        cli.force = options.force;
+        cli.incremental = options.incremental;
+        cli.include_future = options.include_future;
+        cli.deny.clone_from(&options.deny);
+
+        let mut data = site::build_site_with_config(
+            &self.config,
+            &self.input_folder,
+            &output_folder,
+            &Arc::new(cli),
+            None,
+            "",
+        )?;
+
+        Ok(BuildReport {
+            urls: data.generated_urls.clone(),
+            diagnostics: std::mem::take(&mut data.diagnostics),
+            data,
+            elapsed: start.elapsed(),
+            output_folder,
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/api.rs"]
+mod tests;
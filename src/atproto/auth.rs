@@ -1,10 +1,15 @@
-use crate::atproto::client;
+use crate::atproto::client::{self, Authorization};
 use crate::atproto::credentials::{self, Credential};
+use crate::atproto::oauth;
 use crate::cli::Cli;
 use crate::site::Data;
 use std::env;
 
-pub fn auth(input_folder: &std::path::Path, args: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+pub fn auth(
+    input_folder: &std::path::Path,
+    args: &Cli,
+    use_oauth: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     // 1. Read handle from configuration file in input_folder
     let config_path = if args.config.starts_with('.') || args.config.starts_with('/') {
         std::path::PathBuf::from(&args.config)
@@ -32,42 +37,58 @@ pub fn auth(input_folder: &std::path::Path, args: &Cli) -> Result<(), Box<dyn st
             "atproto.handle must be configured in {config_filename} to authenticate."
         ))?;
 
-    // 2. Read password from environment
-    let password = env::var("ATPROTO_APP_PASSWORD").map_err(|_| {
-        concat!(
-            "ATPROTO_APP_PASSWORD env var is required.\n",
-            "Create an app password at: https://bsky.app/settings/app-passwords\n\n",
-            "Then set:\n",
-            "  export ATPROTO_APP_PASSWORD=xxxx-xxxx-xxxx-xxxx"
-        )
-    })?;
+    // 2. Read password from environment, OAuth needs none
+    let password = if use_oauth {
+        None
+    } else {
+        Some(env::var("ATPROTO_APP_PASSWORD").map_err(|_| {
+            concat!(
+                "ATPROTO_APP_PASSWORD env var is required.\n",
+                "Create an app password at: https://bsky.app/settings/app-passwords\n\n",
+                "Then set:\n",
+                "  export ATPROTO_APP_PASSWORD=xxxx-xxxx-xxxx-xxxx\n\n",
+                "Or authorize marmite in the browser with: marmite atproto auth --oauth"
+            )
+        })?)
+    };
 
     // 3. Resolve PDS endpoint (return error instead of falling back to hardcoded default)
-    let pds_url = if let Ok(val) = env::var("ATPROTO_PDS_URL") {
-        val
+    let resolution_error = |e| {
+        format!(
+            "Could not resolve PDS endpoint for handle '{handle}': {e}.\n\
+             If you are self-hosting or the resolution failed, you can set the ATPROTO_PDS_URL environment variable to override."
+        )
+    };
+    let (pds_url, did) = if let Ok(val) = env::var("ATPROTO_PDS_URL") {
+        (val, None)
     } else {
-        client::resolve_pds_endpoint(handle).map_err(|e| {
-            format!(
-                "Could not resolve PDS endpoint for handle '{handle}': {e}.\n\
-                 If you are self-hosting or the resolution failed, you can set the ATPROTO_PDS_URL environment variable to override."
-            )
-        })?
+        let did = client::resolve_handle_to_did(handle).map_err(resolution_error)?;
+        let pds_url = client::resolve_did_to_pds(&did).map_err(resolution_error)?;
+        (pds_url, Some(did))
     };
 
     // 4. Authenticate
-    let session = client::create_session(&pds_url, handle, &password)
-        .map_err(|e| format!("Authentication failed: {e}\nCheck your handle and app password."))?;
+    let (authenticated, session) = if let Some(ref password) = password {
+        let session = client::create_session(&pds_url, handle, password).map_err(|e| {
+            format!("Authentication failed: {e}\nCheck your handle and app password.")
+        })?;
+        (session.handle, None)
+    } else {
+        let session = oauth::authorize(&pds_url, handle, did.as_deref())
+            .map_err(|e| format!("Authorization failed: {e}"))?;
+        (format!("{handle} ({})", session.did), Some(session))
+    };
 
     // 5. Save credentials
     credentials::save(&Credential {
         pds_url,
         identifier: handle.clone(),
         password,
+        oauth: session,
     })?;
 
     log::info!(
-        "Authenticated as @{}\nCredentials saved to {}",
-        session.handle,
+        "Authenticated as @{authenticated}\nCredentials saved to {}",
         credentials::credentials_path().display()
     );
 
@@ -94,3 +115,35 @@ pub fn auth(input_folder: &std::path::Path, args: &Cli) -> Result<(), Box<dyn st
 
     Ok(())
 }
+
+/// Authorizes the requests to the PDS with saved credentials, returning the
+/// DID of the account. App passwords open a new session, OAuth tokens are
+/// refreshed when they expire and saved again, as each refresh token can only
+/// be used once.
+pub fn login(
+    pds_url: &str,
+    cred: &mut Credential,
+) -> Result<(String, Authorization), Box<dyn std::error::Error>> {
+    if let Some(session) = cred.oauth.as_mut() {
+        if session.expired() {
+            oauth::refresh(session).map_err(|e| {
+                format!(
+                    "Could not refresh the OAuth session: {e}\n\
+                     Run: marmite atproto auth --oauth"
+                )
+            })?;
+            credentials::save(cred)?;
+        }
+        let session = cred.oauth.as_ref().ok_or("No OAuth session")?;
+        return Ok((
+            session.did.clone(),
+            Authorization::dpop(&session.access_token, &session.dpop_key)?,
+        ));
+    }
+    let password = cred
+        .password
+        .as_deref()
+        .ok_or("The saved credentials have no app password or OAuth session")?;
+    let session = client::create_session(pds_url, &cred.identifier, password)?;
+    Ok((session.did, Authorization::Bearer(session.access_jwt)))
+}
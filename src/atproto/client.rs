@@ -1,13 +1,23 @@
 /// Minimal synchronous XRPC client backed by ureq.
 ///
-/// Only implements the three calls needed for atproto publishing:
+/// Only implements the calls needed for atproto publishing:
 /// - `com.atproto.server.createSession`
+/// - `com.atproto.repo.listRecords`
 /// - `com.atproto.repo.createRecord`
 /// - `com.atproto.repo.putRecord`
+/// - `com.atproto.repo.deleteRecord`
+/// - `com.atproto.repo.uploadBlob`
+/// - `app.bsky.feed.getPostThread`
+///
+/// The calls changing the repository are authorized with an app-password
+/// session or with OAuth tokens bound to a DPoP key.
+use std::cell::RefCell;
 use std::fmt::Write;
 
 use serde::{Deserialize, Serialize};
 
+use crate::atproto::oauth::DpopKey;
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Session {
@@ -51,6 +61,83 @@ pub fn create_session(
     Ok(session)
 }
 
+/// How the calls changing the repository are authorized
+pub enum Authorization {
+    /// Access token of an app-password session
+    Bearer(String),
+    /// OAuth access token bound to a DPoP key
+    Dpop {
+        access_token: String,
+        key: Box<DpopKey>,
+        /// Last DPoP nonce of the PDS
+        nonce: RefCell<Option<String>>,
+    },
+}
+
+impl Authorization {
+    /// OAuth authorization from a saved access token and DPoP key
+    pub fn dpop(access_token: &str, key: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::Dpop {
+            access_token: access_token.to_string(),
+            key: Box::new(DpopKey::decode(key)?),
+            nonce: RefCell::new(None),
+        })
+    }
+}
+
+/// Sends an authorized POST to the PDS. With DPoP the PDS may ask for a
+/// nonce first, the request is then sent again with it.
+fn post(
+    url: &str,
+    authorization: &Authorization,
+    content_type: &str,
+    body: &[u8],
+) -> Result<ureq::http::Response<ureq::Body>, Box<dyn std::error::Error>> {
+    for _ in 0..2 {
+        let request = ureq::post(url)
+            .config()
+            .http_status_as_error(false)
+            .build()
+            .header("Content-Type", content_type);
+        let mut response = match authorization {
+            Authorization::Bearer(access_jwt) => request
+                .header("Authorization", &format!("Bearer {access_jwt}"))
+                .send(body)?,
+            Authorization::Dpop {
+                access_token,
+                key,
+                nonce,
+            } => {
+                let proof =
+                    key.proof("POST", url, nonce.borrow().as_deref(), Some(access_token))?;
+                let response = request
+                    .header("Authorization", &format!("DPoP {access_token}"))
+                    .header("DPoP", &proof)
+                    .send(body)?;
+                if let Some(value) = response.headers().get("DPoP-Nonce") {
+                    *nonce.borrow_mut() = Some(value.to_str()?.to_string());
+                }
+                let use_nonce = response
+                    .headers()
+                    .get("WWW-Authenticate")
+                    .and_then(|value| value.to_str().ok())
+                    .is_some_and(|value| value.contains("use_dpop_nonce"));
+                if response.status().as_u16() == 401 && use_nonce {
+                    continue;
+                }
+                response
+            }
+        };
+        let status = response.status().as_u16();
+        if status >= 400 {
+            let text = response.body_mut().read_to_string().unwrap_or_default();
+            return Err(format!("http status: {status} {text}").into());
+        }
+        return Ok(response);
+    }
+    Err("The PDS keeps asking for a new DPoP nonce".into())
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RecordItem {
     pub uri: String,
@@ -103,7 +190,7 @@ pub fn list_records(
 /// Calls `com.atproto.repo.createRecord`.
 pub fn create_record(
     pds_url: &str,
-    access_jwt: &str,
+    authorization: &Authorization,
     repo: &str,
     collection: &str,
     record: &serde_json::Value,
@@ -123,10 +210,13 @@ pub fn create_record(
         record,
         validate: false,
     };
-    let mut response = ureq::post(&url)
-        .header("Authorization", &format!("Bearer {access_jwt}"))
-        .send_json(&body)
-        .map_err(|e| format!("createRecord request failed: {e}"))?;
+    let mut response = post(
+        &url,
+        authorization,
+        "application/json",
+        &serde_json::to_vec(&body)?,
+    )
+    .map_err(|e| format!("createRecord request failed: {e}"))?;
 
     let result: RecordRef = response
         .body_mut()
@@ -140,7 +230,7 @@ pub fn create_record(
 /// Calls `com.atproto.repo.putRecord`.
 pub fn put_record(
     pds_url: &str,
-    access_jwt: &str,
+    authorization: &Authorization,
     repo: &str,
     collection: &str,
     rkey: &str,
@@ -163,10 +253,13 @@ pub fn put_record(
         record,
         validate: false,
     };
-    let mut response = ureq::post(&url)
-        .header("Authorization", &format!("Bearer {access_jwt}"))
-        .send_json(&body)
-        .map_err(|e| format!("putRecord request failed: {e}"))?;
+    let mut response = post(
+        &url,
+        authorization,
+        "application/json",
+        &serde_json::to_vec(&body)?,
+    )
+    .map_err(|e| format!("putRecord request failed: {e}"))?;
 
     let result: RecordRef = response
         .body_mut()
@@ -176,6 +269,87 @@ pub fn put_record(
     Ok(result)
 }
 
+/// Delete a repository record.
+/// Calls `com.atproto.repo.deleteRecord`.
+pub fn delete_record(
+    pds_url: &str,
+    authorization: &Authorization,
+    repo: &str,
+    collection: &str,
+    rkey: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Serialize)]
+    struct Body<'a> {
+        repo: &'a str,
+        collection: &'a str,
+        rkey: &'a str,
+    }
+
+    let url = format!("{pds_url}/xrpc/com.atproto.repo.deleteRecord");
+    let body = Body {
+        repo,
+        collection,
+        rkey,
+    };
+    post(
+        &url,
+        authorization,
+        "application/json",
+        &serde_json::to_vec(&body)?,
+    )
+    .map_err(|e| format!("deleteRecord request failed: {e}"))?;
+
+    Ok(())
+}
+
+/// Upload a blob, returning the blob reference to put in a record.
+/// Calls `com.atproto.repo.uploadBlob`.
+pub fn upload_blob(
+    pds_url: &str,
+    authorization: &Authorization,
+    bytes: &[u8],
+    mime_type: &str,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    #[derive(Deserialize)]
+    struct Response {
+        blob: serde_json::Value,
+    }
+
+    let url = format!("{pds_url}/xrpc/com.atproto.repo.uploadBlob");
+    let mut response = post(&url, authorization, mime_type, bytes)
+        .map_err(|e| format!("uploadBlob request failed: {e}"))?;
+
+    let result: Response = response
+        .body_mut()
+        .read_json()
+        .map_err(|e| format!("Failed to parse uploadBlob response: {e}"))?;
+
+    Ok(result.blob)
+}
+
+/// Fetch a post with its replies from an AppView.
+/// Calls `app.bsky.feed.getPostThread`.
+pub fn get_post_thread(
+    appview_url: &str,
+    uri: &str,
+    depth: u32,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let url = format!(
+        "{appview_url}/xrpc/app.bsky.feed.getPostThread?uri={}&depth={depth}&parentHeight=0",
+        urlencoding::encode(uri)
+    );
+    let mut response = ureq::get(&url)
+        .call()
+        .map_err(|e| format!("getPostThread request failed: {e}"))?;
+
+    let thread: serde_json::Value = response
+        .body_mut()
+        .read_json()
+        .map_err(|e| format!("Failed to parse getPostThread response: {e}"))?;
+
+    Ok(thread)
+}
+
 #[derive(Debug, Deserialize)]
 struct DidDocumentService {
     id: String,
@@ -190,17 +364,8 @@ struct DidDocument {
     service: Option<Vec<DidDocumentService>>,
 }
 
-/// Resolves an atproto handle to its PDS endpoint
-pub fn resolve_pds_endpoint(handle: &str) -> Result<String, Box<dyn std::error::Error>> {
-    // 1. Resolve handle to DID (via well-known or DNS)
-    let did = resolve_handle_to_did(handle)?;
-
-    // 2. Resolve DID to DID Document and find PDS
-    let pds = resolve_did_to_pds(&did)?;
-    Ok(pds)
-}
-
-fn resolve_handle_to_did(handle: &str) -> Result<String, Box<dyn std::error::Error>> {
+/// Resolves an atproto handle to its DID, via `.well-known` or DNS
+pub fn resolve_handle_to_did(handle: &str) -> Result<String, Box<dyn std::error::Error>> {
     // Try HTTPS .well-known lookup
     let well_known_url = format!("https://{handle}/.well-known/atproto-did");
     if let Ok(mut resp) = ureq::get(&well_known_url).call() {
@@ -245,7 +410,8 @@ fn resolve_handle_to_did(handle: &str) -> Result<String, Box<dyn std::error::Err
     Err(format!("Could not resolve handle {handle} to a DID").into())
 }
 
-fn resolve_did_to_pds(did: &str) -> Result<String, Box<dyn std::error::Error>> {
+/// Resolves a DID to the PDS endpoint of its DID document
+pub fn resolve_did_to_pds(did: &str) -> Result<String, Box<dyn std::error::Error>> {
     let url = if did.starts_with("did:plc:") {
         format!("https://plc.directory/{did}")
     } else if let Some(stripped) = did.strip_prefix("did:web:") {
@@ -0,0 +1,161 @@
+//! Bluesky replies rendered as static comments.
+//!
+//! `marmite atproto comments` fetches the thread of every post announced by
+//! `atproto publish` from the public AppView and saves the replies to
+//! `.marmite-atproto-comments.json`, the build reads them when
+//! `atproto.comments` is on.
+use crate::atproto::client;
+use crate::parser::escape_html;
+use crate::site::Data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// AppView answering `app.bsky.feed.getPostThread` without authentication
+const DEFAULT_APPVIEW_URL: &str = "https://public.api.bsky.app";
+
+/// How deep the nested replies are fetched
+const THREAD_DEPTH: u32 = 10;
+
+/// The Bluesky post announcing a content, with its replies
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct BskyThread {
+    pub uri: String,
+    /// The post on bsky.app, where readers reply
+    pub url: String,
+    pub replies: Vec<BskyReply>,
+}
+
+/// A reply, in the order of the thread with its nesting `depth` starting at 0.
+/// The text fields are HTML-escaped, they are written by anyone on Bluesky.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct BskyReply {
+    pub uri: String,
+    pub url: String,
+    pub handle: String,
+    pub display_name: String,
+    pub avatar: Option<String>,
+    pub text: String,
+    pub created_at: String,
+    pub like_count: u64,
+    pub depth: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct CommentsFile {
+    posts: HashMap<String, Vec<BskyReply>>,
+}
+
+fn comments_path(input_folder: &Path) -> PathBuf {
+    input_folder.join(".marmite-atproto-comments.json")
+}
+
+/// The bsky.app page of a post from its AT-URI
+/// (`at://{did}/app.bsky.feed.post/{rkey}`)
+#[must_use]
+pub fn post_url(at_uri: &str) -> Option<String> {
+    let rest = at_uri.strip_prefix("at://")?;
+    let (did, rkey) = rest.split_once("/app.bsky.feed.post/")?;
+    Some(format!("https://bsky.app/profile/{did}/post/{rkey}"))
+}
+
+/// Flattens the replies of a `getPostThread` thread, oldest first at each
+/// level. Deleted, blocked and hidden replies are left out with their children.
+fn flatten(thread: &serde_json::Value, depth: usize, replies: &mut Vec<BskyReply>) {
+    let Some(children) = thread.get("replies").and_then(|r| r.as_array()) else {
+        return;
+    };
+    let mut children: Vec<&serde_json::Value> = children
+        .iter()
+        .filter(|child| child.get("post").is_some())
+        .collect();
+    children.sort_by_key(|child| {
+        child["post"]["record"]["createdAt"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string()
+    });
+    for child in children {
+        let post = &child["post"];
+        let author = &post["author"];
+        let uri = post["uri"].as_str().unwrap_or_default().to_string();
+        let handle = author["handle"].as_str().unwrap_or_default();
+        let display_name = author["displayName"]
+            .as_str()
+            .filter(|name| !name.is_empty())
+            .unwrap_or(handle);
+        let record = &post["record"];
+        replies.push(BskyReply {
+            url: escape_html(&post_url(&uri).unwrap_or_default()),
+            uri,
+            display_name: escape_html(display_name),
+            handle: escape_html(handle),
+            avatar: author["avatar"].as_str().map(escape_html),
+            text: escape_html(record["text"].as_str().unwrap_or_default()),
+            created_at: escape_html(record["createdAt"].as_str().unwrap_or_default()),
+            like_count: post["likeCount"].as_u64().unwrap_or_default(),
+            depth,
+        });
+        flatten(child, depth + 1, replies);
+    }
+}
+
+/// Replies saved by `marmite atproto comments`, by slug
+#[must_use]
+pub fn load(input_folder: &Path) -> HashMap<String, Vec<BskyReply>> {
+    fs::read_to_string(comments_path(input_folder))
+        .ok()
+        .and_then(|s| serde_json::from_str::<CommentsFile>(&s).ok())
+        .map(|file| file.posts)
+        .unwrap_or_default()
+}
+
+/// Fetches the replies of every announced post and saves them
+pub fn fetch(input_folder: &Path, config_file: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let site_data = Data::from_file(&input_folder.join(config_file));
+    if !site_data
+        .site
+        .atproto
+        .as_ref()
+        .is_some_and(|atproto| atproto.comments)
+    {
+        eprintln!("Warning: atproto.comments is off, the replies are not rendered until it is on");
+    }
+    let appview_url = env::var("ATPROTO_APPVIEW_URL").unwrap_or(DEFAULT_APPVIEW_URL.to_string());
+
+    let mut comments = CommentsFile::default();
+    let mut failed = 0usize;
+    let mut previous = load(input_folder);
+    for (slug, bsky_uri) in crate::atproto::publish::announced(input_folder) {
+        match client::get_post_thread(&appview_url, &bsky_uri, THREAD_DEPTH) {
+            Ok(thread) => {
+                let mut replies = Vec::new();
+                flatten(&thread["thread"], 0, &mut replies);
+                comments.posts.insert(slug, replies);
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("Warning: could not fetch the replies of '{slug}': {e}");
+                // Keep what was fetched before
+                if let Some(replies) = previous.remove(&slug) {
+                    comments.posts.insert(slug, replies);
+                }
+            }
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&comments)?;
+    fs::write(comments_path(input_folder), json)?;
+    let total: usize = comments.posts.values().map(Vec::len).sum();
+    eprintln!(
+        "Fetched {total} replies of {} posts, {failed} failed",
+        comments.posts.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "../tests/atproto_comments.rs"]
+mod tests;
@@ -1,19 +1,48 @@
+//! Saved atproto credentials, by handle.
+//!
+//! The store is `credentials.json` in the marmite config folder, or the file
+//! in `MARMITE_CREDENTIALS_FILE`. When `MARMITE_CREDENTIALS_KEY` is set the
+//! store is encrypted with AES-256-GCM, with a key derived from it by
+//! PBKDF2, so it can be kept with the site and opened in CI.
+use crate::atproto::oauth::OAuthSession;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::env;
 use std::fs;
+use std::num::NonZeroU32;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
 
+/// PBKDF2 iterations of new encrypted stores
+const ITERATIONS: u32 = 600_000;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Credential {
     pub pds_url: String,
     pub identifier: String,
-    pub password: String,
+    /// App password, absent for OAuth credentials
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth: Option<OAuthSession>,
 }
 
 type CredentialsStore = HashMap<String, Credential>;
 
+/// An encrypted store, as written to the file
+#[derive(Debug, Serialize, Deserialize)]
+struct Sealed {
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
 fn credentials_dir() -> PathBuf {
     dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -21,25 +50,99 @@ fn credentials_dir() -> PathBuf {
 }
 
 pub fn credentials_path() -> PathBuf {
-    credentials_dir().join("credentials.json")
+    env::var("MARMITE_CREDENTIALS_FILE").map_or_else(
+        |_| credentials_dir().join("credentials.json"),
+        PathBuf::from,
+    )
+}
+
+fn passphrase() -> Option<String> {
+    env::var("MARMITE_CREDENTIALS_KEY")
+        .ok()
+        .filter(|key| !key.is_empty())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey, String> {
+    let iterations = NonZeroU32::new(iterations).ok_or("Invalid PBKDF2 iterations")?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| "Invalid encryption key")?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Encrypts the JSON of a store
+fn seal(json: &str, passphrase: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; NONCE_LEN];
+    let rng = SystemRandom::new();
+    rng.fill(&mut salt)
+        .map_err(|_| "Could not generate a salt")?;
+    rng.fill(&mut nonce)
+        .map_err(|_| "Could not generate a nonce")?;
+    let key = derive_key(passphrase, &salt, ITERATIONS)?;
+    let mut data = json.as_bytes().to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+        .map_err(|_| "Could not encrypt the credentials")?;
+    Ok(serde_json::to_string_pretty(&Sealed {
+        iterations: ITERATIONS,
+        salt: BASE64_STANDARD.encode(salt),
+        nonce: BASE64_STANDARD.encode(nonce),
+        ciphertext: BASE64_STANDARD.encode(data),
+    })?)
 }
 
-fn load_store() -> CredentialsStore {
+/// Decrypts a store sealed by [`seal`]
+fn open(sealed: &Sealed, passphrase: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let key = derive_key(
+        passphrase,
+        &BASE64_STANDARD.decode(&sealed.salt)?,
+        sealed.iterations,
+    )?;
+    let nonce = Nonce::try_assume_unique_for_key(&BASE64_STANDARD.decode(&sealed.nonce)?)
+        .map_err(|_| "Invalid nonce")?;
+    let mut data = BASE64_STANDARD.decode(&sealed.ciphertext)?;
+    let json = key
+        .open_in_place(nonce, Aad::empty(), &mut data)
+        .map_err(|_| "Could not decrypt the credentials, check MARMITE_CREDENTIALS_KEY")?;
+    Ok(String::from_utf8(json.to_vec())?)
+}
+
+fn load_store() -> Result<CredentialsStore, Box<dyn std::error::Error>> {
     let path = credentials_path();
     if !path.exists() {
-        return HashMap::new();
+        return Ok(HashMap::new());
     }
-    let Ok(content) = fs::read_to_string(&path) else {
-        return HashMap::new();
+    let content = fs::read_to_string(&path)?;
+    let content = match serde_json::from_str::<Sealed>(&content) {
+        Ok(sealed) => {
+            let passphrase = passphrase().ok_or_else(|| {
+                format!(
+                    "{} is encrypted, set MARMITE_CREDENTIALS_KEY to open it",
+                    path.display()
+                )
+            })?;
+            open(&sealed, &passphrase)?
+        }
+        Err(_) => content,
     };
-    serde_json::from_str(&content).unwrap_or_default()
+    Ok(serde_json::from_str(&content).unwrap_or_default())
 }
 
 fn save_store(store: &CredentialsStore) -> Result<(), Box<dyn std::error::Error>> {
-    let dir = credentials_dir();
-    fs::create_dir_all(&dir)?;
     let path = credentials_path();
-    let json = serde_json::to_string_pretty(store)?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut json = serde_json::to_string_pretty(store)?;
+    if let Some(passphrase) = passphrase() {
+        json = seal(&json, &passphrase)?;
+    }
     fs::write(&path, json)?;
     #[cfg(unix)]
     fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
@@ -47,21 +150,24 @@ fn save_store(store: &CredentialsStore) -> Result<(), Box<dyn std::error::Error>
 }
 
 pub fn save(cred: &Credential) -> Result<(), Box<dyn std::error::Error>> {
-    let mut store = load_store();
+    let mut store = load_store()?;
     store.insert(cred.identifier.clone(), cred.clone());
     save_store(&store)
 }
 
-pub fn load(identifier: &str) -> Option<Credential> {
-    let mut store = load_store();
-    store.remove(identifier)
-}
-
-pub fn load_any() -> Option<Credential> {
-    let store = load_store();
-    if store.len() == 1 {
+/// Credentials of a handle, or the only ones saved
+pub fn load(identifier: &str) -> Result<Option<Credential>, Box<dyn std::error::Error>> {
+    let mut store = load_store()?;
+    if let Some(cred) = store.remove(identifier) {
+        return Ok(Some(cred));
+    }
+    Ok(if store.len() == 1 {
         store.into_values().next()
     } else {
         None
-    }
+    })
 }
+
+#[cfg(test)]
+#[path = "../tests/atproto_credentials.rs"]
+mod tests;
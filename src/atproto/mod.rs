@@ -1,6 +1,8 @@
 pub mod auth;
 pub mod client;
+pub mod comments;
 pub mod credentials;
+pub mod oauth;
 pub mod publish;
 
 use crate::cli::{AtprotoCommand, Cli};
@@ -11,9 +13,12 @@ pub fn dispatch(cmd: &AtprotoCommand, args: &Cli) -> Result<(), Box<dyn std::err
         .as_deref()
         .ok_or("Input folder is required for atproto commands")?;
     match cmd {
-        AtprotoCommand::Auth => auth::auth(input_folder, args),
-        AtprotoCommand::Publish { force, dry_run } => {
-            publish::publish(input_folder, *force, *dry_run, &args.config)
-        }
+        AtprotoCommand::Auth { oauth } => auth::auth(input_folder, args, *oauth),
+        AtprotoCommand::Publish {
+            force,
+            dry_run,
+            keep_removed,
+        } => publish::publish(input_folder, *force, *dry_run, *keep_removed, &args.config),
+        AtprotoCommand::Comments => comments::fetch(input_folder, &args.config),
     }
 }
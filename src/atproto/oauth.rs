@@ -0,0 +1,421 @@
+//! atproto OAuth behind `marmite atproto auth --oauth`.
+//!
+//! The authorization server is discovered from the PDS, the authorization
+//! request is pushed (PAR) with a PKCE challenge, and the tokens are bound to
+//! a DPoP key saved with the credentials. Marmite is a native "localhost"
+//! client: there is no client metadata to host, and the browser comes back
+//! to a one-shot server on 127.0.0.1 with the authorization code.
+use base64::prelude::{Engine as _, BASE64_URL_SAFE_NO_PAD};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// Scope giving the same access as an app password
+const SCOPE: &str = "atproto transition:generic";
+
+/// How long the browser has to come back with the authorization code
+const CALLBACK_TIMEOUT: Duration = Duration::from_mins(5);
+
+/// Tokens are refreshed when they expire in less than this many seconds
+const EXPIRY_MARGIN: i64 = 300;
+
+/// OAuth tokens of a PDS account with the DPoP key they are bound to
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OAuthSession {
+    pub issuer: String,
+    pub token_endpoint: String,
+    pub client_id: String,
+    pub did: String,
+    pub access_token: String,
+    /// Single use, replaced by every refresh
+    pub refresh_token: String,
+    /// Unix timestamp
+    pub expires_at: i64,
+    /// PKCS#8 private key, base64url
+    pub dpop_key: String,
+    /// Last DPoP nonce of the authorization server
+    #[serde(default)]
+    pub dpop_nonce: Option<String>,
+}
+
+impl OAuthSession {
+    #[must_use]
+    pub fn expired(&self) -> bool {
+        chrono::Utc::now().timestamp() + EXPIRY_MARGIN >= self.expires_at
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    pushed_authorization_request_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+    token_type: String,
+    expires_in: Option<i64>,
+    sub: String,
+    scope: String,
+}
+
+fn random(len: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut bytes = vec![0u8; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "Could not generate random bytes")?;
+    Ok(bytes)
+}
+
+fn random_string() -> Result<String, Box<dyn std::error::Error>> {
+    Ok(BASE64_URL_SAFE_NO_PAD.encode(random(32)?))
+}
+
+fn sha256_b64(text: &str) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(text.as_bytes()))
+}
+
+/// PKCE S256 challenge of a code verifier
+fn challenge(verifier: &str) -> String {
+    sha256_b64(verifier)
+}
+
+/// P-256 key proving the possession of the tokens (DPoP, RFC 9449)
+pub struct DpopKey {
+    pkcs8: Vec<u8>,
+    pair: EcdsaKeyPair,
+}
+
+impl DpopKey {
+    pub fn generate() -> Result<Self, Box<dyn std::error::Error>> {
+        let document =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+                .map_err(|_| "Could not generate the DPoP key")?;
+        Self::from_pkcs8(document.as_ref())
+    }
+
+    fn from_pkcs8(pkcs8: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let pair = EcdsaKeyPair::from_pkcs8(
+            &ECDSA_P256_SHA256_FIXED_SIGNING,
+            pkcs8,
+            &SystemRandom::new(),
+        )
+        .map_err(|e| format!("Invalid DPoP key: {e}"))?;
+        Ok(Self {
+            pkcs8: pkcs8.to_vec(),
+            pair,
+        })
+    }
+
+    /// The key saved in an [`OAuthSession`]
+    pub fn decode(encoded: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_pkcs8(&BASE64_URL_SAFE_NO_PAD.decode(encoded)?)
+    }
+
+    #[must_use]
+    pub fn encode(&self) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(&self.pkcs8)
+    }
+
+    /// Public key as a JWK
+    #[must_use]
+    pub fn jwk(&self) -> serde_json::Value {
+        // Uncompressed point: 0x04, then x and y
+        let point = self.pair.public_key().as_ref();
+        serde_json::json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": BASE64_URL_SAFE_NO_PAD.encode(&point[1..33]),
+            "y": BASE64_URL_SAFE_NO_PAD.encode(&point[33..65]),
+        })
+    }
+
+    /// Signed proof for a request, with the hash of the access token when
+    /// the request uses one
+    pub fn proof(
+        &self,
+        method: &str,
+        url: &str,
+        nonce: Option<&str>,
+        access_token: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let header = serde_json::json!({"typ": "dpop+jwt", "alg": "ES256", "jwk": self.jwk()});
+        // The proof is for the URL without query and fragment
+        let htu = url.split(['?', '#']).next().unwrap_or(url);
+        let mut claims = serde_json::json!({
+            "jti": random_string()?,
+            "htm": method,
+            "htu": htu,
+            "iat": chrono::Utc::now().timestamp(),
+        });
+        if let Some(nonce) = nonce {
+            claims["nonce"] = nonce.into();
+        }
+        if let Some(token) = access_token {
+            claims["ath"] = sha256_b64(token).into();
+        }
+        let input = format!(
+            "{}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(header.to_string()),
+            BASE64_URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = self
+            .pair
+            .sign(&SystemRandom::new(), input.as_bytes())
+            .map_err(|_| "Could not sign the DPoP proof")?;
+        Ok(format!(
+            "{input}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(signature.as_ref())
+        ))
+    }
+}
+
+fn get_json(url: &str) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let mut response = ureq::get(url)
+        .call()
+        .map_err(|e| format!("Request to {url} failed: {e}"))?;
+    Ok(response.body_mut().read_json()?)
+}
+
+/// Finds the authorization server of a PDS
+fn discover(pds_url: &str) -> Result<ServerMetadata, Box<dyn std::error::Error>> {
+    let resource = get_json(&format!("{pds_url}/.well-known/oauth-protected-resource"))?;
+    let issuer = resource["authorization_servers"][0]
+        .as_str()
+        .ok_or("The PDS does not name an authorization server")?
+        .trim_end_matches('/');
+    let metadata: ServerMetadata = serde_json::from_value(get_json(&format!(
+        "{issuer}/.well-known/oauth-authorization-server"
+    ))?)?;
+    if metadata.issuer.trim_end_matches('/') != issuer {
+        return Err(format!(
+            "The authorization server metadata is for '{}', not '{issuer}'",
+            metadata.issuer
+        )
+        .into());
+    }
+    Ok(metadata)
+}
+
+/// Client id of a localhost client, which carries its own metadata
+fn client_id(redirect_uri: &str) -> String {
+    format!(
+        "http://localhost?redirect_uri={}&scope={}",
+        urlencoding::encode(redirect_uri),
+        urlencoding::encode(SCOPE)
+    )
+}
+
+/// Posts a form to the authorization server with a DPoP proof, retrying
+/// once with the nonce the server asks for
+fn post_form(
+    url: &str,
+    form: &[(&str, &str)],
+    key: &DpopKey,
+    nonce: &mut Option<String>,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    for _ in 0..2 {
+        let proof = key.proof("POST", url, nonce.as_deref(), None)?;
+        let mut response = ureq::post(url)
+            .config()
+            .http_status_as_error(false)
+            .build()
+            .header("DPoP", &proof)
+            .send_form(form.iter().copied())
+            .map_err(|e| format!("Request to {url} failed: {e}"))?;
+        if let Some(value) = response.headers().get("DPoP-Nonce") {
+            *nonce = Some(value.to_str()?.to_string());
+        }
+        let status = response.status().as_u16();
+        let body: serde_json::Value = response.body_mut().read_json().unwrap_or_default();
+        if status < 300 {
+            return Ok(body);
+        }
+        if body["error"] != "use_dpop_nonce" {
+            return Err(format!(
+                "{url} answered {status}: {}",
+                body["error_description"]
+                    .as_str()
+                    .or(body["error"].as_str())
+                    .unwrap_or("no details")
+            )
+            .into());
+        }
+    }
+    Err(format!("{url} keeps asking for a new DPoP nonce").into())
+}
+
+fn parse_tokens(
+    body: serde_json::Value,
+    did: Option<&str>,
+) -> Result<TokenResponse, Box<dyn std::error::Error>> {
+    let tokens: TokenResponse = serde_json::from_value(body)
+        .map_err(|e| format!("Failed to parse the token response: {e}"))?;
+    if !tokens.token_type.eq_ignore_ascii_case("DPoP") {
+        return Err(format!("Expected DPoP tokens, got '{}'", tokens.token_type).into());
+    }
+    if !tokens.scope.split(' ').any(|scope| scope == "atproto") {
+        return Err(format!("The tokens lack the atproto scope: '{}'", tokens.scope).into());
+    }
+    if let Some(did) = did.filter(|did| *did != tokens.sub) {
+        return Err(format!("The tokens are for '{}', not '{did}'", tokens.sub).into());
+    }
+    Ok(tokens)
+}
+
+/// Waits for the browser to come back to the redirect URI, returning the
+/// authorization code
+fn wait_for_code(
+    server: &tiny_http::Server,
+    state: &str,
+    issuer: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    loop {
+        let request = server
+            .recv_timeout(CALLBACK_TIMEOUT)?
+            .ok_or("Timed out waiting for the authorization")?;
+        let Some(query) = request
+            .url()
+            .strip_prefix("/callback?")
+            .map(ToString::to_string)
+        else {
+            let _ = request.respond(tiny_http::Response::empty(404));
+            continue;
+        };
+        let params: std::collections::HashMap<String, String> =
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect();
+        let param = |name: &str| params.get(name).map(String::as_str);
+        let result = if param("state") != Some(state) {
+            Err("The authorization response does not match the request".to_string())
+        } else if let Some(error) = param("error") {
+            Err(format!(
+                "Authorization denied: {}",
+                param("error_description").unwrap_or(error)
+            ))
+        } else if param("iss").is_some_and(|iss| iss.trim_end_matches('/') != issuer) {
+            Err("The authorization response comes from another server".to_string())
+        } else {
+            param("code")
+                .map(ToString::to_string)
+                .ok_or_else(|| "The authorization response has no code".to_string())
+        };
+        let page = match &result {
+            Ok(_) => "Marmite is authorized, you can close this window.".to_string(),
+            Err(e) => e.clone(),
+        };
+        let _ = request.respond(tiny_http::Response::from_string(page));
+        return Ok(result?);
+    }
+}
+
+/// Runs the authorization flow in the browser for a handle whose PDS is
+/// `pds_url`. When `did` is known the tokens must be for that account.
+pub fn authorize(
+    pds_url: &str,
+    handle: &str,
+    did: Option<&str>,
+) -> Result<OAuthSession, Box<dyn std::error::Error>> {
+    let metadata = discover(pds_url)?;
+    let issuer = metadata.issuer.trim_end_matches('/');
+    let server = tiny_http::Server::http("127.0.0.1:0")
+        .map_err(|e| format!("Could not start the callback server: {e}"))?;
+    let address = server
+        .server_addr()
+        .to_ip()
+        .ok_or("The callback server has no IP address")?;
+    let redirect_uri = format!("http://{address}/callback");
+    let client_id = client_id(&redirect_uri);
+
+    let key = DpopKey::generate()?;
+    let verifier = random_string()?;
+    let state = random_string()?;
+    let mut nonce = None;
+    let pushed = post_form(
+        &metadata.pushed_authorization_request_endpoint,
+        &[
+            ("client_id", &client_id),
+            ("response_type", "code"),
+            ("code_challenge", &challenge(&verifier)),
+            ("code_challenge_method", "S256"),
+            ("redirect_uri", &redirect_uri),
+            ("scope", SCOPE),
+            ("state", &state),
+            ("login_hint", handle),
+        ],
+        &key,
+        &mut nonce,
+    )
+    .map_err(|e| format!("Pushed authorization request failed: {e}"))?;
+    let request_uri = pushed["request_uri"]
+        .as_str()
+        .ok_or("The pushed authorization request returned no request_uri")?;
+
+    log::info!(
+        "Open this URL in your browser to authorize marmite:\n{}?client_id={}&request_uri={}",
+        metadata.authorization_endpoint,
+        urlencoding::encode(&client_id),
+        urlencoding::encode(request_uri)
+    );
+    let code = wait_for_code(&server, &state, issuer)?;
+
+    let body = post_form(
+        &metadata.token_endpoint,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", &code),
+            ("redirect_uri", &redirect_uri),
+            ("code_verifier", &verifier),
+            ("client_id", &client_id),
+        ],
+        &key,
+        &mut nonce,
+    )
+    .map_err(|e| format!("Token request failed: {e}"))?;
+    let tokens = parse_tokens(body, did)?;
+    Ok(OAuthSession {
+        issuer: issuer.to_string(),
+        token_endpoint: metadata.token_endpoint,
+        client_id,
+        did: tokens.sub,
+        access_token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+        expires_at: chrono::Utc::now().timestamp() + tokens.expires_in.unwrap_or_default(),
+        dpop_key: key.encode(),
+        dpop_nonce: nonce,
+    })
+}
+
+/// Exchanges the refresh token for new tokens. The old refresh token is
+/// spent, so the session must be saved again right after.
+pub fn refresh(session: &mut OAuthSession) -> Result<(), Box<dyn std::error::Error>> {
+    let key = DpopKey::decode(&session.dpop_key)?;
+    let mut nonce = session.dpop_nonce.clone();
+    let body = post_form(
+        &session.token_endpoint,
+        &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &session.refresh_token),
+            ("client_id", &session.client_id),
+        ],
+        &key,
+        &mut nonce,
+    )?;
+    let tokens = parse_tokens(body, Some(&session.did))?;
+    session.access_token = tokens.access_token;
+    session.refresh_token = tokens.refresh_token;
+    session.expires_at = chrono::Utc::now().timestamp() + tokens.expires_in.unwrap_or_default();
+    session.dpop_nonce = nonce;
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "../tests/atproto_oauth.rs"]
+mod tests;
@@ -1,5 +1,7 @@
+use crate::atproto::auth;
 use crate::atproto::client;
 use crate::atproto::credentials;
+use crate::config::AtprotoConfig;
 use crate::content::Content;
 use crate::re;
 use crate::site::{get_content_folder, Data};
@@ -18,6 +20,9 @@ struct StateEntry {
     content_hash: String,
     at_uri: String,
     last_published: String,
+    /// The Bluesky post announcing the content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bsky_uri: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -70,11 +75,27 @@ fn rkey_from_at_uri(at_uri: &str) -> Option<String> {
         .and_then(|u| u.rkey().map(std::string::ToString::to_string))
 }
 
+/// What publish does with a document, decided for every document before
+/// anything is applied so `--dry-run` shows the same plan
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum PostAction {
-    Published,
-    Updated,
-    Skipped,
-    NoSource,
+    /// Create the document, and the Bluesky announcement when `announce`
+    Publish {
+        announce: bool,
+    },
+    Update {
+        at_uri: String,
+    },
+    Delete {
+        at_uri: String,
+    },
+    Skip,
+}
+
+/// A content to publish with the hash of its source
+struct LocalDocument {
+    content: Content,
+    hash: String,
 }
 
 fn build_record(
@@ -114,101 +135,348 @@ fn build_record(
 
 struct PublishContext<'a> {
     pds_url: &'a str,
-    session: &'a client::Session,
+    did: &'a str,
+    authorization: &'a client::Authorization,
     marmite: &'a crate::config::Marmite,
     publication_uri: &'a str,
     publish_content: bool,
-    force: bool,
-    dry_run: bool,
+    announce_text: Option<&'a str>,
+    content_folder: &'a Path,
 }
 
-fn process_post(
-    post: &Content,
-    state: &mut PublishState,
-    ctx: &PublishContext<'_>,
-) -> Result<PostAction, Box<dyn std::error::Error>> {
-    let source_path = match &post.source_path {
-        Some(p) => p.clone(),
-        None => return Ok(PostAction::NoSource),
-    };
-
-    let raw_bytes = match fs::read(&source_path) {
-        Ok(b) => b,
-        Err(e) => {
-            eprintln!("Warning: could not read {}: {e}", source_path.display());
-            return Ok(PostAction::NoSource);
-        }
-    };
-    let hash = sha256_hex(&raw_bytes);
+/// Slugs of the documents of the publication on the PDS with their AT-URI.
+/// Records of other publications and paths that are not a marmite slug are
+/// left alone.
+fn remote_documents(
+    records: &[client::RecordItem],
+    publication_uri: &str,
+) -> HashMap<String, String> {
+    records
+        .iter()
+        .filter(|record| record.value.get("site").and_then(|s| s.as_str()) == Some(publication_uri))
+        .filter_map(|record| {
+            let path = record.value.get("path")?.as_str()?;
+            let slug = path.strip_prefix('/')?.strip_suffix(".html")?;
+            (!slug.is_empty() && !slug.contains('/'))
+                .then(|| (slug.to_string(), record.uri.clone()))
+        })
+        .collect()
+}
 
-    let existing = state.posts.get(&post.slug);
-    if !ctx.force {
-        if let Some(entry) = existing {
-            if entry.content_hash == hash {
-                return Ok(PostAction::Skipped);
-            }
-        }
+/// Compares the local documents with the records on the PDS. The remote set
+/// is the truth: a state entry whose record is gone is published again, and
+/// records without local content are deleted unless `keep_removed`. New posts
+/// are announced once with `announce`, pages never are.
+fn plan(
+    local: &[LocalDocument],
+    state: &PublishState,
+    remote: &HashMap<String, String>,
+    force: bool,
+    keep_removed: bool,
+    announce: bool,
+) -> Vec<(String, PostAction)> {
+    let mut steps: Vec<(String, PostAction)> = local
+        .iter()
+        .map(|document| {
+            let slug = &document.content.slug;
+            let action = match remote.get(slug) {
+                None => PostAction::Publish {
+                    announce: announce
+                        && document.content.date.is_some()
+                        && state
+                            .posts
+                            .get(slug)
+                            .is_none_or(|entry| entry.bsky_uri.is_none()),
+                },
+                Some(at_uri) => {
+                    let unchanged = state.posts.get(slug).is_some_and(|entry| {
+                        entry.content_hash == document.hash && &entry.at_uri == at_uri
+                    });
+                    if unchanged && !force {
+                        PostAction::Skip
+                    } else {
+                        PostAction::Update {
+                            at_uri: at_uri.clone(),
+                        }
+                    }
+                }
+            };
+            (slug.clone(), action)
+        })
+        .collect();
+
+    if !keep_removed {
+        let mut removed: Vec<(String, PostAction)> = remote
+            .iter()
+            .filter(|(slug, _)| !local.iter().any(|d| &d.content.slug == *slug))
+            .map(|(slug, at_uri)| {
+                (
+                    slug.clone(),
+                    PostAction::Delete {
+                        at_uri: at_uri.clone(),
+                    },
+                )
+            })
+            .collect();
+        removed.sort_by(|a, b| a.0.cmp(&b.0));
+        steps.extend(removed);
     }
+    steps
+}
 
-    let record = build_record(post, ctx.marmite, ctx.publication_uri, ctx.publish_content);
-
-    if ctx.dry_run {
-        let action = if existing.is_some() {
-            "update"
-        } else {
-            "publish"
+/// Number of steps publishing, updating, deleting and skipping documents
+fn tally(steps: &[(String, PostAction)]) -> [usize; 4] {
+    let mut counts = [0; 4];
+    for (_, action) in steps {
+        let index = match action {
+            PostAction::Publish { .. } => 0,
+            PostAction::Update { .. } => 1,
+            PostAction::Delete { .. } => 2,
+            PostAction::Skip => 3,
         };
-        eprintln!("[dry-run] {action}: {}", post.slug);
-        return Ok(PostAction::Skipped);
+        counts[index] += 1;
     }
+    counts
+}
 
-    let (at_uri, action) = if let Some(entry) = existing {
-        match rkey_from_at_uri(&entry.at_uri) {
-            Some(rkey) => {
-                let result = client::put_record(
-                    ctx.pds_url,
-                    &ctx.session.access_jwt,
-                    &ctx.session.did,
-                    "site.standard.document",
-                    &rkey,
-                    &record,
-                )
-                .map_err(|e| format!("Failed to update '{}': {e}", post.slug))?;
-                (result.uri, PostAction::Updated)
-            }
-            None => {
-                return Err(format!(
-                    "Could not parse record key (rkey) from AT-URI '{}' for post '{}'",
-                    entry.at_uri, post.slug
-                )
-                .into());
+fn print_plan(steps: &[(String, PostAction)]) {
+    for (slug, action) in steps {
+        match action {
+            PostAction::Publish { announce } => {
+                eprintln!("  publish: {slug}");
+                if *announce {
+                    eprintln!("  announce: {slug}");
+                }
             }
+            PostAction::Update { .. } => eprintln!("  update: {slug}"),
+            PostAction::Delete { at_uri } => eprintln!("  delete: {slug} ({at_uri})"),
+            PostAction::Skip => {}
         }
-    } else {
-        let result = client::create_record(
-            ctx.pds_url,
-            &ctx.session.access_jwt,
-            &ctx.session.did,
-            "site.standard.document",
-            &record,
-        )
-        .map_err(|e| format!("Failed to publish '{}': {e}", post.slug))?;
-        (result.uri, PostAction::Published)
-    };
+    }
+    let [publish, update, delete, skip] = tally(steps);
+    eprintln!(
+        "Plan: {publish} to publish, {update} to update, {delete} to delete, {skip} unchanged"
+    );
+}
+
+fn rkey(slug: &str, at_uri: &str) -> Result<String, Box<dyn std::error::Error>> {
+    rkey_from_at_uri(at_uri).ok_or_else(|| {
+        format!("Could not parse record key (rkey) from AT-URI '{at_uri}' for '{slug}'").into()
+    })
+}
 
+fn apply_step(
+    document: Option<&LocalDocument>,
+    slug: &str,
+    action: &PostAction,
+    state: &mut PublishState,
+    ctx: &PublishContext<'_>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let collection = "site.standard.document";
+    let at_uri = match (action, document) {
+        (PostAction::Skip, _) => return Ok(()),
+        (PostAction::Delete { at_uri }, _) => {
+            client::delete_record(
+                ctx.pds_url,
+                ctx.authorization,
+                ctx.did,
+                collection,
+                &rkey(slug, at_uri)?,
+            )
+            .map_err(|e| format!("Failed to delete '{slug}': {e}"))?;
+            state.posts.remove(slug);
+            return Ok(());
+        }
+        (PostAction::Publish { .. }, Some(document)) => {
+            let record = build_record(
+                &document.content,
+                ctx.marmite,
+                ctx.publication_uri,
+                ctx.publish_content,
+            );
+            client::create_record(ctx.pds_url, ctx.authorization, ctx.did, collection, &record)
+                .map_err(|e| format!("Failed to publish '{slug}': {e}"))?
+                .uri
+        }
+        (PostAction::Update { at_uri }, Some(document)) => {
+            let record = build_record(
+                &document.content,
+                ctx.marmite,
+                ctx.publication_uri,
+                ctx.publish_content,
+            );
+            client::put_record(
+                ctx.pds_url,
+                ctx.authorization,
+                ctx.did,
+                collection,
+                &rkey(slug, at_uri)?,
+                &record,
+            )
+            .map_err(|e| format!("Failed to update '{slug}': {e}"))?
+            .uri
+        }
+        (_, None) => return Err(format!("No local content for '{slug}'").into()),
+    };
+    let hash = document.map(|d| d.hash.clone()).unwrap_or_default();
+    let bsky_uri = state
+        .posts
+        .get(slug)
+        .and_then(|entry| entry.bsky_uri.clone());
     state.posts.insert(
-        post.slug.clone(),
+        slug.to_string(),
         StateEntry {
             content_hash: hash,
             at_uri,
             last_published: Utc::now().to_rfc3339(),
+            bsky_uri,
         },
     );
 
-    Ok(action)
+    // The document is in the state before announcing, a failed announcement
+    // does not publish it twice
+    if let (PostAction::Publish { announce: true }, Some(document)) = (action, document) {
+        let bsky_uri =
+            announce(document, ctx).map_err(|e| format!("Failed to announce '{slug}': {e}"))?;
+        if let Some(entry) = state.posts.get_mut(slug) {
+            entry.bsky_uri = Some(bsky_uri);
+        }
+    }
+    Ok(())
+}
+
+/// Uploads the card image of a content for the link card. Remote images,
+/// missing files and images over the 1MB limit of Bluesky are left out.
+fn upload_card_image(
+    content: &Content,
+    ctx: &PublishContext<'_>,
+) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error>> {
+    let Some(card_image) = content.card_image.as_deref() else {
+        return Ok(None);
+    };
+    let card_image = card_image.trim_matches('"');
+    let relative = card_image
+        .strip_prefix(ctx.marmite.url.trim_end_matches('/'))
+        .unwrap_or(card_image);
+    if relative.contains("://") {
+        return Ok(None);
+    }
+    let path = ctx.content_folder.join(relative.trim_start_matches('/'));
+    let Some(mime_type) = crate::book::media_type(&path) else {
+        return Ok(None);
+    };
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Warning: could not read {}: {e}", path.display());
+            return Ok(None);
+        }
+    };
+    if bytes.len() > 1_000_000 {
+        eprintln!(
+            "Warning: {} is over 1MB, the link card of '{}' has no image",
+            path.display(),
+            content.slug
+        );
+        return Ok(None);
+    }
+    let blob = client::upload_blob(ctx.pds_url, ctx.authorization, &bytes, mime_type)?;
+    Ok(Some(blob))
+}
+
+/// The `app.bsky.feed.post` announcing a content with a link card
+fn announcement_record(
+    content: &Content,
+    marmite: &crate::config::Marmite,
+    text: Option<&str>,
+    thumb: Option<serde_json::Value>,
+) -> serde_json::Value {
+    let url = format!(
+        "{}/{}.html",
+        marmite.url.trim_end_matches('/'),
+        content.slug
+    );
+    // Posts are limited to 300 graphemes
+    let text: String = text
+        .unwrap_or("{title}")
+        .replace("{title}", &content.title)
+        .replace("{url}", &url)
+        .chars()
+        .take(300)
+        .collect();
+
+    let mut external = serde_json::json!({
+        "uri": url,
+        "title": content.title,
+        "description": content.description.as_deref().unwrap_or_default().trim_matches('"'),
+    });
+    if let Some(thumb) = thumb {
+        external["thumb"] = thumb;
+    }
+    let mut record = serde_json::json!({
+        "$type": "app.bsky.feed.post",
+        "text": text,
+        "createdAt": Utc::now().to_rfc3339(),
+        "langs": [content.language.as_deref().unwrap_or(&marmite.language)],
+        "embed": {
+            "$type": "app.bsky.embed.external",
+            "external": external,
+        },
+    });
+    // Links in the text are only clickable with a facet
+    if let Some(start) = text.find(&url) {
+        record["facets"] = serde_json::json!([{
+            "index": {"byteStart": start, "byteEnd": start + url.len()},
+            "features": [{"$type": "app.bsky.richtext.facet#link", "uri": url}],
+        }]);
+    }
+    record
+}
+
+/// Creates the Bluesky post announcing a content, returning its AT-URI
+fn announce(
+    document: &LocalDocument,
+    ctx: &PublishContext<'_>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let thumb = upload_card_image(&document.content, ctx)?;
+    let record = announcement_record(&document.content, ctx.marmite, ctx.announce_text, thumb);
+    let result = client::create_record(
+        ctx.pds_url,
+        ctx.authorization,
+        ctx.did,
+        "app.bsky.feed.post",
+        &record,
+    )?;
+    Ok(result.uri)
+}
+
+/// Slugs of the announced contents with the AT-URI of their Bluesky post
+pub fn announced(input_folder: &Path) -> Vec<(String, String)> {
+    let mut announced: Vec<(String, String)> = load_state(input_folder)
+        .posts
+        .into_iter()
+        .filter_map(|(slug, entry)| Some((slug, entry.bsky_uri?)))
+        .collect();
+    announced.sort();
+    announced
 }
 
-fn collect_publishable_posts(input_folder: &Path, config_path: &Path) -> Vec<Content> {
+/// Whether a content is published: posts of the configured streams, all but
+/// `draft` when none is set, and pages when `publish_pages` is on
+fn is_publishable(content: &Content, atproto: &AtprotoConfig) -> bool {
+    match content.stream.as_deref() {
+        // Pages have no date and no stream
+        None => content.date.is_none() && atproto.publish_pages,
+        Some(stream) if atproto.streams.is_empty() => stream != "draft",
+        Some(stream) => atproto.streams.iter().any(|s| s == stream),
+    }
+}
+
+fn collect_publishable(
+    input_folder: &Path,
+    config_path: &Path,
+    atproto: &AtprotoConfig,
+) -> Vec<LocalDocument> {
     let site_data = Data::from_file(config_path);
     let content_dir = get_content_folder(&site_data.site, input_folder);
     let fragments = HashMap::new();
@@ -233,7 +501,20 @@ fn collect_publishable_posts(input_folder: &Path, config_path: &Path) -> Vec<Con
             )
             .ok()
         })
-        .filter(|post| post.date.is_some() && post.stream.as_deref() != Some("draft"))
+        .filter(|content| is_publishable(content, atproto))
+        .filter_map(|content| {
+            let path = content.source_path.as_ref()?;
+            match fs::read(path) {
+                Ok(bytes) => Some(LocalDocument {
+                    hash: sha256_hex(&bytes),
+                    content,
+                }),
+                Err(e) => {
+                    eprintln!("Warning: could not read {}: {e}", path.display());
+                    None
+                }
+            }
+        })
         .collect()
 }
 
@@ -241,6 +522,7 @@ pub fn publish(
     input_folder: &Path,
     force: bool,
     dry_run: bool,
+    keep_removed: bool,
     config_file: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let config_path = input_folder.join(config_file);
@@ -270,87 +552,77 @@ pub fn publish(
         .ok_or("No publication found. Add atproto.publication_uri to marmite.yaml.")?;
 
     // 2. Load credentials
-    let cred = credentials::load(handle)
-        .or_else(credentials::load_any)
-        .ok_or_else(|| {
-            format!(
-                "No credentials found for '{handle}'.\n\
+    let mut cred = credentials::load(handle)?.ok_or_else(|| {
+        format!(
+            "No credentials found for '{handle}'.\n\
                  Run: marmite atproto auth\n\
-                 (set ATPROTO_APP_PASSWORD env var first)"
-            )
-        })?;
+                 (set ATPROTO_APP_PASSWORD env var first, or add --oauth)"
+        )
+    })?;
 
     // 3. Authenticate
     let pds_url = env::var("ATPROTO_PDS_URL").unwrap_or(cred.pds_url.clone());
-    let session = client::create_session(&pds_url, &cred.identifier, &cred.password)
-        .map_err(|e| format!("Authentication failed: {e}"))?;
+    let (did, authorization) =
+        auth::login(&pds_url, &mut cred).map_err(|e| format!("Authentication failed: {e}"))?;
 
-    // 4. Load state
+    // 4. Compare the local content with the records on the PDS
     let mut state = load_state(input_folder);
+    let records = client::list_records(&pds_url, &did, "site.standard.document")
+        .map_err(|e| format!("Failed to list the records on the PDS: {e}"))?;
+    let remote = remote_documents(&records, publication_uri);
+    let local = collect_publishable(input_folder, &config_path, atproto);
+    let steps = plan(
+        &local,
+        &state,
+        &remote,
+        force,
+        keep_removed,
+        atproto.announce,
+    );
 
-    // Auto-sync if state is empty
-    if state.posts.is_empty() {
-        eprintln!("State file is empty, syncing from PDS...");
-        if let Ok(records) = client::list_records(&pds_url, &session.did, "site.standard.document")
-        {
-            for record in records {
-                if let Some(path_val) = record.value.get("path") {
-                    if let Some(path_str) = path_val.as_str() {
-                        let slug = path_str
-                            .trim_start_matches('/')
-                            .trim_end_matches(".html")
-                            .to_string();
-                        state.posts.insert(
-                            slug,
-                            StateEntry {
-                                content_hash: String::new(), // Leave empty to force an update on first run
-                                at_uri: record.uri.clone(),
-                                last_published: Utc::now().to_rfc3339(),
-                            },
-                        );
-                    }
-                }
-            }
-            eprintln!("Synced {} posts from PDS.", state.posts.len());
-        } else {
-            eprintln!("Warning: Failed to sync state from PDS.");
-        }
+    print_plan(&steps);
+    if dry_run {
+        eprintln!("[dry-run] done — no changes made");
+        return Ok(());
     }
 
-    // 5. Collect publishable posts
-    let posts = collect_publishable_posts(input_folder, &config_path);
-
-    let mut published = 0usize;
-    let mut updated = 0usize;
-    let mut skipped = 0usize;
-
+    // 5. Apply the plan, saving the state even when a step fails
     let ctx = PublishContext {
         pds_url: &pds_url,
-        session: &session,
+        did: &did,
+        authorization: &authorization,
         marmite,
         publication_uri,
         publish_content: atproto.publish_content,
-        force,
-        dry_run,
+        announce_text: atproto.announce_text.as_deref(),
+        content_folder: &get_content_folder(marmite, input_folder),
     };
-
-    for post in &posts {
-        match process_post(post, &mut state, &ctx)? {
-            PostAction::Published => published += 1,
-            PostAction::Updated => updated += 1,
-            PostAction::Skipped | PostAction::NoSource => skipped += 1,
+    let mut result = Ok(());
+    for (slug, action) in &steps {
+        let document = local.iter().find(|d| &d.content.slug == slug);
+        result = apply_step(document, slug, action, &mut state, &ctx);
+        if result.is_err() {
+            break;
         }
     }
-
-    if !dry_run {
-        save_state(input_folder, &state)?;
+    if result.is_ok() {
+        // Entries of content that is gone and has no record left
+        state.posts.retain(|slug, _| {
+            local.iter().any(|d| &d.content.slug == slug)
+                || (keep_removed && remote.contains_key(slug))
+        });
     }
+    save_state(input_folder, &state)?;
+    result?;
 
-    if dry_run {
-        eprintln!("[dry-run] done — no changes made");
-    } else {
-        eprintln!("Published {published}, updated {updated}, skipped {skipped} posts");
-    }
+    let [published, updated, deleted, skipped] = tally(&steps);
+    eprintln!(
+        "Published {published}, updated {updated}, deleted {deleted}, skipped {skipped} documents"
+    );
 
     Ok(())
 }
+
+#[cfg(test)]
+#[path = "../tests/atproto_publish.rs"]
+mod tests;
@@ -0,0 +1,812 @@
+//! Books behind `marmite <folder> export <format>`.
+//!
+//! Collects the posts of a series, in the order of the series page, or of a
+//! tag, oldest first, and writes them as an EPUB 3 file or as a single HTML
+//! page to print or save as PDF. The images of the media folder are embedded
+//! in both and the first `banner_image` of the chapters becomes the cover.
+use crate::cli::{Cli, ExportArgs, ExportFormat};
+use crate::content::Content;
+use crate::parser::{escape_html, get_table_of_contents_from_html};
+use crate::site::{collect_content, collect_content_fragments, get_content_folder, Data};
+use crate::slugify::slugify;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use chrono::NaiveDateTime;
+use log::warn;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Style shared by the EPUB chapters and the print page
+const BOOK_CSS: &str = "body { font-family: serif; line-height: 1.5; }
+h1, h2, h3, h4, h5, h6 { font-family: sans-serif; line-height: 1.2; }
+img, svg, video { max-width: 100%; height: auto; }
+pre { white-space: pre-wrap; word-wrap: break-word; font-size: 0.85em; }
+code { font-family: monospace; }
+blockquote { margin-left: 1em; padding-left: 1em; border-left: 3px solid #ccc; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; }
+.cover { text-align: center; }
+.cover img { max-height: 60vh; }
+";
+
+/// Added to [`BOOK_CSS`] in the print page
+const PRINT_CSS: &str = "@page { margin: 2cm; }
+body { max-width: 45em; margin: 0 auto; }
+.cover, .toc, .chapter { break-after: page; }
+h1, h2, h3, h4, h5, h6 { break-after: avoid; }
+pre, blockquote, figure, img, table { break-inside: avoid; }
+a { color: inherit; }
+";
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// The contents of a book and its metadata
+#[derive(Debug, Clone)]
+pub struct Book {
+    pub title: String,
+    pub slug: String,
+    pub description: Option<String>,
+    pub language: String,
+    pub authors: Vec<String>,
+    pub publisher: String,
+    pub identifier: String,
+    /// Latest date of the chapters
+    pub modified: NaiveDateTime,
+    /// `src` of the cover image, as used in the content
+    pub cover: Option<String>,
+    pub chapters: Vec<Content>,
+}
+
+/// A media file embedded in the book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// Path inside the book, `images/{name}`
+    pub href: String,
+    pub source: PathBuf,
+    pub media_type: &'static str,
+}
+
+/// Finds the images of the book and gives each one a unique name
+pub struct Images<'a> {
+    media: HashMap<String, PathBuf>,
+    content_folder: &'a Path,
+    site_url: &'a str,
+    pub embedded: Vec<Image>,
+}
+
+impl<'a> Images<'a> {
+    pub fn new(content_folder: &'a Path, media_path: &str, site_url: &'a str) -> Self {
+        Self {
+            media: media_files(content_folder, media_path),
+            content_folder,
+            site_url,
+            embedded: Vec::new(),
+        }
+    }
+
+    /// Embeds the file of a `src`, returning the image or `None` for remote
+    /// and unknown files, which are left as they are
+    pub fn add(&mut self, src: &str) -> Option<&Image> {
+        let source = self.resolve(src)?;
+        let media_type = media_type(&source)?;
+        if let Some(index) = self.embedded.iter().position(|i| i.source == source) {
+            return self.embedded.get(index);
+        }
+        let name = source.file_name()?.to_string_lossy().to_string();
+        let (stem, extension) = name.rsplit_once('.').unwrap_or((&name, ""));
+        let mut href = format!("images/{}.{extension}", slugify(stem));
+        let mut counter = 1;
+        while self.embedded.iter().any(|i| i.href == href) {
+            counter += 1;
+            href = format!("images/{}-{counter}.{extension}", slugify(stem));
+        }
+        self.embedded.push(Image {
+            href,
+            source,
+            media_type,
+        });
+        self.embedded.last()
+    }
+
+    fn resolve(&self, src: &str) -> Option<PathBuf> {
+        let site_url = self.site_url.trim_end_matches('/');
+        let path = if !site_url.is_empty() && src.starts_with(site_url) {
+            &src[site_url.len()..]
+        } else if src.contains("://") || src.starts_with("//") || src.starts_with("data:") {
+            return None;
+        } else {
+            src
+        };
+        let path = path.split(['?', '#']).next()?.trim_start_matches('/');
+        let path = urlencoding::decode(path).map_or_else(|_| path.to_string(), |p| p.to_string());
+        if let Some(file) = self.media.get(&path) {
+            return Some(file.clone());
+        }
+        let file = self.content_folder.join(&path);
+        (!path.contains("..") && file.is_file()).then_some(file)
+    }
+}
+
+/// Source file of each media path of the output, like `media/photo.png`,
+/// the same way the build copies the media folders
+fn media_files(content_folder: &Path, media_path: &str) -> HashMap<String, PathBuf> {
+    let mut files = HashMap::new();
+    let mut add = |folder: &Path, prefix: &str| {
+        for entry in WalkDir::new(folder).into_iter().filter_map(Result::ok) {
+            if entry.file_type().is_file() {
+                if let Ok(relative) = entry.path().strip_prefix(folder) {
+                    let relative = relative.to_string_lossy().replace('\\', "/");
+                    files.insert(format!("{prefix}/{relative}"), entry.into_path());
+                }
+            }
+        }
+    };
+    add(&content_folder.join(media_path), media_path);
+    // content/{folder}/media/ is copied to media/{folder}/
+    for entry in WalkDir::new(content_folder)
+        .min_depth(2)
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if !entry.file_type().is_dir() || entry.file_name() != media_path {
+            continue;
+        }
+        if let Some(folder) = path
+            .parent()
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str())
+        {
+            add(path, &format!("{media_path}/{folder}"));
+        }
+    }
+    files
+}
+
+pub fn media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "avif" => "image/avif",
+        _ => return None,
+    })
+}
+
+/// Picks the chapters of a series, or of a tag, from the site
+pub fn select(site_data: &Data, series: Option<&str>, tag: Option<&str>) -> Result<Book, String> {
+    let find = |map: &HashMap<String, Vec<Content>>, wanted: &str| {
+        map.iter()
+            .find(|(name, _)| *name == wanted)
+            .or_else(|| {
+                map.iter()
+                    .find(|(name, _)| slugify(name) == slugify(wanted))
+            })
+            .map(|(name, contents)| (name.clone(), contents.clone()))
+    };
+    let site = &site_data.site;
+    let (kind, name, title, description, chapters) = if let Some(wanted) = series {
+        let (name, chapters) = find(&site_data.series.map, wanted)
+            .ok_or_else(|| format!("No series named '{wanted}'"))?;
+        let config = site.series.get(&name);
+        let title = config.map_or_else(|| name.clone(), |c| c.display_name.clone());
+        let description = config.and_then(|c| c.description.clone());
+        ("series", name, title, description, chapters)
+    } else if let Some(wanted) = tag {
+        let (name, mut chapters) =
+            find(&site_data.tag.map, wanted).ok_or_else(|| format!("No tag named '{wanted}'"))?;
+        // Tag pages list the newest first, a book reads from the oldest
+        chapters.sort_by_key(|content| content.date);
+        ("tag", name.clone(), name, None, chapters)
+    } else {
+        return Err("Pass --series or --tag to choose the chapters".to_string());
+    };
+
+    let slug = slugify(&name);
+    let mut authors = Vec::new();
+    for author in chapters.iter().flat_map(|content| &content.authors) {
+        let author = site
+            .authors
+            .get(author)
+            .map_or_else(|| author.clone(), |a| a.name.clone());
+        if !authors.contains(&author) {
+            authors.push(author);
+        }
+    }
+    let language = chapters
+        .iter()
+        .find_map(|content| content.language.clone())
+        .unwrap_or_else(|| site.language.clone());
+    let identifier = if site.url.is_empty() {
+        format!("urn:marmite:{kind}:{slug}")
+    } else {
+        format!("{}/{kind}-{slug}.html", site.url.trim_end_matches('/'))
+    };
+    Ok(Book {
+        title,
+        description,
+        language: if language.is_empty() {
+            "en".to_string()
+        } else {
+            language
+        },
+        authors,
+        publisher: site.name.clone(),
+        identifier,
+        modified: chapters
+            .iter()
+            .filter_map(|content| content.date)
+            .max()
+            .unwrap_or_default(),
+        cover: chapters
+            .iter()
+            .find_map(|content| content.banner_image.clone()),
+        slug,
+        chapters,
+    })
+}
+
+/// Headings of a chapter as (level, id, title), read from its table of contents
+fn headings(html: &str) -> Vec<(usize, String, String)> {
+    let toc = get_table_of_contents_from_html(html);
+    let re = Regex::new(r##"<ul>|</ul>|<li><a href="#([^"]*)">(.*?)</a></li>"##)
+        .expect("Table of contents regex should compile");
+    let mut level: usize = 0;
+    let mut headings = Vec::new();
+    for cap in re.captures_iter(&toc) {
+        match &cap[0] {
+            "<ul>" => level += 1,
+            "</ul>" => level = level.saturating_sub(1),
+            _ => headings.push((level, cap[1].to_string(), cap[2].to_string())),
+        }
+    }
+    headings
+}
+
+/// Nested `<ol>` of (level, href, title) entries, an entry deeper than the
+/// previous one is nested a single step whatever the gap between the levels
+fn nested_list(entries: &[(usize, String, String)]) -> String {
+    let mut html = String::from("<ol>\n");
+    // Levels of the open items, from the outermost
+    let mut open: Vec<usize> = Vec::new();
+    for (level, href, title) in entries {
+        match open.last() {
+            Some(last) if level > last => html.push_str("\n<ol>\n"),
+            Some(_) => {
+                html.push_str("</li>\n");
+                open.pop();
+                while open.last().is_some_and(|parent| parent >= level) {
+                    html.push_str("</ol>\n</li>\n");
+                    open.pop();
+                }
+            }
+            None => {}
+        }
+        let _ = write!(html, "<li><a href=\"{href}\">{title}</a>");
+        open.push(*level);
+    }
+    if open.pop().is_some() {
+        html.push_str("</li>\n");
+    }
+    for _ in open {
+        html.push_str("</ol>\n</li>\n");
+    }
+    html.push_str("</ol>\n");
+    html
+}
+
+/// Entries of the table of contents of the book, a chapter and the headings
+/// below its title
+fn toc_entries(
+    book: &Book,
+    href: impl Fn(&Content, Option<&str>) -> String,
+) -> Vec<(usize, String, String)> {
+    let mut entries = Vec::new();
+    for chapter in &book.chapters {
+        entries.push((0, href(chapter, None), escape_html(&chapter.title)));
+        for (level, id, title) in headings(&chapter.html) {
+            if level > 1 {
+                entries.push((level - 1, href(chapter, Some(&id)), title));
+            }
+        }
+    }
+    entries
+}
+
+/// The html of a chapter with its title, as the content page shows it
+fn chapter_html(content: &Content) -> String {
+    if content.html.trim_start().starts_with("<h1") {
+        content.html.clone()
+    } else {
+        format!("<h1>{}</h1>\n{}", escape_html(&content.title), content.html)
+    }
+}
+
+/// Points the links to other chapters of the book to `href(slug, anchor)`
+/// and the other links to the site to its URL
+fn rewrite_links(
+    html: &str,
+    book: &Book,
+    site_url: &str,
+    href: impl Fn(&str, Option<&str>) -> String,
+) -> String {
+    let re =
+        Regex::new(r##"href="([^"#:]*\.html)(?:#([^"]*))?""##).expect("Link regex should compile");
+    re.replace_all(html, |caps: &Captures| {
+        let path = caps[1].trim_start_matches("./").trim_start_matches('/');
+        let anchor = caps.get(2).map(|m| m.as_str());
+        let slug = path.trim_end_matches(".html");
+        if book.chapters.iter().any(|chapter| chapter.slug == slug) {
+            format!("href=\"{}\"", href(slug, anchor))
+        } else if site_url.is_empty() {
+            caps[0].to_string()
+        } else {
+            let anchor = anchor.map(|a| format!("#{a}")).unwrap_or_default();
+            format!("href=\"{}/{path}{anchor}\"", site_url.trim_end_matches('/'))
+        }
+    })
+    .into_owned()
+}
+
+/// Replaces the `src` of the embedded images with `src(image)`
+fn rewrite_images(html: &str, images: &mut Images, src: impl Fn(&Image) -> String) -> String {
+    let re = Regex::new(r#"(<(?:img|source|video|audio)\b[^>]*?\ssrc=")([^"]+)(")"#)
+        .expect("Image regex should compile");
+    let srcset = Regex::new(r#"\s(?:srcset|sizes)="[^"]*""#).expect("Srcset regex should compile");
+    let html = re.replace_all(html, |caps: &Captures| match images.add(&caps[2]) {
+        Some(image) => format!("{}{}{}", &caps[1], src(image), &caps[3]),
+        None => caps[0].to_string(),
+    });
+    // Variants of the images are not embedded
+    srcset.replace_all(&html, "").into_owned()
+}
+
+/// Character of the HTML entities that XML does not define
+fn named_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "nbsp" => '\u{a0}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "mdash" => '—',
+        "ndash" => '–',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "middot" => '·',
+        "times" => '×',
+        "deg" => '°',
+        "rarr" => '→',
+        "larr" => '←',
+        _ => return None,
+    })
+}
+
+/// Makes the HTML markdown renders well-formed XHTML, closing the void
+/// elements and replacing the entities XML does not define
+pub fn to_xhtml(html: &str) -> String {
+    let void = Regex::new(
+        r"(?i)<(area|base|br|col|embed|hr|img|input|link|meta|source|track|wbr)\b([^>]*?)\s*/?>",
+    )
+    .expect("Void element regex should compile");
+    let entity = Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[A-Za-z][A-Za-z0-9]*);|&")
+        .expect("Entity regex should compile");
+    let html = void.replace_all(html, "<$1$2 />");
+    entity
+        .replace_all(&html, |caps: &Captures| {
+            match caps.get(1).map(|m| m.as_str()) {
+                Some(name)
+                    if name.starts_with('#')
+                        || matches!(name, "amp" | "lt" | "gt" | "quot" | "apos") =>
+                {
+                    caps[0].to_string()
+                }
+                Some(name) => named_entity(name).map_or_else(
+                    || format!("&amp;{name};"),
+                    |c| format!("&#{};", u32::from(c)),
+                ),
+                None => "&amp;".to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Error of the first tag that is not well-formed, readers refuse such files
+fn xml_error(xhtml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xhtml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Eof) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(format!("{e} at byte {}", reader.error_position())),
+        }
+    }
+}
+
+fn xhtml_page(title: &str, language: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{language}" lang="{language}">
+<head>
+<meta charset="UTF-8" />
+<title>{}</title>
+<link rel="stylesheet" type="text/css" href="style.css" />
+</head>
+<body>
+{body}
+</body>
+</html>
+"#,
+        escape_html(title)
+    )
+}
+
+fn chapter_file(index: usize) -> String {
+    format!("chapter-{}.xhtml", index + 1)
+}
+
+/// Writes the book as an EPUB 3 file
+pub fn write_epub(book: &Book, images: &mut Images, path: &Path) -> Result<(), String> {
+    let file_of = |slug: &str| {
+        book.chapters
+            .iter()
+            .position(|chapter| chapter.slug == slug)
+            .map(chapter_file)
+            .unwrap_or_default()
+    };
+    let remote =
+        Regex::new(r#"\ssrc="(?:https?:)?//"#).expect("Remote source regex should compile");
+    let mut chapters = Vec::new();
+    for (index, chapter) in book.chapters.iter().enumerate() {
+        let html = rewrite_links(
+            &chapter_html(chapter),
+            book,
+            images.site_url,
+            |slug, anchor| {
+                let anchor = anchor.map(|a| format!("#{a}")).unwrap_or_default();
+                format!("{}{anchor}", file_of(slug))
+            },
+        );
+        let html = rewrite_images(&html, images, |image| image.href.clone());
+        let xhtml = xhtml_page(&chapter.title, &book.language, &to_xhtml(&html));
+        if let Some(error) = xml_error(&xhtml) {
+            warn!(
+                "'{}' is not well-formed XHTML, some readers may not open it: {error}",
+                chapter.slug
+            );
+        }
+        let mut properties = Vec::new();
+        if html.contains("<svg") {
+            properties.push("svg");
+        }
+        if remote.is_match(&html) {
+            properties.push("remote-resources");
+        }
+        chapters.push((chapter_file(index), xhtml, properties.join(" ")));
+    }
+    let cover = book
+        .cover
+        .as_deref()
+        .and_then(|cover| images.add(cover))
+        .cloned();
+
+    let entries = toc_entries(book, |chapter, id| {
+        let anchor = id.map(|id| format!("#{id}")).unwrap_or_default();
+        format!("{}{anchor}", file_of(&chapter.slug))
+    });
+    let nav = xhtml_page(
+        &book.title,
+        &book.language,
+        &format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n{}</nav>",
+            escape_html(&book.title),
+            to_xhtml(&nested_list(&entries))
+        ),
+    );
+
+    let opf = package_opf(book, &chapters, &images.embedded, cover.as_ref());
+
+    let mut files: Vec<(String, Vec<u8>)> = vec![
+        ("META-INF/container.xml".to_string(), CONTAINER_XML.into()),
+        ("OEBPS/content.opf".to_string(), opf.into_bytes()),
+        ("OEBPS/nav.xhtml".to_string(), nav.into_bytes()),
+        ("OEBPS/style.css".to_string(), BOOK_CSS.into()),
+    ];
+    if let Some(cover) = &cover {
+        let body = format!(
+            "<section class=\"cover\" epub:type=\"cover\">\n<img src=\"{}\" alt=\"{}\" />\n</section>",
+            escape_html(&cover.href),
+            escape_html(&book.title)
+        );
+        files.push((
+            "OEBPS/cover.xhtml".to_string(),
+            xhtml_page(&book.title, &book.language, &body).into_bytes(),
+        ));
+    }
+    for (file, xhtml, _) in chapters {
+        files.push((format!("OEBPS/{file}"), xhtml.into_bytes()));
+    }
+    for image in &images.embedded {
+        let data =
+            fs::read(&image.source).map_err(|e| format!("{}: {e}", image.source.display()))?;
+        files.push((format!("OEBPS/{}", image.href), data));
+    }
+
+    write_zip(path, &files)
+}
+
+/// Package document listing the metadata, the files and the reading order
+fn package_opf(
+    book: &Book,
+    chapters: &[(String, String, String)],
+    images: &[Image],
+    cover: Option<&Image>,
+) -> String {
+    let mut metadata = format!(
+        "    <dc:identifier id=\"book-id\">{}</dc:identifier>\n    <dc:title>{}</dc:title>\n    <dc:language>{}</dc:language>\n",
+        escape_html(&book.identifier),
+        escape_html(&book.title),
+        escape_html(&book.language)
+    );
+    for author in &book.authors {
+        let _ = writeln!(
+            metadata,
+            "    <dc:creator>{}</dc:creator>",
+            escape_html(author)
+        );
+    }
+    if let Some(description) = &book.description {
+        let _ = writeln!(
+            metadata,
+            "    <dc:description>{}</dc:description>",
+            escape_html(description)
+        );
+    }
+    if !book.publisher.is_empty() {
+        let _ = writeln!(
+            metadata,
+            "    <dc:publisher>{}</dc:publisher>",
+            escape_html(&book.publisher)
+        );
+    }
+    let _ = writeln!(
+        metadata,
+        "    <meta property=\"dcterms:modified\">{}</meta>",
+        book.modified.format("%Y-%m-%dT%H:%M:%SZ")
+    );
+
+    let mut manifest = String::from(
+        "    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n    <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::new();
+    if cover.is_some() {
+        manifest.push_str(
+            "    <item id=\"cover\" href=\"cover.xhtml\" media-type=\"application/xhtml+xml\"/>\n",
+        );
+        spine.push_str("    <itemref idref=\"cover\"/>\n");
+    }
+    spine.push_str("    <itemref idref=\"nav\"/>\n");
+    for (index, (file, _, properties)) in chapters.iter().enumerate() {
+        let properties = if properties.is_empty() {
+            String::new()
+        } else {
+            format!(" properties=\"{properties}\"")
+        };
+        let _ = writeln!(
+            manifest,
+            "    <item id=\"chapter-{}\" href=\"{file}\" media-type=\"application/xhtml+xml\"{properties}/>",
+            index + 1
+        );
+        let _ = writeln!(spine, "    <itemref idref=\"chapter-{}\"/>", index + 1);
+    }
+    for (index, image) in images.iter().enumerate() {
+        let properties = if cover == Some(image) {
+            " properties=\"cover-image\""
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            manifest,
+            "    <item id=\"image-{}\" href=\"{}\" media-type=\"{}\"{properties}/>",
+            index + 1,
+            escape_html(&image.href),
+            image.media_type
+        );
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+{metadata}  </metadata>
+  <manifest>
+{manifest}  </manifest>
+  <spine>
+{spine}  </spine>
+</package>
+"#,
+        escape_html(&book.language)
+    )
+}
+
+/// Zips the files after the uncompressed `mimetype`, which comes first so
+/// readers can identify the file
+fn write_zip(path: &Path, files: &[(String, Vec<u8>)]) -> Result<(), String> {
+    let write = || -> zip::result::ZipResult<()> {
+        let mut zip = ZipWriter::new(fs::File::create(path)?);
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(b"application/epub+zip")?;
+        for (name, data) in files {
+            zip.start_file(name.as_str(), deflated)?;
+            zip.write_all(data)?;
+        }
+        zip.finish()?;
+        Ok(())
+    };
+    write().map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// Writes the book as a single HTML page with the images inlined, ready to
+/// print or save as PDF from a browser
+pub fn write_print(book: &Book, images: &mut Images, path: &Path) -> Result<(), String> {
+    let ids = Regex::new(r#"\sid="([^"]*)""#).expect("Id regex should compile");
+    let anchors = Regex::new(r##"href="#([^"]*)""##).expect("Anchor regex should compile");
+    let inline = |image: &Image| {
+        fs::read(&image.source).map_or_else(
+            |_| image.href.clone(),
+            |data| {
+                format!(
+                    "data:{};base64,{}",
+                    image.media_type,
+                    BASE64_STANDARD.encode(data)
+                )
+            },
+        )
+    };
+
+    let mut chapters = String::new();
+    for chapter in &book.chapters {
+        // Headings of different chapters share ids, they are prefixed by the slug
+        let slug = &chapter.slug;
+        let html = chapter_html(chapter);
+        let html = ids.replace_all(&html, format!(" id=\"{slug}-$1\"").as_str());
+        let html = anchors.replace_all(&html, format!("href=\"#{slug}-$1\"").as_str());
+        let html = rewrite_links(&html, book, images.site_url, |slug, anchor| {
+            anchor.map_or_else(|| format!("#{slug}"), |a| format!("#{slug}-{a}"))
+        });
+        let html = rewrite_images(&html, images, inline);
+        let _ = write!(
+            chapters,
+            "<article class=\"chapter\" id=\"{slug}\">\n{html}\n</article>\n"
+        );
+    }
+
+    let mut cover = String::new();
+    if let Some(image) = book.cover.as_deref().and_then(|c| images.add(c)).cloned() {
+        let _ = writeln!(cover, "<img src=\"{}\" alt=\"\">", inline(&image));
+    }
+    let _ = writeln!(cover, "<h1>{}</h1>", escape_html(&book.title));
+    if let Some(description) = &book.description {
+        let _ = writeln!(cover, "<p>{}</p>", escape_html(description));
+    }
+    if !book.authors.is_empty() {
+        let _ = writeln!(cover, "<p>{}</p>", escape_html(&book.authors.join(", ")));
+    }
+    let entries = toc_entries(book, |chapter, id| {
+        id.map_or_else(
+            || format!("#{}", chapter.slug),
+            |id| format!("#{}-{id}", chapter.slug),
+        )
+    });
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="{language}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+{BOOK_CSS}{PRINT_CSS}</style>
+</head>
+<body>
+<section class="cover">
+{cover}</section>
+<nav class="toc">
+{toc}</nav>
+{chapters}</body>
+</html>
+"#,
+        language = escape_html(&book.language),
+        title = escape_html(&book.title),
+        toc = nested_list(&entries),
+    );
+    fs::write(path, html).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+pub fn run(export_args: &ExportArgs, args: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let input_folder = args
+        .input_folder
+        .as_deref()
+        .ok_or("Input folder is required for export")?;
+    let mut site_data = Data::from_file(&input_folder.join(&args.config));
+    site_data
+        .site
+        .override_from_cli_args(&std::sync::Arc::new(args.clone()));
+    let content_folder = get_content_folder(&site_data.site, input_folder);
+    let fragments = collect_content_fragments(&content_folder);
+    let folder_defaults = crate::site::load_folder_frontmatter(&content_folder);
+    collect_content(
+        &content_folder,
+        &mut site_data,
+        &fragments,
+        None,
+        &folder_defaults,
+    )?;
+    site_data.sort_all();
+
+    let mut book = select(
+        &site_data,
+        export_args.series.as_deref(),
+        export_args.tag.as_deref(),
+    )?;
+    if let Some(title) = &export_args.title {
+        book.title.clone_from(title);
+    }
+    let extension = match export_args.format {
+        ExportFormat::Epub => "epub",
+        ExportFormat::Print => "html",
+    };
+    let output = export_args
+        .output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(format!("{}.{extension}", book.slug)));
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut images = Images::new(
+        &content_folder,
+        &site_data.site.media_path,
+        &site_data.site.url,
+    );
+    match export_args.format {
+        ExportFormat::Epub => write_epub(&book, &mut images, &output)?,
+        ExportFormat::Print => write_print(&book, &mut images, &output)?,
+    }
+    println!(
+        "Wrote '{}' with {} chapter(s) and {} image(s) to {}",
+        book.title,
+        book.chapters.len(),
+        images.embedded.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "tests/book.rs"]
+mod tests;
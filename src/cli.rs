@@ -1,5 +1,5 @@
 #![allow(clippy::struct_excessive_bools)]
-use clap::{Args, Parser, Subcommand};
+use clap::{builder::PossibleValuesParser, Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 /// atproto / standard.site subcommands
@@ -7,20 +7,59 @@ use std::path::PathBuf;
 pub enum AtprotoCommand {
     /// Authenticate with your atproto PDS using app-password credentials.
     ///
-    /// Reads atproto.handle from marmite.yaml and `ATPROTO_APP_PASSWORD` env var.
+    /// Reads atproto.handle from marmite.yaml and `ATPROTO_APP_PASSWORD` env var,
+    /// or authorizes marmite in the browser with `--oauth`.
     /// If no publication exists, prints the config needed in marmite.yaml.
-    Auth,
+    Auth {
+        /// Authorize with atproto OAuth instead of an app password
+        #[arg(long)]
+        oauth: bool,
+    },
 
     /// Publish posts to atproto as site.standard.document records.
+    ///
+    /// Compares the content with the records on the PDS, publishing new
+    /// content, updating changed content and deleting the records of removed
+    /// or drafted content.
     Publish {
         /// Force re-publish all posts, ignoring change detection
         #[arg(long, short)]
         force: bool,
 
-        /// Preview what would be published without making any changes
+        /// Print the plan without making any changes
+        #[arg(long, short = 'n')]
+        dry_run: bool,
+
+        /// Keep the records of removed or drafted content instead of deleting them
+        #[arg(long)]
+        keep_removed: bool,
+    },
+
+    /// Fetch the Bluesky replies to the announced posts, rendered as comments
+    /// by the next build when atproto.comments is on.
+    Comments,
+}
+
+/// Webmention subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum WebmentionCommand {
+    /// Send Webmentions to the links of new and changed posts.
+    ///
+    /// Discovers the endpoint of each linked page and remembers what was sent
+    /// in `.marmite-webmention-state.json`, so unchanged posts are skipped.
+    Send {
+        /// Print the links to notify without sending anything
         #[arg(long, short = 'n')]
         dry_run: bool,
+
+        /// Notify every link again, ignoring the saved state
+        #[arg(long, short)]
+        force: bool,
     },
+
+    /// Fetch the mentions received by the site from webmention.source,
+    /// rendered by the next build.
+    Fetch,
 }
 
 /// Global CLI Subcommands
@@ -29,6 +68,118 @@ pub enum CliSubcommand {
     /// Manage atproto / standard.site integration
     #[command(subcommand)]
     Atproto(AtprotoCommand),
+
+    /// Check the external links of every content, caching the results
+    CheckLinks(CheckLinksArgs),
+
+    /// Export a series or a tag as an EPUB book or a single page to print
+    Export(ExportArgs),
+
+    /// Convert a WordPress export, a Jekyll site or a Hugo site to marmite content
+    Import(ImportArgs),
+
+    /// Check the content for missing descriptions, images without alt text and
+    /// other rules set in the `lint` section of marmite.yaml
+    Lint,
+
+    /// Send Webmentions for the posts and fetch the ones received
+    #[command(subcommand)]
+    Webmention(WebmentionCommand),
+}
+
+/// Options of `check-links`, unset options use `link_checker` from marmite.yaml
+#[derive(Args, Debug, Clone)]
+pub struct CheckLinksArgs {
+    /// Check every link, ignoring the cached results
+    #[arg(long, short)]
+    pub force: bool,
+
+    /// Regex of URLs to skip, can be repeated
+    #[arg(long, value_name = "PATTERN")]
+    pub ignore: Vec<String>,
+
+    /// Maximum number of links checked at the same time
+    #[arg(long)]
+    pub concurrency: Option<usize>,
+
+    /// Seconds to wait for each request
+    #[arg(long, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+}
+
+/// Formats written by `export`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// EPUB 3 book
+    Epub,
+    /// Single HTML page to print or save as PDF
+    Print,
+}
+
+/// Options of `export`
+#[derive(Args, Debug, Clone)]
+pub struct ExportArgs {
+    /// Format of the book
+    pub format: ExportFormat,
+
+    /// Series to export, in the order of its page
+    #[arg(long, conflicts_with = "tag", required_unless_present = "tag")]
+    pub series: Option<String>,
+
+    /// Tag to export, oldest post first
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    /// File to write [default: {slug}.epub or {slug}.html]
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+
+    /// Title of the book [default: display name of the series, or the tag]
+    #[arg(long)]
+    pub title: Option<String>,
+}
+
+/// Sources read by `import`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// WordPress WXR export file
+    Wordpress,
+    /// Jekyll site folder, with `_posts`
+    Jekyll,
+    /// Hugo site folder, or its `content` folder
+    Hugo,
+}
+
+/// Options of `import`
+#[derive(Args, Debug, Clone)]
+pub struct ImportArgs {
+    /// Format of the source
+    pub format: ImportFormat,
+
+    /// WXR file, or folder of the Jekyll or Hugo site
+    pub source: PathBuf,
+
+    /// Stream of the imported posts
+    #[arg(long)]
+    pub stream: Option<String>,
+
+    /// Keep the media links pointing to the old site
+    #[arg(long)]
+    pub no_media: bool,
+
+    /// Replace content files that already exist
+    #[arg(long, short)]
+    pub force: bool,
+
+    /// List what would be imported without writing anything
+    #[arg(long, short = 'n')]
+    pub dry_run: bool,
+}
+
+/// Formats accepted by `--report`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
 }
 
 /// Command Line Argument Parser for Marmite CLI
@@ -96,6 +247,10 @@ pub struct Cli {
     #[arg(long)]
     pub force: bool,
 
+    /// Only parse changed files and render the outputs that depend on them
+    #[arg(long)]
+    pub incremental: bool,
+
     /// List all available shortcodes
     #[arg(long)]
     pub shortcodes: bool,
@@ -104,6 +259,28 @@ pub struct Cli {
     #[arg(long)]
     pub show_urls: bool,
 
+    /// Publish content scheduled for the future, for previews
+    #[arg(long)]
+    pub include_future: bool,
+
+    /// Show the scheduled and expired content left out of the site
+    #[arg(long)]
+    pub show_scheduled: bool,
+
+    /// Print the build diagnostics (broken links, slug collisions, ...) to stdout
+    #[arg(long, value_name = "FORMAT")]
+    pub report: Option<ReportFormat>,
+
+    /// Fail the build when diagnostics with these codes are found,
+    /// `warnings` denies all of them
+    #[arg(
+        long,
+        value_name = "CODE",
+        value_delimiter = ',',
+        value_parser = PossibleValuesParser::new(crate::diagnostics::DENY_CODES)
+    )]
+    pub deny: Vec<String>,
+
     /// Print the embedded agent skill document (SKILL.md) to stdout
     #[arg(long)]
     pub skill: bool,
@@ -244,6 +421,10 @@ pub struct Configuration {
     #[arg(long)]
     pub json_feed: Option<bool>,
 
+    /// Generate Atom Feed [default: false or from config file]
+    #[arg(long)]
+    pub atom_feed: Option<bool>,
+
     /// Show next and previous links in posts [default: true or from config file]
     #[arg(long)]
     pub show_next_prev_links: Option<bool>,
@@ -273,6 +454,10 @@ pub struct Configuration {
     #[arg(long)]
     pub publish_urls_json: Option<bool>,
 
+    /// Generate the static JSON API in `json_api.path` [default: false or from config file]
+    #[arg(long)]
+    pub json_api: Option<bool>,
+
     /// Enable shortcodes processing [default: true or from config file]
     #[arg(long)]
     pub enable_shortcodes: Option<bool>,
@@ -305,4 +490,8 @@ pub struct Configuration {
     /// Render mermaid diagrams to SVG at build time [default: true or from config file]
     #[arg(long)]
     pub native_mermaid_render: Option<bool>,
+
+    /// Reject changes made through the dev server API [default: false or from config file]
+    #[arg(long)]
+    pub read_only: Option<bool>,
 }
@@ -1,9 +1,10 @@
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
-use std::{collections::HashMap, path::Path, process, sync::Arc};
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 use crate::cli::Cli;
+use crate::error::Error;
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub enum ImageProvider {
@@ -86,6 +87,284 @@ impl Default for CodeHighlightConfig {
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct SearchWeights {
+    #[serde(default = "default_search_weight_title")]
+    pub title: f64,
+    #[serde(default = "default_search_weight_tags")]
+    pub tags: f64,
+    #[serde(default = "default_search_weight_description")]
+    pub description: f64,
+    #[serde(default = "default_search_weight_body")]
+    pub body: f64,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        Self {
+            title: default_search_weight_title(),
+            tags: default_search_weight_tags(),
+            description: default_search_weight_description(),
+            body: default_search_weight_body(),
+        }
+    }
+}
+
+/// What each feed item carries as its content
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedContent {
+    /// The full rendered HTML of the content
+    #[default]
+    Full,
+    /// The description, falling back to the excerpt when there is none
+    Summary,
+    /// The first `excerpt_length` characters of the text
+    Excerpt,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct FeedConfig {
+    /// Maximum number of items in each feed
+    #[serde(default = "default_feed_items")]
+    pub items: usize,
+    #[serde(default)]
+    pub content: FeedContent,
+    /// Number of characters of the text used by the `excerpt` content
+    #[serde(default = "default_feed_excerpt_length")]
+    pub excerpt_length: usize,
+    /// Content tagged with any of these tags is left out of the feeds
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+    /// Include when the content was last updated, taken from the file modification time
+    #[serde(default)]
+    pub updated: bool,
+    /// Overrides for the feeds of a stream, keyed by stream name
+    #[serde(default)]
+    pub streams: HashMap<String, FeedOverrides>,
+    /// Overrides for the feeds of a series, keyed by series name
+    #[serde(default)]
+    pub series: HashMap<String, FeedOverrides>,
+    /// Overrides for the feeds of a tag, keyed by tag name
+    #[serde(default)]
+    pub tags: HashMap<String, FeedOverrides>,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            items: default_feed_items(),
+            content: FeedContent::default(),
+            excerpt_length: default_feed_excerpt_length(),
+            exclude_tags: Vec::new(),
+            updated: false,
+            streams: HashMap::new(),
+            series: HashMap::new(),
+            tags: HashMap::new(),
+        }
+    }
+}
+
+/// Options of `marmite check-links`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct LinkCheckerConfig {
+    /// Maximum number of links checked at the same time
+    #[serde(default = "default_link_checker_concurrency")]
+    pub concurrency: usize,
+    /// Seconds to wait for each request
+    #[serde(default = "default_link_checker_timeout")]
+    pub timeout: u64,
+    /// Milliseconds between two requests to the same domain
+    #[serde(default = "default_link_checker_domain_delay")]
+    pub domain_delay: u64,
+    /// Hours a cached result is used before the link is checked again
+    #[serde(default = "default_link_checker_cache_max_age")]
+    pub cache_max_age: u64,
+    /// Regex of URLs that are not checked
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Cache file, relative to the input folder
+    #[serde(default = "default_link_checker_cache_file")]
+    pub cache_file: String,
+}
+
+impl Default for LinkCheckerConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: default_link_checker_concurrency(),
+            timeout: default_link_checker_timeout(),
+            domain_delay: default_link_checker_domain_delay(),
+            cache_max_age: default_link_checker_cache_max_age(),
+            ignore: Vec::new(),
+            cache_file: default_link_checker_cache_file(),
+        }
+    }
+}
+
+/// Static JSON API written with the site, see `json_api.rs`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct JsonApiConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Folder of the API inside the output folder
+    #[serde(default = "default_json_api_path")]
+    pub path: String,
+    /// Summaries in each page of the post and page listings
+    #[serde(default = "default_json_api_per_page")]
+    pub per_page: usize,
+    /// Include the markdown source in the JSON of each content
+    #[serde(default = "default_true")]
+    pub markdown: bool,
+}
+
+impl Default for JsonApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_json_api_path(),
+            per_page: default_json_api_per_page(),
+            markdown: true,
+        }
+    }
+}
+
+/// Level of a rule of `marmite lint`
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Off,
+    Warning,
+    /// Fails `marmite lint`
+    Error,
+}
+
+/// Rules of `marmite lint`, see `lint.rs`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct LintConfig {
+    /// Level of each rule by name, rules not listed keep their default
+    #[serde(default)]
+    pub rules: HashMap<String, LintLevel>,
+    /// Longest title accepted by the `long-title` rule
+    #[serde(default = "default_lint_max_title_length")]
+    pub max_title_length: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            rules: HashMap::new(),
+            max_title_length: default_lint_max_title_length(),
+        }
+    }
+}
+
+/// Access control and history of the `/__marmite__/` API of the dev server
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ServerConfig {
+    /// Token required by the API, also read from `MARMITE_API_TOKEN`
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// `user:password` required by every request to the server
+    #[serde(default)]
+    pub basic_auth: Option<String>,
+    /// Reject every change made through the API
+    #[serde(default)]
+    pub read_only: bool,
+    /// Origins besides the server itself allowed to change content,
+    /// e.g. `https://editor.example.com`
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Log of the changes made through the API, relative to the input
+    /// folder, empty disables it
+    #[serde(default = "default_server_audit_log")]
+    pub audit_log: String,
+    /// Revisions kept for each content changed through the API, 0 disables
+    /// the history
+    #[serde(default = "default_server_history_limit")]
+    pub history_limit: usize,
+    /// Commit every change made through the API to the git repository of
+    /// the site
+    #[serde(default)]
+    pub git_commit: bool,
+    /// Largest request accepted by the media upload endpoint, in bytes
+    #[serde(default = "default_server_max_upload_size")]
+    pub max_upload_size: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            api_token: None,
+            basic_auth: None,
+            read_only: false,
+            allowed_origins: Vec::new(),
+            audit_log: default_server_audit_log(),
+            history_limit: default_server_history_limit(),
+            git_commit: false,
+            max_upload_size: default_server_max_upload_size(),
+        }
+    }
+}
+
+/// Options of a single feed, unset options use the global `feed` options
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
+pub struct FeedOverrides {
+    pub items: Option<usize>,
+    pub content: Option<FeedContent>,
+    pub excerpt_length: Option<usize>,
+    pub exclude_tags: Option<Vec<String>>,
+    pub updated: Option<bool>,
+}
+
+/// Resolved options used to generate one feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedSettings {
+    pub items: usize,
+    pub content: FeedContent,
+    pub excerpt_length: usize,
+    pub exclude_tags: Vec<String>,
+    pub updated: bool,
+}
+
+impl FeedConfig {
+    /// Settings for feeds without overrides, e.g. author and archive feeds
+    pub fn settings(&self) -> FeedSettings {
+        self.resolve(None)
+    }
+
+    pub fn stream_settings(&self, stream: &str) -> FeedSettings {
+        self.resolve(self.streams.get(stream))
+    }
+
+    pub fn series_settings(&self, series: &str) -> FeedSettings {
+        self.resolve(self.series.get(series))
+    }
+
+    /// Tags are matched by their slug, so `Rust` and `rust` share the same overrides
+    pub fn tag_settings(&self, tag: &str) -> FeedSettings {
+        let slug = crate::slugify::slugify(tag);
+        self.resolve(self.tags.get(tag).or_else(|| {
+            self.tags
+                .iter()
+                .find(|(key, _)| crate::slugify::slugify(key) == slug)
+                .map(|(_, overrides)| overrides)
+        }))
+    }
+
+    fn resolve(&self, overrides: Option<&FeedOverrides>) -> FeedSettings {
+        let overrides = overrides.cloned().unwrap_or_default();
+        FeedSettings {
+            items: overrides.items.unwrap_or(self.items),
+            content: overrides.content.unwrap_or(self.content),
+            excerpt_length: overrides.excerpt_length.unwrap_or(self.excerpt_length),
+            exclude_tags: overrides
+                .exclude_tags
+                .unwrap_or_else(|| self.exclude_tags.clone()),
+            updated: overrides.updated.unwrap_or(self.updated),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
 pub struct ParserOptions {
     #[serde(default)]
@@ -206,6 +485,10 @@ pub struct Marmite {
     #[serde(default = "default_search_match_count")]
     pub search_match_count: usize,
 
+    /// Relevance of each field when ranking search results
+    #[serde(default)]
+    pub search_weights: SearchWeights,
+
     #[serde(default = "default_enable_related_content")]
     pub enable_related_content: bool,
 
@@ -257,12 +540,39 @@ pub struct Marmite {
     #[serde(default)]
     pub series: HashMap<String, SeriesConfig>,
 
+    /// Groupings declared by the site besides tags, series and streams, keyed by name
+    #[serde(default)]
+    pub taxonomies: HashMap<String, TaxonomyConfig>,
+
     #[serde(default)]
     pub toc: bool,
 
     #[serde(default)]
     pub json_feed: bool,
 
+    #[serde(default)]
+    pub atom_feed: bool,
+
+    /// Size, content and filters of the RSS, Atom and JSON feeds
+    #[serde(default)]
+    pub feed: FeedConfig,
+
+    /// Options of `marmite check-links`
+    #[serde(default)]
+    pub link_checker: LinkCheckerConfig,
+
+    /// Authentication and read-only mode of the dev server API
+    #[serde(default)]
+    pub server: ServerConfig,
+
+    /// Static JSON API of the content
+    #[serde(default)]
+    pub json_api: JsonApiConfig,
+
+    /// Rules checked by `marmite lint`
+    #[serde(default)]
+    pub lint: LintConfig,
+
     #[serde(default = "default_true")]
     pub show_next_prev_links: bool,
 
@@ -340,6 +650,14 @@ pub struct Marmite {
     /// atproto standard.site publishing configuration
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub atproto: Option<AtprotoConfig>,
+
+    /// Webmention endpoint and the options of `marmite webmention`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webmention: Option<WebmentionConfig>,
+
+    /// ActivityPub actors, outboxes and WebFinger written with the site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activitypub: Option<ActivityPubConfig>,
 }
 
 fn default_true() -> bool {
@@ -347,6 +665,7 @@ fn default_true() -> bool {
 }
 
 impl Marmite {
+    #[must_use]
     pub fn new() -> Self {
         Marmite {
             name: default_name(),
@@ -387,33 +706,60 @@ impl Marmite {
     }
 
     /// Get the resolved templates path based on theme configuration
-    pub fn get_templates_path(&self, input_folder: &Path) -> std::path::PathBuf {
-        if let Some(theme) = &self.theme {
-            let theme_path = input_folder.join(theme);
-            if !theme_path.exists() {
-                error!("Theme folder '{}' does not exist", theme_path.display());
-                process::exit(1);
-            }
-            theme_path.join(&self.templates_path)
-        } else {
-            input_folder.join(&self.templates_path)
-        }
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ThemeNotFound`] when the theme folder does not exist.
+    pub fn get_templates_path(&self, input_folder: &Path) -> Result<std::path::PathBuf, Error> {
+        Ok(self
+            .get_theme_path(input_folder)?
+            .join(&self.templates_path))
     }
 
     /// Get the resolved static path based on theme configuration
-    pub fn get_static_path(&self, input_folder: &Path) -> std::path::PathBuf {
-        if let Some(theme) = &self.theme {
-            let theme_path = input_folder.join(theme);
-            if !theme_path.exists() {
-                error!("Theme folder '{}' does not exist", theme_path.display());
-                process::exit(1);
-            }
-            theme_path.join(&self.static_path)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ThemeNotFound`] when the theme folder does not exist.
+    pub fn get_static_path(&self, input_folder: &Path) -> Result<std::path::PathBuf, Error> {
+        Ok(self.get_theme_path(input_folder)?.join(&self.static_path))
+    }
+
+    /// The theme folder, or the input folder when no theme is set
+    fn get_theme_path(&self, input_folder: &Path) -> Result<std::path::PathBuf, Error> {
+        let Some(theme) = &self.theme else {
+            return Ok(input_folder.to_path_buf());
+        };
+        let theme_path = input_folder.join(theme);
+        if theme_path.exists() {
+            Ok(theme_path)
         } else {
-            input_folder.join(&self.static_path)
+            Err(Error::ThemeNotFound(theme_path))
         }
     }
 
+    /// Checks that the custom taxonomies do not write over the built-in
+    /// pages and stay in the output folder
+    ///
+    /// # Errors
+    ///
+    /// Returns the problem of the first invalid taxonomy.
+    pub fn validate_taxonomies(&self) -> Result<(), String> {
+        self.taxonomy_list().iter().try_for_each(Taxonomy::validate)
+    }
+
+    /// Custom taxonomies with their defaults applied, sorted by name
+    #[must_use]
+    pub fn taxonomy_list(&self) -> Vec<Taxonomy> {
+        let mut taxonomies: Vec<Taxonomy> = self
+            .taxonomies
+            .iter()
+            .map(|(name, config)| Taxonomy::new(name, config))
+            .collect();
+        taxonomies.sort_by(|a, b| a.name.cmp(&b.name));
+        taxonomies
+    }
+
     #[allow(clippy::too_many_lines)]
     pub fn override_from_cli_args(&mut self, cli_args: &Arc<Cli>) {
         if let Some(name) = &cli_args.configuration.name {
@@ -481,6 +827,9 @@ impl Marmite {
         if let Some(json_feed) = cli_args.configuration.json_feed {
             self.json_feed = json_feed;
         }
+        if let Some(atom_feed) = cli_args.configuration.atom_feed {
+            self.atom_feed = atom_feed;
+        }
         if let Some(show_next_prev_links) = cli_args.configuration.show_next_prev_links {
             self.show_next_prev_links = show_next_prev_links;
         }
@@ -507,6 +856,9 @@ impl Marmite {
         if let Some(publish_urls_json) = cli_args.configuration.publish_urls_json {
             self.publish_urls_json = publish_urls_json;
         }
+        if let Some(json_api) = cli_args.configuration.json_api {
+            self.json_api.enabled = json_api;
+        }
         if let Some(enable_shortcodes) = cli_args.configuration.enable_shortcodes {
             self.enable_shortcodes = enable_shortcodes;
         }
@@ -531,6 +883,14 @@ impl Marmite {
         if let Some(native_mermaid_render) = cli_args.configuration.native_mermaid_render {
             self.native_mermaid_render = native_mermaid_render;
         }
+        if let Some(read_only) = cli_args.configuration.read_only {
+            self.server.read_only = read_only;
+        }
+        if let Ok(api_token) = std::env::var("MARMITE_API_TOKEN") {
+            if !api_token.is_empty() {
+                self.server.api_token = Some(api_token);
+            }
+        }
     }
 }
 
@@ -553,6 +913,115 @@ pub struct SeriesConfig {
     pub description: Option<String>,
 }
 
+/// A custom taxonomy, unset options are derived from its name
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TaxonomyConfig {
+    /// Frontmatter key holding the terms [default: name]
+    pub key: Option<String>,
+    /// Term pages are written to `{prefix}-{term}.html` [default: name]
+    pub prefix: Option<String>,
+    /// The index page is written to `{index}.html` [default: `{prefix}-index`]
+    pub index: Option<String>,
+    /// Title of the index page [default: name]
+    pub title: Option<String>,
+    /// Title of each term page, `$term` is replaced by the term [default: `$term`]
+    pub content_title: Option<String>,
+    #[serde(default = "default_taxonomy_template")]
+    pub template: String,
+    #[serde(default = "default_taxonomy_index_template")]
+    pub index_template: String,
+    /// Generate feeds for each term
+    #[serde(default = "default_true")]
+    pub feeds: bool,
+}
+
+/// Prefixes of the built-in term pages, `tag-rust.html`, `archive-2024.html`...
+const RESERVED_TAXONOMY_PREFIXES: [&str; 5] = ["tag", "author", "series", "stream", "archive"];
+
+/// Built-in index and list pages
+const RESERVED_TAXONOMY_INDEXES: [&str; 9] = [
+    "index",
+    "pages",
+    "tags",
+    "authors",
+    "series",
+    "streams",
+    "archive",
+    "languages",
+    "404",
+];
+
+/// A custom taxonomy with its defaults applied
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Taxonomy {
+    pub name: String,
+    pub key: String,
+    pub prefix: String,
+    pub index: String,
+    pub title: String,
+    pub content_title: String,
+    pub template: String,
+    pub index_template: String,
+    pub feeds: bool,
+}
+
+impl Taxonomy {
+    #[must_use]
+    pub fn new(name: &str, config: &TaxonomyConfig) -> Self {
+        let prefix = config.prefix.clone().unwrap_or_else(|| name.to_string());
+        Self {
+            name: name.to_string(),
+            key: config.key.clone().unwrap_or_else(|| name.to_string()),
+            index: config
+                .index
+                .clone()
+                .unwrap_or_else(|| format!("{prefix}-index")),
+            prefix,
+            title: config.title.clone().unwrap_or_else(|| name.to_string()),
+            content_title: config
+                .content_title
+                .clone()
+                .unwrap_or_else(|| "$term".to_string()),
+            template: config.template.clone(),
+            index_template: config.index_template.clone(),
+            feeds: config.feeds,
+        }
+    }
+
+    /// The prefix and the index must be slugs, so the pages are written in
+    /// the output folder, and must not be the ones of the built-in pages
+    fn validate(&self) -> Result<(), String> {
+        for (field, value) in [("prefix", &self.prefix), ("index", &self.index)] {
+            if value.is_empty() || crate::slugify::slugify(value) != *value {
+                return Err(format!(
+                    "taxonomy '{}': {field} '{value}' must be a slug like '{}'",
+                    self.name,
+                    crate::slugify::slugify(value)
+                ));
+            }
+        }
+        if RESERVED_TAXONOMY_PREFIXES.contains(&self.prefix.as_str()) {
+            return Err(format!(
+                "taxonomy '{}': prefix '{}' is used by the built-in pages",
+                self.name, self.prefix
+            ));
+        }
+        if RESERVED_TAXONOMY_INDEXES.contains(&self.index.as_str()) {
+            return Err(format!(
+                "taxonomy '{}': index '{}' is used by the built-in pages",
+                self.name, self.index
+            ));
+        }
+        Ok(())
+    }
+
+    /// Name (without extension) of the pages of `term`
+    #[must_use]
+    pub fn term_filename(&self, term: &str) -> String {
+        format!("{}-{}", self.prefix, crate::slugify::slugify(term))
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct LanguageConfig {
     #[serde(alias = "name")]
@@ -565,6 +1034,7 @@ pub struct FileMapping {
     pub dest: String,
 }
 
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
 pub struct AtprotoConfig {
     /// atproto handle (e.g. myhandle.bsky.social)
@@ -574,6 +1044,93 @@ pub struct AtprotoConfig {
     /// Include markdown body as textContent in document records [default: true]
     #[serde(default = "default_true")]
     pub publish_content: bool,
+    /// Publish pages as documents too [default: false]
+    #[serde(default)]
+    pub publish_pages: bool,
+    /// Streams whose posts are published, all but `draft` when empty
+    #[serde(default)]
+    pub streams: Vec<String>,
+    /// Announce new posts on Bluesky with a link card [default: false]
+    #[serde(default)]
+    pub announce: bool,
+    /// Text of the announcement, `{title}` and `{url}` are replaced
+    /// [default: the title]
+    pub announce_text: Option<String>,
+    /// Render the Bluesky replies fetched by `marmite atproto comments`
+    /// [default: false]
+    #[serde(default)]
+    pub comments: bool,
+}
+
+/// Options of `marmite webmention`, see `webmention.rs`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct WebmentionConfig {
+    /// Endpoint receiving the mentions of the site, advertised with
+    /// `<link rel="webmention">` (e.g. the one of webmention.io)
+    pub endpoint: Option<String>,
+    /// URL or file (relative to the input folder) of the JF2 feed read by
+    /// `marmite webmention fetch`
+    pub source: Option<String>,
+    /// Seconds to wait for each request
+    #[serde(default = "default_webmention_timeout")]
+    pub timeout: u64,
+    /// Regex of links that are not notified
+    #[serde(default)]
+    pub ignore: Vec<String>,
+}
+
+impl Default for WebmentionConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            source: None,
+            timeout: default_webmention_timeout(),
+            ignore: Vec::new(),
+        }
+    }
+}
+
+/// Type of the ActivityPub objects published for the posts
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityPubObject {
+    /// The title, the description and the link, shown in full by Mastodon
+    #[default]
+    Note,
+    /// The title and the full HTML, shown as a link by most servers
+    Article,
+}
+
+/// ActivityPub documents generated with the site, see `activitypub.rs`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ActivityPubConfig {
+    /// Authors published as actors, the first one answers the static
+    /// `.well-known/webfinger` (default: every author)
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub object_type: ActivityPubObject,
+    /// Posts listed in each outbox
+    #[serde(default = "default_activitypub_outbox_items")]
+    pub outbox_items: usize,
+    /// Folder of the output holding the documents
+    #[serde(default = "default_activitypub_path")]
+    pub path: String,
+    /// Accept follows in the inboxes when running `--serve`
+    #[serde(default)]
+    pub inbox: bool,
+}
+
+impl Default for ActivityPubConfig {
+    fn default() -> Self {
+        Self {
+            authors: Vec::new(),
+            object_type: ActivityPubObject::default(),
+            outbox_items: default_activitypub_outbox_items(),
+            path: default_activitypub_path(),
+            inbox: false,
+        }
+    }
 }
 
 /// Generates a default configuration file
@@ -712,6 +1269,22 @@ fn default_search_match_count() -> usize {
     3
 }
 
+fn default_search_weight_title() -> f64 {
+    10.0
+}
+
+fn default_search_weight_tags() -> f64 {
+    5.0
+}
+
+fn default_search_weight_description() -> f64 {
+    3.0
+}
+
+fn default_search_weight_body() -> f64 {
+    1.0
+}
+
 fn default_language() -> String {
     "en".to_string()
 }
@@ -806,6 +1379,78 @@ fn default_gallery_thumb_size() -> u32 {
     50
 }
 
+fn default_feed_items() -> usize {
+    15
+}
+
+fn default_feed_excerpt_length() -> usize {
+    300
+}
+
+fn default_taxonomy_template() -> String {
+    "list.html".to_string()
+}
+
+fn default_taxonomy_index_template() -> String {
+    "group.html".to_string()
+}
+
+fn default_json_api_path() -> String {
+    "api".to_string()
+}
+
+fn default_json_api_per_page() -> usize {
+    10
+}
+
+fn default_lint_max_title_length() -> usize {
+    70
+}
+
+fn default_webmention_timeout() -> u64 {
+    10
+}
+
+fn default_activitypub_outbox_items() -> usize {
+    20
+}
+
+fn default_activitypub_path() -> String {
+    "activitypub".to_string()
+}
+
+fn default_link_checker_concurrency() -> usize {
+    8
+}
+
+fn default_link_checker_timeout() -> u64 {
+    10
+}
+
+fn default_link_checker_domain_delay() -> u64 {
+    250
+}
+
+fn default_link_checker_cache_max_age() -> u64 {
+    24
+}
+
+fn default_link_checker_cache_file() -> String {
+    ".marmite-link-cache.json".to_string()
+}
+
+fn default_server_audit_log() -> String {
+    ".marmite-audit.log".to_string()
+}
+
+fn default_server_history_limit() -> usize {
+    50
+}
+
+fn default_server_max_upload_size() -> u64 {
+    10 * 1024 * 1024
+}
+
 fn default_code_highlight_enabled() -> bool {
     true
 }
@@ -1,5 +1,7 @@
+use crate::atproto::comments::BskyThread;
 use crate::cli::Cli;
 use crate::config::Marmite;
+use crate::error::Error;
 use crate::highlight::MarmiteHighlighter;
 use crate::image_provider;
 use crate::parser::{
@@ -8,17 +10,17 @@ use crate::parser::{
 };
 use crate::re;
 use crate::site::{get_content_folder, Data};
+use crate::webmention::Webmentions;
 use chrono::{NaiveDate, NaiveDateTime};
 use frontmatter_gen::{Frontmatter, Value};
 use log::error;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io::Write;
 use std::path::Path;
-use std::process;
 use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize)]
@@ -29,6 +31,8 @@ pub enum Kind {
     Stream,
     Series,
     Language,
+    /// A custom taxonomy declared in `taxonomies`
+    Taxonomy,
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -39,6 +43,7 @@ pub struct GroupedContent {
 }
 
 impl GroupedContent {
+    #[must_use]
     pub fn new(kind: Kind) -> Self {
         Self {
             kind,
@@ -72,14 +77,14 @@ impl GroupedContent {
 
         match self.kind {
             Kind::Tag => {
-                // sort by number of contents
-                vec.sort_by_key(|a| std::cmp::Reverse(a.1.len()));
+                // sort by number of contents, ties by name to keep the output stable
+                vec.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
             }
             Kind::Archive => {
                 // sort by year, newest first
                 vec.sort_by(|a, b| b.0.cmp(a.0));
             }
-            Kind::Author | Kind::Stream | Kind::Series | Kind::Language => {
+            Kind::Author | Kind::Stream | Kind::Series | Kind::Language | Kind::Taxonomy => {
                 // sort alphabetically
                 vec.sort_by(|a, b| a.0.cmp(b.0));
             }
@@ -104,6 +109,10 @@ pub struct Content {
     pub html: String,
     pub tags: Vec<String>,
     pub date: Option<NaiveDateTime>,
+    /// Content is hidden until this date, defaults to `date`
+    pub publish_at: Option<NaiveDateTime>,
+    /// Content is hidden after this date
+    pub expires_at: Option<NaiveDateTime>,
     pub extra: Option<Value>,
     pub links_to: Option<Vec<String>>,
     pub media_links_to: Option<Vec<String>>,
@@ -121,10 +130,20 @@ pub struct Content {
     pub previous: Option<Box<Content>>,
     pub source_path: Option<std::path::PathBuf>,
     pub at_uri: Option<String>,
+    /// The Bluesky post announcing the content, with the replies rendered as
+    /// comments
+    #[serde(default)]
+    pub bsky: Option<BskyThread>,
+    /// Mentions received by the content, fetched by `marmite webmention fetch`
+    #[serde(default)]
+    pub webmentions: Option<Webmentions>,
     pub aliases: Vec<String>,
     pub language: Option<String>,
     pub translations: Vec<TranslationRef>,
     pub translates: Option<String>,
+    /// Terms of each custom taxonomy, keyed by taxonomy name
+    #[serde(default)]
+    pub taxonomies: BTreeMap<String, Vec<String>>,
 }
 
 impl Content {
@@ -133,6 +152,10 @@ impl Content {
     /// if the file is a fragment, the markdown content will be modified to include the references
     /// if is a regular content then content will be modified to include the `markdown_header`
     /// and `markdown_footer` and references
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the file can not be read or the frontmatter is invalid.
     #[allow(clippy::too_many_lines)]
     pub fn from_markdown(
         path: &Path,
@@ -142,9 +165,16 @@ impl Content {
         highlighter: Option<&MarmiteHighlighter>,
         folder_defaults: Option<&Frontmatter>,
         content_dir: Option<&Path>,
-    ) -> Result<Content, String> {
-        let file_content = fs::read_to_string(path).map_err(|e| e.to_string())?;
-        let (mut frontmatter, raw_markdown) = parse_front_matter(&file_content)?;
+    ) -> Result<Content, Error> {
+        let file_content = fs::read_to_string(path).map_err(|e| Error::Io {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        let (mut frontmatter, raw_markdown) =
+            parse_front_matter(&file_content).map_err(|message| Error::Frontmatter {
+                path: path.to_path_buf(),
+                message,
+            })?;
 
         let page_mermaid_config: Option<serde_yaml::Value> = frontmatter
             .remove("mermaid_config")
@@ -212,7 +242,10 @@ impl Content {
 
         let description = get_description(&frontmatter);
         let tags = get_tags(&frontmatter);
-        let date = get_date(&frontmatter, path);
+        let taxonomies = get_taxonomies(&frontmatter, site);
+        let date = get_date(&frontmatter, path)?;
+        let publish_at = get_schedule_date(&frontmatter, "publish_at", path)?;
+        let expires_at = get_schedule_date(&frontmatter, "expires_at", path)?;
         let extra = frontmatter.get("extra").map(std::borrow::ToOwned::to_owned);
         let links_to = get_links_to(&html);
         let media_links_to = get_media_links_to(&html);
@@ -271,6 +304,8 @@ impl Content {
             html,
             tags,
             date,
+            publish_at,
+            expires_at,
             extra,
             links_to,
             media_links_to,
@@ -288,13 +323,40 @@ impl Content {
             previous: None,
             source_path: Some(path.to_path_buf()),
             at_uri: None,
+            bsky: None,
+            webmentions: None,
             aliases,
             language,
             translations: frontmatter_translations,
             translates,
+            taxonomies,
         };
         Ok(content)
     }
+
+    /// Whether the content is published at the build time `now`.
+    /// Content is scheduled while `publish_at`, or `date` when it is not set,
+    /// is in the future and expired once `expires_at` has passed.
+    #[must_use]
+    pub fn schedule(&self, now: NaiveDateTime) -> Schedule {
+        if let Some(expires_at) = self.expires_at.filter(|expires_at| *expires_at <= now) {
+            return Schedule::Expired(expires_at);
+        }
+        match self.publish_at.or(self.date) {
+            Some(publish_at) if publish_at > now => Schedule::Scheduled(publish_at),
+            _ => Schedule::Published,
+        }
+    }
+}
+
+/// Publication state of a content at build time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    Published,
+    /// Hidden until the given date
+    Scheduled(NaiveDateTime),
+    /// Hidden since the given date
+    Expired(NaiveDateTime),
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -306,6 +368,8 @@ pub struct ContentBuilder {
     html: Option<String>,
     tags: Option<Vec<String>>,
     date: Option<NaiveDateTime>,
+    publish_at: Option<NaiveDateTime>,
+    expires_at: Option<NaiveDateTime>,
     extra: Option<Value>,
     links_to: Option<Vec<String>>,
     media_links_to: Option<Vec<String>>,
@@ -324,6 +388,7 @@ pub struct ContentBuilder {
     language: Option<String>,
     translations: Option<Vec<TranslationRef>>,
     translates: Option<String>,
+    taxonomies: Option<BTreeMap<String, Vec<String>>>,
 }
 
 #[allow(dead_code)]
@@ -362,6 +427,16 @@ impl ContentBuilder {
         self
     }
 
+    pub fn publish_at(mut self, publish_at: NaiveDateTime) -> Self {
+        self.publish_at = Some(publish_at);
+        self
+    }
+
+    pub fn expires_at(mut self, expires_at: NaiveDateTime) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
     pub fn extra(mut self, extra: Value) -> Self {
         self.extra = Some(extra);
         self
@@ -452,6 +527,11 @@ impl ContentBuilder {
         self
     }
 
+    pub fn taxonomies(mut self, taxonomies: BTreeMap<String, Vec<String>>) -> Self {
+        self.taxonomies = Some(taxonomies);
+        self
+    }
+
     pub fn build(self) -> Content {
         Content {
             title: self.title.unwrap_or_default(),
@@ -460,6 +540,8 @@ impl ContentBuilder {
             html: self.html.unwrap_or_default(),
             tags: self.tags.unwrap_or_default(),
             date: self.date,
+            publish_at: self.publish_at,
+            expires_at: self.expires_at,
             extra: self.extra,
             links_to: self.links_to,
             media_links_to: self.media_links_to,
@@ -477,10 +559,13 @@ impl ContentBuilder {
             previous: None,
             source_path: self.source_path,
             at_uri: self.at_uri,
+            bsky: None,
+            webmentions: None,
             aliases: self.aliases.unwrap_or_default(),
             language: self.language,
             translations: self.translations.unwrap_or_default(),
             translates: self.translates,
+            taxonomies: self.taxonomies.unwrap_or_default(),
         }
     }
 }
@@ -698,19 +783,34 @@ fn extract_stream_from_s_pattern(filename: &str) -> Option<String> {
 }
 
 pub fn get_tags(frontmatter: &Frontmatter) -> Vec<String> {
-    let tags: Vec<String> = match frontmatter.get("tags") {
-        Some(Value::Array(tags)) => tags
+    get_terms(frontmatter, "tags")
+}
+
+/// Terms of every custom taxonomy set in the frontmatter, keyed by taxonomy name
+pub fn get_taxonomies(frontmatter: &Frontmatter, site: &Marmite) -> BTreeMap<String, Vec<String>> {
+    site.taxonomy_list()
+        .into_iter()
+        .map(|taxonomy| (taxonomy.name, get_terms(frontmatter, &taxonomy.key)))
+        .filter(|(_, terms)| !terms.is_empty())
+        .collect()
+}
+
+/// Values of `key` given as a list or a comma separated string
+fn get_terms(frontmatter: &Frontmatter, key: &str) -> Vec<String> {
+    let terms: Vec<String> = match frontmatter.get(key) {
+        Some(Value::Array(terms)) => terms
             .iter()
             .map(Value::to_string)
             .map(|t| t.trim_matches('"').to_string())
             .collect(),
-        Some(Value::String(tags)) => tags.split(',').map(str::trim).map(String::from).collect(),
+        Some(Value::String(terms)) => terms.split(',').map(str::trim).map(String::from).collect(),
         _ => Vec::new(),
     };
 
-    // Remove empty tags but keep original names
-    tags.iter()
-        .filter(|tag| !tag.is_empty())
+    // Remove empty terms but keep original names
+    terms
+        .iter()
+        .filter(|term| !term.is_empty())
         .map(|t| t.trim().to_string())
         .collect()
 }
@@ -776,6 +876,30 @@ pub fn get_aliases(frontmatter: &Frontmatter) -> Vec<String> {
         .collect()
 }
 
+/// File written for a redirect alias, relative to the output folder:
+/// `old-post` is `old-post.html`, `2019/05/old-post/` is
+/// `2019/05/old-post/index.html` and `old-post.html` is kept as is.
+/// Returns `None` for aliases leaving the output folder.
+pub fn alias_output_path(alias: &str) -> Option<String> {
+    let alias = alias.trim().trim_start_matches('/');
+    if alias.is_empty()
+        || alias.contains('\\')
+        || alias.split('/').any(|part| part == ".." || part == ".")
+    {
+        return None;
+    }
+    if alias.ends_with('/') {
+        Some(format!("{alias}index.html"))
+    } else if Path::new(alias)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"))
+    {
+        Some(alias.to_string())
+    } else {
+        Some(format!("{alias}.html"))
+    }
+}
+
 pub fn merge_frontmatter(defaults: &Frontmatter, file_fm: &mut Frontmatter) {
     for (key, value) in defaults.iter() {
         if key == "title" || key == "slug" {
@@ -846,27 +970,48 @@ fn get_frontmatter_translations(frontmatter: &Frontmatter) -> Vec<TranslationRef
 /// Input examples:
 ///   frontmatter = Frontmatter {date: Value("2024-10-10")}
 ///   path = "2024-01-01-myfile.md"
-pub fn get_date(frontmatter: &Frontmatter, path: &Path) -> Option<NaiveDateTime> {
+///
+/// # Errors
+///
+/// Returns [`Error::Frontmatter`] when `date` is set but is not a valid date,
+/// the content is then skipped with an `invalid-frontmatter` diagnostic.
+pub fn get_date(frontmatter: &Frontmatter, path: &Path) -> Result<Option<NaiveDateTime>, Error> {
     if let Some(input) = frontmatter.get("date").and_then(|v| v.as_str()) {
-        match try_to_parse_date(input) {
-            Ok(date) => return Some(date),
-            Err(e) => {
-                error!(
-                    "ERROR: Invalid date format {} when parsing {}, {}",
-                    input,
-                    path.display(),
-                    e
-                );
-                process::exit(1);
-            }
-        }
-    }
-    extract_date_from_filename(path)
+        return try_to_parse_date(input)
+            .map(Some)
+            .map_err(|e| Error::Frontmatter {
+                path: path.to_path_buf(),
+                message: format!("Invalid date format {input}: {e}"),
+            });
+    }
+    Ok(extract_date_from_filename(path))
+}
+
+/// Get `publish_at` or `expires_at` from the frontmatter, accepts the same formats as `date`
+///
+/// # Errors
+///
+/// Returns [`Error::Frontmatter`] when `key` is set but is not a valid date,
+/// the content is then skipped with an `invalid-frontmatter` diagnostic.
+pub fn get_schedule_date(
+    frontmatter: &Frontmatter,
+    key: &str,
+    path: &Path,
+) -> Result<Option<NaiveDateTime>, Error> {
+    let Some(input) = frontmatter.get(key).and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+    try_to_parse_date(input)
+        .map(Some)
+        .map_err(|e| Error::Frontmatter {
+            path: path.to_path_buf(),
+            message: format!("Invalid {key} format {input}: {e}"),
+        })
 }
 
 /// Tries to parse 3 different date formats or return Error.
 /// input: "2024-01-01 15:40:56" | "2024-01-01 15:40" | "2024-01-01"
-fn try_to_parse_date(input: &str) -> Result<NaiveDateTime, chrono::ParseError> {
+pub fn try_to_parse_date(input: &str) -> Result<NaiveDateTime, chrono::ParseError> {
     // Fix input to match the format "2023-02-08 19:03:32" or "2023-02-08 19:03" or "2023-02-08"
     // even if the input is on format 2020-01-19T21:05:12.984Z or 2020-01-19T21:05:12+0000
     let re = Regex::new(re::CAPTURE_DATE_PREFIX_FROM_TEXT)
@@ -1178,7 +1323,7 @@ fn json_to_fm_value(v: &serde_json::Value) -> Option<frontmatter_gen::Value> {
     }
 }
 
-fn fm_value_to_json(v: &frontmatter_gen::Value) -> serde_json::Value {
+pub fn fm_value_to_json(v: &frontmatter_gen::Value) -> serde_json::Value {
     match v {
         frontmatter_gen::Value::Null => serde_json::Value::Null,
         frontmatter_gen::Value::String(s) => serde_json::Value::String(s.clone()),
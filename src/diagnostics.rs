@@ -0,0 +1,361 @@
+//! Problems found while building that do not stop the build.
+//!
+//! Every problem is a [`Warning`] with a stable `code`, located in a source
+//! file when possible. `--report json` prints them and `--deny` turns them
+//! into errors that fail the build.
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena, Options};
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Implements `Warning::code` and builds `DENY_CODES` from the same list,
+/// so every code reported can also be denied
+macro_rules! warning_codes {
+    ($($variant:ident => $code:literal),+ $(,)?) => {
+        /// Codes accepted by `--deny`, `warnings` denies all of them
+        pub const DENY_CODES: &[&str] = &["warnings", $($code),+];
+
+        impl Warning {
+            #[must_use]
+            pub fn code(&self) -> &'static str {
+                match self {
+                    $(Self::$variant { .. } => $code,)+
+                }
+            }
+        }
+    };
+}
+
+/// What went wrong, serialized with its `code`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "code", rename_all = "kebab-case")]
+pub enum Warning {
+    /// A link to a page that is not generated, found by `check_internal_links`
+    BrokenInternalLink { source: String, target: String },
+    /// A link to a media file that does not exist, found by `check_media_links`
+    BrokenMediaLink { source: String, target: String },
+    /// Two contents share a slug, the latest rendered overwrites the other
+    SlugCollision { slug: String },
+    /// A `translations` entry points to content that does not exist
+    MissingTranslation { source: String, target: String },
+    /// A shortcode failed to render and was replaced by an error box
+    BrokenShortcode {
+        source: Option<String>,
+        name: String,
+        message: String,
+    },
+    /// A content file was skipped because its frontmatter could not be parsed
+    InvalidFrontmatter { source: String, message: String },
+    /// A template failed to render, only recorded by `--serve` which keeps
+    /// running with the pages that did render
+    TemplateError {
+        template: Option<String>,
+        message: String,
+    },
+    /// An outbound link that failed, found by `marmite check-links`
+    BrokenExternalLink {
+        source: String,
+        target: String,
+        status: Option<u16>,
+        error: Option<String>,
+    },
+    /// A content breaks a rule of `marmite lint`
+    Lint { rule: String, detail: String },
+}
+
+warning_codes! {
+    BrokenInternalLink => "broken-internal-link",
+    BrokenMediaLink => "broken-media-link",
+    SlugCollision => "slug-collision",
+    MissingTranslation => "missing-translation",
+    BrokenShortcode => "broken-shortcode",
+    InvalidFrontmatter => "invalid-frontmatter",
+    TemplateError => "template-error",
+    BrokenExternalLink => "broken-external-link",
+    Lint => "lint",
+}
+
+impl Warning {
+    /// Finds the line of the link, shortcode or translation this warning is
+    /// about in `source`, from the positions of the parsed markdown.
+    /// Text in code blocks or prose is never matched.
+    fn find_line(&self, source: &str) -> Option<usize> {
+        let (frontmatter, markdown) = blank_frontmatter(source);
+        match self {
+            Self::MissingTranslation { target, .. } => frontmatter
+                .lines()
+                .position(|line| yaml_value(line) == target)
+                .map(|index| index + 1),
+            Self::BrokenInternalLink { .. }
+            | Self::BrokenMediaLink { .. }
+            | Self::BrokenExternalLink { .. }
+            | Self::BrokenShortcode { .. } => self.find_node_line(&markdown),
+            Self::SlugCollision { .. }
+            | Self::InvalidFrontmatter { .. }
+            | Self::TemplateError { .. }
+            | Self::Lint { .. } => None,
+        }
+    }
+
+    fn find_node_line(&self, markdown: &str) -> Option<usize> {
+        let arena = Arena::new();
+        let mut options = Options::default();
+        options.extension.autolink = true;
+        options.extension.footnotes = true;
+        options.extension.strikethrough = true;
+        options.extension.table = true;
+        options.extension.wikilinks_title_after_pipe = true;
+        let root = parse_document(&arena, markdown, &options);
+        root.descendants().find_map(|node| {
+            let ast = node.data.borrow();
+            let start = ast.sourcepos.start.line;
+            match (&ast.value, self) {
+                (NodeValue::Link(link), Self::BrokenInternalLink { target, .. }) => {
+                    (internal_slug(&link.url) == target).then_some(start)
+                }
+                (NodeValue::WikiLink(link), Self::BrokenInternalLink { target, .. }) => {
+                    (crate::slugify::slugify(&link.url) == *target).then_some(start)
+                }
+                (
+                    NodeValue::Link(link) | NodeValue::Image(link),
+                    Self::BrokenMediaLink { target, .. },
+                ) => (Path::new(strip_fragment(&link.url)).file_name()
+                    == Path::new(target).file_name())
+                .then_some(start),
+                (
+                    NodeValue::Link(link) | NodeValue::Image(link),
+                    Self::BrokenExternalLink { target, .. },
+                ) => (strip_fragment(&link.url) == target).then_some(start),
+                (NodeValue::HtmlBlock(block), Self::BrokenShortcode { name, .. }) => block
+                    .literal
+                    .lines()
+                    .position(|line| is_shortcode(line, name))
+                    .map(|offset| start + offset),
+                (NodeValue::HtmlInline(html), Self::BrokenShortcode { name, .. }) => {
+                    is_shortcode(html, name).then_some(start)
+                }
+                _ => None,
+            }
+        })
+    }
+}
+
+/// Splits `source` into its frontmatter and its markdown, with the lines of
+/// the other part blanked so both keep the line numbers of the file
+fn blank_frontmatter(source: &str) -> (String, String) {
+    let lines: Vec<&str> = source.lines().collect();
+    let end = lines
+        .first()
+        .filter(|first| matches!(first.trim_end(), "---" | "+++"))
+        .and_then(|first| {
+            let delimiter = first.trim_end();
+            lines[1..]
+                .iter()
+                .position(|line| line.trim_end() == delimiter)
+                .map(|index| index + 2)
+        })
+        .unwrap_or(0);
+    let blank = |keep: &dyn Fn(usize) -> bool| {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| if keep(index) { *line } else { "" })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    (blank(&|index| index < end), blank(&|index| index >= end))
+}
+
+/// The value of a `key: value` or `- value` frontmatter line, unquoted
+fn yaml_value(line: &str) -> &str {
+    let line = line.trim();
+    let value = line
+        .strip_prefix("- ")
+        .or_else(|| line.split_once(": ").map(|(_, value)| value))
+        .unwrap_or(line);
+    value.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+fn strip_fragment(url: &str) -> &str {
+    url.split(['#', '?']).next().unwrap_or(url)
+}
+
+/// The slug a relative link points to, as `check_internal_links` reports it
+fn internal_slug(url: &str) -> &str {
+    let path = strip_fragment(url);
+    let path = path.strip_prefix("./").unwrap_or(path);
+    let path = path.strip_prefix('/').unwrap_or(path);
+    path.strip_suffix(".html").unwrap_or(path)
+}
+
+/// Whether `html` is the `<!-- .name ... -->` comment of the shortcode `name`
+fn is_shortcode(html: &str, name: &str) -> bool {
+    html.trim_start()
+        .strip_prefix("<!--")
+        .and_then(|comment| comment.trim_start().strip_prefix('.'))
+        .and_then(|comment| comment.strip_prefix(name))
+        .is_some_and(|rest| rest.starts_with(char::is_whitespace) || rest.starts_with("-->"))
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BrokenInternalLink { source, target } => write!(
+                f,
+                "Broken internal link in \"{source}.html\": \"{target}.html\" does not exist"
+            ),
+            Self::BrokenMediaLink { source, target } => write!(
+                f,
+                "Broken media link in \"{source}\": \"{target}\" does not exist"
+            ),
+            Self::SlugCollision { slug } => write!(
+                f,
+                "Duplicate slug found: '{slug}' \
+                - try setting `title` or `slug` as a unique text, \
+                or leave both empty so filename will be assumed. \
+                - The latest content rendered will overwrite the previous one."
+            ),
+            Self::MissingTranslation { source, target } => write!(
+                f,
+                "Translation reference '{target}' in '{source}' not found"
+            ),
+            Self::BrokenShortcode { name, message, .. } => {
+                write!(f, "Shortcode '{name}' failed to render: {message}")
+            }
+            Self::InvalidFrontmatter { source, message } => {
+                write!(f, "Invalid frontmatter in \"{source}\": {message}")
+            }
+            Self::TemplateError {
+                template: Some(template),
+                message,
+            } => write!(f, "Template '{template}' failed to render: {message}"),
+            Self::TemplateError {
+                template: None,
+                message,
+            } => write!(f, "Templates failed to render: {message}"),
+            Self::BrokenExternalLink {
+                source,
+                target,
+                status,
+                error,
+            } => {
+                write!(f, "Broken external link in \"{source}.html\": \"{target}\"")?;
+                match (status, error) {
+                    (Some(status), _) => write!(f, " returned {status}"),
+                    (None, Some(error)) => write!(f, " failed: {error}"),
+                    (None, None) => Ok(()),
+                }
+            }
+            Self::Lint { rule, detail } => write!(f, "{detail} [{rule}]"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    /// Denied with `--deny`, fails the build
+    Error,
+}
+
+/// A warning with where it was found
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    #[serde(flatten)]
+    pub warning: Warning,
+    pub severity: Severity,
+    pub file: Option<PathBuf>,
+    /// 1-based line in `file`
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates a warning found in `file`, looking for the line it refers to
+    #[must_use]
+    pub fn new(warning: Warning, file: Option<&Path>) -> Self {
+        let line = file
+            .and_then(|file| fs::read_to_string(file).ok())
+            .and_then(|source| warning.find_line(&source));
+        Self {
+            message: warning.to_string(),
+            warning,
+            severity: Severity::Warning,
+            file: file.map(Path::to_path_buf),
+            line,
+        }
+    }
+
+    /// Sets the line when it is known from the error instead of searched
+    #[must_use]
+    pub fn with_line(mut self, line: Option<usize>) -> Self {
+        if line.is_some() {
+            self.line = line;
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        self.warning.code()
+    }
+
+    /// Whether `deny` (the `--deny` values) turns this diagnostic into an error
+    #[must_use]
+    pub fn is_denied(&self, deny: &[String]) -> bool {
+        deny.iter()
+            .any(|code| code == "warnings" || code == self.code())
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{line}: {}", file.display(), self.message),
+            (Some(file), None) => write!(f, "{}: {}", file.display(), self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Output of `--report json`
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    pub diagnostics: &'a [Diagnostic],
+    pub warnings: usize,
+    pub errors: usize,
+}
+
+impl<'a> Report<'a> {
+    #[must_use]
+    pub fn new(diagnostics: &'a [Diagnostic]) -> Self {
+        let errors = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        Self {
+            diagnostics,
+            warnings: diagnostics.len() - errors,
+            errors,
+        }
+    }
+}
+
+/// Marks the denied diagnostics as errors and returns them
+pub fn apply_deny(diagnostics: &mut [Diagnostic], deny: &[String]) -> Vec<Diagnostic> {
+    diagnostics
+        .iter_mut()
+        .filter(|d| d.is_denied(deny))
+        .map(|d| {
+            d.severity = Severity::Error;
+            d.clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "tests/diagnostics.rs"]
+mod tests;
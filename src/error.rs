@@ -0,0 +1,53 @@
+//! Errors returned by site builds
+use crate::diagnostics::Diagnostic;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Why a site build failed
+#[derive(Debug)]
+pub enum Error {
+    /// The configuration file could not be read or parsed
+    Config { path: PathBuf, message: String },
+    /// The front matter of a content file is not valid, the file is skipped
+    Frontmatter { path: PathBuf, message: String },
+    /// The `theme` set in the configuration does not exist
+    ThemeNotFound(PathBuf),
+    /// Templates could not be loaded or rendered
+    Template(String),
+    /// Broken links were found and `strict_internal_links` is enabled
+    BrokenLinks(Vec<Diagnostic>),
+    /// Diagnostics denied with `--deny` were found
+    Denied(Vec<Diagnostic>),
+    /// Reading or writing files failed
+    Io { path: PathBuf, message: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config { path, message } => {
+                write!(f, "Failed to parse config {}: {message}", path.display())
+            }
+            Self::Frontmatter { path, message } => {
+                write!(f, "Invalid front matter in {}: {message}", path.display())
+            }
+            Self::ThemeNotFound(path) => {
+                write!(f, "Theme folder '{}' does not exist", path.display())
+            }
+            Self::Template(message) => write!(f, "Failed to render templates: {message}"),
+            Self::BrokenLinks(links) => write!(
+                f,
+                "Build failed due to {} broken link(s) (strict_internal_links is enabled)",
+                links.len()
+            ),
+            Self::Denied(diagnostics) => write!(
+                f,
+                "Build failed due to {} denied diagnostic(s)",
+                diagnostics.len()
+            ),
+            Self::Io { path, message } => write!(f, "{}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -5,18 +5,14 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
 
-use crate::config::Marmite;
+use crate::config::{FeedContent, FeedSettings, Marmite};
 use crate::content::Content;
+use crate::search::{html_to_text, truncate_text};
+use crate::slugify::slugify;
 
-pub fn generate_rss(
-    contents: &[Content],
-    output_path: &Path,
-    filename: &str,
-    config: &Marmite,
-) -> Result<(), String> {
-    let date_format = "%a, %d %b %Y %H:%M:%S GMT"; // Loose RFC-822 format
-
-    let feed_url = if !config.url.starts_with("http://") && !config.url.starts_with("https://") {
+/// The site url with the protocol, feeds require absolute links
+pub(crate) fn site_url(config: &Marmite) -> String {
+    if !config.url.starts_with("http://") && !config.url.starts_with("https://") {
         let protocol = if config.https.unwrap_or(false) {
             "https://"
         } else {
@@ -25,43 +21,119 @@ pub fn generate_rss(
         format!("{}{}", protocol, &config.url)
     } else {
         config.url.clone()
-    };
-
-    let mut channel = ChannelBuilder::default()
-        .title(&config.name)
-        .link(&feed_url)
-        .description(&config.tagline)
-        .generator("marmite".to_string())
-        .build();
+    }
+}
 
-    // Filter out content with stream "draft" and content without dates
-    let filtered_contents: Vec<&Content> = contents
+/// Contents published in a feed: drafts, content without a date and content
+/// with an excluded tag (matched by slug) are left out, at most `settings.items` are kept
+fn feed_contents<'a>(contents: &'a [Content], settings: &FeedSettings) -> Vec<&'a Content> {
+    contents
         .iter()
         .filter(|content| {
             content
                 .stream
                 .as_ref()
                 .is_none_or(|stream| stream != "draft")
-                && content.date.is_some() // Only include content with dates in RSS feed
+                && content.date.is_some() // Only include content with dates in feeds
+                && !content.tags.iter().any(|tag| {
+                    let slug = slugify(tag);
+                    settings
+                        .exclude_tags
+                        .iter()
+                        .any(|excluded| slugify(excluded) == slug)
+                })
         })
-        .collect();
+        .take(settings.items)
+        .collect()
+}
+
+/// Plain text used as the item content by the `summary` and `excerpt` modes,
+/// `None` when the feed carries the full HTML
+fn item_text(content: &Content, settings: &FeedSettings) -> Option<String> {
+    let excerpt = || truncate_text(&html_to_text(&content.html), settings.excerpt_length);
+    match settings.content {
+        FeedContent::Full => None,
+        FeedContent::Summary => Some(
+            content
+                .description
+                .clone()
+                .filter(|description| !description.is_empty())
+                .unwrap_or_else(excerpt),
+        ),
+        FeedContent::Excerpt => Some(excerpt()),
+    }
+}
+
+/// When the content was last updated, the latest of its date and the
+/// modification time of its file, `None` unless `settings.updated` is enabled
+fn item_updated(content: &Content, settings: &FeedSettings) -> Option<chrono::NaiveDateTime> {
+    if !settings.updated {
+        return None;
+    }
+    let date = content.date?;
+    let modified = content
+        .modified_time
+        .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+        .map(|modified| modified.naive_utc());
+    Some(modified.map_or(date, |modified| modified.max(date)))
+}
+
+/// Generates the RSS feed and, when enabled, the JSON and Atom feeds
+pub fn generate_feeds(
+    contents: &[Content],
+    output_path: &Path,
+    filename: &str,
+    config: &Marmite,
+    settings: &FeedSettings,
+) -> Result<(), String> {
+    generate_rss(contents, output_path, filename, config, settings)?;
+    if config.json_feed {
+        generate_json(contents, output_path, filename, config, settings)?;
+    }
+    if config.atom_feed {
+        generate_atom(contents, output_path, filename, config, settings)?;
+    }
+    Ok(())
+}
+
+pub fn generate_rss(
+    contents: &[Content],
+    output_path: &Path,
+    filename: &str,
+    config: &Marmite,
+    settings: &FeedSettings,
+) -> Result<(), String> {
+    let date_format = "%a, %d %b %Y %H:%M:%S GMT"; // Loose RFC-822 format
+
+    let feed_url = site_url(config);
 
-    for content in filtered_contents.iter().take(15) {
+    let mut channel = ChannelBuilder::default()
+        .title(&config.name)
+        .link(&feed_url)
+        .description(&config.tagline)
+        .generator("marmite".to_string())
+        .build();
+
+    let filtered_contents = feed_contents(contents, settings);
+
+    for content in &filtered_contents {
         // Safe to unwrap here because we filtered for content with dates
         let content_date = content
             .date
             .expect("Content should have date - filtered above");
+        // summary and excerpt feeds carry the text as the description, without the full content
+        let text = item_text(content, settings);
         let mut item = ItemBuilder::default()
             .title(content.title.clone())
             .link(format!("{}/{}.html", &feed_url, &content.slug))
-            .description(content.description.clone())
+            .description(text.clone().or_else(|| content.description.clone()))
             .guid(
                 rss::GuidBuilder::default()
                     .value(format!("{}/{}.html", &feed_url, &content.slug))
                     .build(),
             )
             .pub_date(content_date.format(date_format).to_string())
-            .content(content.html.clone())
+            .content(text.is_none().then(|| content.html.clone()))
             .source(
                 rss::SourceBuilder::default()
                     .url(&feed_url)
@@ -85,7 +157,15 @@ pub fn generate_rss(
         channel.pub_date = latest_item.pub_date.clone();
     }
 
-    channel.last_build_date = Some(chrono::Utc::now().format(date_format).to_string());
+    // RSS has no per item update date, with `updated` the channel reports
+    // when its newest item changed instead of when the site was built
+    let last_update = filtered_contents
+        .iter()
+        .filter_map(|content| item_updated(content, settings))
+        .max()
+        .map(|updated| updated.format(date_format).to_string());
+    channel.last_build_date =
+        Some(last_update.unwrap_or_else(|| chrono::Utc::now().format(date_format).to_string()));
 
     if !config.card_image.is_empty() {
         channel.image = Some(
@@ -104,6 +184,154 @@ pub fn generate_rss(
     Ok(())
 }
 
+/// Maps a content author to an Atom person using the `authors` configuration
+fn atom_person(author: &str, config: &Marmite) -> atom_syndication::Person {
+    match config.authors.get(author) {
+        Some(config_author) => atom_syndication::Person {
+            name: config_author.name.clone(),
+            uri: config_author
+                .links
+                .as_ref()
+                .and_then(|links| links.first())
+                .map(|(_, url)| url.clone()),
+            ..Default::default()
+        },
+        None => atom_syndication::Person {
+            name: author.to_string(),
+            ..Default::default()
+        },
+    }
+}
+
+/// Builds the Atom entry for a content
+fn atom_entry(
+    content: &Content,
+    feed_url: &str,
+    config: &Marmite,
+    settings: &FeedSettings,
+) -> atom_syndication::Entry {
+    // Safe to unwrap here because feed contents are filtered for content with dates
+    let content_date = content
+        .date
+        .expect("Content should have date - filtered above")
+        .and_utc()
+        .fixed_offset();
+    let updated = item_updated(content, settings)
+        .map_or(content_date, |updated| updated.and_utc().fixed_offset());
+    let text = item_text(content, settings);
+    let url = format!("{}/{}.html", feed_url, &content.slug);
+    let language = content
+        .language
+        .clone()
+        .unwrap_or_else(|| config.language.clone());
+    atom_syndication::Entry {
+        title: content.title.clone().into(),
+        id: url.clone(),
+        updated,
+        published: Some(content_date),
+        authors: content
+            .authors
+            .iter()
+            .map(|author| atom_person(author, config))
+            .collect(),
+        categories: content
+            .tags
+            .iter()
+            .map(|tag| atom_syndication::Category {
+                term: tag.clone(),
+                ..Default::default()
+            })
+            .collect(),
+        links: vec![atom_syndication::Link {
+            href: url,
+            rel: "alternate".to_string(),
+            mime_type: Some("text/html".to_string()),
+            ..Default::default()
+        }],
+        summary: text
+            .clone()
+            .or_else(|| content.description.clone())
+            .map(atom_syndication::Text::plain),
+        // summary and excerpt feeds carry only the summary
+        content: text.is_none().then(|| atom_syndication::Content {
+            lang: Some(language),
+            value: Some(content.html.clone()),
+            content_type: Some("html".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Generate an Atom 1.0 feed for the given contents
+/// <https://www.rfc-editor.org/rfc/rfc4287>
+/// saves the feed to the output path with the given filename and extension .atom
+pub fn generate_atom(
+    contents: &[Content],
+    output_path: &Path,
+    filename: &str,
+    config: &Marmite,
+    settings: &FeedSettings,
+) -> Result<(), String> {
+    let feed_url = site_url(config);
+
+    let entries: Vec<atom_syndication::Entry> = feed_contents(contents, settings)
+        .into_iter()
+        .map(|content| atom_entry(content, &feed_url, config, settings))
+        .collect();
+
+    // Atom requires an author on every entry, the feed author covers entries without one
+    let feed_author = if config.default_author.is_empty() {
+        config.name.clone()
+    } else {
+        config.default_author.clone()
+    };
+
+    let feed = atom_syndication::Feed {
+        title: config.name.clone().into(),
+        id: format!("{}/{}.atom", &feed_url, filename),
+        updated: entries
+            .iter()
+            .map(|entry| entry.updated)
+            .max()
+            .unwrap_or_else(|| chrono::Utc::now().fixed_offset()),
+        authors: vec![atom_person(&feed_author, config)],
+        generator: Some(atom_syndication::Generator {
+            value: "marmite".to_string(),
+            uri: Some("https://marmite.blog".to_string()),
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        }),
+        links: vec![
+            atom_syndication::Link {
+                href: format!("{}/{}.atom", &feed_url, filename),
+                rel: "self".to_string(),
+                mime_type: Some("application/atom+xml".to_string()),
+                ..Default::default()
+            },
+            atom_syndication::Link {
+                href: format!("{}/{}.html", &feed_url, filename),
+                rel: "alternate".to_string(),
+                mime_type: Some("text/html".to_string()),
+                ..Default::default()
+            },
+        ],
+        logo: (!config.card_image.is_empty())
+            .then(|| format!("{}/{}", &feed_url, &config.card_image)),
+        subtitle: (!config.tagline.is_empty())
+            .then(|| atom_syndication::Text::plain(config.tagline.clone())),
+        lang: Some(config.language.clone()),
+        entries,
+        ..Default::default()
+    };
+
+    let feed_path = output_path.join(format!("{filename}.atom"));
+    let file = File::create(&feed_path).map_err(|e| e.to_string())?;
+    feed.write_to(file).map_err(|e| e.to_string())?;
+    info!("Generated {}", &feed_path.display());
+
+    Ok(())
+}
+
 /// Struct to represent a JSON feed for a Content
 /// <https://jsonfeed.org/version/1>
 #[allow(clippy::module_name_repetitions)]
@@ -123,10 +351,14 @@ pub struct JsonFeedItem {
     id: String,
     url: String,
     title: String,
-    content_html: String,
-    // content_text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_html: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_text: Option<String>,
     summary: String,
     date_published: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_modified: Option<String>,
     image: String,
     authors: Vec<JsonFeedAuthor>,
     tags: Vec<String>,
@@ -149,37 +381,32 @@ pub fn generate_json(
     output_path: &Path,
     filename: &str,
     config: &Marmite,
+    settings: &FeedSettings,
 ) -> Result<(), String> {
     let date_format = "%Y-%m-%dT%H:%M:%S-00:00"; // Loose RFC3339 format
     let mut items = Vec::new();
 
-    // Filter out content with stream "draft" and content without dates
-    let filtered_contents: Vec<&Content> = contents
-        .iter()
-        .filter(|content| {
-            content
-                .stream
-                .as_ref()
-                .is_none_or(|stream| stream != "draft")
-                && content.date.is_some() // Only include content with dates in JSON feed
-        })
-        .collect();
+    let filtered_contents = feed_contents(contents, settings);
 
-    for content in filtered_contents.iter().take(15) {
+    for content in &filtered_contents {
         // Safe to unwrap here because we filtered for content with dates
         let content_date = content
             .date
             .expect("Content should have date - filtered above");
+        let text = item_text(content, settings);
         let item = JsonFeedItem {
             id: format!("{}/{}.html", &config.url, &content.slug),
             url: format!("{}/{}.html", &config.url, &content.slug),
             title: content.title.clone(),
-            content_html: content.html.clone(),
-            // content_text: content.html.clone(), // requires stripping HTML tags
+            // summary and excerpt feeds carry the text instead of the HTML
+            content_html: text.is_none().then(|| content.html.clone()),
+            content_text: text,
             summary: content.description.clone().unwrap_or(String::new()),
             // date_published: content.date.unwrap().to_string(),
             // date published should be in RFC-822 format
             date_published: content_date.format(date_format).to_string(),
+            date_modified: item_updated(content, settings)
+                .map(|updated| updated.format(date_format).to_string()),
             image: content.card_image.clone().unwrap_or(String::new()),
             authors: content
                 .authors
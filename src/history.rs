@@ -0,0 +1,325 @@
+//! Revision history of the contents changed through the dev server API.
+//!
+//! Before the API edits, moves, deletes or restores a content file, the
+//! current version is copied to `.marmite/history/<slug>/` in the input
+//! folder, so the authors of the built-in editor can compare and restore
+//! earlier versions. With `server.git_commit` each change is also committed
+//! to the git repository of the site.
+use chrono::Utc;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Folder of the revisions, relative to the input folder
+pub const HISTORY_DIR: &str = ".marmite/history";
+
+/// Lines of unchanged text around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+/// Above this many line comparisons the diff replaces the whole text
+const DIFF_MAX_CELLS: usize = 4_000_000;
+
+/// A previous version of a content file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    pub id: String,
+    pub slug: String,
+    /// The change that replaced this version: `edit`, `frontmatter`,
+    /// `move`, `delete` or `restore`
+    pub action: String,
+    /// Where the file was, relative to the input folder
+    pub path: String,
+    /// Unix timestamp in milliseconds
+    pub timestamp: i64,
+    pub size: u64,
+}
+
+/// Files of the history, of git and the `.marmite-*` state files written by
+/// the builds and commands, changing them does not rebuild the site
+pub fn is_metadata_path(path: &Path) -> bool {
+    path.components()
+        .any(|component| component.as_os_str() == ".marmite" || component.as_os_str() == ".git")
+        || path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(".marmite-"))
+}
+
+/// Slugs and revision ids are used as file names
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\'])
+}
+
+fn slug_dir(input_folder: &Path, slug: &str) -> Result<PathBuf, String> {
+    if !is_safe_name(slug) {
+        return Err(format!("Invalid slug '{slug}'"));
+    }
+    Ok(input_folder.join(HISTORY_DIR).join(slug))
+}
+
+/// Saves the current content of `file` as a revision of `slug` and keeps
+/// the `limit` latest ones. Nothing is saved when `limit` is 0.
+pub fn record(
+    input_folder: &Path,
+    slug: &str,
+    file: &Path,
+    action: &str,
+    limit: usize,
+) -> Result<Option<Revision>, String> {
+    if limit == 0 {
+        return Ok(None);
+    }
+    let dir = slug_dir(input_folder, slug)?;
+    let content = fs::read(file).map_err(|e| format!("Failed to read {}: {e}", file.display()))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+
+    let timestamp = Utc::now().timestamp_millis();
+    let mut id = format!("{timestamp}-{action}");
+    let mut counter = 1;
+    while dir.join(format!("{id}.json")).exists() {
+        counter += 1;
+        id = format!("{timestamp}-{action}-{counter}");
+    }
+    let path = file.strip_prefix(input_folder).unwrap_or(file);
+    let revision = Revision {
+        id,
+        slug: slug.to_string(),
+        action: action.to_string(),
+        path: path.to_string_lossy().replace('\\', "/"),
+        timestamp,
+        size: content.len() as u64,
+    };
+
+    fs::write(dir.join(format!("{}.snapshot", revision.id)), content)
+        .map_err(|e| format!("Failed to write revision: {e}"))?;
+    let meta = serde_json::to_string_pretty(&revision).map_err(|e| e.to_string())?;
+    fs::write(dir.join(format!("{}.json", revision.id)), meta)
+        .map_err(|e| format!("Failed to write revision: {e}"))?;
+    debug!("Saved revision {} of {slug}", revision.id);
+
+    for old in list(input_folder, slug).iter().skip(limit) {
+        let _ = fs::remove_file(dir.join(format!("{}.json", old.id)));
+        let _ = fs::remove_file(dir.join(format!("{}.snapshot", old.id)));
+    }
+    Ok(Some(revision))
+}
+
+/// Revisions of `slug`, the latest first
+pub fn list(input_folder: &Path, slug: &str) -> Vec<Revision> {
+    let Ok(dir) = slug_dir(input_folder, slug) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut revisions: Vec<Revision> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|meta| serde_json::from_str(&meta).ok())
+        .collect();
+    revisions.sort_by(|a, b| (b.timestamp, &b.id).cmp(&(a.timestamp, &a.id)));
+    revisions
+}
+
+/// The revision `id` of `slug` and its content
+pub fn read(input_folder: &Path, slug: &str, id: &str) -> Result<(Revision, String), String> {
+    let dir = slug_dir(input_folder, slug)?;
+    if !is_safe_name(id) {
+        return Err(format!("Revision '{id}' not found"));
+    }
+    let meta = fs::read_to_string(dir.join(format!("{id}.json")))
+        .map_err(|_| format!("Revision '{id}' not found"))?;
+    let revision: Revision = serde_json::from_str(&meta).map_err(|e| e.to_string())?;
+    let content = fs::read_to_string(dir.join(format!("{id}.snapshot")))
+        .map_err(|_| format!("Revision '{id}' not found"))?;
+    Ok((revision, content))
+}
+
+/// Where a revision was stored from, refusing paths outside the input folder
+pub fn original_path(input_folder: &Path, revision: &Revision) -> Result<PathBuf, String> {
+    let path = Path::new(&revision.path);
+    if path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(format!("Invalid revision path '{}'", revision.path));
+    }
+    Ok(input_folder.join(path))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line changes from `old` to `new`, by longest common subsequence after
+/// skipping the lines both texts start and end with
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut lines: Vec<Line> = old[..prefix].iter().map(|l| Line::Same(l)).collect();
+    if old_mid.len().saturating_mul(new_mid.len()) > DIFF_MAX_CELLS {
+        lines.extend(old_mid.iter().map(|l| Line::Removed(l)));
+        lines.extend(new_mid.iter().map(|l| Line::Added(l)));
+    } else {
+        // lengths[i][j]: common lines of old_mid[i..] and new_mid[j..]
+        let width = new_mid.len() + 1;
+        let mut lengths = vec![0u32; (old_mid.len() + 1) * width];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                lengths[i * width + j] = if old_mid[i] == new_mid[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_mid.len() && j < new_mid.len() {
+            if old_mid[i] == new_mid[j] {
+                lines.push(Line::Same(old_mid[i]));
+                i += 1;
+                j += 1;
+            } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+                lines.push(Line::Removed(old_mid[i]));
+                i += 1;
+            } else {
+                lines.push(Line::Added(new_mid[j]));
+                j += 1;
+            }
+        }
+        lines.extend(old_mid[i..].iter().map(|l| Line::Removed(l)));
+        lines.extend(new_mid[j..].iter().map(|l| Line::Added(l)));
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|l| Line::Same(l)));
+    lines
+}
+
+/// Unified diff from `old` to `new`, empty when they are equal
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let lines = diff_lines(&old_lines, &new_lines);
+    let changes: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+        .map(|(index, _)| index)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Changes closer than twice the context share a hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &index in &changes {
+        let start = index.saturating_sub(DIFF_CONTEXT);
+        let end = (index + DIFF_CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut output = format!("--- {old_label}\n+++ {new_label}\n");
+    // Line numbers where each line of `lines` sits in the old and new texts
+    let mut positions = Vec::with_capacity(lines.len());
+    let (mut old_line, mut new_line) = (1, 1);
+    for line in &lines {
+        positions.push((old_line, new_line));
+        match line {
+            Line::Same(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            Line::Removed(_) => old_line += 1,
+            Line::Added(_) => new_line += 1,
+        }
+    }
+    for (start, end) in hunks {
+        let hunk = &lines[start..end];
+        let old_count = hunk.iter().filter(|l| !matches!(l, Line::Added(_))).count();
+        let new_count = hunk
+            .iter()
+            .filter(|l| !matches!(l, Line::Removed(_)))
+            .count();
+        let (old_start, new_start) = positions[start];
+        // Empty ranges point at the line before, as `diff -u` does
+        let old_start = if old_count == 0 {
+            old_start - 1
+        } else {
+            old_start
+        };
+        let new_start = if new_count == 0 {
+            new_start - 1
+        } else {
+            new_start
+        };
+        let _ = writeln!(
+            output,
+            "@@ -{old_start},{old_count} +{new_start},{new_count} @@"
+        );
+        for line in hunk {
+            let (sign, text) = match line {
+                Line::Same(text) => (' ', text),
+                Line::Removed(text) => ('-', text),
+                Line::Added(text) => ('+', text),
+            };
+            output.push(sign);
+            output.push_str(text);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// Commits `paths` (added, changed or deleted) to the git repository of the
+/// input folder, leaving any other staged change out of the commit
+pub fn commit(input_folder: &Path, paths: &[&Path], message: &str) -> Result<(), String> {
+    let git = |args: &[&std::ffi::OsStr]| -> Result<(), String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(input_folder)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run git: {e}"))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        }
+    };
+    let paths: Vec<&std::ffi::OsStr> = paths.iter().map(|path| path.as_os_str()).collect();
+
+    let mut add = vec!["add".as_ref(), "-A".as_ref(), "--".as_ref()];
+    add.extend(&paths);
+    git(&add)?;
+    let mut commit = vec![
+        "commit".as_ref(),
+        "-m".as_ref(),
+        message.as_ref(),
+        "--".as_ref(),
+    ];
+    commit.extend(&paths);
+    git(&commit)
+}
+
+#[cfg(test)]
+#[path = "tests/history.rs"]
+mod tests;
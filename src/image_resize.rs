@@ -1,8 +1,15 @@
-use image::{imageops::FilterType, GenericImageView, ImageDecoder, ImageError, ImageReader};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::metadata::Orientation;
+use image::{
+    imageops::FilterType, DynamicImage, GenericImageView, ImageDecoder, ImageError, ImageReader,
+};
 use log::{debug, error, info, warn};
 use rayon::prelude::*;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter};
 use std::path::{Component, Path, PathBuf};
@@ -12,6 +19,7 @@ use tempfile::Builder as TempFileBuilder;
 use walkdir::WalkDir;
 
 use crate::config::Marmite;
+use crate::re;
 
 /// Progress reporting interval: log every N images processed
 const PROGRESS_INTERVAL: usize = 10;
@@ -19,6 +27,20 @@ const PROGRESS_INTERVAL: usize = 10;
 /// State file name for tracking processed images
 const STATE_FILE_NAME: &str = ".marmite-resize-state.json";
 
+/// State file name for tracking generated responsive variants
+const RESPONSIVE_STATE_FILE_NAME: &str = ".marmite-responsive-state.json";
+
+/// Folder inside the media folder holding the responsive variants
+const RESPONSIVE_DIR: &str = "_responsive";
+
+/// Extra formats emitted when `responsive_image_formats` is not set
+const DEFAULT_RESPONSIVE_FORMATS: [&str; 2] = ["avif", "webp"];
+
+/// AVIF encoder speed (1 slowest to 10 fastest)
+const AVIF_SPEED: u8 = 8;
+/// AVIF encoder quality (1 to 100)
+const AVIF_QUALITY: u8 = 80;
+
 /// Minimum allowed image width for resize configuration (in pixels)
 const MIN_IMAGE_WIDTH: u32 = 1;
 /// Maximum allowed image width for resize configuration (in pixels)
@@ -48,8 +70,8 @@ struct ResizeState {
 
 impl ResizeState {
     /// Load state from file, or return empty state if not found
-    fn load(output_path: &Path) -> Self {
-        let state_file = output_path.join(STATE_FILE_NAME);
+    fn load(output_path: &Path, file_name: &str) -> Self {
+        let state_file = output_path.join(file_name);
         if let Ok(file) = File::open(&state_file) {
             let reader = BufReader::new(file);
             if let Ok(state) = serde_json::from_reader(reader) {
@@ -60,8 +82,8 @@ impl ResizeState {
     }
 
     /// Save state to file
-    fn save(&self, output_path: &Path) {
-        let state_file = output_path.join(STATE_FILE_NAME);
+    fn save(&self, output_path: &Path, file_name: &str) {
+        let state_file = output_path.join(file_name);
         if let Ok(file) = File::create(&state_file) {
             let writer = BufWriter::new(file);
             if let Err(e) = serde_json::to_writer(writer, self) {
@@ -450,11 +472,12 @@ fn collect_image_paths(output_media_path: &Path) -> Vec<PathBuf> {
         .filter_map(|entry| {
             let path = entry.path();
 
-            // Skip thumbnails directory
-            if path
-                .components()
-                .any(|c| c.as_os_str() == "thumbnails" || c.as_os_str() == "_resized")
-            {
+            // Skip thumbnails and generated variants directories
+            if path.components().any(|c| {
+                c.as_os_str() == "thumbnails"
+                    || c.as_os_str() == "_resized"
+                    || c.as_os_str() == RESPONSIVE_DIR
+            }) {
                 return None;
             }
 
@@ -674,7 +697,7 @@ pub fn process_media_images(
     }
 
     // Load previous state for incremental builds
-    let state = ResizeState::load(output_media_path);
+    let state = ResizeState::load(output_media_path, STATE_FILE_NAME);
     let cfg_hash = config_hash(&settings);
 
     // Check if config changed
@@ -728,7 +751,7 @@ pub fn process_media_images(
         aggregate_results(results, &state, cfg_hash);
 
     // Save state for incremental builds
-    new_state.save(output_media_path);
+    new_state.save(output_media_path, STATE_FILE_NAME);
 
     // Report final statistics with elapsed time
     let elapsed = start_time.elapsed();
@@ -748,6 +771,552 @@ pub fn process_media_images(
     }
 }
 
+/// Responsive image settings parsed from configuration.
+#[derive(Debug, Clone)]
+struct ResponsiveSettings {
+    widths: Vec<u32>,
+    formats: Vec<String>,
+}
+
+/// Get responsive image settings from config.extra, `None` when
+/// `responsive_image_widths` is not set.
+fn get_responsive_settings(config: &Marmite) -> Option<ResponsiveSettings> {
+    let extra = config.extra.as_ref()?;
+
+    let mut widths: Vec<u32> = extra
+        .get("responsive_image_widths")?
+        .as_sequence()?
+        .iter()
+        .filter_map(serde_yaml::Value::as_u64)
+        .filter_map(|v| u32::try_from(v).ok())
+        .filter_map(|v| validate_width(v, "responsive_image_widths"))
+        .collect();
+    widths.sort_unstable();
+    widths.dedup();
+    if widths.is_empty() {
+        return None;
+    }
+
+    let formats = match extra
+        .get("responsive_image_formats")
+        .and_then(serde_yaml::Value::as_sequence)
+    {
+        Some(values) => values
+            .iter()
+            .filter_map(serde_yaml::Value::as_str)
+            .filter_map(|format| {
+                let format = format.to_lowercase();
+                if matches!(format.as_str(), "avif" | "webp") {
+                    Some(format)
+                } else {
+                    warn!(
+                        "Invalid responsive_image_formats value '{format}'. Valid options: 'avif', 'webp'. Format ignored."
+                    );
+                    None
+                }
+            })
+            .collect(),
+        None => DEFAULT_RESPONSIVE_FORMATS
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+    };
+
+    Some(ResponsiveSettings { widths, formats })
+}
+
+/// Generate a hash of the responsive configuration for change detection
+fn responsive_config_hash(settings: &ResizeSettings, responsive: &ResponsiveSettings) -> String {
+    format!(
+        "{},rw:{:?},rf:{:?}",
+        config_hash(settings),
+        responsive.widths,
+        responsive.formats
+    )
+}
+
+/// A media image served through `srcset` variants.
+///
+/// Planned from the source media before rendering, so the content and the
+/// templates can point at variants that are generated later on.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponsiveImage {
+    /// Path relative to the media folder, e.g. `photos/cat.jpg`
+    pub path: String,
+    /// Width of the image in the output, capped by `max_image_width` or
+    /// `banner_image_width`
+    pub width: u32,
+    /// Height matching `width`
+    pub height: u32,
+    /// Widths of the variants in ascending order, the last one is `width`
+    pub widths: Vec<u32>,
+    /// Formats emitted on top of the original one, e.g. `avif`
+    pub formats: Vec<String>,
+}
+
+impl ResponsiveImage {
+    /// Lowercase extension of the original image
+    fn extension(&self) -> String {
+        Path::new(&self.path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_lowercase()
+    }
+
+    /// Path of a variant relative to the media folder,
+    /// e.g. `_responsive/photos/cat-480w.webp`
+    fn variant_path(&self, width: u32, format: &str) -> String {
+        let path = Path::new(&self.path);
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let file_name = format!("{stem}-{width}w.{format}");
+        match path
+            .parent()
+            .and_then(|p| p.to_str())
+            .filter(|p| !p.is_empty())
+        {
+            Some(parent) => format!("{RESPONSIVE_DIR}/{parent}/{file_name}"),
+            None => format!("{RESPONSIVE_DIR}/{file_name}"),
+        }
+    }
+
+    /// Variants to generate as `(width, format)`. The original format at
+    /// full width is the media file itself.
+    fn variants(&self) -> Vec<(u32, String)> {
+        let extension = self.extension();
+        let mut variants: Vec<(u32, String)> = self
+            .widths
+            .iter()
+            .filter(|width| **width < self.width)
+            .map(|width| (*width, extension.clone()))
+            .collect();
+        for format in &self.formats {
+            variants.extend(self.widths.iter().map(|width| (*width, format.clone())));
+        }
+        variants
+    }
+
+    /// Height of the variant with the given width
+    fn height_for(&self, width: u32) -> u32 {
+        scaled_height(self.width, self.height, width)
+    }
+
+    /// `srcset` value for one format, `media_url` is the URL of the media
+    /// folder as written in the page, e.g. `media/`
+    fn srcset(&self, media_url: &str, format: &str) -> String {
+        let original = format == self.extension();
+        self.widths
+            .iter()
+            .map(|width| {
+                let path = if original && *width == self.width {
+                    self.path.clone()
+                } else {
+                    self.variant_path(*width, format)
+                };
+                format!("{media_url}{} {width}w", encode_srcset_path(&path))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Wrap an `<img>` tag pointing at this image into a `<picture>` with a
+    /// `<source>` per extra format, adding `srcset`, `sizes` and, when
+    /// missing, `width` and `height` to the tag.
+    fn to_picture(&self, img_tag: &str, media_url: &str) -> String {
+        let sizes = format!(
+            "(max-width: {width}px) 100vw, {width}px",
+            width = self.width
+        );
+        let mut html = String::from("<picture>");
+        for format in &self.formats {
+            let _ = write!(
+                html,
+                r#"<source type="image/{format}" srcset="{}" sizes="{sizes}">"#,
+                self.srcset(media_url, format)
+            );
+        }
+
+        let (attributes, close) = img_tag
+            .strip_suffix("/>")
+            .map_or((img_tag.trim_end_matches('>'), ">"), |attributes| {
+                (attributes, " />")
+            });
+        html.push_str(attributes.trim_end());
+        let _ = write!(
+            html,
+            r#" srcset="{}" sizes="{sizes}""#,
+            self.srcset(media_url, &self.extension())
+        );
+        if !has_attribute(img_tag, "width") && !has_attribute(img_tag, "height") {
+            let _ = write!(html, r#" width="{}" height="{}""#, self.width, self.height);
+        }
+        html.push_str(close);
+        html.push_str("</picture>");
+        html
+    }
+}
+
+/// Height of an image scaled to `target_width`, keeping the aspect ratio
+fn scaled_height(width: u32, height: u32, target_width: u32) -> u32 {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let scaled = (f64::from(height) * f64::from(target_width) / f64::from(width)).round() as u32;
+    scaled.max(1)
+}
+
+/// Escape a media path for use inside a `srcset` attribute, where spaces
+/// and commas separate the candidates
+fn encode_srcset_path(path: &str) -> String {
+    path.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace(' ', "%20")
+        .replace(',', "%2C")
+}
+
+/// Check if an HTML tag has the given attribute
+fn has_attribute(tag: &str, name: &str) -> bool {
+    tag.split(char::is_whitespace).skip(1).any(|part| {
+        part.split('=')
+            .next()
+            .is_some_and(|attribute| attribute.eq_ignore_ascii_case(name))
+    })
+}
+
+/// Split an image `src` into the URL of the media folder and the path
+/// inside it.
+///
+/// # Examples
+/// - `"media/cat.jpg"` -> `("media/", "cat.jpg")`
+/// - `"../media/photos/cat.jpg"` -> `("../media/", "photos/cat.jpg")`
+/// - `"https://example.com/cat.jpg"` -> `None` (external)
+fn split_media_src<'a>(src: &'a str, media_path: &str) -> Option<(&'a str, &'a str)> {
+    if src.contains("://") || src.starts_with("//") || src.starts_with("data:") {
+        return None;
+    }
+
+    let mut rest = src;
+    while let Some(stripped) = rest
+        .strip_prefix("./")
+        .or_else(|| rest.strip_prefix("../"))
+        .or_else(|| rest.strip_prefix('/'))
+    {
+        rest = stripped;
+    }
+
+    let relative = rest
+        .strip_prefix(media_path.trim_matches('/'))?
+        .strip_prefix('/')?;
+    if relative.is_empty() || relative.contains(['?', '#']) {
+        return None;
+    }
+    Some((&src[..src.len() - relative.len()], relative))
+}
+
+/// Rewrite the `<img>` tags pointing at images with responsive variants
+/// into `<picture>` elements. Tags that already have a `srcset` are left
+/// untouched.
+pub fn rewrite_img_tags(
+    html: &str,
+    images: &HashMap<String, ResponsiveImage>,
+    media_path: &str,
+) -> String {
+    if images.is_empty() || !html.contains("<img") {
+        return html.to_string();
+    }
+
+    let img_re = Regex::new(re::MATCH_IMG_HTMLTAG).expect("Image tag regex should compile");
+    let src_re =
+        Regex::new(re::CAPTURE_SRC_FROM_IMG_HTMLTAG).expect("Image src regex should compile");
+
+    img_re
+        .replace_all(html, |caps: &regex::Captures| {
+            let tag = &caps[0];
+            if has_attribute(tag, "srcset") {
+                return tag.to_string();
+            }
+            src_re
+                .captures(tag)
+                .and_then(|src| split_media_src(src.get(1)?.as_str(), media_path))
+                .and_then(|(media_url, relative)| {
+                    images
+                        .get(relative)
+                        .map(|image| image.to_picture(tag, media_url))
+                })
+                .unwrap_or_else(|| tag.to_string())
+        })
+        .into_owned()
+}
+
+/// Read the dimensions of an image as displayed, after EXIF orientation,
+/// without decoding the pixels
+fn read_dimensions(path: &Path) -> Result<(u32, u32), ImageError> {
+    let mut decoder = ImageReader::open(path)
+        .map_err(ImageError::IoError)?
+        .with_guessed_format()
+        .map_err(ImageError::IoError)?
+        .into_decoder()?;
+    let (width, height) = decoder.dimensions();
+    let rotated = matches!(
+        decoder.orientation(),
+        Ok(Orientation::Rotate90
+            | Orientation::Rotate270
+            | Orientation::Rotate90FlipH
+            | Orientation::Rotate270FlipH)
+    );
+    Ok(if rotated {
+        (height, width)
+    } else {
+        (width, height)
+    })
+}
+
+/// Apply the resize settings to an image uploaded through the dev server,
+/// in place: banners to `banner_image_width`, other images to
+/// `max_image_width`. GIFs are kept as they are so animations keep working.
+///
+/// Returns the final dimensions and whether the image was resized.
+pub fn resize_uploaded_image(
+    path: &Path,
+    config: &Marmite,
+) -> Result<(u32, u32, bool), ImageError> {
+    let (width, height) = read_dimensions(path)?;
+    let is_gif = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+    if config.skip_image_resize || is_gif || !is_image_file(path) {
+        return Ok((width, height, false));
+    }
+    let settings = get_resize_settings(config);
+    let target = if is_banner_image(path) {
+        settings.banner_width
+    } else {
+        settings.max_width
+    };
+    match target {
+        Some(max_width) if width > max_width => {
+            resize_image(path, path, max_width, settings.filter)?;
+            let (width, height) = read_dimensions(path)?;
+            Ok((width, height, true))
+        }
+        _ => Ok((width, height, false)),
+    }
+}
+
+/// Plan the responsive variants of the images in the source media folder,
+/// keyed by their path relative to it.
+///
+/// Returns an empty map unless `responsive_image_widths` is set. GIFs are
+/// left out so animations keep working.
+pub fn collect_responsive_images(
+    media_source: &Path,
+    config: &Marmite,
+    banner_paths: &HashSet<String>,
+) -> HashMap<String, ResponsiveImage> {
+    let Some(responsive) = get_responsive_settings(config) else {
+        return HashMap::new();
+    };
+    let settings = get_resize_settings(config);
+
+    collect_image_paths(media_source)
+        .par_iter()
+        .filter_map(|path| {
+            let relative_path = path
+                .strip_prefix(media_source)
+                .ok()?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let extension = path.extension()?.to_str()?.to_lowercase();
+            if extension == "gif" {
+                return None;
+            }
+
+            let (width, height) = read_dimensions(path)
+                .inspect_err(|e| warn!("Failed to read image {}: {e}", path.display()))
+                .ok()?;
+            let is_banner = is_banner_image(path) || banner_paths.contains(&relative_path);
+            let max_width = if is_banner {
+                settings.banner_width
+            } else {
+                settings.max_width
+            };
+            let target_width = max_width.map_or(width, |max_width| width.min(max_width));
+
+            let mut widths: Vec<u32> = responsive
+                .widths
+                .iter()
+                .copied()
+                .filter(|w| *w < target_width)
+                .collect();
+            widths.push(target_width);
+
+            let image = ResponsiveImage {
+                path: relative_path.clone(),
+                width: target_width,
+                height: scaled_height(width, height, target_width),
+                widths,
+                formats: responsive
+                    .formats
+                    .iter()
+                    .filter(|format| **format != extension)
+                    .cloned()
+                    .collect(),
+            };
+            Some((relative_path, image))
+        })
+        .collect()
+}
+
+/// Save a variant, AVIF and WebP go through their encoders so the speed
+/// and color type can be set.
+fn save_variant(img: &DynamicImage, path: &Path, format: &str) -> Result<(), ImageError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(ImageError::IoError)?;
+    }
+    match format {
+        "avif" => {
+            let writer = BufWriter::new(File::create(path).map_err(ImageError::IoError)?);
+            let encoder = AvifEncoder::new_with_speed_quality(writer, AVIF_SPEED, AVIF_QUALITY);
+            DynamicImage::ImageRgba8(img.to_rgba8()).write_with_encoder(encoder)
+        }
+        "webp" => {
+            let writer = BufWriter::new(File::create(path).map_err(ImageError::IoError)?);
+            let encoder = WebPEncoder::new_lossless(writer);
+            DynamicImage::ImageRgba8(img.to_rgba8()).write_with_encoder(encoder)
+        }
+        _ => img.save(path),
+    }
+}
+
+/// Generate every variant of one image from its source
+fn generate_variants(
+    source: &Path,
+    output_media_path: &Path,
+    image: &ResponsiveImage,
+    filter: FilterType,
+) -> Result<(), ImageError> {
+    let img = open_and_orient(source)?;
+    for (width, format) in image.variants() {
+        let resized = img.resize_exact(width, image.height_for(width), filter);
+        save_variant(
+            &resized,
+            &output_media_path.join(image.variant_path(width, &format)),
+            &format,
+        )?;
+    }
+    debug!(
+        "Generated responsive variants for {} ({:?})",
+        source.display(),
+        image.widths
+    );
+    Ok(())
+}
+
+/// Generate the variants of a single image unless they are up to date
+fn process_responsive_image(
+    image: &ResponsiveImage,
+    media_source: &Path,
+    output_media_path: &Path,
+    filter: FilterType,
+    state: &ResizeState,
+    cfg_hash: &str,
+) -> ProcessResult {
+    let source = media_source.join(&image.path);
+    let (source_size, source_modified) = get_file_metadata(&source).unwrap_or((0, 0));
+
+    let cached = !state.needs_processing(
+        &image.path,
+        source_size,
+        source_modified,
+        image.width,
+        cfg_hash,
+    ) && image.variants().iter().all(|(width, format)| {
+        output_media_path
+            .join(image.variant_path(*width, format))
+            .exists()
+    });
+
+    let result = if cached {
+        debug!("Cached (unchanged): {}", source.display());
+        ProcessOutcome::Cached
+    } else {
+        match generate_variants(&source, output_media_path, image, filter) {
+            Ok(()) => ProcessOutcome::Resized,
+            Err(e) => {
+                error!(
+                    "Failed to generate responsive variants for {}: {e}",
+                    source.display()
+                );
+                ProcessOutcome::Error
+            }
+        }
+    };
+
+    ProcessResult {
+        relative_path: image.path.clone(),
+        source_size,
+        source_modified,
+        target_width: image.width,
+        result,
+    }
+}
+
+/// Generate the variants planned by `collect_responsive_images` from the
+/// source media into `{output_media_path}/_responsive`.
+///
+/// Variants are made from the original images, not from the resized copies,
+/// and images that did not change since the last build are skipped.
+pub fn process_responsive_images(
+    media_source: &Path,
+    output_media_path: &Path,
+    config: &Marmite,
+    images: &HashMap<String, ResponsiveImage>,
+) {
+    if images.is_empty() {
+        return;
+    }
+    let Some(responsive) = get_responsive_settings(config) else {
+        return;
+    };
+    let settings = get_resize_settings(config);
+
+    let state = ResizeState::load(output_media_path, RESPONSIVE_STATE_FILE_NAME);
+    let cfg_hash = responsive_config_hash(&settings, &responsive);
+    if !state.config_hash.is_empty() && state.config_hash != cfg_hash {
+        info!("Responsive image configuration changed, regenerating all variants");
+    }
+
+    info!(
+        "Generating responsive variants for {} images (parallel)...",
+        images.len()
+    );
+    let start_time = Instant::now();
+
+    let results: Vec<ProcessResult> = images
+        .par_iter()
+        .map(|(_, image)| {
+            process_responsive_image(
+                image,
+                media_source,
+                output_media_path,
+                settings.filter,
+                &state,
+                &cfg_hash,
+            )
+        })
+        .collect();
+
+    let (new_state, generated_count, _, cached_count, error_count) =
+        aggregate_results(results, &state, cfg_hash);
+    new_state.save(output_media_path, RESPONSIVE_STATE_FILE_NAME);
+
+    let elapsed_secs = start_time.elapsed().as_secs_f64();
+    info!(
+        "Responsive images complete in {elapsed_secs:.2}s: {generated_count} generated, {cached_count} cached, {error_count} errors"
+    );
+}
+
 #[cfg(test)]
 #[path = "tests/image_resize.rs"]
 mod tests;
@@ -0,0 +1,1216 @@
+//! Importer behind `marmite <folder> import <format> <source>`.
+//!
+//! Reads a WordPress WXR export, a Jekyll site or a Hugo site and writes each
+//! post and page as a markdown file with the frontmatter keys marmite reads:
+//! `title`, `slug`, `date`, `description`, `tags`, `authors`, `series`,
+//! `stream`, `card_image` and `aliases`. The old URL of each content becomes
+//! an alias, so the redirect pages keep the existing links working, and the
+//! media used by a content is downloaded or copied to `{media_path}/{slug}/`.
+use crate::cli::{Cli, ImportArgs, ImportFormat};
+use crate::content::{alias_output_path, try_to_parse_date};
+use crate::parser::parse_front_matter;
+use crate::site::{get_content_folder, Data};
+use crate::slugify::slugify;
+use crate::upload::safe_filename;
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use frontmatter_gen::{Frontmatter, Value};
+use log::{info, warn};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Extensions of the linked files that are imported as media
+const MEDIA_EXTENSIONS: [&str; 17] = [
+    "jpg", "jpeg", "png", "gif", "webp", "svg", "avif", "bmp", "tiff", "ico", "pdf", "mp3", "mp4",
+    "webm", "ogg", "wav", "zip",
+];
+
+/// `src` and `href` attributes and markdown link targets
+const REFERENCE_PATTERN: &str = r#"(?:src|href)\s*=\s*["']([^"']+)["']|\]\(\s*<?([^)\s>]+)"#;
+
+/// Where the media linked by an imported content is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaBase {
+    /// `wp-content/uploads` of the WordPress site at this URL
+    Remote(String),
+    /// Root-relative links are read from `root`, relative links from `dir`,
+    /// files outside the `site` folder are never read
+    Local {
+        site: PathBuf,
+        root: PathBuf,
+        dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum MediaSource {
+    Url(String),
+    File(PathBuf),
+}
+
+/// A post or page converted to marmite
+#[derive(Debug, Clone, PartialEq)]
+pub struct Imported {
+    pub title: String,
+    pub slug: String,
+    /// `None` for pages
+    pub date: Option<NaiveDateTime>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub authors: Vec<String>,
+    pub series: Option<String>,
+    pub stream: Option<String>,
+    pub card_image: Option<String>,
+    /// Old URLs, as paths from the site root
+    pub aliases: Vec<String>,
+    /// Absolute URL on the old site, used to point links between imported
+    /// contents to the new pages
+    pub link: Option<String>,
+    pub body: String,
+    pub media_base: MediaBase,
+}
+
+#[derive(Serialize)]
+struct ImportedFrontmatter<'a> {
+    title: &'a str,
+    slug: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    authors: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    series: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    card_image: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    aliases: &'a [String],
+}
+
+impl Imported {
+    /// Slug of the generated page, marmite prefixes it with the stream
+    pub fn final_slug(&self) -> String {
+        match self.stream.as_deref() {
+            Some(stream) if stream != "index" => format!("{stream}-{}", self.slug),
+            _ => self.slug.clone(),
+        }
+    }
+
+    /// Keeps the aliases that are valid and not the page itself
+    fn clean_aliases(&mut self) {
+        let own_page = format!("{}.html", self.final_slug());
+        let mut seen = HashSet::new();
+        self.aliases.retain(|alias| {
+            alias_output_path(alias).is_some_and(|file| file != own_page && seen.insert(file))
+        });
+    }
+
+    /// The markdown file with its frontmatter
+    pub fn to_markdown(&self) -> String {
+        let frontmatter = ImportedFrontmatter {
+            title: &self.title,
+            slug: &self.slug,
+            date: self
+                .date
+                .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string()),
+            description: self.description.as_deref(),
+            tags: &self.tags,
+            authors: &self.authors,
+            series: self.series.as_deref(),
+            stream: self.stream.as_deref(),
+            card_image: self.card_image.as_deref(),
+            aliases: &self.aliases,
+        };
+        let yaml = serde_yaml::to_string(&frontmatter).unwrap_or_default();
+        format!("---\n{yaml}---\n\n{}\n", self.body.trim())
+    }
+}
+
+fn parse_date(input: &str) -> Option<NaiveDateTime> {
+    try_to_parse_date(&input.trim().replacen('T', " ", 1)).ok()
+}
+
+fn title_from_slug(slug: &str) -> String {
+    slug.split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .enumerate()
+        .map(|(index, word)| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) if index == 0 => first.to_uppercase().chain(chars).collect(),
+                _ => word.to_string(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn push_unique(list: &mut Vec<String>, values: impl IntoIterator<Item = String>) {
+    for value in values {
+        if !value.is_empty() && !list.contains(&value) {
+            list.push(value);
+        }
+    }
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.trim().to_string(),
+        other => other.to_string().trim_matches('"').trim().to_string(),
+    }
+}
+
+/// A text field of the frontmatter, the first item of a list
+fn text(frontmatter: &Frontmatter, key: &str) -> Option<String> {
+    match frontmatter.get(key)? {
+        Value::Array(items) => items.first().map(value_to_string),
+        Value::Null | Value::Object(_) => None,
+        value => Some(value_to_string(value)),
+    }
+    .filter(|value| !value.is_empty())
+}
+
+/// Items of a list, or of a string split on `separator`
+fn terms(frontmatter: &Frontmatter, key: &str, separator: fn(char) -> bool) -> Vec<String> {
+    match frontmatter.get(key) {
+        Some(Value::Array(items)) => items.iter().map(value_to_string).collect(),
+        Some(Value::String(text)) => text
+            .split(separator)
+            .map(|t| t.trim().to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+    .into_iter()
+    .filter(|term| !term.is_empty())
+    .collect()
+}
+
+fn is_true(frontmatter: &Frontmatter, key: &str) -> bool {
+    matches!(frontmatter.get(key), Some(Value::Boolean(true)))
+}
+
+fn is_false(frontmatter: &Frontmatter, key: &str) -> bool {
+    matches!(frontmatter.get(key), Some(Value::Boolean(false)))
+}
+
+/// Replaces the `:name` placeholders of a permalink pattern, unknown names
+/// are kept
+pub fn expand_permalink(pattern: &str, vars: &HashMap<&str, String>) -> String {
+    let re = Regex::new(r":([a-z_]+)").expect("Permalink regex should compile");
+    let path = re.replace_all(pattern, |caps: &Captures| {
+        vars.get(&caps[1])
+            .cloned()
+            .unwrap_or_else(|| caps[0].to_string())
+    });
+    let slashes = Regex::new(r"/{2,}").expect("Slash regex should compile");
+    slashes.replace_all(&path, "/").to_string()
+}
+
+fn date_vars(date: &NaiveDateTime) -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("year", date.format("%Y").to_string()),
+        ("short_year", date.format("%y").to_string()),
+        ("month", date.format("%m").to_string()),
+        ("i_month", date.month().to_string()),
+        ("monthname", date.format("%B").to_string().to_lowercase()),
+        ("day", date.format("%d").to_string()),
+        ("i_day", date.day().to_string()),
+        ("y_day", format!("{:03}", date.ordinal())),
+        ("hour", format!("{:02}", date.hour())),
+        ("minute", format!("{:02}", date.minute())),
+        ("second", format!("{:02}", date.second())),
+    ])
+}
+
+/// Path of `url` without the scheme and host, `None` for query URLs
+fn url_path(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    if parsed.query().is_some() {
+        return None;
+    }
+    Some(parsed.path().to_string()).filter(|path| path != "/")
+}
+
+fn strip_tags(html: &str) -> String {
+    let re = Regex::new(r"<[^>]*>").expect("Tag regex should compile");
+    unescape_html(re.replace_all(html, "").trim())
+}
+
+/// Decodes the HTML entities WordPress leaves in titles and code
+pub fn unescape_html(text: &str) -> String {
+    let re = Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|amp|lt|gt|quot|apos|nbsp);")
+        .expect("Entity regex should compile");
+    re.replace_all(text, |caps: &Captures| {
+        let entity = &caps[1];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .map_or_else(
+                    || entity[1..].parse::<u32>().ok(),
+                    |hex| u32::from_str_radix(hex, 16).ok(),
+                )
+                .and_then(char::from_u32),
+        };
+        decoded.map_or_else(|| caps[0].to_string(), String::from)
+    })
+    .to_string()
+}
+
+fn collapse_blank_lines(text: &str) -> String {
+    let re = Regex::new(r"\n{3,}").expect("Blank lines regex should compile");
+    re.replace_all(text.trim(), "\n\n").to_string()
+}
+
+/// Keeps the HTML of a WordPress post, markdown renders it as it is, without
+/// the block editor comments, the responsive image attributes and with the
+/// `[caption]` and `[code]` shortcodes converted
+pub fn convert_wordpress(html: &str) -> String {
+    let blocks = Regex::new(r"<!-- /?wp:[^>]*-->").expect("Block regex should compile");
+    let responsive =
+        Regex::new(r#"\s(?:srcset|sizes)="[^"]*""#).expect("Srcset regex should compile");
+    let caption = Regex::new(r"\[/?caption[^\]]*\]").expect("Caption regex should compile");
+    let code = Regex::new(
+        r#"(?s)\[(code|sourcecode)(?:[^\]]*?\s(?:lang|language)="?([\w+#-]+)"?)?[^\]]*\](.*?)\[/(?:code|sourcecode)\]"#,
+    )
+    .expect("Code regex should compile");
+
+    let html = blocks.replace_all(html, "");
+    let html = responsive.replace_all(&html, "");
+    let html = caption.replace_all(&html, "");
+    let html = code.replace_all(&html, |caps: &Captures| {
+        let lang = caps.get(2).map_or("", |m| m.as_str());
+        format!(
+            "\n\n```{lang}\n{}\n```\n\n",
+            unescape_html(caps[3].trim_matches('\n'))
+        )
+    });
+    collapse_blank_lines(&html)
+}
+
+/// Replaces the Liquid tags marmite has an equivalent for
+pub fn convert_liquid(body: &str) -> String {
+    let highlight = Regex::new(r"\{%-?\s*highlight\s+([\w+#-]+)[^%]*-?%\}")
+        .expect("Highlight regex should compile");
+    let end_highlight =
+        Regex::new(r"\{%-?\s*endhighlight\s*-?%\}").expect("Highlight regex should compile");
+    let raw = Regex::new(r"\{%-?\s*(?:end)?raw\s*-?%\}").expect("Raw regex should compile");
+    let post_url = Regex::new(r"\{%-?\s*post_url\s+(?:[^\s%]*/)?([^\s%/]+)\s*-?%\}")
+        .expect("Post url regex should compile");
+    let site_url = Regex::new(r"\{\{-?\s*site\.(?:baseurl|url)\s*-?\}\}")
+        .expect("Site url regex should compile");
+    let url_filter =
+        Regex::new(r#"\{\{-?\s*["']([^"']+)["']\s*\|\s*(?:relative_url|absolute_url)\s*-?\}\}"#)
+            .expect("Url filter regex should compile");
+
+    let body = highlight.replace_all(body, "```$1");
+    let body = end_highlight.replace_all(&body, "```");
+    let body = raw.replace_all(&body, "");
+    let body = post_url.replace_all(&body, |caps: &Captures| {
+        format!("{}.html", slugify(split_date_prefix(&caps[1]).1))
+    });
+    let body = site_url.replace_all(&body, "");
+    url_filter.replace_all(&body, "$1").to_string()
+}
+
+/// Replaces the Hugo shortcodes marmite has an equivalent for
+pub fn convert_hugo_shortcodes(body: &str) -> String {
+    let figure = Regex::new(r"\{\{[<%]\s*figure\s+(.*?)\s*/?\s*[>%]\}\}")
+        .expect("Figure regex should compile");
+    let attribute = Regex::new(r#"(\w+)\s*=\s*"([^"]*)""#).expect("Attribute regex should compile");
+    let highlight = Regex::new(r"\{\{[<%]\s*highlight\s+([\w+#-]+)[^}]*?[>%]\}\}")
+        .expect("Highlight regex should compile");
+    let end_highlight =
+        Regex::new(r"\{\{[<%]\s*/highlight\s*[>%]\}\}").expect("Highlight regex should compile");
+    let reference = Regex::new(r#"\{\{[<%]\s*(?:rel)?ref\s+"([^"]+)"\s*[>%]\}\}"#)
+        .expect("Ref regex should compile");
+
+    let body = figure.replace_all(body, |caps: &Captures| {
+        let attrs: HashMap<&str, &str> = attribute
+            .captures_iter(caps.get(1).map_or("", |m| m.as_str()))
+            .filter_map(|attr| Some((attr.get(1)?.as_str(), attr.get(2)?.as_str())))
+            .collect();
+        let alt = ["alt", "caption", "title"]
+            .iter()
+            .find_map(|key| attrs.get(key))
+            .unwrap_or(&"");
+        format!("![{alt}]({})", attrs.get("src").unwrap_or(&""))
+    });
+    let body = highlight.replace_all(&body, "```$1");
+    let body = end_highlight.replace_all(&body, "```");
+    reference
+        .replace_all(&body, |caps: &Captures| {
+            let (target, anchor) = caps[1].split_once('#').unwrap_or((&caps[1], ""));
+            let path = Path::new(target.trim_end_matches('/'));
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            let name = if stem == "index" || stem == "_index" {
+                path.parent()
+                    .and_then(|p| p.file_name())
+                    .and_then(|s| s.to_str())
+                    .unwrap_or(stem)
+            } else {
+                stem
+            };
+            let anchor = if anchor.is_empty() {
+                String::new()
+            } else {
+                format!("#{anchor}")
+            };
+            format!("{}.html{anchor}", slugify(name))
+        })
+        .to_string()
+}
+
+/// `2024-01-31-my-post` is split into the date and `my-post`
+fn split_date_prefix(name: &str) -> (Option<NaiveDateTime>, &str) {
+    let re = Regex::new(r"^(\d{4}-\d{2}-\d{2})-(.+)$").expect("Date prefix regex should compile");
+    re.captures(name)
+        .and_then(|caps| {
+            let rest = caps.get(2)?.as_str();
+            Some((parse_date(caps.get(1)?.as_str()), rest))
+        })
+        .unwrap_or((None, name))
+}
+
+fn modified_time(path: &Path) -> Option<NaiveDateTime> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(chrono::DateTime::<chrono::Local>::from(modified).naive_local())
+}
+
+fn is_markdown(path: &Path, with_html: bool) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext, "md" | "markdown") || (with_html && ext == "html"))
+}
+
+#[derive(Debug, Default)]
+struct WxrItem {
+    title: String,
+    link: String,
+    creator: String,
+    content: String,
+    excerpt: String,
+    post_id: String,
+    post_date: String,
+    post_date_gmt: String,
+    post_name: String,
+    status: String,
+    post_type: String,
+    attachment_url: String,
+    categories: Vec<String>,
+    tags: Vec<String>,
+    meta: Vec<(String, String)>,
+}
+
+/// Reads the posts and pages of a WordPress WXR export
+pub fn parse_wxr(xml: &str) -> Result<Vec<Imported>, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut items: Vec<WxrItem> = Vec::new();
+    let mut item: Option<WxrItem> = None;
+    let mut site_url = String::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut category_domain: Option<String> = None;
+    let mut meta_key = String::new();
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid WXR file at byte {}: {e}", reader.buffer_position()))?;
+        match event {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.name().as_ref()).to_string();
+                if name == "item" {
+                    item = Some(WxrItem::default());
+                } else if name == "category" {
+                    category_domain = element
+                        .try_get_attribute("domain")
+                        .ok()
+                        .flatten()
+                        .map(|attr| String::from_utf8_lossy(&attr.value).to_string());
+                }
+                path.push(name);
+                text.clear();
+            }
+            Event::Text(content) => {
+                text.push_str(&content.decode().map_err(|e| e.to_string())?);
+            }
+            Event::CData(content) => {
+                text.push_str(&content.decode().map_err(|e| e.to_string())?);
+            }
+            Event::GeneralRef(reference) => {
+                if let Ok(Some(c)) = reference.resolve_char_ref() {
+                    text.push(c);
+                } else {
+                    let name = reference.decode().map_err(|e| e.to_string())?;
+                    text.push_str(match name.as_ref() {
+                        "amp" => "&",
+                        "lt" => "<",
+                        "gt" => ">",
+                        "quot" => "\"",
+                        "apos" => "'",
+                        _ => "",
+                    });
+                }
+            }
+            Event::End(_) => {
+                let name = path.pop().unwrap_or_default();
+                let value = std::mem::take(&mut text);
+                let Some(current) = item.as_mut() else {
+                    if name == "wp:base_site_url"
+                        || (name == "link"
+                            && site_url.is_empty()
+                            && path.ends_with(&["channel".to_string()]))
+                    {
+                        site_url = value.trim().to_string();
+                    }
+                    continue;
+                };
+                match name.as_str() {
+                    "item" => items.extend(item.take()),
+                    "title" => current.title = value,
+                    "link" => current.link = value.trim().to_string(),
+                    "dc:creator" => current.creator = value.trim().to_string(),
+                    "content:encoded" => current.content = value,
+                    "excerpt:encoded" => current.excerpt = value,
+                    "wp:post_id" => current.post_id = value.trim().to_string(),
+                    "wp:post_date" => current.post_date = value,
+                    "wp:post_date_gmt" => current.post_date_gmt = value,
+                    "wp:post_name" => current.post_name = value.trim().to_string(),
+                    "wp:status" => current.status = value.trim().to_string(),
+                    "wp:post_type" => current.post_type = value.trim().to_string(),
+                    "wp:attachment_url" => current.attachment_url = value.trim().to_string(),
+                    "category" => match category_domain.take().as_deref() {
+                        Some("post_tag") => current.tags.push(unescape_html(value.trim())),
+                        Some("category") => current.categories.push(unescape_html(value.trim())),
+                        _ => {}
+                    },
+                    "wp:meta_key" => meta_key = value.trim().to_string(),
+                    "wp:meta_value" => current
+                        .meta
+                        .push((std::mem::take(&mut meta_key), value.trim().to_string())),
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let attachments: HashMap<String, String> = items
+        .iter()
+        .filter(|item| item.post_type == "attachment" && !item.attachment_url.is_empty())
+        .map(|item| (item.post_id.clone(), item.attachment_url.clone()))
+        .collect();
+    Ok(items
+        .into_iter()
+        .filter_map(|item| wxr_content(item, &attachments, &site_url))
+        .collect())
+}
+
+fn wxr_content(
+    item: WxrItem,
+    attachments: &HashMap<String, String>,
+    site_url: &str,
+) -> Option<Imported> {
+    let is_post = match item.post_type.as_str() {
+        "post" => true,
+        "page" => false,
+        _ => return None,
+    };
+    let draft = match item.status.as_str() {
+        "publish" | "future" | "" => false,
+        "draft" | "pending" | "private" => true,
+        _ => return None,
+    };
+
+    let title = unescape_html(item.title.trim());
+    let name = urlencoding::decode(&item.post_name)
+        .map_or_else(|_| item.post_name.clone(), |n| n.to_string());
+    let slug = [slugify(&name), slugify(&title)]
+        .into_iter()
+        .find(|slug| !slug.is_empty())
+        .unwrap_or_else(|| format!("{}-{}", item.post_type, item.post_id));
+    let date = is_post.then(|| {
+        parse_date(&item.post_date)
+            .or_else(|| parse_date(&item.post_date_gmt))
+            .unwrap_or_else(|| chrono::Local::now().naive_local())
+    });
+
+    let mut tags = Vec::new();
+    push_unique(&mut tags, item.tags);
+    push_unique(
+        &mut tags,
+        item.categories
+            .into_iter()
+            .filter(|category| category != "Uncategorized"),
+    );
+
+    let card_image = item
+        .meta
+        .iter()
+        .find(|(key, _)| key == "_thumbnail_id")
+        .and_then(|(_, id)| attachments.get(id))
+        .cloned();
+
+    Some(Imported {
+        title: if title.is_empty() {
+            title_from_slug(&slug)
+        } else {
+            title
+        },
+        slug,
+        date,
+        description: Some(strip_tags(&item.excerpt)).filter(|text| !text.is_empty()),
+        tags,
+        authors: Some(item.creator)
+            .filter(|c| !c.is_empty())
+            .into_iter()
+            .collect(),
+        series: None,
+        stream: draft.then(|| "draft".to_string()),
+        card_image,
+        aliases: url_path(&item.link).into_iter().collect(),
+        link: Some(item.link).filter(|link| !link.is_empty()),
+        body: convert_wordpress(&item.content),
+        media_base: MediaBase::Remote(site_url.to_string()),
+    })
+}
+
+/// Permalink pattern of a Jekyll `permalink` setting
+fn jekyll_permalink(style: &str) -> &str {
+    match style {
+        "date" => "/:categories/:year/:month/:day/:title:output_ext",
+        "pretty" => "/:categories/:year/:month/:day/:title/",
+        "ordinal" => "/:categories/:year/:y_day/:title:output_ext",
+        "none" => "/:categories/:title:output_ext",
+        custom => custom,
+    }
+}
+
+fn read_jekyll_file(
+    path: &Path,
+    root: &Path,
+    permalink: &str,
+    is_post: bool,
+    draft: bool,
+) -> Result<Option<Imported>, String> {
+    let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if !is_post && !source.starts_with("---") {
+        // Jekyll only renders pages with a frontmatter
+        return Ok(None);
+    }
+    let (frontmatter, body) = parse_front_matter(&source)?;
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let (file_date, name) = split_date_prefix(stem);
+    let slug = slugify(text(&frontmatter, "slug").as_deref().unwrap_or(name));
+
+    let date = is_post.then(|| {
+        text(&frontmatter, "date")
+            .and_then(|date| parse_date(&date))
+            .or(file_date)
+            .or_else(|| modified_time(path))
+            .unwrap_or_else(|| chrono::Local::now().naive_local())
+    });
+
+    let mut categories = terms(&frontmatter, "categories", char::is_whitespace);
+    push_unique(&mut categories, text(&frontmatter, "category"));
+    let mut tags = terms(&frontmatter, "tags", char::is_whitespace);
+    push_unique(&mut tags, categories.clone());
+
+    let old_url = text(&frontmatter, "permalink").unwrap_or_else(|| {
+        if let Some(date) = date {
+            let mut vars = date_vars(&date);
+            vars.insert("title", name.to_string());
+            vars.insert("slug", slugify(name));
+            vars.insert(
+                "categories",
+                categories.iter().map(slugify).collect::<Vec<_>>().join("/"),
+            );
+            vars.insert("output_ext", ".html".to_string());
+            expand_permalink(permalink, &vars)
+        } else {
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(path)
+                .with_extension("html");
+            format!("/{}", relative.to_string_lossy().replace('\\', "/"))
+        }
+    });
+    let mut aliases = vec![old_url];
+    push_unique(
+        &mut aliases,
+        terms(&frontmatter, "redirect_from", |c| c == ','),
+    );
+
+    let mut authors = terms(&frontmatter, "authors", |c| c == ',');
+    push_unique(&mut authors, text(&frontmatter, "author"));
+
+    let card_image = match frontmatter.get("image") {
+        Some(Value::Object(image)) => text(image, "path"),
+        _ => text(&frontmatter, "image"),
+    };
+
+    Ok(Some(Imported {
+        title: text(&frontmatter, "title").unwrap_or_else(|| title_from_slug(name)),
+        slug,
+        date,
+        description: text(&frontmatter, "description").or_else(|| text(&frontmatter, "excerpt")),
+        tags,
+        authors,
+        series: text(&frontmatter, "series"),
+        stream: (draft || is_false(&frontmatter, "published")).then(|| "draft".to_string()),
+        card_image,
+        aliases,
+        link: None,
+        body: convert_liquid(body),
+        media_base: MediaBase::Local {
+            site: root.to_path_buf(),
+            root: root.to_path_buf(),
+            dir: path.parent().unwrap_or(root).to_path_buf(),
+        },
+    }))
+}
+
+/// Reads `_posts`, `_drafts` and the markdown pages of a Jekyll site
+pub fn read_jekyll(root: &Path) -> Result<Vec<Imported>, String> {
+    if !root.join("_posts").is_dir() {
+        return Err(format!("No _posts folder in {}", root.display()));
+    }
+    let config: serde_yaml::Value = fs::read_to_string(root.join("_config.yml"))
+        .ok()
+        .and_then(|config| serde_yaml::from_str(&config).ok())
+        .unwrap_or_default();
+    let permalink = jekyll_permalink(
+        config
+            .get("permalink")
+            .and_then(serde_yaml::Value::as_str)
+            .unwrap_or("date"),
+    )
+    .to_string();
+
+    let mut files: Vec<(PathBuf, bool, bool)> = Vec::new();
+    for (folder, draft) in [("_posts", false), ("_drafts", true)] {
+        let folder = root.join(folder);
+        if !folder.is_dir() {
+            continue;
+        }
+        for entry in WalkDir::new(folder).sort_by_file_name() {
+            let path = entry.map_err(|e| e.to_string())?.into_path();
+            if path.is_file() && is_markdown(&path, true) {
+                files.push((path, true, draft));
+            }
+        }
+    }
+    let pages = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_name().to_str().is_some_and(|name| {
+                    name.starts_with(['_', '.']) || matches!(name, "node_modules" | "vendor")
+                })
+        })
+        .filter_map(Result::ok)
+        .map(walkdir::DirEntry::into_path)
+        .filter(|path| {
+            path.is_file()
+                && is_markdown(path, false)
+                && path
+                    .file_stem()
+                    .is_some_and(|stem| stem != "index" && stem != "README")
+        });
+    files.extend(pages.map(|path| (path, false, false)));
+
+    let mut contents = Vec::new();
+    for (path, is_post, draft) in files {
+        match read_jekyll_file(&path, root, &permalink, is_post, draft) {
+            Ok(content) => contents.extend(content),
+            Err(e) => warn!("Skipping {}: {e}", path.display()),
+        }
+    }
+    Ok(contents)
+}
+
+/// `permalinks` of the Hugo configuration, keyed by section
+fn hugo_permalinks(site_root: &Path) -> HashMap<String, String> {
+    let names = [
+        "hugo.toml",
+        "hugo.yaml",
+        "hugo.yml",
+        "config.toml",
+        "config.yaml",
+        "config.yml",
+    ];
+    let Some((name, config)) = names
+        .iter()
+        .find_map(|name| Some((*name, fs::read_to_string(site_root.join(name)).ok()?)))
+    else {
+        return HashMap::new();
+    };
+    // The frontmatter parser reads TOML and YAML documents alike
+    let delimiter = if Path::new(name)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+    {
+        "+++"
+    } else {
+        "---"
+    };
+    let document = format!("{delimiter}\n{config}\n{delimiter}\n");
+    let Some(Value::Object(permalinks)) = parse_front_matter(&document)
+        .ok()
+        .and_then(|(frontmatter, _)| frontmatter.get("permalinks").cloned())
+    else {
+        return HashMap::new();
+    };
+    let mut sections = HashMap::new();
+    for (key, value) in permalinks.iter() {
+        match value {
+            Value::String(pattern) => {
+                sections.insert(key.clone(), pattern.clone());
+            }
+            Value::Object(kind) if key == "page" => {
+                for (section, pattern) in kind.iter() {
+                    if let Value::String(pattern) = pattern {
+                        sections.insert(section.clone(), pattern.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    sections
+}
+
+fn read_hugo_file(
+    path: &Path,
+    content_root: &Path,
+    static_root: &Path,
+    permalinks: &HashMap<String, String>,
+) -> Result<Imported, String> {
+    let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let (frontmatter, body) = parse_front_matter(&source)?;
+    let relative = path.strip_prefix(content_root).unwrap_or(path);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let mut folders: Vec<String> = relative
+        .parent()
+        .map(|parent| {
+            parent
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    // A leaf bundle is named by its folder
+    let basename = if stem == "index" {
+        folders.pop().unwrap_or_else(|| stem.to_string())
+    } else {
+        stem.to_string()
+    };
+    let section = folders.first().cloned().unwrap_or_default();
+
+    let title = text(&frontmatter, "title").unwrap_or_else(|| title_from_slug(&basename));
+    let slug = slugify(text(&frontmatter, "slug").as_deref().unwrap_or(&basename));
+    let date = ["date", "publishDate", "pubDate"]
+        .iter()
+        .find_map(|key| text(&frontmatter, key).and_then(|date| parse_date(&date)));
+    let date = date.filter(|_| !section.is_empty());
+
+    let old_url = text(&frontmatter, "url").unwrap_or_else(|| {
+        if let (Some(pattern), Some(date)) = (permalinks.get(&section), date) {
+            let mut vars = date_vars(&date);
+            let url_slug = text(&frontmatter, "slug").unwrap_or_else(|| slugify(&title));
+            vars.insert("section", section.clone());
+            vars.insert("sections", folders.join("/"));
+            vars.insert("title", slugify(&title));
+            vars.insert("slugorfilename", url_slug.clone());
+            vars.insert("slug", url_slug);
+            vars.insert("filename", basename.clone());
+            vars.insert("contentbasename", basename.clone());
+            expand_permalink(pattern, &vars)
+        } else {
+            let mut parts = folders.clone();
+            parts.push(text(&frontmatter, "slug").unwrap_or_else(|| basename.clone()));
+            format!("/{}/", parts.join("/")).to_lowercase()
+        }
+    });
+    let mut aliases = vec![old_url];
+    push_unique(&mut aliases, terms(&frontmatter, "aliases", |c| c == ','));
+
+    let mut tags = terms(&frontmatter, "tags", |c| c == ',');
+    push_unique(&mut tags, terms(&frontmatter, "categories", |c| c == ','));
+    let mut authors = terms(&frontmatter, "authors", |c| c == ',');
+    push_unique(&mut authors, text(&frontmatter, "author"));
+
+    Ok(Imported {
+        title,
+        slug,
+        date,
+        description: text(&frontmatter, "description").or_else(|| text(&frontmatter, "summary")),
+        tags,
+        authors,
+        series: text(&frontmatter, "series"),
+        stream: is_true(&frontmatter, "draft").then(|| "draft".to_string()),
+        card_image: text(&frontmatter, "images").or_else(|| text(&frontmatter, "image")),
+        aliases,
+        link: None,
+        body: convert_hugo_shortcodes(body),
+        media_base: MediaBase::Local {
+            site: static_root.parent().unwrap_or(content_root).to_path_buf(),
+            root: static_root.to_path_buf(),
+            dir: path.parent().unwrap_or(content_root).to_path_buf(),
+        },
+    })
+}
+
+/// Reads the markdown files of a Hugo site, or of its `content` folder.
+/// Files inside a section with a date are posts, the others are pages.
+pub fn read_hugo(source: &Path) -> Result<Vec<Imported>, String> {
+    let (site_root, content_root) = if source.join("content").is_dir() {
+        (source.to_path_buf(), source.join("content"))
+    } else if source.is_dir() {
+        (
+            source.parent().unwrap_or(source).to_path_buf(),
+            source.to_path_buf(),
+        )
+    } else {
+        return Err(format!("{} is not a folder", source.display()));
+    };
+    let static_root = site_root.join("static");
+    let permalinks = hugo_permalinks(&site_root);
+
+    let mut contents = Vec::new();
+    for entry in WalkDir::new(&content_root).sort_by_file_name() {
+        let path = entry.map_err(|e| e.to_string())?.into_path();
+        let is_list = path
+            .file_stem()
+            .is_some_and(|stem| stem.to_string_lossy().starts_with("_index"));
+        if !path.is_file() || !is_markdown(&path, false) || is_list {
+            continue;
+        }
+        match read_hugo_file(&path, &content_root, &static_root, &permalinks) {
+            Ok(content) => contents.push(content),
+            Err(e) => warn!("Skipping {}: {e}", path.display()),
+        }
+    }
+    Ok(contents)
+}
+
+/// Links of the body that point to files with a media extension
+pub fn media_references(body: &str) -> Vec<String> {
+    let re = Regex::new(REFERENCE_PATTERN).expect("Reference regex should compile");
+    let mut references: Vec<String> = Vec::new();
+    for caps in re.captures_iter(body) {
+        let Some(reference) = caps.get(1).or_else(|| caps.get(2)) else {
+            continue;
+        };
+        let reference = reference.as_str().to_string();
+        if is_media(&reference) && !references.contains(&reference) {
+            references.push(reference);
+        }
+    }
+    references
+}
+
+fn is_media(reference: &str) -> bool {
+    let path = reference.split(['?', '#']).next().unwrap_or_default();
+    path.rsplit_once('.').is_some_and(|(_, ext)| {
+        MEDIA_EXTENSIONS.contains(&ext.to_lowercase().as_str()) && !ext.contains('/')
+    })
+}
+
+fn resolve_media(reference: &str, base: &MediaBase) -> Option<MediaSource> {
+    let path = reference.split(['?', '#']).next()?;
+    match base {
+        MediaBase::Remote(site) => {
+            let url = if let Some(rest) = path.strip_prefix("//") {
+                format!("https://{rest}")
+            } else if path.starts_with('/') {
+                format!("{}{path}", site.trim_end_matches('/'))
+            } else {
+                path.to_string()
+            };
+            (url.starts_with("http") && url.contains("/wp-content/uploads/"))
+                .then_some(MediaSource::Url(url))
+        }
+        MediaBase::Local { site, root, dir } => {
+            if path.contains("://") || path.starts_with("//") || path.starts_with("mailto:") {
+                return None;
+            }
+            let path = urlencoding::decode(path).ok()?;
+            let file = match path.strip_prefix('/') {
+                Some(relative) => root.join(relative),
+                None => dir.join(path.as_ref()),
+            };
+            // `..` and symlinks must not reach files outside the source site
+            let file = file.canonicalize().ok()?;
+            let site = site.canonicalize().ok()?;
+            (file.starts_with(&site) && file.is_file()).then_some(MediaSource::File(file))
+        }
+    }
+}
+
+fn fetch(source: &MediaSource, target: &Path) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let result = match source {
+        MediaSource::File(path) => fs::copy(path, target)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        MediaSource::Url(url) => {
+            ureq::get(url)
+                .call()
+                .map_err(|e| e.to_string())
+                .and_then(|response| {
+                    let mut file = fs::File::create(target).map_err(|e| e.to_string())?;
+                    std::io::copy(&mut response.into_body().as_reader(), &mut file)
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                })
+        }
+    };
+    if result.is_err() {
+        let _ = fs::remove_file(target);
+    }
+    result
+}
+
+/// Points the links of `body` found in `replacements` to their new target
+pub fn rewrite_references(body: &str, replacements: &HashMap<String, String>) -> String {
+    let re = Regex::new(REFERENCE_PATTERN).expect("Reference regex should compile");
+    re.replace_all(body, |caps: &Captures| {
+        let whole = caps.get(0).expect("A match has a whole group");
+        let Some(target) = caps.get(1).or_else(|| caps.get(2)) else {
+            return whole.as_str().to_string();
+        };
+        match replacements.get(target.as_str()) {
+            Some(new) => {
+                let start = target.start() - whole.start();
+                let end = target.end() - whole.start();
+                format!(
+                    "{}{new}{}",
+                    &whole.as_str()[..start],
+                    &whole.as_str()[end..]
+                )
+            }
+            None => whole.as_str().to_string(),
+        }
+    })
+    .to_string()
+}
+
+/// Downloads or copies the media linked by `content` to
+/// `{media_path}/{slug}/` and points the links to the new files. Files that
+/// already exist are kept, so an import can run again.
+fn import_media(
+    content: &mut Imported,
+    content_folder: &Path,
+    media_path: &str,
+    dry_run: bool,
+) -> usize {
+    let mut references = media_references(&content.body);
+    push_unique(&mut references, content.card_image.clone());
+    let url_dir = format!("{media_path}/{}", content.slug);
+    let dir = content_folder.join(&url_dir);
+
+    let mut names: HashMap<MediaSource, String> = HashMap::new();
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut replacements = HashMap::new();
+    for reference in references {
+        let Some(source) = resolve_media(&reference, &content.media_base) else {
+            continue;
+        };
+        let name = if let Some(name) = names.get(&source) {
+            name.clone()
+        } else {
+            let file_name = reference
+                .split(['?', '#'])
+                .next()
+                .and_then(|path| path.rsplit('/').next())
+                .map(|name| {
+                    urlencoding::decode(name).map_or_else(|_| name.to_string(), |n| n.to_string())
+                })
+                .and_then(|name| safe_filename(&name));
+            let Some(file_name) = file_name else {
+                continue;
+            };
+            let (stem, ext) = file_name.rsplit_once('.').unwrap_or((&file_name, ""));
+            let mut name = file_name.clone();
+            let mut counter = 1;
+            while taken.contains(&name) {
+                counter += 1;
+                name = format!("{stem}-{counter}.{ext}");
+            }
+            let target = dir.join(&name);
+            if !dry_run && !target.exists() {
+                if let Err(e) = fetch(&source, &target) {
+                    warn!("Failed to import {reference} of '{}': {e}", content.slug);
+                    continue;
+                }
+            }
+            taken.insert(name.clone());
+            names.insert(source, name.clone());
+            name
+        };
+        replacements.insert(reference, format!("{url_dir}/{name}"));
+    }
+
+    content.body = rewrite_references(&content.body, &replacements);
+    if let Some(card_image) = &content.card_image {
+        if let Some(new) = replacements.get(card_image) {
+            content.card_image = Some(new.clone());
+        }
+    }
+    names.len()
+}
+
+/// Gives each content a unique slug and points the links between imported
+/// contents to their new pages
+fn prepare(contents: &mut [Imported], stream: Option<&str>) {
+    let mut slugs = HashSet::new();
+    for content in contents.iter_mut() {
+        if let Some(stream) = stream {
+            if content.date.is_some() && content.stream.is_none() {
+                content.stream = Some(stream.to_string());
+            }
+        }
+        let base = content.slug.clone();
+        let mut counter = 1;
+        while !slugs.insert(content.final_slug()) {
+            counter += 1;
+            content.slug = format!("{base}-{counter}");
+        }
+        content.clean_aliases();
+    }
+
+    let mut links = HashMap::new();
+    for content in contents.iter() {
+        if let Some(link) = &content.link {
+            let page = format!("{}.html", content.final_slug());
+            links.insert(link.trim_end_matches('/').to_string(), page.clone());
+            links.insert(format!("{}/", link.trim_end_matches('/')), page);
+        }
+    }
+    if !links.is_empty() {
+        for content in contents.iter_mut() {
+            content.body = rewrite_references(&content.body, &links);
+        }
+    }
+}
+
+/// Runs `marmite <folder> import`
+pub fn run(import_args: &ImportArgs, args: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let input_folder = args
+        .input_folder
+        .as_deref()
+        .ok_or("Input folder is required for import")?;
+    let config = Data::from_file(&input_folder.join(&args.config)).site;
+    let source = &import_args.source;
+    let mut contents = match import_args.format {
+        ImportFormat::Wordpress => parse_wxr(
+            &fs::read_to_string(source)
+                .map_err(|e| format!("Failed to read {}: {e}", source.display()))?,
+        )?,
+        ImportFormat::Jekyll => read_jekyll(source)?,
+        ImportFormat::Hugo => read_hugo(source)?,
+    };
+    if contents.is_empty() {
+        return Err(format!("No posts or pages found in {}", source.display()).into());
+    }
+    prepare(&mut contents, import_args.stream.as_deref());
+
+    let content_folder = if input_folder.join(&config.content_path).is_dir() {
+        get_content_folder(&config, input_folder)
+    } else {
+        input_folder.join(&config.content_path)
+    };
+    if !import_args.dry_run {
+        fs::create_dir_all(&content_folder)?;
+    }
+
+    let (mut posts, mut pages, mut media, mut skipped) = (0, 0, 0, 0);
+    for content in &mut contents {
+        let file = content_folder.join(format!("{}.md", content.slug));
+        if file.exists() && !import_args.force {
+            warn!(
+                "Skipping '{}', {} already exists",
+                content.title,
+                file.display()
+            );
+            skipped += 1;
+            continue;
+        }
+        if !import_args.no_media {
+            media += import_media(
+                content,
+                &content_folder,
+                &config.media_path,
+                import_args.dry_run,
+            );
+        }
+        for (syntax, marker) in [("Liquid", "{%"), ("Hugo shortcode", "{{<")] {
+            if content.body.contains(marker) {
+                warn!(
+                    "'{}' has {syntax} tags left to convert by hand",
+                    content.slug
+                );
+            }
+        }
+        if content.date.is_some() {
+            posts += 1;
+        } else {
+            pages += 1;
+        }
+        if import_args.dry_run {
+            println!("{} -> {}", content.title, file.display());
+            continue;
+        }
+        fs::write(&file, content.to_markdown())?;
+        info!("Imported {}", file.display());
+    }
+
+    let action = if import_args.dry_run {
+        "Would import"
+    } else {
+        "Imported"
+    };
+    println!(
+        "{action} {posts} post(s), {pages} page(s) and {media} media file(s) into {}",
+        content_folder.display()
+    );
+    if skipped > 0 {
+        println!("Skipped {skipped} existing file(s), use --force to replace them");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "tests/import.rs"]
+mod tests;
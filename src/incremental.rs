@@ -0,0 +1,434 @@
+//! Incremental builds driven by a persisted content dependency graph.
+//!
+//! When `--incremental` is passed, every build writes `.marmite-build-graph.json`
+//! to the input folder. The graph records:
+//!
+//! - a hash of every content markdown file along with its parsed `Content`,
+//!   so unchanged files are not parsed again on the next build;
+//! - fingerprints of everything else that feeds the build (config, fragments,
+//!   `frontmatter.yaml`, media, templates, static files, shortcodes);
+//! - a metadata hash and a body hash for every post and page;
+//! - the content slugs each rendered output depends on (content pages, stream,
+//!   series, tag, archive and author lists with their feeds, and the pages list).
+//!
+//! On the next build only the touched files are parsed again. When only the
+//! body of some content changed (title, date, tags, links and other metadata
+//! are the same) only the outputs that depend on that content are rendered.
+//! Any other change renders the whole site, exactly like a full build.
+use crate::config::Marmite;
+use crate::content::Content;
+use crate::site::Data;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+pub const GRAPH_FILE: &str = ".marmite-build-graph.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SourceEntry {
+    pub hash: String,
+    pub modified_time: Option<i64>,
+    pub content: Content,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ContentHashes {
+    pub meta: String,
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BuildGraph {
+    pub marmite_version: String,
+    pub output_folder: String,
+    pub parse_fingerprint: String,
+    pub render_fingerprint: String,
+    pub generated_at: Option<String>,
+    pub sources: BTreeMap<String, SourceEntry>,
+    pub contents: BTreeMap<String, ContentHashes>,
+    pub outputs: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl BuildGraph {
+    fn load(path: &Path) -> Option<Self> {
+        let json = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&json) {
+            Ok(graph) => Some(graph),
+            Err(e) => {
+                debug!("Ignoring unreadable build graph {}: {e}", path.display());
+                None
+            }
+        }
+    }
+}
+
+/// Parsed content reusable from the previous build plus the entries
+/// recorded while collecting content for the current build.
+#[derive(Default)]
+pub struct SourceCache {
+    previous: BTreeMap<String, SourceEntry>,
+    current: BTreeMap<String, SourceEntry>,
+    reused: usize,
+}
+
+impl SourceCache {
+    /// Returns the previously parsed content for `path` if its hash and
+    /// modification time did not change.
+    pub fn get(&self, path: &Path, hash: &str, modified_time: Option<i64>) -> Option<Content> {
+        self.previous
+            .get(&path.to_string_lossy().to_string())
+            .filter(|entry| entry.hash == hash && entry.modified_time == modified_time)
+            .map(|entry| entry.content.clone())
+    }
+
+    pub fn record(&mut self, path: &Path, entry: SourceEntry, reused: bool) {
+        if reused {
+            self.reused += 1;
+        }
+        self.current
+            .insert(path.to_string_lossy().to_string(), entry);
+    }
+}
+
+/// State of an incremental build, created before content is collected and
+/// saved after the site is written.
+pub struct IncrementalBuild {
+    previous: Option<BuildGraph>,
+    graph: BuildGraph,
+    pub sources: SourceCache,
+}
+
+impl IncrementalBuild {
+    /// Loads the graph of the previous build and fingerprints the current inputs.
+    /// `generated_at` is the timestamp of the `marmite.json` currently in the output,
+    /// a graph written by a different build than the one on disk is discarded.
+    pub fn load(
+        input_folder: &Path,
+        output_folder: &Path,
+        content_folder: &Path,
+        site: &Marmite,
+        generated_at: Option<&str>,
+    ) -> Self {
+        let graph = BuildGraph {
+            marmite_version: env!("CARGO_PKG_VERSION").to_string(),
+            output_folder: output_folder.to_string_lossy().to_string(),
+            parse_fingerprint: parse_fingerprint(content_folder, output_folder, site),
+            render_fingerprint: render_fingerprint(input_folder, output_folder, content_folder),
+            ..Default::default()
+        };
+
+        let previous = BuildGraph::load(&input_folder.join(GRAPH_FILE)).filter(|previous| {
+            previous.marmite_version == graph.marmite_version
+                && previous.output_folder == graph.output_folder
+                && previous.generated_at.is_some()
+                && previous.generated_at.as_deref() == generated_at
+        });
+
+        let sources = SourceCache {
+            previous: previous
+                .as_ref()
+                .filter(|previous| previous.parse_fingerprint == graph.parse_fingerprint)
+                .map(|previous| previous.sources.clone())
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+
+        Self {
+            previous,
+            graph,
+            sources,
+        }
+    }
+
+    /// Decides which outputs must be rendered for `site_data`.
+    /// Returns `None` when the whole site must be rendered, otherwise the
+    /// names (without extension) of the outputs affected by the changes.
+    pub fn plan(&mut self, site_data: &Data) -> Option<HashSet<String>> {
+        self.graph.contents = content_hashes(site_data);
+        self.graph.outputs = output_dependencies(site_data);
+        info!(
+            "Incremental build: reused {} of {} parsed content file(s)",
+            self.sources.reused,
+            self.sources.current.len()
+        );
+
+        let previous = self.previous.as_ref()?;
+        if site_data.force_render
+            || previous.parse_fingerprint != self.graph.parse_fingerprint
+            || previous.render_fingerprint != self.graph.render_fingerprint
+        {
+            info!("Incremental build: build inputs changed, rendering the whole site");
+            return None;
+        }
+
+        let same_structure = previous.contents.len() == self.graph.contents.len()
+            && self.graph.contents.iter().all(|(slug, hashes)| {
+                previous
+                    .contents
+                    .get(slug)
+                    .is_some_and(|prev| prev.meta == hashes.meta)
+            });
+        if !same_structure {
+            info!("Incremental build: content metadata changed, rendering the whole site");
+            return None;
+        }
+
+        let dirty: HashSet<&String> = self
+            .graph
+            .contents
+            .iter()
+            .filter(|(slug, hashes)| {
+                previous
+                    .contents
+                    .get(*slug)
+                    .is_none_or(|prev| prev.body != hashes.body)
+            })
+            .map(|(slug, _)| slug)
+            .collect();
+
+        let render: HashSet<String> = self
+            .graph
+            .outputs
+            .iter()
+            .filter(|(_, deps)| deps.iter().any(|slug| dirty.contains(slug)))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        info!(
+            "Incremental build: {} changed content, rendering {} output(s)",
+            dirty.len(),
+            render.len()
+        );
+        Some(render)
+    }
+
+    /// Persists the graph for the next build.
+    /// `generated_at` must be the timestamp written to `marmite.json` by this build.
+    pub fn save(mut self, input_folder: &Path, generated_at: Option<String>) {
+        self.graph.sources = self.sources.current;
+        self.graph.generated_at = generated_at;
+        let graph_path = input_folder.join(GRAPH_FILE);
+        match serde_json::to_string(&self.graph) {
+            Ok(json) => {
+                if let Err(e) = fs::write(&graph_path, json) {
+                    error!("Failed to write {}: {e:?}", graph_path.display());
+                } else {
+                    debug!("Saved build graph to {}", graph_path.display());
+                }
+            }
+            Err(e) => error!("Failed to serialize build graph: {e}"),
+        }
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    let result = hasher.finalize();
+    result.iter().fold(String::new(), |mut output, b| {
+        let _ = write!(output, "{b:02x}");
+        output
+    })
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A markdown file that is collected as a post or page
+pub fn is_content_source(path: &Path, content_folder: &Path) -> bool {
+    path.starts_with(content_folder)
+        && path.extension().and_then(|ext| ext.to_str()) == Some("md")
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| !name.starts_with('_'))
+}
+
+fn file_stamp(path: &Path, base: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos());
+    let relative = path.strip_prefix(base).unwrap_or(path);
+    Some(format!(
+        "{}:{}:{modified}\n",
+        relative.display(),
+        metadata.len()
+    ))
+}
+
+/// Stamps (path, size and mtime) of every file below `folder` that is not a
+/// content markdown file, skipping hidden files and directories, like the
+/// `.marmite-*` files written by the server, and `excluded` folders.
+fn folder_stamps(folder: &Path, content_folder: &Path, excluded: &[PathBuf]) -> Vec<String> {
+    let mut stamps: Vec<String> = WalkDir::new(folder)
+        .into_iter()
+        .filter_entry(|e| {
+            if e.depth() == 0 {
+                return true;
+            }
+            if e.file_name().to_string_lossy().starts_with('.') {
+                return false;
+            }
+            !e.file_type().is_dir() || !excluded.iter().any(|ex| canonical(e.path()) == *ex)
+        })
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.file_name() != GRAPH_FILE && !is_content_source(e.path(), content_folder))
+        .filter_map(|e| file_stamp(e.path(), folder))
+        .collect();
+    stamps.sort();
+    stamps
+}
+
+/// Fingerprint of the inputs that change how markdown is parsed:
+/// the configuration and every non-content file in the content folder
+/// (fragments, `frontmatter.yaml`, media used for banner and card images).
+fn parse_fingerprint(content_folder: &Path, output_folder: &Path, site: &Marmite) -> String {
+    let mut input = serde_json::to_string(site).unwrap_or_default();
+    for stamp in folder_stamps(content_folder, content_folder, &[canonical(output_folder)]) {
+        input.push_str(&stamp);
+    }
+    sha256_hex(input.as_bytes())
+}
+
+/// Fingerprint of every other input of the render step
+/// (templates, static files, shortcodes, themes, atproto state).
+fn render_fingerprint(input_folder: &Path, output_folder: &Path, content_folder: &Path) -> String {
+    let mut excluded = vec![canonical(output_folder)];
+    if canonical(content_folder) != canonical(input_folder) {
+        excluded.push(canonical(content_folder));
+    }
+    let mut input = String::new();
+    for stamp in folder_stamps(input_folder, content_folder, &excluded) {
+        input.push_str(&stamp);
+    }
+    sha256_hex(input.as_bytes())
+}
+
+/// Hashes the metadata (everything list pages, groups and navigation use)
+/// and the body of every post and page.
+fn content_hashes(site_data: &Data) -> BTreeMap<String, ContentHashes> {
+    site_data
+        .posts
+        .iter()
+        .chain(&site_data.pages)
+        .map(|content| {
+            let meta = serde_json::json!({
+                "title": content.title,
+                "description": content.description,
+                "tags": content.tags,
+                "date": content.date,
+                "extra": content.extra,
+                "links_to": content.links_to,
+                "media_links_to": content.media_links_to,
+                "card_image": content.card_image,
+                "banner_image": content.banner_image,
+                "authors": content.authors,
+                "stream": content.stream,
+                "series": content.series,
+                "pinned": content.pinned,
+                "comments": content.comments,
+                "source_path": content.source_path,
+                "at_uri": content.at_uri,
+                "aliases": content.aliases,
+                "language": content.language,
+                "translations": content.translations,
+                "translates": content.translates,
+                "taxonomies": content.taxonomies,
+            });
+            let body = serde_json::json!({
+                "html": content.html,
+                "toc": content.toc,
+                "modified_time": content.modified_time,
+            });
+            (
+                content.slug.clone(),
+                ContentHashes {
+                    meta: sha256_hex(meta.to_string().as_bytes()),
+                    body: sha256_hex(body.to_string().as_bytes()),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Maps every output name (without extension) to the content slugs it renders.
+/// List outputs cover their pagination pages and feeds.
+fn output_dependencies(site_data: &Data) -> BTreeMap<String, BTreeSet<String>> {
+    let slugs = |contents: &[Content]| -> BTreeSet<String> {
+        contents.iter().map(|c| c.slug.clone()).collect()
+    };
+    let mut outputs: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for content in site_data.posts.iter().chain(&site_data.pages) {
+        let mut deps = BTreeSet::from([content.slug.clone()]);
+        deps.extend(content.back_links.iter().map(|c| c.slug.clone()));
+        deps.extend(content.next.iter().map(|c| c.slug.clone()));
+        deps.extend(content.previous.iter().map(|c| c.slug.clone()));
+        outputs
+            .entry(content.slug.clone())
+            .or_default()
+            .extend(deps);
+    }
+
+    for (stream, contents) in site_data.stream.iter() {
+        outputs
+            .entry(crate::slugify::slugify(stream))
+            .or_default()
+            .extend(slugs(&contents));
+    }
+    for (series, contents) in site_data.series.iter() {
+        outputs
+            .entry(format!("series-{}", crate::slugify::slugify(series)))
+            .or_default()
+            .extend(slugs(&contents));
+    }
+    for (tag, contents) in site_data.tag.iter() {
+        outputs
+            .entry(format!("tag-{}", crate::slugify::slugify(tag)))
+            .or_default()
+            .extend(slugs(&contents));
+    }
+    for (year, contents) in site_data.archive.iter() {
+        outputs
+            .entry(format!("archive-{year}"))
+            .or_default()
+            .extend(slugs(&contents));
+    }
+    for (username, contents) in site_data.author.iter() {
+        outputs
+            .entry(format!("author-{}", crate::slugify::slugify(username)))
+            .or_default()
+            .extend(slugs(&contents));
+    }
+    for taxonomy in site_data.site.taxonomy_list() {
+        let Some(grouped) = site_data.taxonomies.get(&taxonomy.name) else {
+            continue;
+        };
+        for (term, contents) in grouped.iter() {
+            outputs
+                .entry(taxonomy.term_filename(term))
+                .or_default()
+                .extend(slugs(&contents));
+        }
+    }
+    outputs
+        .entry("pages".to_string())
+        .or_default()
+        .extend(slugs(&site_data.pages));
+
+    outputs
+}
+
+#[cfg(test)]
+#[path = "tests/incremental.rs"]
+mod tests;
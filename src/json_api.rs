@@ -0,0 +1,356 @@
+//! Static JSON API written next to the site.
+//!
+//! With `json_api.enabled` the build writes read-only JSON files that apps
+//! can fetch instead of scraping the HTML, all under `json_api.path`:
+//!
+//! - `index.json`: site information, counts and the other endpoints
+//! - `posts/page/{n}.json` and `pages/page/{n}.json`: paginated summaries
+//! - `posts/{slug}.json` and `pages/{slug}.json`: rendered HTML, markdown
+//!   source and metadata of a content
+//! - `tags.json`, `series.json`, `authors.json`, `streams.json`: the groups
+//!   with their number of posts, and `{group}/{slug}.json` with the summaries
+//!
+//! Every file carries `"version": SCHEMA_VERSION`, which only changes when
+//! fields are removed or change meaning.
+use crate::content::{fm_value_to_json, Content, GroupedContent};
+use crate::parser::parse_front_matter;
+use crate::site::Data;
+use crate::slugify::slugify;
+use crate::tera_functions::UrlFor;
+use chrono::{DateTime, NaiveDateTime};
+use log::{error, info};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Version of the schema of the files
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Fields of a post or page shared by the listings and the full content
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentSummary {
+    pub slug: String,
+    pub title: String,
+    pub description: Option<String>,
+    /// `post` when the content has a date, `page` otherwise
+    pub kind: &'static str,
+    /// URL of the HTML page
+    pub url: String,
+    /// URL of the JSON of the content
+    pub api_url: String,
+    /// `YYYY-MM-DDTHH:MM:SS`, in the time zone of the frontmatter
+    pub date: Option<String>,
+    /// RFC 3339 modification time of the source file
+    pub updated: Option<String>,
+    pub tags: Vec<String>,
+    pub authors: Vec<String>,
+    pub stream: Option<String>,
+    pub series: Option<String>,
+    pub language: Option<String>,
+    pub pinned: bool,
+    pub card_image: Option<String>,
+    pub banner_image: Option<String>,
+    pub taxonomies: BTreeMap<String, Vec<String>>,
+}
+
+/// A post or page with its rendered HTML and markdown source
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentDetail {
+    pub version: u32,
+    #[serde(flatten)]
+    pub summary: ContentSummary,
+    pub html: String,
+    /// The markdown after the frontmatter, as written by the author
+    pub markdown: Option<String>,
+    pub toc: Option<String>,
+    /// Frontmatter fields that are not part of the schema
+    pub extra: Option<Value>,
+    pub previous: Option<String>,
+    pub next: Option<String>,
+    pub translations: BTreeMap<String, String>,
+}
+
+/// A page of a listing
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentPage {
+    pub version: u32,
+    pub page: usize,
+    pub total_pages: usize,
+    pub total_items: usize,
+    pub per_page: usize,
+    pub previous: Option<String>,
+    pub next: Option<String>,
+    pub items: Vec<ContentSummary>,
+}
+
+/// A tag, series, author or stream
+#[derive(Debug, Clone, Serialize)]
+pub struct Group {
+    pub name: String,
+    pub slug: String,
+    pub count: usize,
+    pub url: String,
+    pub api_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct GroupList<'a> {
+    version: u32,
+    items: &'a [Group],
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct GroupDetail<'a> {
+    version: u32,
+    #[serde(flatten)]
+    group: &'a Group,
+    items: Vec<ContentSummary>,
+}
+
+struct Writer<'a> {
+    site_data: &'a Data,
+    url_for: UrlFor,
+    use_abs: bool,
+    api_path: String,
+    api_dir: &'a Path,
+}
+
+impl Writer<'_> {
+    fn url(&self, path: &str) -> String {
+        self.url_for.resolve(path, self.use_abs)
+    }
+
+    fn api_url(&self, path: &str) -> String {
+        self.url(&format!("{}/{path}", self.api_path))
+    }
+
+    fn write<T: Serialize>(&self, path: &str, value: &T) -> bool {
+        let file = self.api_dir.join(path);
+        if let Some(parent) = file.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                error!("Failed to create JSON API directory: {e:?}");
+                return false;
+            }
+        }
+        match serde_json::to_string_pretty(value) {
+            Ok(json) => match fs::write(&file, json) {
+                Ok(()) => true,
+                Err(e) => {
+                    error!("Failed to write {}: {e:?}", file.display());
+                    false
+                }
+            },
+            Err(e) => {
+                error!("Failed to serialize {path}: {e}");
+                false
+            }
+        }
+    }
+
+    fn summary(&self, content: &Content) -> ContentSummary {
+        let (kind, folder) = if content.date.is_some() {
+            ("post", "posts")
+        } else {
+            ("page", "pages")
+        };
+        ContentSummary {
+            slug: content.slug.clone(),
+            title: content.title.clone(),
+            description: content.description.clone(),
+            kind,
+            url: self.url(&format!("{}.html", content.slug)),
+            api_url: self.api_url(&format!("{folder}/{}.json", content.slug)),
+            date: content.date.as_ref().map(format_date),
+            updated: content
+                .modified_time
+                .and_then(|secs| DateTime::from_timestamp(secs, 0))
+                .map(|time| time.to_rfc3339()),
+            tags: content.tags.clone(),
+            authors: content.authors.clone(),
+            stream: content.stream.clone(),
+            series: content.series.clone(),
+            language: content.language.clone(),
+            pinned: content.pinned,
+            card_image: content.card_image.clone(),
+            banner_image: content.banner_image.clone(),
+            taxonomies: content.taxonomies.clone(),
+        }
+    }
+
+    fn detail(&self, content: &Content) -> ContentDetail {
+        let markdown = self
+            .site_data
+            .site
+            .json_api
+            .markdown
+            .then_some(content.source_path.as_ref())
+            .flatten()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|source| {
+                parse_front_matter(&source)
+                    .ok()
+                    .map(|(_, md)| md.trim_start_matches(['\n', '\r']).to_string())
+            });
+        ContentDetail {
+            version: SCHEMA_VERSION,
+            summary: self.summary(content),
+            html: content.html.clone(),
+            markdown,
+            toc: content.toc.clone(),
+            extra: content.extra.as_ref().map(fm_value_to_json),
+            previous: content.previous.as_ref().map(|c| c.slug.clone()),
+            next: content.next.as_ref().map(|c| c.slug.clone()),
+            translations: content
+                .translations
+                .iter()
+                .map(|t| (t.lang.clone(), t.slug.clone()))
+                .collect(),
+        }
+    }
+
+    /// Writes `{kind}/page/{n}.json`, the first page even when empty
+    fn write_pages(&self, kind: &str, contents: &[Content]) -> usize {
+        let per_page = self.site_data.site.json_api.per_page.max(1);
+        let total_pages = contents.len().div_ceil(per_page).max(1);
+        let chunks: Vec<&[Content]> = if contents.is_empty() {
+            vec![&[]]
+        } else {
+            contents.chunks(per_page).collect()
+        };
+        let page_url = |page: usize| self.api_url(&format!("{kind}/page/{page}.json"));
+        let mut written = 0;
+        for (index, chunk) in chunks.iter().enumerate() {
+            let page = index + 1;
+            let listing = ContentPage {
+                version: SCHEMA_VERSION,
+                page,
+                total_pages,
+                total_items: contents.len(),
+                per_page,
+                previous: (page > 1).then(|| page_url(page - 1)),
+                next: (page < total_pages).then(|| page_url(page + 1)),
+                items: chunk.iter().map(|c| self.summary(c)).collect(),
+            };
+            written += usize::from(self.write(&format!("{kind}/page/{page}.json"), &listing));
+        }
+        for content in contents {
+            written += usize::from(self.write(
+                &format!("{kind}/{}.json", content.slug),
+                &self.detail(content),
+            ));
+        }
+        written
+    }
+
+    /// Writes `{name}.json` listing the groups and `{name}/{slug}.json`
+    /// with the summaries of each one. `html_prefix` is the prefix of the
+    /// HTML page of a group, e.g. `tag-`.
+    fn write_groups(&self, name: &str, grouped: &GroupedContent, html_prefix: &str) -> usize {
+        let mut groups: BTreeMap<String, (Group, Vec<Content>)> = BTreeMap::new();
+        for (key, contents) in grouped.iter() {
+            let slug = slugify(key);
+            // Tags are also keyed by their slug, keep the name as written
+            if key == &slug && groups.contains_key(&slug) {
+                continue;
+            }
+            let group = Group {
+                name: key.clone(),
+                slug: slug.clone(),
+                count: contents.len(),
+                url: self.url(&format!("{html_prefix}{slug}.html")),
+                api_url: self.api_url(&format!("{name}/{slug}.json")),
+            };
+            groups.insert(slug, (group, contents));
+        }
+
+        let list: Vec<Group> = groups.values().map(|(group, _)| group.clone()).collect();
+        let mut written = usize::from(self.write(
+            &format!("{name}.json"),
+            &GroupList {
+                version: SCHEMA_VERSION,
+                items: &list,
+            },
+        ));
+        for (group, contents) in groups.values() {
+            let detail = GroupDetail {
+                version: SCHEMA_VERSION,
+                group,
+                items: contents.iter().map(|c| self.summary(c)).collect(),
+            };
+            written += usize::from(self.write(&format!("{name}/{}.json", group.slug), &detail));
+        }
+        written
+    }
+}
+
+fn format_date(date: &NaiveDateTime) -> String {
+    date.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+/// Writes the JSON API to `json_api.path` inside `output_path`, replacing
+/// the files of the previous build
+pub fn generate_json_api(site_data: &Data, output_path: &Path, path_prefix: &str) {
+    let config = &site_data.site.json_api;
+    let api_path = config.path.trim_matches('/');
+    if api_path.is_empty() || api_path.split('/').any(|part| part == "..") {
+        error!("Invalid json_api.path '{}'", config.path);
+        return;
+    }
+    let api_dir = output_path.join(api_path);
+    if api_dir.exists() {
+        if let Err(e) = fs::remove_dir_all(&api_dir) {
+            error!("Failed to clean the JSON API directory: {e:?}");
+            return;
+        }
+    }
+
+    let writer = Writer {
+        site_data,
+        url_for: UrlFor {
+            base_url: site_data.site.url.clone(),
+            path_prefix: path_prefix.to_string(),
+            ..Default::default()
+        },
+        use_abs: !site_data.site.url.is_empty(),
+        api_path: api_path.to_string(),
+        api_dir: &api_dir,
+    };
+
+    let mut written = writer.write_pages("posts", &site_data.posts);
+    written += writer.write_pages("pages", &site_data.pages);
+    written += writer.write_groups("tags", &site_data.tag, "tag-");
+    written += writer.write_groups("series", &site_data.series, "series-");
+    written += writer.write_groups("authors", &site_data.author, "author-");
+    written += writer.write_groups("streams", &site_data.stream, "");
+
+    let endpoint = |path: &str| writer.api_url(path);
+    let index = serde_json::json!({
+        "version": SCHEMA_VERSION,
+        "site": {
+            "name": site_data.site.name,
+            "tagline": site_data.site.tagline,
+            "url": site_data.site.url,
+            "language": site_data.site.language,
+        },
+        "counts": {
+            "posts": site_data.posts.len(),
+            "pages": site_data.pages.len(),
+        },
+        "endpoints": {
+            "posts": endpoint("posts/page/1.json"),
+            "pages": endpoint("pages/page/1.json"),
+            "tags": endpoint("tags.json"),
+            "series": endpoint("series.json"),
+            "authors": endpoint("authors.json"),
+            "streams": endpoint("streams.json"),
+        },
+    });
+    written += usize::from(writer.write("index.json", &index));
+    info!("Generated {written} JSON API files in /{api_path}/");
+}
+
+#[cfg(test)]
+#[path = "tests/json_api.rs"]
+mod tests;
@@ -0,0 +1,305 @@
+//! External link checker behind `marmite <folder> check-links`.
+//!
+//! Outbound `http(s)` links are extracted from the HTML of every content and
+//! checked with a `HEAD` request, falling back to `GET` for servers that do
+//! not answer `HEAD`. Results are stored in a cache file in the input folder
+//! so the next runs only check links whose result is older than
+//! `link_checker.cache_max_age` hours.
+use crate::cli::{CheckLinksArgs, Cli, ReportFormat};
+use crate::config::LinkCheckerConfig;
+use crate::diagnostics::{apply_deny, Report, Warning};
+use crate::parser::get_external_links;
+use crate::site::{collect_content, collect_content_fragments, get_content_folder, Data};
+use chrono::Utc;
+use log::{debug, error, info, warn};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Result of checking one URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkStatus {
+    pub ok: bool,
+    /// HTTP status of the last request, `None` when it failed to connect
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    /// Unix timestamp of the check
+    pub checked_at: i64,
+}
+
+/// Results of previous runs, keyed by URL
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LinkCache {
+    pub links: BTreeMap<String, LinkStatus>,
+}
+
+impl LinkCache {
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) {
+        match serde_json::to_string_pretty(self) {
+            Ok(json) => {
+                if let Err(e) = fs::write(path, json) {
+                    error!("Failed to write link cache {}: {e}", path.display());
+                }
+            }
+            Err(e) => error!("Failed to serialize link cache: {e}"),
+        }
+    }
+
+    /// Whether `url` was checked less than `max_age` seconds before `now`
+    pub fn is_fresh(&self, url: &str, now: i64, max_age: i64) -> bool {
+        self.links
+            .get(url)
+            .is_some_and(|status| now - status.checked_at < max_age)
+    }
+}
+
+/// Waits so requests to the same domain are at least `delay` apart
+struct RateLimiter {
+    delay: Duration,
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl RateLimiter {
+    fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            next: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn wait(&self, url: &str) {
+        let domain = url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let wait = {
+            let mut next = self
+                .next
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let now = Instant::now();
+            let slot = next.get(&domain).map_or(now, |at| (*at).max(now));
+            next.insert(domain, slot + self.delay);
+            slot - now
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+pub struct LinkChecker {
+    agent: ureq::Agent,
+    limiter: RateLimiter,
+    concurrency: usize,
+}
+
+impl LinkChecker {
+    pub fn new(config: &LinkCheckerConfig) -> Self {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(config.timeout)))
+            .http_status_as_error(false)
+            .user_agent(concat!(
+                "marmite/",
+                env!("CARGO_PKG_VERSION"),
+                " link-checker"
+            ))
+            .build()
+            .into();
+        Self {
+            agent,
+            limiter: RateLimiter::new(Duration::from_millis(config.domain_delay)),
+            concurrency: config.concurrency.max(1),
+        }
+    }
+
+    /// Checks `url` with `HEAD`, retrying with `GET` when it fails
+    pub fn check(&self, url: &str) -> LinkStatus {
+        self.limiter.wait(url);
+        let head = self.agent.head(url).call();
+        let result = match head {
+            Ok(response) if response.status().as_u16() < 400 => Ok(response.status().as_u16()),
+            _ => {
+                debug!("HEAD {url} failed, retrying with GET");
+                self.limiter.wait(url);
+                self.agent
+                    .get(url)
+                    .call()
+                    .map(|response| response.status().as_u16())
+            }
+        };
+        let checked_at = Utc::now().timestamp();
+        match result {
+            Ok(status) => LinkStatus {
+                ok: status < 400,
+                status: Some(status),
+                error: None,
+                checked_at,
+            },
+            Err(e) => LinkStatus {
+                ok: false,
+                status: None,
+                error: Some(e.to_string()),
+                checked_at,
+            },
+        }
+    }
+
+    /// Checks the URLs, at most `concurrency` at the same time
+    pub fn check_all(&self, urls: &[String]) -> Vec<(String, LinkStatus)> {
+        let check = || {
+            urls.par_iter()
+                .map(|url| (url.clone(), self.check(url)))
+                .collect()
+        };
+        match rayon::ThreadPoolBuilder::new()
+            .num_threads(self.concurrency)
+            .build()
+        {
+            Ok(pool) => pool.install(check),
+            Err(e) => {
+                warn!("Failed to create the link checker thread pool: {e}");
+                check()
+            }
+        }
+    }
+}
+
+/// Checks the links not found in `cache` or older than `max_age` seconds,
+/// every link when `force` is set. Returns how many were checked.
+pub fn refresh_cache(
+    checker: &LinkChecker,
+    urls: &[String],
+    cache: &mut LinkCache,
+    max_age: i64,
+    force: bool,
+) -> usize {
+    let now = Utc::now().timestamp();
+    let stale: Vec<String> = urls
+        .iter()
+        .filter(|url| force || !cache.is_fresh(url, now, max_age))
+        .cloned()
+        .collect();
+    for (url, status) in checker.check_all(&stale) {
+        cache.links.insert(url, status);
+    }
+    stale.len()
+}
+
+/// Runs `check-links`, fails when broken links were found
+pub fn run(check_args: &CheckLinksArgs, args: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let input_folder = args
+        .input_folder
+        .as_deref()
+        .ok_or("Input folder is required for check-links")?;
+    let mut site_data = Data::from_file(&input_folder.join(&args.config));
+    site_data
+        .site
+        .override_from_cli_args(&std::sync::Arc::new(args.clone()));
+    // Scheduled content will be published, its links are checked too
+    site_data.include_future = true;
+    let content_folder = get_content_folder(&site_data.site, input_folder);
+    let fragments = collect_content_fragments(&content_folder);
+    let folder_defaults = crate::site::load_folder_frontmatter(&content_folder);
+    collect_content(
+        &content_folder,
+        &mut site_data,
+        &fragments,
+        None,
+        &folder_defaults,
+    )?;
+
+    let mut config = site_data.site.link_checker.clone();
+    config.ignore.extend(check_args.ignore.iter().cloned());
+    if let Some(concurrency) = check_args.concurrency {
+        config.concurrency = concurrency;
+    }
+    if let Some(timeout) = check_args.timeout {
+        config.timeout = timeout;
+    }
+    let ignore = config
+        .ignore
+        .iter()
+        .map(|pattern| Regex::new(pattern))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid link_checker ignore pattern: {e}"))?;
+
+    // (content slug, url) for every outbound link
+    let mut links: Vec<(String, String)> = Vec::new();
+    for content in site_data.posts.iter().chain(&site_data.pages) {
+        for url in get_external_links(&content.html) {
+            if !ignore.iter().any(|re| re.is_match(&url)) {
+                links.push((content.slug.clone(), url));
+            }
+        }
+    }
+    let mut urls: Vec<String> = links.iter().map(|(_, url)| url.clone()).collect();
+    urls.sort();
+    urls.dedup();
+
+    let cache_path = input_folder.join(&config.cache_file);
+    let mut cache = if check_args.force {
+        LinkCache::default()
+    } else {
+        LinkCache::load(&cache_path)
+    };
+    let checker = LinkChecker::new(&config);
+    let max_age = i64::try_from(config.cache_max_age.saturating_mul(3600)).unwrap_or(i64::MAX);
+    let refreshed = refresh_cache(&checker, &urls, &mut cache, max_age, check_args.force);
+    // Links no longer used by any content are dropped from the cache
+    cache.links.retain(|url, _| urls.binary_search(url).is_ok());
+    cache.save(&cache_path);
+    info!(
+        "Checked {refreshed} of {} external link(s), {} from cache",
+        urls.len(),
+        urls.len() - refreshed
+    );
+
+    let mut broken = 0;
+    for (source, url) in links {
+        let Some(status) = cache.links.get(&url).filter(|status| !status.ok) else {
+            continue;
+        };
+        let warning = Warning::BrokenExternalLink {
+            source: source.clone(),
+            target: url,
+            status: status.status,
+            error: status.error.clone(),
+        };
+        warn!("{warning}");
+        site_data.add_diagnostic(warning, Some(&source));
+        broken += 1;
+    }
+
+    let denied = apply_deny(&mut site_data.diagnostics, &args.deny);
+    if args.report == Some(ReportFormat::Json) {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&Report::new(&site_data.diagnostics))?
+        );
+    }
+    if broken > 0 {
+        Err(format!("Found {broken} broken external link(s)").into())
+    } else if !denied.is_empty() {
+        Err(format!("Found {} denied diagnostic(s)", denied.len()).into())
+    } else {
+        info!("No broken external links found");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/link_checker.rs"]
+mod tests;
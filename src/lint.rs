@@ -0,0 +1,396 @@
+//! Content checks behind `marmite <folder> lint`.
+//!
+//! Reads the content files the same way the build does and reports the rules
+//! they break. Each rule is `off`, `warning` or `error` in the `lint` section
+//! of marmite.yaml, and any error fails the command so it can gate CI.
+use crate::cli::{Cli, ReportFormat};
+use crate::config::{LintConfig, LintLevel};
+use crate::content::Content;
+use crate::diagnostics::{apply_deny, Diagnostic, Report, Severity, Warning};
+use crate::site::{collect_content, collect_content_fragments, get_content_folder, Data};
+use crate::slugify::slugify;
+use log::warn;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Every rule with its level when marmite.yaml does not set one
+pub const RULES: [(&str, LintLevel); 9] = [
+    ("invalid-frontmatter", LintLevel::Error),
+    ("missing-description", LintLevel::Warning),
+    ("missing-card-image", LintLevel::Off),
+    ("long-title", LintLevel::Warning),
+    ("duplicate-title", LintLevel::Warning),
+    ("unknown-author", LintLevel::Warning),
+    ("inconsistent-tag-case", LintLevel::Warning),
+    ("heading-level-skip", LintLevel::Warning),
+    ("image-missing-alt", LintLevel::Warning),
+];
+
+/// A rule broken by a content file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub file: Option<PathBuf>,
+    /// 1-based line in `file`
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// Level of a rule, the configured one or its default
+pub fn level(config: &LintConfig, rule: &str) -> LintLevel {
+    config.rules.get(rule).copied().unwrap_or_else(|| {
+        RULES
+            .iter()
+            .find(|(name, _)| *name == rule)
+            .map_or(LintLevel::Off, |(_, level)| *level)
+    })
+}
+
+/// Source of a content with the line of the first text matching a predicate
+struct Source {
+    text: String,
+}
+
+impl Source {
+    fn read(content: &Content) -> Self {
+        Self {
+            text: content
+                .source_path
+                .as_ref()
+                .and_then(|path| fs::read_to_string(path).ok())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn line(&self, matches: impl Fn(&str) -> bool) -> Option<usize> {
+        self.text.lines().position(matches).map(|index| index + 1)
+    }
+
+    /// Line of a frontmatter key, in YAML, TOML or JSON
+    fn key_line(&self, key: &str) -> Option<usize> {
+        self.line(|line| {
+            let line = line.trim_start().trim_start_matches('"');
+            line.strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with([':', '=', '"']))
+        })
+    }
+
+    fn text_line(&self, text: &str) -> Option<usize> {
+        self.line(|line| line.contains(text))
+    }
+}
+
+fn violation(
+    rule: &'static str,
+    content: &Content,
+    line: Option<usize>,
+    message: String,
+) -> Violation {
+    Violation {
+        rule,
+        file: content.source_path.clone(),
+        line,
+        message,
+    }
+}
+
+fn strip_tags(html: &str) -> String {
+    Regex::new(r"<[^>]*>")
+        .expect("Tag regex should compile")
+        .replace_all(html, "")
+        .trim()
+        .to_string()
+}
+
+/// Rules checked on each content alone
+pub fn check_content(content: &Content, data: &Data) -> Vec<Violation> {
+    let config = &data.site.lint;
+    let source = Source::read(content);
+    let mut violations = Vec::new();
+
+    let description = content
+        .description
+        .as_deref()
+        .unwrap_or_default()
+        .trim_matches(|c: char| c == '"' || c.is_whitespace());
+    if description.is_empty() {
+        violations.push(violation(
+            "missing-description",
+            content,
+            None,
+            "Missing description".to_string(),
+        ));
+    }
+    if content.card_image.is_none() {
+        violations.push(violation(
+            "missing-card-image",
+            content,
+            None,
+            "No card image, set card_image or add an image to the content".to_string(),
+        ));
+    }
+    let length = content.title.chars().count();
+    if length > config.max_title_length {
+        violations.push(violation(
+            "long-title",
+            content,
+            source.key_line("title"),
+            format!(
+                "Title has {length} characters, more than {}",
+                config.max_title_length
+            ),
+        ));
+    }
+    for author in &content.authors {
+        if !data.site.authors.contains_key(author) {
+            violations.push(violation(
+                "unknown-author",
+                content,
+                source.text_line(author),
+                format!("Author \"{author}\" is not in the authors of marmite.yaml"),
+            ));
+        }
+    }
+
+    let heading =
+        Regex::new(r"(?s)<h([1-6])\b[^>]*>(.*?)</h[1-6]>").expect("Heading regex should compile");
+    // The page shows the title as h1 when the content does not start with one
+    let mut previous = 1;
+    for cap in heading.captures_iter(&content.html) {
+        let level: usize = cap[1].parse().unwrap_or(1);
+        let text = strip_tags(&cap[2]);
+        if level > previous + 1 {
+            violations.push(violation(
+                "heading-level-skip",
+                content,
+                source.text_line(&text),
+                format!("Heading \"{text}\" is h{level} after h{previous}"),
+            ));
+        }
+        previous = level;
+    }
+
+    let image = Regex::new(r"<img\b[^>]*>").expect("Image regex should compile");
+    let alt = Regex::new(r#"\salt\s*=\s*"([^"]*)""#).expect("Alt regex should compile");
+    let src = Regex::new(r#"\ssrc\s*=\s*"([^"]*)""#).expect("Src regex should compile");
+    let attribute = |regex: &Regex, tag: &str| {
+        regex
+            .captures(tag)
+            .map(|cap| cap[1].to_string())
+            .unwrap_or_default()
+    };
+    for tag in image.find_iter(&content.html) {
+        if attribute(&alt, tag.as_str()).trim().is_empty() {
+            let src = attribute(&src, tag.as_str());
+            let name = src.rsplit('/').next().unwrap_or(&src);
+            violations.push(violation(
+                "image-missing-alt",
+                content,
+                source.text_line(name).filter(|_| !name.is_empty()),
+                format!("Image \"{src}\" has no alt text"),
+            ));
+        }
+    }
+    violations
+}
+
+/// Contents sharing a title, ignoring case
+pub fn check_duplicate_titles(contents: &[&Content]) -> Vec<Violation> {
+    let mut titles: BTreeMap<String, Vec<&Content>> = BTreeMap::new();
+    for content in contents {
+        titles
+            .entry(content.title.trim().to_lowercase())
+            .or_default()
+            .push(content);
+    }
+    let mut violations = Vec::new();
+    for same in titles.values().filter(|same| same.len() > 1) {
+        for content in same {
+            let others: Vec<&str> = same
+                .iter()
+                .filter(|other| other.slug != content.slug)
+                .map(|other| other.slug.as_str())
+                .collect();
+            violations.push(violation(
+                "duplicate-title",
+                content,
+                Source::read(content).key_line("title"),
+                format!(
+                    "Title \"{}\" is also used by {}",
+                    content.title,
+                    others.join(", ")
+                ),
+            ));
+        }
+    }
+    violations
+}
+
+/// Tags written in different ways that become the same tag page, reported on
+/// the contents that do not use the most common spelling
+pub fn check_tag_case(contents: &[&Content]) -> Vec<Violation> {
+    let mut tags: BTreeMap<String, BTreeMap<&str, Vec<&Content>>> = BTreeMap::new();
+    for content in contents {
+        for tag in &content.tags {
+            tags.entry(slugify(tag))
+                .or_default()
+                .entry(tag.as_str())
+                .or_default()
+                .push(content);
+        }
+    }
+    let mut violations = Vec::new();
+    for spellings in tags.values().filter(|spellings| spellings.len() > 1) {
+        // Ties go to the first spelling in order
+        let Some((common, _)) = spellings
+            .iter()
+            .rev()
+            .max_by_key(|(_, contents)| contents.len())
+        else {
+            continue;
+        };
+        for (tag, users) in spellings.iter().filter(|(tag, _)| *tag != common) {
+            for content in users {
+                violations.push(violation(
+                    "inconsistent-tag-case",
+                    content,
+                    Source::read(content).text_line(tag),
+                    format!("Tag \"{tag}\" is written \"{common}\" elsewhere, both become one tag"),
+                ));
+            }
+        }
+    }
+    violations
+}
+
+/// Checks every content of the site, returning the diagnostics of the rules
+/// that are not off
+pub fn lint(data: &Data) -> Vec<Diagnostic> {
+    let config = &data.site.lint;
+    for rule in config.rules.keys() {
+        if !RULES.iter().any(|(name, _)| name == rule) {
+            warn!("Unknown lint rule '{rule}' in marmite.yaml");
+        }
+    }
+    let contents: Vec<&Content> = data
+        .posts
+        .iter()
+        .chain(&data.pages)
+        .chain(&data.scheduled)
+        .collect();
+
+    let mut violations: Vec<Violation> = data
+        .diagnostics
+        .iter()
+        .filter_map(|diagnostic| match &diagnostic.warning {
+            Warning::InvalidFrontmatter { message, .. } => Some(Violation {
+                rule: "invalid-frontmatter",
+                file: diagnostic.file.clone(),
+                line: diagnostic.line,
+                message: format!("Invalid frontmatter, the file is skipped: {message}"),
+            }),
+            _ => None,
+        })
+        .collect();
+    for content in &contents {
+        violations.extend(check_content(content, data));
+    }
+    violations.extend(check_duplicate_titles(&contents));
+    violations.extend(check_tag_case(&contents));
+
+    let mut diagnostics: Vec<Diagnostic> = violations
+        .into_iter()
+        .filter_map(|violation| {
+            let severity = match level(config, violation.rule) {
+                LintLevel::Off => return None,
+                LintLevel::Warning => Severity::Warning,
+                LintLevel::Error => Severity::Error,
+            };
+            let warning = Warning::Lint {
+                rule: violation.rule.to_string(),
+                detail: violation.message,
+            };
+            let mut diagnostic =
+                Diagnostic::new(warning, violation.file.as_deref()).with_line(violation.line);
+            diagnostic.severity = severity;
+            Some(diagnostic)
+        })
+        .collect();
+    diagnostics.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    diagnostics
+}
+
+pub fn run(args: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let input_folder = args
+        .input_folder
+        .as_deref()
+        .ok_or("Input folder is required for lint")?;
+    let mut site_data = Data::from_file(&input_folder.join(&args.config));
+    site_data
+        .site
+        .override_from_cli_args(&std::sync::Arc::new(args.clone()));
+    // Scheduled content will be published, it is checked too
+    site_data.include_future = true;
+    let content_folder = get_content_folder(&site_data.site, input_folder);
+    let fragments = collect_content_fragments(&content_folder);
+    let folder_defaults = crate::site::load_folder_frontmatter(&content_folder);
+    collect_content(
+        &content_folder,
+        &mut site_data,
+        &fragments,
+        None,
+        &folder_defaults,
+    )?;
+
+    let mut diagnostics = lint(&site_data);
+    apply_deny(&mut diagnostics, &args.deny);
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    if args.report == Some(ReportFormat::Json) {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&Report::new(&diagnostics))?
+        );
+    } else {
+        for diagnostic in &diagnostics {
+            let severity = match diagnostic.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            println!("{severity}: {}", relative(diagnostic, input_folder));
+        }
+        if diagnostics.is_empty() {
+            println!("No problems found");
+        } else {
+            println!(
+                "Found {} problem(s), {errors} error(s) and {} warning(s)",
+                diagnostics.len(),
+                diagnostics.len() - errors
+            );
+        }
+    }
+    if errors == 0 {
+        Ok(())
+    } else {
+        Err(format!("Lint failed with {errors} error(s)").into())
+    }
+}
+
+/// The diagnostic with its file relative to the input folder, as users
+/// refer to their files
+fn relative(diagnostic: &Diagnostic, input_folder: &Path) -> String {
+    let mut diagnostic = diagnostic.clone();
+    diagnostic.file = diagnostic.file.map(|file| {
+        file.strip_prefix(input_folder)
+            .map_or(file.clone(), Path::to_path_buf)
+    });
+    diagnostic.to_string()
+}
+
+#[cfg(test)]
+#[path = "tests/lint.rs"]
+mod tests;
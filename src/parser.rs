@@ -6,7 +6,7 @@ use comrak::{
     markdown_to_html, markdown_to_html_with_plugins, options::BrokenLinkReference,
     options::Plugins, options::RenderPlugins, Options, ResolvedReference,
 };
-use frontmatter_gen::{detect_format, extract_raw_frontmatter, parse, Frontmatter};
+use frontmatter_gen::{detect_format, extract_raw_frontmatter, parse, Format, Frontmatter};
 use log::warn;
 use regex::Regex;
 use std::fmt::Write as _;
@@ -56,6 +56,30 @@ pub fn get_links_to(html: &str) -> Option<Vec<String>> {
     Some(result)
 }
 
+/// Extract the external http(s) links and sources of the html content,
+/// without fragments and duplicates
+pub fn get_external_links(html: &str) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    let re = Regex::new(re::CAPTURE_EXTERNAL_URL_FROM_HREF_OR_SRC)
+        .expect("External links regex should compile");
+    for cap in re.captures_iter(html) {
+        let href = cap[1].replace("&amp;", "&");
+        let link = href.split('#').next().unwrap_or_default().to_string();
+        if !result.contains(&link) {
+            result.push(link);
+        }
+    }
+    result
+}
+
+/// Escapes text written into HTML elements and attributes
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 pub fn get_media_links_to(html: &str) -> Option<Vec<String>> {
     let mut result = Vec::new();
     let re = Regex::new(re::CAPTURE_MEDIA_SRC_OR_HREF).expect("Media links regex should compile");
@@ -403,12 +427,42 @@ pub fn parse_front_matter(content: &str) -> Result<(Frontmatter, &str), String>
     if !has_frontmatter {
         return Ok((Frontmatter::new(), content));
     }
+    // An empty block such as `---\n---` is valid and means no frontmatter
+    for delimiter in ["---", "+++"] {
+        let empty_block = content
+            .strip_prefix(delimiter)
+            .filter(|rest| rest.starts_with(['\n', '\r']))
+            .and_then(|rest| rest.trim_start().strip_prefix(delimiter));
+        if let Some(body) = empty_block {
+            return Ok((Frontmatter::new(), body));
+        }
+    }
     extract_fm_content(content)
 }
 
+/// Line of the content file where a frontmatter error returned by
+/// [`parse_front_matter`] happened, the parsers count lines from the first
+/// line after the `---` or `+++` delimiter.
+pub fn frontmatter_error_line(content: &str, message: &str) -> Option<usize> {
+    let re = Regex::new(re::CAPTURE_LINE_FROM_PARSE_ERROR).expect("Line regex should compile");
+    let line: usize = re.captures(message)?.get(1)?.as_str().parse().ok()?;
+    let trimmed = content.trim_start_matches('\n');
+    let skipped = content.len() - trimmed.len();
+    let delimiter = usize::from(trimmed.starts_with("---") || trimmed.starts_with("+++"));
+    Some(line + skipped + delimiter)
+}
+
 pub fn extract_fm_content(content: &str) -> Result<(Frontmatter, &str), String> {
     let (raw_frontmatter, remaining_content) = extract_raw_frontmatter(content)?;
-    let format = detect_format(raw_frontmatter)?;
+    // The delimiter tells the format, guessing it from the text reads TOML
+    // values with a `:` such as datetimes as YAML
+    let format = if content.starts_with("+++") {
+        Format::Toml
+    } else if content.starts_with("---") {
+        Format::Yaml
+    } else {
+        detect_format(raw_frontmatter)?
+    };
     let frontmatter = parse(raw_frontmatter, format)?;
     Ok((frontmatter, remaining_content))
 }
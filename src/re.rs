@@ -15,6 +15,12 @@ pub const MATCH_HTML_TAGS: &str = r"<[^>]*>";
 /// Used for converting markdown links to HTML links
 pub const CAPTURE_SLUG_ANCHOR_FROM_HREF: &str = r#"href=['\"]([^'\"]+)\.html(#[^'\"]+)?['\"]"#;
 
+/// Matches href and src attributes in HTML that point to http(s) URLs
+/// Captures: 1) the absolute URL
+/// Used for finding the external links of a post to check them and send Webmentions
+pub const CAPTURE_EXTERNAL_URL_FROM_HREF_OR_SRC: &str =
+    r#"(?:href|src)\s*=\s*["'](https?://[^"'\s]+)["']"#;
+
 /// Matches HTML heading tags (h1-h6) with optional id attribute
 /// Captures: 1) heading level, 2) optional id from the h tag, 3) heading inner HTML
 /// Used for extracting table of contents from HTML
@@ -37,6 +43,10 @@ pub const CAPTURE_LINK_AND_TEXT_FROM_A_TAG: &str =
 /// Used for extracting image URLs from HTML content
 pub const CAPTURE_SRC_FROM_IMG_HTMLTAG: &str = r#"<img[^>]*src=['\"]([^'\"]+)['\"]"#;
 
+/// Matches whole img tags
+/// Used for rewriting content images into responsive `<picture>` elements
+pub const MATCH_IMG_HTMLTAG: &str = r"<img\b[^>]*>";
+
 /// Matches wikilink anchor tags with data-wikilink attribute
 /// Captures: 1) href attribute value, 2) link text content
 /// Used for fixing Obsidian wikilinks to use proper slugs instead of filename-based hrefs
@@ -125,6 +135,25 @@ pub const CAPTURE_MEDIA_SRC_OR_HREF: &str = r#"(?:src|href)=['\"]([^'\"]+)['\"]"
 /// Captures: 1) the HTML-escaped mermaid source code inside the code tag
 pub const CAPTURE_MERMAID_BLOCK: &str = r#"<pre[^>]*class="[^"]*marmite-code[^"]*"[^>]*>\s*<code[^>]*class="[^"]*language-mermaid[^"]*"[^>]*>([\s\S]*?)</code>\s*</pre>"#;
 
+// === Error Location Patterns ===
+
+/// Matches the line number in YAML, TOML and JSON parse errors
+/// Captures: 1) the 1-based line
+/// Used for locating frontmatter errors in the content file
+pub const CAPTURE_LINE_FROM_PARSE_ERROR: &str = r"\bline (\d+)";
+
+/// Matches the location line of a Tera error report (`--> name:line:column`)
+/// Captures: 1) the template name, 2) the 1-based line
+/// Used for locating template errors shown by the dev server
+pub const CAPTURE_TEMPLATE_AND_LINE_FROM_TERA_ERROR: &str = r"--> ([^\s:]+):(\d+):\d+";
+
+// === HTTP Patterns ===
+
+/// Matches the `name="value"` parameters of an HTTP `Signature` header
+/// Captures: 1) the parameter name, 2) its value
+/// Used for verifying the activities posted to the ActivityPub inboxes
+pub const CAPTURE_NAME_VALUE_FROM_SIGNATURE_HEADER: &str = r#"(\w+)="([^"]*)""#;
+
 // === Text Processing Patterns ===
 
 #[cfg(test)]
@@ -0,0 +1,322 @@
+//! Full-text search index generated at build time.
+//!
+//! Instead of shipping the text of every post, the build writes one inverted
+//! index per language to `static/search/{language}.json` plus a small
+//! `static/search/index.json` manifest. Each shard maps stemmed terms to the
+//! documents containing them with a precomputed BM25 score, so `search.js`
+//! only has to look up the query terms and add up the scores.
+use crate::config::SearchWeights;
+use crate::content::Content;
+use crate::re;
+use crate::site::Data;
+use log::{error, info};
+use regex::Regex;
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// BM25 term frequency saturation
+const K1: f64 = 1.2;
+/// BM25 document length normalization
+const B: f64 = 0.75;
+/// Number of characters of the body kept to display in the results
+const EXCERPT_LENGTH: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SearchDocument {
+    pub slug: String,
+    pub title: String,
+    pub excerpt: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct SearchShard {
+    pub language: String,
+    pub documents: Vec<SearchDocument>,
+    /// term -> flat list of `[document index, score, document index, score, ...]`
+    /// with scores multiplied by 100 and sorted by document index
+    pub terms: BTreeMap<String, Vec<u32>>,
+    /// words whose stem is not a prefix of the word, e.g. `happy -> happi`,
+    /// so the client can find the term without running a stemmer
+    pub aliases: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchManifest {
+    pub default_language: String,
+    /// language -> shard file name relative to the manifest
+    pub languages: BTreeMap<String, String>,
+}
+
+/// Maps a language code like `pt-BR` to the snowball stemmer for it
+pub fn stemmer_algorithm(language: &str) -> Option<Algorithm> {
+    let code = language
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let algorithm = match code.as_str() {
+        "ar" => Algorithm::Arabic,
+        "da" => Algorithm::Danish,
+        "nl" => Algorithm::Dutch,
+        "en" => Algorithm::English,
+        "fi" => Algorithm::Finnish,
+        "fr" => Algorithm::French,
+        "de" => Algorithm::German,
+        "el" => Algorithm::Greek,
+        "hu" => Algorithm::Hungarian,
+        "it" => Algorithm::Italian,
+        "no" | "nb" | "nn" => Algorithm::Norwegian,
+        "pt" => Algorithm::Portuguese,
+        "ro" => Algorithm::Romanian,
+        "ru" => Algorithm::Russian,
+        "es" => Algorithm::Spanish,
+        "sv" => Algorithm::Swedish,
+        "ta" => Algorithm::Tamil,
+        "tr" => Algorithm::Turkish,
+        _ => return None,
+    };
+    Some(algorithm)
+}
+
+/// Lowercases and removes diacritics, `Ação` -> `acao`.
+/// `search.js` applies the same normalization to the query.
+pub fn normalize(word: &str) -> String {
+    word.to_lowercase()
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect()
+}
+
+/// Splits text into lowercase words of letters and digits
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 1)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Turns text into index terms for a single language
+pub struct Analyzer {
+    stemmer: Option<Stemmer>,
+}
+
+impl Analyzer {
+    pub fn new(language: &str) -> Self {
+        Self {
+            stemmer: stemmer_algorithm(language).map(Stemmer::create),
+        }
+    }
+
+    /// Returns `(term, word)` pairs where `term` is the normalized stem
+    /// and `word` the normalized word it came from
+    pub fn analyze(&self, text: &str) -> Vec<(String, String)> {
+        tokenize(text)
+            .into_iter()
+            .map(|word| {
+                let stem = match &self.stemmer {
+                    Some(stemmer) => normalize(&stemmer.stem(&word)),
+                    None => normalize(&word),
+                };
+                (stem, normalize(&word))
+            })
+            .filter(|(stem, _)| !stem.is_empty())
+            .collect()
+    }
+}
+
+/// Removes HTML tags and template tags and decodes the common entities
+pub fn html_to_text(html: &str) -> String {
+    let re = Regex::new(re::MATCH_HTML_OR_TEMPLATE_TAGS).unwrap_or_else(|e| {
+        error!("Regex compilation failed: {e}");
+        Regex::new(re::MATCH_HTML_TAGS).expect("Basic regex should compile")
+    });
+    re.replace_all(html, " ")
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn excerpt(content: &Content, text: &str) -> String {
+    if let Some(description) = content.description.as_ref().filter(|d| !d.is_empty()) {
+        return description.clone();
+    }
+    truncate_text(text, EXCERPT_LENGTH)
+}
+
+/// Cuts text to at most `length` characters at a word boundary, adding `...`
+/// when the text was cut
+pub fn truncate_text(text: &str, length: usize) -> String {
+    if text.chars().count() <= length {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(length).collect();
+    if let Some(last_space) = truncated.rfind(' ') {
+        truncated.truncate(last_space);
+    }
+    truncated.push_str("...");
+    truncated
+}
+
+/// Builds the ranked inverted index for the contents of one language
+pub fn build_shard(language: &str, contents: &[&Content], weights: &SearchWeights) -> SearchShard {
+    let analyzer = Analyzer::new(language);
+    let mut shard = SearchShard {
+        language: language.to_string(),
+        ..Default::default()
+    };
+
+    // weighted term frequency of every term in every document
+    let mut frequencies: Vec<HashMap<String, f64>> = Vec::with_capacity(contents.len());
+    let mut lengths: Vec<f64> = Vec::with_capacity(contents.len());
+
+    for content in contents {
+        let text = html_to_text(&content.html);
+        let description = content.description.clone().unwrap_or_default();
+        let tags = content.tags.join(" ");
+        let fields = [
+            (content.title.as_str(), weights.title),
+            (tags.as_str(), weights.tags),
+            (description.as_str(), weights.description),
+            (text.as_str(), weights.body),
+        ];
+
+        let mut frequency: HashMap<String, f64> = HashMap::new();
+        let mut length = 0.0;
+        for (field, weight) in fields {
+            if weight <= 0.0 {
+                continue;
+            }
+            for (term, word) in analyzer.analyze(field) {
+                if !word.starts_with(&term) {
+                    shard.aliases.entry(word).or_insert_with(|| term.clone());
+                }
+                *frequency.entry(term).or_default() += weight;
+                length += weight;
+            }
+        }
+
+        frequencies.push(frequency);
+        lengths.push(length);
+        shard.documents.push(SearchDocument {
+            slug: content.slug.clone(),
+            title: content.title.clone(),
+            excerpt: excerpt(content, &text),
+        });
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let total = contents.len() as f64;
+    let average_length = if lengths.is_empty() {
+        1.0
+    } else {
+        (lengths.iter().sum::<f64>() / total).max(1.0)
+    };
+
+    let mut document_frequency: HashMap<&String, f64> = HashMap::new();
+    for frequency in &frequencies {
+        for term in frequency.keys() {
+            *document_frequency.entry(term).or_default() += 1.0;
+        }
+    }
+
+    for (index, frequency) in frequencies.iter().enumerate() {
+        let normalization = K1 * (1.0 - B + B * lengths[index] / average_length);
+        for (term, tf) in frequency {
+            let df = document_frequency[term];
+            let idf = (1.0 + (total - df + 0.5) / (df + 0.5)).ln();
+            let score = idf * tf * (K1 + 1.0) / (tf + normalization);
+            let postings = shard.terms.entry(term.clone()).or_default();
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            postings.extend([
+                u32::try_from(index).unwrap_or(u32::MAX),
+                (score * 100.0).round().max(1.0) as u32,
+            ]);
+        }
+    }
+
+    shard
+}
+
+/// Groups the published contents by language and builds one shard for each
+pub fn build_index(site_data: &Data) -> BTreeMap<String, SearchShard> {
+    let mut by_language: BTreeMap<String, Vec<&Content>> = BTreeMap::new();
+    for content in site_data.posts.iter().chain(&site_data.pages) {
+        if content.stream.as_deref() == Some("draft") {
+            continue;
+        }
+        let language = content
+            .language
+            .clone()
+            .unwrap_or_else(|| site_data.site.language.clone());
+        by_language.entry(language).or_default().push(content);
+    }
+
+    by_language
+        .into_iter()
+        .map(|(language, contents)| {
+            let shard = build_shard(&language, &contents, &site_data.site.search_weights);
+            (language, shard)
+        })
+        .collect()
+}
+
+/// Writes `static/search/index.json` and one `static/search/{language}.json`
+/// shard per language
+pub fn generate_search_index(site_data: &Data, output_folder: &Path) {
+    let search_dir = output_folder
+        .join(&site_data.site.static_path)
+        .join("search");
+    if let Err(e) = fs::create_dir_all(&search_dir) {
+        error!("Failed to create search index directory: {e:?}");
+        return;
+    }
+
+    let shards = build_index(site_data);
+    let mut manifest = SearchManifest {
+        default_language: site_data.site.language.clone(),
+        languages: BTreeMap::new(),
+    };
+
+    for (language, shard) in &shards {
+        let filename = format!("{}.json", crate::slugify::slugify(language));
+        match serde_json::to_string(shard) {
+            Ok(json) => {
+                if let Err(e) = fs::write(search_dir.join(&filename), json) {
+                    error!("Failed to write search index {filename}: {e:?}");
+                    continue;
+                }
+                manifest.languages.insert(language.clone(), filename);
+            }
+            Err(e) => error!("Failed to serialize search index for {language}: {e}"),
+        }
+    }
+
+    match serde_json::to_string(&manifest) {
+        Ok(json) => {
+            if let Err(e) = fs::write(search_dir.join("index.json"), json) {
+                error!("Failed to write search index manifest: {e:?}");
+            } else {
+                info!(
+                    "Generated search index for {} language(s)",
+                    manifest.languages.len()
+                );
+            }
+        }
+        Err(e) => error!("Failed to serialize search index manifest: {e}"),
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/search.rs"]
+mod tests;
@@ -1,19 +1,31 @@
+use base64::prelude::{Engine as _, BASE64_STANDARD};
 use chrono::Utc;
 use log::{error, info, warn};
 use serde_json::json;
+use std::collections::HashMap;
 use std::fmt::Write as _;
-use std::io::{Cursor, ErrorKind};
-use std::path::PathBuf;
+use std::fs;
+use std::io::{Cursor, ErrorKind, Read, Write as _};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc;
 use std::sync::{Arc, LazyLock, Mutex};
+use std::time::SystemTime;
 use std::{fs::File, thread};
 use tiny_http::{Header, Method, Request, Response, Server};
 use tungstenite::handshake::derive_accept_key;
 use tungstenite::protocol::Role;
 use tungstenite::Error as WsError;
 use tungstenite::Message;
+use url::Url;
 use urlencoding::decode;
+use walkdir::WalkDir;
+
+use crate::config::ServerConfig;
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::error::Error;
+use crate::incremental::sha256_hex;
+use crate::site::template_error_location;
 
 pub struct ServerContext {
     pub output_folder: Arc<PathBuf>,
@@ -21,6 +33,9 @@ pub struct ServerContext {
     pub config_path: Arc<PathBuf>,
     pub enable_toolbar: bool,
     pub watch_enabled: bool,
+    /// WebFinger and inboxes of the `activitypub` section
+    pub activitypub: Option<crate::activitypub::Service>,
+    pub server_config: ServerConfig,
 }
 
 const FALLBACK_BIND_ADDRESS: &str = "0.0.0.0:0";
@@ -33,10 +48,22 @@ const CONFIG_API_PATH: &str = "/__marmite__/config";
 const DATA_API_PATH: &str = "/__marmite__/data";
 const FILES_API_PATH: &str = "/__marmite__/files";
 const INIT_API_PATH: &str = "/__marmite__/init";
+const MEDIA_API_PATH: &str = "/__marmite__/media";
 const FILE_API_PATH: &str = "/__marmite__/file/";
 const EDITOR_PAGE_PATH: &str = "/__marmite__/editor/";
 const EDITOR_JS_PATH: &str = "__marmite__/editor.js";
 const EDITOR_CSS_PATH: &str = "__marmite__/editor.css";
+const LOGIN_PATH: &str = "/__marmite__/login";
+/// Cookie holding the API token, set by a `POST` to `/__marmite__/login`
+const TOKEN_COOKIE: &str = "marmite_token";
+/// Page asking for the API token, posted back to `/__marmite__/login`
+const LOGIN_FORM: &str = r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><title>Marmite login</title></head>
+<body><form method="post" action="/__marmite__/login">
+<label>API token <input type="password" name="token" autofocus></label>
+<button type="submit">Log in</button>
+</form></body></html>
+"#;
 const LIVE_RELOAD_SCRIPT: &str = r#"(() => {
     const isHttps = window.location.protocol === "https:";
     const hostPart = window.location.hostname.includes(":") ? `[${window.location.hostname}]` : window.location.hostname;
@@ -44,15 +71,124 @@ const LIVE_RELOAD_SCRIPT: &str = r#"(() => {
     const portSegment = window.location.port ? `:${window.location.port}` : "";
     const wsPath = "/__marmite__/livereload";
     const wsUrl = `${wsProtocol}://${hostPart}${portSegment}${wsPath}`;
+    const scrollKey = `marmite-scroll:${window.location.pathname}`;
+
+    const savedScroll = sessionStorage.getItem(scrollKey);
+    if (savedScroll !== null) {
+        sessionStorage.removeItem(scrollKey);
+        const [x, y] = savedScroll.split(",").map(Number);
+        window.addEventListener("load", () => window.scrollTo(x, y));
+    }
+
+    const reload = () => {
+        sessionStorage.setItem(scrollKey, `${window.scrollX},${window.scrollY}`);
+        window.location.reload();
+    };
+
+    const currentPaths = () => {
+        const path = decodeURIComponent(window.location.pathname);
+        if (path.endsWith("/")) {
+            return [`${path}index.html`];
+        }
+        return [path, `${path}.html`, `${path}/index.html`];
+    };
+
+    // Changed paths are relative to the output folder, the page may be
+    // served under a prefix (workspaces) so compare the end of the path.
+    const matches = (path, changed) =>
+        path === changed || path.endsWith(changed);
+
+    const swapStylesheets = (paths) => {
+        const links = [...document.querySelectorAll('link[rel="stylesheet"]')];
+        const changed = links.filter((link) => {
+            const path = decodeURIComponent(new URL(link.href, window.location.href).pathname);
+            return paths.some((changedPath) => matches(path, changedPath));
+        });
+        // A changed file that is not linked may be imported by any of them
+        const targets = changed.length > 0 ? changed : links;
+        for (const link of targets) {
+            const url = new URL(link.href, window.location.href);
+            url.searchParams.set("marmite-reload", Date.now());
+            const replacement = link.cloneNode();
+            replacement.href = url.toString();
+            replacement.addEventListener("load", () => link.remove());
+            replacement.addEventListener("error", () => link.remove());
+            link.after(replacement);
+        }
+        console.log(`Live reload swapped ${targets.length} stylesheet(s)`);
+    };
+
+    const overlayId = "marmite-build-errors";
+    const hideErrors = () => document.getElementById(overlayId)?.remove();
+
+    const showErrors = (errors) => {
+        hideErrors();
+        const overlay = document.createElement("div");
+        overlay.id = overlayId;
+        overlay.setAttribute("role", "alertdialog");
+        overlay.style.cssText = "position:fixed;inset:0;z-index:2147483647;overflow:auto;" +
+            "padding:2rem;background:rgba(20,20,20,.92);color:#eee;" +
+            "font:14px/1.5 ui-monospace,SFMono-Regular,Menlo,monospace;text-align:left";
+
+        const header = document.createElement("div");
+        header.style.cssText = "display:flex;justify-content:space-between;align-items:center;margin-bottom:1rem";
+        const title = document.createElement("strong");
+        title.style.cssText = "color:#ff6b6b;font-size:1.2em";
+        title.textContent = errors.length === 1 ? "Build error" : `${errors.length} build errors`;
+        const close = document.createElement("button");
+        close.type = "button";
+        close.textContent = "Dismiss (Esc)";
+        close.style.cssText = "font:inherit;padding:.25rem .75rem;cursor:pointer";
+        close.addEventListener("click", hideErrors);
+        header.append(title, close);
+        overlay.append(header);
+
+        for (const error of errors) {
+            const item = document.createElement("div");
+            item.style.cssText = "margin-bottom:1.5rem;border-left:3px solid #ff6b6b;padding-left:1rem";
+            const location = document.createElement("div");
+            location.style.color = "rgb(142, 197, 255)";
+            const place = error.file ? (error.line ? `${error.file}:${error.line}` : error.file) : "";
+            location.textContent = [place, `[${error.code}]`].filter(Boolean).join(" ");
+            const message = document.createElement("pre");
+            message.style.cssText = "margin:.5rem 0 0;white-space:pre-wrap;color:inherit;background:none;font:inherit";
+            message.textContent = error.message;
+            item.append(location, message);
+            overlay.append(item);
+        }
+        document.body.append(overlay);
+    };
+
+    document.addEventListener("keydown", (event) => {
+        if (event.key === "Escape") {
+            hideErrors();
+        }
+    });
 
     const connect = () => {
         const socket = new WebSocket(wsUrl);
         socket.addEventListener("message", (event) => {
             try {
                 const payload = JSON.parse(event.data);
-                if (payload.event === "reload") {
-                    console.log("Live reload triggered, reloading page...");
-                    window.location.reload();
+                // Lets the toolbar take over while it waits for its own changes
+                const notCanceled = window.dispatchEvent(
+                    new CustomEvent("marmite:livereload", { detail: payload, cancelable: true })
+                );
+                if (!notCanceled) {
+                    return;
+                }
+                if (payload.event === "build-error") {
+                    showErrors(payload.errors || []);
+                } else if (payload.event === "build-ok") {
+                    hideErrors();
+                } else if (payload.event === "css") {
+                    swapStylesheets(payload.paths || []);
+                } else if (payload.event === "reload") {
+                    const paths = payload.paths;
+                    if (!paths || currentPaths().some((path) => paths.some((changed) => matches(path, changed)))) {
+                        console.log("Live reload triggered, reloading page...");
+                        reload();
+                    }
                 }
             } catch (err) {
                 console.warn("Failed to parse live reload payload", err);
@@ -122,6 +258,12 @@ pub fn start(bind_address: &str, ctx: &ServerContext, live_reload: Option<&LiveR
         }
         // Continue with request handling
         for mut request in server.incoming_requests() {
+            if let Some(response) = check_basic_auth(&request, ctx) {
+                if let Err(err) = request.respond(response) {
+                    error!("Error sending response: {err:?}");
+                }
+                continue;
+            }
             if let Some(live_reload_handler) = live_reload {
                 if is_live_reload_ws_request(&request) {
                     live_reload_handler.accept(request);
@@ -129,7 +271,7 @@ pub fn start(bind_address: &str, ctx: &ServerContext, live_reload: Option<&LiveR
                 }
             }
 
-            let response = match handle_request(&mut request, ctx, live_reload.is_some()) {
+            let response = match handle_request(&mut request, ctx, &[], live_reload.is_some()) {
                 Ok(response) => response,
                 Err(err) => {
                     error!("Error handling request: {err:?}");
@@ -146,13 +288,31 @@ pub fn start(bind_address: &str, ctx: &ServerContext, live_reload: Option<&LiveR
     let server_port = server_addr.port();
     let server_bind_address = format!("{}:{}", server_addr.ip(), server_port);
 
+    let server_config = &ctx.server_config;
+    if !server_addr.ip().is_loopback()
+        && server_config.api_token.is_none()
+        && server_config.basic_auth.is_none()
+        && !server_config.read_only
+    {
+        warn!(
+            "The content API is writable by anyone who can reach {server_bind_address}, set `server.api_token`, `server.basic_auth` or `--read-only`"
+        );
+    }
+
     if live_reload.is_some() {
         info!("Live reload WebSocket available at ws://{server_bind_address}{LIVE_RELOAD_WS_PATH}");
     }
 
     info!("Server started at http://{server_bind_address}/ - Type ^C to stop.");
 
+    let local_origins = local_origins(server_addr);
     for mut request in server.incoming_requests() {
+        if let Some(response) = check_basic_auth(&request, ctx) {
+            if let Err(err) = request.respond(response) {
+                error!("Failed to send response: {err:?}");
+            }
+            continue;
+        }
         if let Some(live_reload_handler) = live_reload {
             if is_live_reload_ws_request(&request) {
                 live_reload_handler.accept(request);
@@ -160,13 +320,14 @@ pub fn start(bind_address: &str, ctx: &ServerContext, live_reload: Option<&LiveR
             }
         }
 
-        let response = match handle_request(&mut request, ctx, live_reload.is_some()) {
-            Ok(response) => response,
-            Err(err) => {
-                error!("Error handling request: {err:?}");
-                Response::from_string("Internal Server Error").with_status_code(500)
-            }
-        };
+        let response =
+            match handle_request(&mut request, ctx, &local_origins, live_reload.is_some()) {
+                Ok(response) => response,
+                Err(err) => {
+                    error!("Error handling request: {err:?}");
+                    Response::from_string("Internal Server Error").with_status_code(500)
+                }
+            };
 
         if let Err(err) = request.respond(response) {
             error!("Failed to send response: {err:?}");
@@ -181,6 +342,7 @@ pub fn start(bind_address: &str, ctx: &ServerContext, live_reload: Option<&LiveR
 fn handle_request(
     request: &mut tiny_http::Request,
     ctx: &ServerContext,
+    local_origins: &[String],
     live_reload_enabled: bool,
 ) -> Result<Response<Cursor<Vec<u8>>>, String> {
     let output_folder = ctx.output_folder.as_path();
@@ -192,30 +354,28 @@ fn handle_request(
         }
     };
 
-    if decoded_url.starts_with(CONTENT_API_PATH) {
-        return Ok(handle_content_api(request, &decoded_url, ctx));
+    let path = decoded_url
+        .split_once('?')
+        .map_or(decoded_url.as_str(), |(path, _)| path);
+    if path == LOGIN_PATH {
+        return Ok(handle_login(request, &ctx.server_config, local_origins));
     }
 
-    if decoded_url == CONFIG_API_PATH {
-        return Ok(handle_config_api(request, ctx));
-    }
-
-    if decoded_url == DATA_API_PATH {
-        return Ok(handle_data_api(ctx));
-    }
-
-    if decoded_url == FILES_API_PATH {
-        return Ok(handle_files_api(ctx));
-    }
-
-    if decoded_url == INIT_API_PATH && request.method() == &Method::Post {
-        return Ok(handle_init_api(ctx));
+    if let Some(response) = ctx
+        .activitypub
+        .as_ref()
+        .and_then(|activitypub| activitypub.handle(request))
+    {
+        return Ok(response);
     }
 
-    if let Some(file_path) = decoded_url.strip_prefix(FILE_API_PATH) {
-        if !file_path.is_empty() {
-            return Ok(handle_file_api(request, file_path, ctx));
-        }
+    if is_api_path(&decoded_url, request.method()) {
+        return Ok(handle_api_request(
+            request,
+            &decoded_url,
+            ctx,
+            local_origins,
+        ));
     }
 
     if live_reload_enabled && decoded_url == format!("/{LIVE_RELOAD_SCRIPT_PATH}") {
@@ -347,7 +507,12 @@ fn handle_request(
                     request.http_version()
                 );
                 let mut resp = Response::from_data(buffer);
-                if let Some(content_type) = content_type_for(&request_path) {
+                let content_type = ctx
+                    .activitypub
+                    .as_ref()
+                    .and_then(|activitypub| activitypub.content_type(&request_path))
+                    .or_else(|| content_type_for(&request_path));
+                if let Some(content_type) = content_type {
                     match Header::from_bytes("Content-Type", content_type) {
                         Ok(header) => resp.add_header(header),
                         Err(e) => error!("Failed to create Content-Type header: {e:?}"),
@@ -376,6 +541,354 @@ fn handle_request(
     }
 }
 
+fn is_api_path(url: &str, method: &Method) -> bool {
+    url.starts_with(CONTENT_API_PATH)
+        || url == CONFIG_API_PATH
+        || url == DATA_API_PATH
+        || url == FILES_API_PATH
+        || (url == INIT_API_PATH && method == &Method::Post)
+        || (url == MEDIA_API_PATH && method == &Method::Post)
+        || url
+            .strip_prefix(FILE_API_PATH)
+            .is_some_and(|file_path| !file_path.is_empty())
+}
+
+fn dispatch_api(
+    request: &mut Request,
+    url: &str,
+    ctx: &ServerContext,
+) -> Response<Cursor<Vec<u8>>> {
+    if url.starts_with(CONTENT_API_PATH) {
+        return handle_content_api(request, url, ctx);
+    }
+    match url {
+        CONFIG_API_PATH => handle_config_api(request, ctx),
+        DATA_API_PATH => handle_data_api(ctx),
+        FILES_API_PATH => handle_files_api(ctx),
+        INIT_API_PATH => handle_init_api(ctx),
+        MEDIA_API_PATH => handle_media_upload(request, ctx),
+        _ => {
+            let file_path = url.strip_prefix(FILE_API_PATH).unwrap_or_default();
+            handle_file_api(request, file_path, ctx)
+        }
+    }
+}
+
+fn is_mutating(method: &Method) -> bool {
+    matches!(
+        method,
+        Method::Post | Method::Put | Method::Patch | Method::Delete
+    )
+}
+
+/// Checks the token, the origin and the read-only mode before handing a
+/// request to the API, then records mutations in the audit log
+fn handle_api_request(
+    request: &mut Request,
+    url: &str,
+    ctx: &ServerContext,
+    local_origins: &[String],
+) -> Response<Cursor<Vec<u8>>> {
+    let config = &ctx.server_config;
+    let method = request.method().clone();
+    let mutating = is_mutating(&method);
+    let user = api_user(request.headers(), config);
+
+    let response = if user.is_none() {
+        json_response(401, &json!({"error": "a valid API token is required"}))
+    } else if mutating
+        && !is_allowed_origin(request.headers(), local_origins, &config.allowed_origins)
+    {
+        json_response(403, &json!({"error": "cross-origin request rejected"}))
+    } else if mutating && config.read_only {
+        json_response(403, &json!({"error": "the API is read-only"}))
+    } else {
+        dispatch_api(request, url, ctx)
+    };
+
+    if mutating {
+        let remote = request
+            .remote_addr()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        audit_mutation(
+            ctx,
+            &json!({
+                "timestamp": Utc::now().to_rfc3339(),
+                "remote": remote,
+                "user": user.as_deref().unwrap_or("unauthenticated"),
+                "method": method.as_str(),
+                "path": url,
+                "status": response.status_code().0,
+            }),
+        );
+    }
+    response
+}
+
+fn header_value<'a>(headers: &'a [Header], name: &'static str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+/// Strong validator of a file: the quoted sha256 of its bytes
+fn file_etag(path: &Path) -> Option<String> {
+    std::fs::read(path)
+        .ok()
+        .map(|bytes| format!("\"{}\"", sha256_hex(&bytes)))
+}
+
+/// Whether the `If-Match` header of a write allows replacing the file whose
+/// `ETag` is `current`, `None` when the file does not exist. Writes without the
+/// header are always allowed. `If-Match` uses the strong comparison, weak
+/// tags never match.
+fn if_match_allows(request: &Request, current: Option<&str>) -> bool {
+    let Some(expected) = header_value(request.headers(), "If-Match") else {
+        return true;
+    };
+    let Some(current) = current else {
+        return false;
+    };
+    expected.trim() == "*"
+        || expected
+            .split(',')
+            .map(str::trim)
+            .any(|tag| !tag.starts_with("W/") && tag == current)
+}
+
+/// 412 for an `If-Match` listing only weak tags, which no file can match
+fn weak_if_match(request: &Request) -> Option<Response<Cursor<Vec<u8>>>> {
+    let expected = header_value(request.headers(), "If-Match")?;
+    expected
+        .split(',')
+        .all(|tag| tag.trim().starts_with("W/"))
+        .then(|| {
+            json_response(
+                412,
+                &json!({"error": "If-Match needs a strong ETag, weak tags never match"}),
+            )
+        })
+}
+
+fn with_etag(
+    mut response: Response<Cursor<Vec<u8>>>,
+    etag: Option<&str>,
+) -> Response<Cursor<Vec<u8>>> {
+    if let Some(Ok(header)) = etag.map(|etag| Header::from_bytes("ETag", etag)) {
+        response.add_header(header);
+    }
+    response
+}
+
+/// 409 carrying the current version, so the client can merge it
+fn conflict_response(mut current: serde_json::Value) -> Response<Cursor<Vec<u8>>> {
+    let etag = current["etag"].as_str().map(str::to_string);
+    current["error"] = json!("The file was changed since it was loaded");
+    with_etag(json_response(409, &current), etag.as_deref())
+}
+
+/// Compares secrets without leaking where they differ through timing
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// User name of valid `Authorization: Basic` credentials
+fn basic_auth_user(headers: &[Header], credentials: &str) -> Option<String> {
+    let encoded = header_value(headers, "Authorization")?.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(BASE64_STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    constant_time_eq(&decoded, credentials).then(|| {
+        decoded
+            .split_once(':')
+            .map_or(decoded.as_str(), |(user, _)| user)
+            .to_string()
+    })
+}
+
+/// Whether the request carries the API token as a bearer token, an
+/// `X-Marmite-Token` header or the login cookie
+fn has_api_token(headers: &[Header], token: &str) -> bool {
+    let bearer = header_value(headers, "Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    let custom = header_value(headers, "X-Marmite-Token");
+    let cookie = header_value(headers, "Cookie").and_then(|cookies| {
+        cookies
+            .split(';')
+            .find_map(|cookie| cookie.trim().strip_prefix(TOKEN_COOKIE)?.strip_prefix('='))
+    });
+    [bearer, custom, cookie]
+        .into_iter()
+        .flatten()
+        .any(|value| constant_time_eq(value, token))
+}
+
+/// Name recorded in the audit log, `None` when the API token is missing
+fn api_user(headers: &[Header], config: &ServerConfig) -> Option<String> {
+    if let Some(token) = &config.api_token {
+        if !has_api_token(headers, token) {
+            return None;
+        }
+    }
+    let user = config
+        .basic_auth
+        .as_deref()
+        .and_then(|credentials| basic_auth_user(headers, credentials));
+    Some(user.unwrap_or_else(|| {
+        if config.api_token.is_some() {
+            "token".to_string()
+        } else {
+            "anonymous".to_string()
+        }
+    }))
+}
+
+/// Origins of the server itself: the bound address, and `localhost` when
+/// bound to a loopback or to every interface
+fn local_origins(addr: std::net::SocketAddr) -> Vec<String> {
+    let mut hosts = Vec::new();
+    if addr.ip().is_loopback() || addr.ip().is_unspecified() {
+        hosts.extend(["localhost", "127.0.0.1", "[::1]"].map(str::to_string));
+    }
+    if !addr.ip().is_unspecified() {
+        hosts.push(match addr.ip() {
+            std::net::IpAddr::V4(ip) => ip.to_string(),
+            std::net::IpAddr::V6(ip) => format!("[{ip}]"),
+        });
+    }
+    let mut origins: Vec<String> = Vec::new();
+    for host in hosts {
+        let Ok(url) = Url::parse(&format!("http://{host}:{}", addr.port())) else {
+            continue;
+        };
+        let origin = url.origin().ascii_serialization();
+        if !origins.contains(&origin) {
+            origins.push(origin);
+        }
+    }
+    origins
+}
+
+/// Whether a mutating request comes from the server itself or from one of
+/// `allowed_origins`. The `Host` header is not trusted, a DNS rebinding
+/// attack controls it. Requests without `Origin`, like the ones made by
+/// `curl`, are allowed unless a browser sent them, which `Referer` and
+/// `Sec-Fetch-Site` tell.
+fn is_allowed_origin(
+    headers: &[Header],
+    local_origins: &[String],
+    allowed_origins: &[String],
+) -> bool {
+    let Some(source) = header_value(headers, "Origin") else {
+        return header_value(headers, "Referer").is_none()
+            && header_value(headers, "Sec-Fetch-Site").is_none();
+    };
+    let Ok(url) = Url::parse(source) else {
+        return false;
+    };
+
+    let origin = url.origin().ascii_serialization();
+    local_origins
+        .iter()
+        .chain(allowed_origins)
+        .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(&origin))
+}
+
+/// Rejects requests without the `server.basic_auth` credentials, when set
+fn check_basic_auth(request: &Request, ctx: &ServerContext) -> Option<Response<Cursor<Vec<u8>>>> {
+    let credentials = ctx.server_config.basic_auth.as_deref()?;
+    if basic_auth_user(request.headers(), credentials).is_some() {
+        return None;
+    }
+    let mut response = Response::from_string("Unauthorized").with_status_code(401);
+    if let Ok(header) = Header::from_bytes(
+        "WWW-Authenticate",
+        r#"Basic realm="marmite", charset="UTF-8""#,
+    ) {
+        response.add_header(header);
+    }
+    Some(response)
+}
+
+/// Stores the API token in a cookie so the toolbar and the editor can use
+/// the API from the browser. `GET` shows a form, the token is only accepted
+/// in the body of a `POST` from an allowed origin, so it stays out of URLs
+/// and other sites can not log a browser in with their own token.
+fn handle_login(
+    request: &mut Request,
+    config: &ServerConfig,
+    local_origins: &[String],
+) -> Response<Cursor<Vec<u8>>> {
+    match request.method() {
+        Method::Get => {
+            let mut response = Response::from_string(LOGIN_FORM);
+            if let Ok(header) = Header::from_bytes("Content-Type", "text/html; charset=utf-8") {
+                response.add_header(header);
+            }
+            return response;
+        }
+        Method::Post => {}
+        _ => return json_response(405, &json!({"error": "use POST to log in"})),
+    }
+    if !is_allowed_origin(request.headers(), local_origins, &config.allowed_origins) {
+        return json_response(403, &json!({"error": "cross-origin request rejected"}));
+    }
+    let body = match read_request_body(request) {
+        Ok(body) => body,
+        Err(e) => return json_response(400, &json!({"error": e})),
+    };
+    let token = url::form_urlencoded::parse(body.as_bytes())
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.into_owned());
+    let valid = match (&config.api_token, &token) {
+        (Some(expected), Some(token)) => constant_time_eq(token, expected),
+        _ => false,
+    };
+    if !valid {
+        return json_response(401, &json!({"error": "invalid token"}));
+    }
+
+    let mut response = Response::from_string("").with_status_code(303);
+    let cookie = format!(
+        "{TOKEN_COOKIE}={}; Path=/; HttpOnly; SameSite=Strict",
+        token.unwrap_or_default()
+    );
+    for (name, value) in [("Set-Cookie", cookie.as_str()), ("Location", "/")] {
+        if let Ok(header) = Header::from_bytes(name, value) {
+            response.add_header(header);
+        }
+    }
+    response
+}
+
+/// Appends a change made through the API to `server.audit_log`
+fn audit_mutation(ctx: &ServerContext, entry: &serde_json::Value) {
+    info!(
+        "API {} {} by {} -> {}",
+        entry["method"].as_str().unwrap_or_default(),
+        entry["path"].as_str().unwrap_or_default(),
+        entry["user"].as_str().unwrap_or_default(),
+        entry["status"]
+    );
+    if ctx.server_config.audit_log.is_empty() {
+        return;
+    }
+    let path = ctx.input_folder.join(&ctx.server_config.audit_log);
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{entry}"));
+    if let Err(e) = written {
+        error!("Failed to write audit log {}: {e}", path.display());
+    }
+}
+
 fn json_response(status: u16, body: &serde_json::Value) -> Response<Cursor<Vec<u8>>> {
     let json_bytes = serde_json::to_string_pretty(body)
         .unwrap_or_else(|_| r#"{"error":"serialization failed"}"#.to_string())
@@ -387,6 +900,15 @@ fn json_response(status: u16, body: &serde_json::Value) -> Response<Cursor<Vec<u
     resp
 }
 
+/// Reads the config again for an API request, a config that can not be
+/// parsed is a 500 instead of stopping the server
+fn load_site_data(ctx: &ServerContext) -> Result<crate::site::Data, Response<Cursor<Vec<u8>>>> {
+    crate::site::Data::try_from_file(&ctx.config_path).map_err(|e| {
+        error!("{e}");
+        json_response(500, &json!({"error": e.to_string()}))
+    })
+}
+
 fn read_request_body(request: &mut Request) -> Result<String, String> {
     let mut body = String::new();
     request
@@ -406,6 +928,12 @@ fn handle_content_api(
         .and_then(|s| s.strip_prefix('/'))
         .unwrap_or("");
 
+    if let Some((slug, route)) = rest.split_once("/history") {
+        if route.is_empty() || route.starts_with('/') {
+            return handle_history_api(request, slug, route.trim_start_matches('/'), ctx);
+        }
+    }
+
     match *request.method() {
         Method::Get if rest.ends_with("/body") => {
             let slug = rest.strip_suffix("/body").unwrap_or("");
@@ -513,6 +1041,11 @@ fn handle_create_content(request: &mut Request, ctx: &ServerContext) -> Response
 
     match crate::content::create_content(&ctx.input_folder, &ctx.config_path, &params) {
         Ok(result) => {
+            commit_change(
+                ctx,
+                &[&result.file_path],
+                &format!("Create {}", result.slug),
+            );
             let mut output = serde_json::Map::new();
             output.insert("file".into(), json!(result.file_path.display().to_string()));
             output.insert("title".into(), json!(result.title));
@@ -556,7 +1089,10 @@ fn handle_patch_content(
         return json_response(400, &json!({"error": "no fields to update"}));
     }
 
-    let site_data = crate::site::Data::from_file(&ctx.config_path);
+    let site_data = match load_site_data(ctx) {
+        Ok(site_data) => site_data,
+        Err(response) => return response,
+    };
     let content_folder = crate::site::get_content_folder(&site_data.site, &ctx.input_folder);
 
     let Some(file_path) = crate::content::find_file_by_slug(&content_folder, slug) else {
@@ -566,32 +1102,67 @@ fn handle_patch_content(
         );
     };
 
+    if let Some(response) = weak_if_match(request) {
+        return response;
+    }
+    if !if_match_allows(request, file_etag(&file_path).as_deref()) {
+        return match content_body_json(slug, &content_folder, ctx) {
+            Ok(current) => conflict_response(current),
+            Err(e) => json_response(500, &json!({"error": e})),
+        };
+    }
+    if let Err(e) = save_revision(ctx, slug, &file_path, "frontmatter") {
+        return json_response(500, &json!({"error": e}));
+    }
     match crate::content::update_frontmatter(&file_path, &patch_fields) {
-        Ok(frontmatter) => json_response(
-            200,
-            &json!({
-                "slug": slug,
-                "file": file_path.display().to_string(),
-                "frontmatter": frontmatter,
-            }),
-        ),
+        Ok(frontmatter) => {
+            let etag = file_etag(&file_path);
+            commit_change(
+                ctx,
+                &[&file_path],
+                &format!("Update the frontmatter of {slug}"),
+            );
+            with_etag(
+                json_response(
+                    200,
+                    &json!({
+                        "slug": slug,
+                        "file": file_path.display().to_string(),
+                        "frontmatter": frontmatter,
+                        "etag": etag,
+                    }),
+                ),
+                etag.as_deref(),
+            )
+        }
         Err(e) => json_response(500, &json!({"error": e})),
     }
 }
 
 fn handle_delete_content(slug: &str, ctx: &ServerContext) -> Response<Cursor<Vec<u8>>> {
-    let site_data = crate::site::Data::from_file(&ctx.config_path);
+    let site_data = match load_site_data(ctx) {
+        Ok(site_data) => site_data,
+        Err(response) => return response,
+    };
     let content_folder = crate::site::get_content_folder(&site_data.site, &ctx.input_folder);
 
+    if let Some(file_path) = crate::content::find_file_by_slug(&content_folder, slug) {
+        if let Err(e) = save_revision(ctx, slug, &file_path, "delete") {
+            return json_response(500, &json!({"error": e}));
+        }
+    }
     match crate::content::delete_content(&content_folder, slug) {
-        Ok(file_path) => json_response(
-            200,
-            &json!({
-                "slug": slug,
-                "file": file_path.display().to_string(),
-                "deleted": true,
-            }),
-        ),
+        Ok(file_path) => {
+            commit_change(ctx, &[&file_path], &format!("Delete {slug}"));
+            json_response(
+                200,
+                &json!({
+                    "slug": slug,
+                    "file": file_path.display().to_string(),
+                    "deleted": true,
+                }),
+            )
+        }
         Err(e) if e.contains("not found") => json_response(404, &json!({"error": e})),
         Err(e) => json_response(500, &json!({"error": e})),
     }
@@ -616,11 +1187,24 @@ fn handle_move_content(
         return json_response(400, &json!({"error": "filename is required"}));
     };
 
-    let site_data = crate::site::Data::from_file(&ctx.config_path);
+    let site_data = match load_site_data(ctx) {
+        Ok(site_data) => site_data,
+        Err(response) => return response,
+    };
     let content_folder = crate::site::get_content_folder(&site_data.site, &ctx.input_folder);
 
+    if let Some(file_path) = crate::content::find_file_by_slug(&content_folder, slug) {
+        if let Err(e) = save_revision(ctx, slug, &file_path, "move") {
+            return json_response(500, &json!({"error": e}));
+        }
+    }
     match crate::content::move_content(&content_folder, slug, new_filename) {
         Ok((old_path, new_path)) => {
+            commit_change(
+                ctx,
+                &[&old_path, &new_path],
+                &format!("Move {slug} to {new_filename}"),
+            );
             let mut new_slug = new_path.file_stem().and_then(|s| s.to_str()).map_or_else(
                 || slug.to_string(),
                 crate::content::remove_date_from_filename,
@@ -670,18 +1254,28 @@ fn handle_clone_content(
 
     let new_slug = parsed.get("slug").and_then(|v| v.as_str());
 
-    let site_data = crate::site::Data::from_file(&ctx.config_path);
+    let site_data = match load_site_data(ctx) {
+        Ok(site_data) => site_data,
+        Err(response) => return response,
+    };
     let content_folder = crate::site::get_content_folder(&site_data.site, &ctx.input_folder);
 
     match crate::content::clone_content(&content_folder, slug, title, new_slug) {
-        Ok((file_path, result_slug)) => json_response(
-            201,
-            &json!({
-                "slug": result_slug,
-                "file": file_path.display().to_string(),
-                "source": slug,
-            }),
-        ),
+        Ok((file_path, result_slug)) => {
+            commit_change(
+                ctx,
+                &[&file_path],
+                &format!("Clone {slug} as {result_slug}"),
+            );
+            json_response(
+                201,
+                &json!({
+                    "slug": result_slug,
+                    "file": file_path.display().to_string(),
+                    "source": slug,
+                }),
+            )
+        }
         Err(e) if e.contains("not found") => json_response(404, &json!({"error": e})),
         Err(e) => json_response(400, &json!({"error": e})),
     }
@@ -932,36 +1526,22 @@ fn handle_file_api(
                 );
             }
             match std::fs::read_to_string(&file_path) {
-                Ok(content) => json_response(200, &json!({ "path": rel_path, "content": content })),
-                Err(e) => {
-                    json_response(500, &json!({"error": format!("failed to read file: {e}")}))
-                }
-            }
-        }
-        Method::Put => {
-            let body = match read_request_body(request) {
-                Ok(b) => b,
-                Err(e) => return json_response(400, &json!({"error": e})),
-            };
-            let parsed: serde_json::Value = match serde_json::from_str(&body) {
-                Ok(v) => v,
-                Err(e) => {
-                    return json_response(400, &json!({"error": format!("Invalid JSON: {e}")}))
+                Ok(content) => {
+                    let etag = file_etag(&file_path);
+                    with_etag(
+                        json_response(
+                            200,
+                            &json!({ "path": rel_path, "content": content, "etag": etag }),
+                        ),
+                        etag.as_deref(),
+                    )
                 }
-            };
-            let Some(content) = parsed.get("content").and_then(|v| v.as_str()) else {
-                return json_response(400, &json!({"error": "content field is required"}));
-            };
-            if let Some(parent) = file_path.parent() {
-                let _ = std::fs::create_dir_all(parent);
-            }
-            match std::fs::write(&file_path, content) {
-                Ok(()) => json_response(200, &json!({"path": rel_path})),
                 Err(e) => {
-                    json_response(500, &json!({"error": format!("failed to write file: {e}")}))
+                    json_response(500, &json!({"error": format!("failed to read file: {e}")}))
                 }
             }
         }
+        Method::Put => handle_put_file(request, rel_path, &file_path),
         Method::Delete => {
             if !file_path.is_file() {
                 return json_response(
@@ -981,9 +1561,163 @@ fn handle_file_api(
     }
 }
 
+/// `PUT /__marmite__/file/{path}`, checking `If-Match` against the file
+fn handle_put_file(
+    request: &mut Request,
+    rel_path: &str,
+    file_path: &Path,
+) -> Response<Cursor<Vec<u8>>> {
+    let body = match read_request_body(request) {
+        Ok(b) => b,
+        Err(e) => return json_response(400, &json!({"error": e})),
+    };
+    let parsed: serde_json::Value = match serde_json::from_str(&body) {
+        Ok(v) => v,
+        Err(e) => return json_response(400, &json!({"error": format!("Invalid JSON: {e}")})),
+    };
+    let Some(content) = parsed.get("content").and_then(|v| v.as_str()) else {
+        return json_response(400, &json!({"error": "content field is required"}));
+    };
+    if let Some(response) = weak_if_match(request) {
+        return response;
+    }
+    let current_etag = file_path.is_file().then(|| file_etag(file_path)).flatten();
+    if !if_match_allows(request, current_etag.as_deref()) {
+        return conflict_response(json!({
+            "path": rel_path,
+            "content": current_etag
+                .as_ref()
+                .and_then(|_| std::fs::read_to_string(file_path).ok()),
+            "etag": current_etag,
+        }));
+    }
+    if let Some(parent) = file_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match std::fs::write(file_path, content) {
+        Ok(()) => {
+            let etag = file_etag(file_path);
+            with_etag(
+                json_response(200, &json!({"path": rel_path, "etag": etag})),
+                etag.as_deref(),
+            )
+        }
+        Err(e) => json_response(500, &json!({"error": format!("failed to write file: {e}")})),
+    }
+}
+
+/// `POST /__marmite__/media`: stores the `file` fields of a multipart body in
+/// the media folder and answers with the markdown to insert them
+fn handle_media_upload(request: &mut Request, ctx: &ServerContext) -> Response<Cursor<Vec<u8>>> {
+    let Some(boundary) =
+        header_value(request.headers(), "Content-Type").and_then(crate::upload::boundary)
+    else {
+        return json_response(
+            400,
+            &json!({"error": "a multipart/form-data body is required"}),
+        );
+    };
+    let max_size = ctx.server_config.max_upload_size;
+    let too_large = || {
+        json_response(
+            413,
+            &json!({"error": format!("uploads are limited to {max_size} bytes")}),
+        )
+    };
+    if request
+        .body_length()
+        .is_some_and(|length| length as u64 > max_size)
+    {
+        return too_large();
+    }
+    let mut body = Vec::new();
+    if let Err(e) =
+        Read::take(request.as_reader(), max_size.saturating_add(1)).read_to_end(&mut body)
+    {
+        return json_response(
+            400,
+            &json!({"error": format!("failed to read upload: {e}")}),
+        );
+    }
+    if body.len() as u64 > max_size {
+        return too_large();
+    }
+
+    let parts = match crate::upload::parse_multipart(&body, &boundary) {
+        Ok(parts) => parts,
+        Err(e) => return json_response(400, &json!({"error": e})),
+    };
+    let text_field = |name: &str| {
+        parts
+            .iter()
+            .find(|part| part.name == name && part.filename.is_none())
+            .map(|part| String::from_utf8_lossy(&part.data).trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let slug = text_field("slug");
+    let alt = text_field("alt");
+    if let Err(e) = slug.as_deref().map_or(Ok(()), crate::upload::check_slug) {
+        return json_response(400, &json!({"error": e}));
+    }
+
+    let mut files = Vec::new();
+    for part in parts.iter().filter(|part| part.name == "file") {
+        let original = part.filename.as_deref().unwrap_or_default();
+        let Some(filename) = crate::upload::safe_filename(original) else {
+            return json_response(
+                400,
+                &json!({"error": format!("invalid file name '{original}'")}),
+            );
+        };
+        if let Err(e) = crate::upload::check_image(&filename, &part.data) {
+            return json_response(415, &json!({"error": e}));
+        }
+        files.push((filename, &part.data));
+    }
+    if files.is_empty() {
+        return json_response(400, &json!({"error": "no file field in the upload"}));
+    }
+
+    let site_data = match load_site_data(ctx) {
+        Ok(site_data) => site_data,
+        Err(response) => return response,
+    };
+    let content_folder = crate::site::get_content_folder(&site_data.site, &ctx.input_folder);
+    let mut uploads = Vec::new();
+    for (filename, data) in files {
+        match crate::upload::store_image(
+            &ctx.input_folder,
+            &content_folder,
+            &site_data.site,
+            slug.as_deref(),
+            &filename,
+            alt.as_deref(),
+            data,
+        ) {
+            Ok(upload) => uploads.push(upload),
+            Err(e) => return json_response(500, &json!({"error": e, "files": uploads})),
+        }
+    }
+
+    let paths: Vec<PathBuf> = uploads
+        .iter()
+        .map(|upload| ctx.input_folder.join(&upload.path))
+        .collect();
+    let names: Vec<&str> = uploads.iter().map(|upload| upload.url.as_str()).collect();
+    commit_change(
+        ctx,
+        &paths.iter().map(PathBuf::as_path).collect::<Vec<_>>(),
+        &format!("Upload {}", names.join(", ")),
+    );
+    json_response(201, &json!({"files": uploads}))
+}
+
 fn handle_files_api(ctx: &ServerContext) -> Response<Cursor<Vec<u8>>> {
     let input_folder = ctx.input_folder.as_path();
-    let site_data = crate::site::Data::from_file(&ctx.config_path);
+    let site_data = match load_site_data(ctx) {
+        Ok(site_data) => site_data,
+        Err(response) => return response,
+    };
     let content_path = &site_data.site.content_path;
     let site_path = &site_data.site.site_path;
     let templates_path = &site_data.site.templates_path;
@@ -1245,28 +1979,38 @@ fn handle_patch_config(request: &mut Request, ctx: &ServerContext) -> Response<C
     )
 }
 
+/// Frontmatter, body and `ETag` of the file of `slug`
+fn content_body_json(
+    slug: &str,
+    content_folder: &Path,
+    ctx: &ServerContext,
+) -> Result<serde_json::Value, String> {
+    let (frontmatter, body, file_path, frontmatter_lines) =
+        crate::content::get_raw_content(content_folder, slug)?;
+    let source_path = file_path
+        .strip_prefix(&*ctx.input_folder)
+        .unwrap_or(&file_path)
+        .display()
+        .to_string();
+    Ok(json!({
+        "slug": slug,
+        "frontmatter": frontmatter,
+        "body": body,
+        "source_path": source_path,
+        "frontmatter_lines": frontmatter_lines,
+        "etag": file_etag(&file_path),
+    }))
+}
+
 fn handle_get_content_body(slug: &str, ctx: &ServerContext) -> Response<Cursor<Vec<u8>>> {
-    let site_data = crate::site::Data::from_file(&ctx.config_path);
+    let site_data = match load_site_data(ctx) {
+        Ok(site_data) => site_data,
+        Err(response) => return response,
+    };
     let content_folder = crate::site::get_content_folder(&site_data.site, &ctx.input_folder);
 
-    match crate::content::get_raw_content(&content_folder, slug) {
-        Ok((frontmatter, body, file_path, frontmatter_lines)) => {
-            let source_path = file_path
-                .strip_prefix(&*ctx.input_folder)
-                .unwrap_or(&file_path)
-                .display()
-                .to_string();
-            json_response(
-                200,
-                &json!({
-                    "slug": slug,
-                    "frontmatter": frontmatter,
-                    "body": body,
-                    "source_path": source_path,
-                    "frontmatter_lines": frontmatter_lines,
-                }),
-            )
-        }
+    match content_body_json(slug, &content_folder, ctx) {
+        Ok(output) => with_etag(json_response(200, &output), output["etag"].as_str()),
         Err(e) if e.contains("not found") => json_response(404, &json!({"error": e})),
         Err(e) => json_response(500, &json!({"error": e})),
     }
@@ -1296,21 +2040,194 @@ fn handle_put_content_body(
         .and_then(|v| v.as_object())
         .cloned();
 
-    let site_data = crate::site::Data::from_file(&ctx.config_path);
+    let site_data = match load_site_data(ctx) {
+        Ok(site_data) => site_data,
+        Err(response) => return response,
+    };
     let content_folder = crate::site::get_content_folder(&site_data.site, &ctx.input_folder);
 
+    let file_path = crate::content::find_file_by_slug(&content_folder, slug);
+    if let Some(file_path) = &file_path {
+        if let Some(response) = weak_if_match(request) {
+            return response;
+        }
+        if !if_match_allows(request, file_etag(file_path).as_deref()) {
+            return match content_body_json(slug, &content_folder, ctx) {
+                Ok(current) => conflict_response(current),
+                Err(e) => json_response(500, &json!({"error": e})),
+            };
+        }
+        if let Err(e) = save_revision(ctx, slug, file_path, "edit") {
+            return json_response(500, &json!({"error": e}));
+        }
+    }
     match crate::content::update_content_body(&content_folder, slug, new_body, fm_updates.as_ref())
     {
-        Ok(frontmatter) => json_response(
+        Ok(frontmatter) => {
+            let etag = file_path.as_deref().and_then(file_etag);
+            if let Some(file_path) = &file_path {
+                commit_change(ctx, &[file_path], &format!("Edit {slug}"));
+            }
+            with_etag(
+                json_response(
+                    200,
+                    &json!({
+                        "slug": slug,
+                        "frontmatter": frontmatter,
+                        "etag": etag,
+                    }),
+                ),
+                etag.as_deref(),
+            )
+        }
+        Err(e) if e.contains("not found") => json_response(404, &json!({"error": e})),
+        Err(e) => json_response(500, &json!({"error": e})),
+    }
+}
+
+/// Saves the current version of `file` in the history before the API
+/// changes it, the change is refused when it cannot be saved
+fn save_revision(ctx: &ServerContext, slug: &str, file: &Path, action: &str) -> Result<(), String> {
+    crate::history::record(
+        &ctx.input_folder,
+        slug,
+        file,
+        action,
+        ctx.server_config.history_limit,
+    )
+    .map(|_| ())
+    .map_err(|e| format!("Failed to save a revision of '{slug}': {e}"))
+}
+
+/// Commits the files changed by the API when `server.git_commit` is set
+fn commit_change(ctx: &ServerContext, paths: &[&Path], summary: &str) {
+    if !ctx.server_config.git_commit {
+        return;
+    }
+    let message = format!("{summary}\n\nChanged through the marmite dev server API.");
+    match crate::history::commit(&ctx.input_folder, paths, &message) {
+        Ok(()) => info!("Committed: {summary}"),
+        Err(e) => warn!("Failed to commit '{summary}': {e}"),
+    }
+}
+
+/// `GET {slug}/history`, `GET {slug}/history/{id}`,
+/// `GET {slug}/history/{id}/diff` and `POST {slug}/history/{id}/restore`
+fn handle_history_api(
+    request: &Request,
+    slug: &str,
+    route: &str,
+    ctx: &ServerContext,
+) -> Response<Cursor<Vec<u8>>> {
+    let parts: Vec<&str> = route.split('/').collect();
+    match (request.method(), parts.as_slice()) {
+        (Method::Get, [""]) => json_response(
             200,
             &json!({
                 "slug": slug,
-                "frontmatter": frontmatter,
+                "revisions": crate::history::list(&ctx.input_folder, slug),
             }),
         ),
-        Err(e) if e.contains("not found") => json_response(404, &json!({"error": e})),
-        Err(e) => json_response(500, &json!({"error": e})),
+        (Method::Get, [id]) => match crate::history::read(&ctx.input_folder, slug, id) {
+            Ok((revision, content)) => {
+                let mut output = json!(revision);
+                output["content"] = json!(content);
+                json_response(200, &output)
+            }
+            Err(e) => json_response(404, &json!({"error": e})),
+        },
+        (Method::Get, [id, "diff"]) => handle_revision_diff(slug, id, ctx),
+        (Method::Post, [id, "restore"]) => handle_restore_revision(slug, id, ctx),
+        _ => json_response(405, &json!({"error": "method not allowed"})),
+    }
+}
+
+/// Changes from the revision to the current file, or to nothing when the
+/// content was deleted
+fn handle_revision_diff(slug: &str, id: &str, ctx: &ServerContext) -> Response<Cursor<Vec<u8>>> {
+    let (revision, old) = match crate::history::read(&ctx.input_folder, slug, id) {
+        Ok(found) => found,
+        Err(e) => return json_response(404, &json!({"error": e})),
+    };
+    let site_data = match load_site_data(ctx) {
+        Ok(site_data) => site_data,
+        Err(response) => return response,
+    };
+    let content_folder = crate::site::get_content_folder(&site_data.site, &ctx.input_folder);
+    let current = crate::content::find_file_by_slug(&content_folder, slug);
+    let new = current
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    let current_path = current.as_ref().map_or_else(
+        || "/dev/null".to_string(),
+        |path| {
+            path.strip_prefix(&*ctx.input_folder)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        },
+    );
+    let diff = crate::history::unified_diff(
+        &old,
+        &new,
+        &format!("{} ({id})", revision.path),
+        &format!("{current_path} (current)"),
+    );
+    json_response(
+        200,
+        &json!({
+            "slug": slug,
+            "id": id,
+            "diff": diff,
+        }),
+    )
+}
+
+/// Writes the revision back to the current file of the content, or to
+/// where it was when it was deleted
+fn handle_restore_revision(slug: &str, id: &str, ctx: &ServerContext) -> Response<Cursor<Vec<u8>>> {
+    let (revision, content) = match crate::history::read(&ctx.input_folder, slug, id) {
+        Ok(found) => found,
+        Err(e) => return json_response(404, &json!({"error": e})),
+    };
+    let site_data = match load_site_data(ctx) {
+        Ok(site_data) => site_data,
+        Err(response) => return response,
+    };
+    let content_folder = crate::site::get_content_folder(&site_data.site, &ctx.input_folder);
+    let target = match crate::content::find_file_by_slug(&content_folder, slug) {
+        Some(path) => {
+            if let Err(e) = save_revision(ctx, slug, &path, "restore") {
+                return json_response(500, &json!({"error": e}));
+            }
+            path
+        }
+        None => match crate::history::original_path(&ctx.input_folder, &revision) {
+            Ok(path) => path,
+            Err(e) => return json_response(400, &json!({"error": e})),
+        },
+    };
+    if let Some(parent) = target.parent() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            return json_response(
+                500,
+                &json!({"error": format!("Failed to create directory: {e}")}),
+            );
+        }
+    }
+    if let Err(e) = std::fs::write(&target, content) {
+        return json_response(500, &json!({"error": format!("Failed to write file: {e}")}));
     }
+    commit_change(ctx, &[&target], &format!("Restore {slug} to revision {id}"));
+    json_response(
+        200,
+        &json!({
+            "slug": slug,
+            "file": target.display().to_string(),
+            "restored": id,
+        }),
+    )
 }
 
 fn handle_editor_page(slug: &str, _live_reload_enabled: bool) -> Response<Cursor<Vec<u8>>> {
@@ -1340,6 +2257,7 @@ fn content_type_for(path: &str) -> Option<&'static str> {
         "js" | "mjs" => "text/javascript; charset=utf-8",
         "json" => "application/json; charset=utf-8",
         "xml" => "application/xml; charset=utf-8",
+        "atom" => "application/atom+xml; charset=utf-8",
         "svg" => "image/svg+xml",
         "png" => "image/png",
         "jpg" | "jpeg" => "image/jpeg",
@@ -1445,10 +2363,183 @@ fn is_live_reload_ws_request(request: &Request) -> bool {
 #[path = "tests/server.rs"]
 mod tests;
 
+/// Hashes of the files in the output folder, compared after each rebuild to
+/// find out which files changed.
+#[derive(Default)]
+struct OutputSnapshot {
+    files: HashMap<String, SnapshotEntry>,
+}
+
+struct SnapshotEntry {
+    len: u64,
+    modified: Option<SystemTime>,
+    hash: String,
+}
+
+impl OutputSnapshot {
+    /// Hashes every file under `folder`, keyed by its `/`-prefixed relative
+    /// path. Files with the same size and mtime as in `previous` are not read.
+    fn capture(folder: &Path, previous: &OutputSnapshot) -> Self {
+        let mut files = HashMap::new();
+        for entry in WalkDir::new(folder)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+        {
+            let Ok(relative) = entry.path().strip_prefix(folder) else {
+                continue;
+            };
+            let key = format!("/{}", relative.to_string_lossy().replace('\\', "/"));
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let len = metadata.len();
+            let modified = metadata.modified().ok();
+            let hash = match previous.files.get(&key) {
+                Some(old) if old.len == len && modified.is_some() && old.modified == modified => {
+                    old.hash.clone()
+                }
+                _ => match fs::read(entry.path()) {
+                    Ok(bytes) => sha256_hex(&bytes),
+                    Err(_) => continue,
+                },
+            };
+            files.insert(
+                key,
+                SnapshotEntry {
+                    len,
+                    modified,
+                    hash,
+                },
+            );
+        }
+        OutputSnapshot { files }
+    }
+
+    /// Paths added, modified or removed since `previous`, sorted.
+    fn changed_since(&self, previous: &OutputSnapshot) -> Vec<String> {
+        let mut changed: Vec<String> = self
+            .files
+            .iter()
+            .filter(|(path, entry)| {
+                previous
+                    .files
+                    .get(*path)
+                    .is_none_or(|old| old.hash != entry.hash)
+            })
+            .map(|(path, _)| path.clone())
+            .chain(
+                previous
+                    .files
+                    .keys()
+                    .filter(|path| !self.files.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+        changed.sort();
+        changed
+    }
+}
+
+/// Builds the live reload events for a list of changed output files.
+///
+/// Stylesheets are swapped in place, pages are reloaded only on the browsers
+/// showing them, any other asset (scripts, images, fonts) reloads every page.
+/// Feeds, sitemaps and JSON data are not displayed so they are ignored.
+fn reload_events(changed: &[String]) -> Vec<serde_json::Value> {
+    let extension = |path: &str| {
+        Path::new(path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default()
+    };
+    let mut stylesheets = Vec::new();
+    let mut pages = Vec::new();
+    let mut full_reload = false;
+    for path in changed {
+        match extension(path).as_str() {
+            "css" => stylesheets.push(path),
+            "html" | "htm" => pages.push(path),
+            "json" | "xml" | "rss" | "txt" | "md" | "gz" | "map" => {}
+            _ => full_reload = true,
+        }
+    }
+
+    let timestamp = Utc::now().timestamp_millis();
+    if full_reload {
+        return vec![json!({ "event": "reload", "timestamp": timestamp })];
+    }
+    let mut events = Vec::new();
+    if !stylesheets.is_empty() {
+        events.push(json!({ "event": "css", "paths": stylesheets, "timestamp": timestamp }));
+    }
+    if !pages.is_empty() {
+        events.push(json!({ "event": "reload", "paths": pages, "timestamp": timestamp }));
+    }
+    events
+}
+
+/// Diagnostics shown in the error overlay, the failures that leave pages
+/// missing or broken. Link warnings are left to the build log.
+fn overlay_errors(diagnostics: &[Diagnostic]) -> Vec<serde_json::Value> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            diagnostic.severity == Severity::Error
+                || matches!(
+                    diagnostic.code(),
+                    "template-error" | "invalid-frontmatter" | "broken-shortcode"
+                )
+        })
+        .map(|diagnostic| {
+            json!({
+                "code": diagnostic.code(),
+                "file": diagnostic.file,
+                "line": diagnostic.line,
+                "message": diagnostic.message,
+            })
+        })
+        .collect()
+}
+
+/// Overlay entries for a rebuild that failed
+fn failure_errors(error: &(dyn std::error::Error + 'static)) -> Vec<serde_json::Value> {
+    let entry = |code: &str, file: Option<String>, line: Option<usize>, message: String| json!({ "code": code, "file": file, "line": line, "message": message });
+    match error.downcast_ref::<Error>() {
+        Some(Error::BrokenLinks(diagnostics) | Error::Denied(diagnostics)) => {
+            overlay_errors(diagnostics)
+        }
+        Some(Error::Template(message)) => {
+            let (file, line) = template_error_location(message)
+                .map_or((None, None), |(file, line)| (Some(file), Some(line)));
+            vec![entry("template-error", file, line, message.clone())]
+        }
+        Some(Error::Config { path, message } | Error::Io { path, message }) => vec![entry(
+            "build-error",
+            Some(path.display().to_string()),
+            None,
+            message.clone(),
+        )],
+        _ => vec![entry("build-error", None, None, error.to_string())],
+    }
+}
+
+fn build_error_event(errors: &[serde_json::Value]) -> String {
+    json!({
+        "event": "build-error",
+        "errors": errors,
+        "timestamp": Utc::now().timestamp_millis(),
+    })
+    .to_string()
+}
+
 #[derive(Clone)]
 pub struct LiveReload {
     clients: Arc<Mutex<Vec<ClientSender>>>,
     next_id: Arc<AtomicUsize>,
+    snapshot: Arc<Mutex<OutputSnapshot>>,
+    /// Errors of the last build, sent to the browsers that connect later
+    build_errors: Arc<Mutex<Vec<serde_json::Value>>>,
 }
 
 impl LiveReload {
@@ -1456,6 +2547,70 @@ impl LiveReload {
         LiveReload {
             clients: Arc::new(Mutex::new(Vec::new())),
             next_id: Arc::new(AtomicUsize::new(1)),
+            snapshot: Arc::new(Mutex::new(OutputSnapshot::default())),
+            build_errors: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Shows the errors among the build `diagnostics` and the `failures` of
+    /// the builds that stopped in the browsers, or clears the overlay when
+    /// the build is clean again.
+    pub fn notify_build(
+        &self,
+        diagnostics: &[Diagnostic],
+        failures: &[&(dyn std::error::Error + 'static)],
+    ) {
+        let mut errors = overlay_errors(diagnostics);
+        for failure in failures {
+            errors.extend(failure_errors(*failure));
+        }
+        self.set_build_errors(errors);
+    }
+
+    fn set_build_errors(&self, errors: Vec<serde_json::Value>) {
+        let Ok(mut build_errors) = self.build_errors.lock() else {
+            return;
+        };
+        if !errors.is_empty() {
+            self.broadcast(&build_error_event(&errors));
+        } else if !build_errors.is_empty() {
+            self.broadcast(
+                &json!({
+                    "event": "build-ok",
+                    "timestamp": Utc::now().timestamp_millis(),
+                })
+                .to_string(),
+            );
+        }
+        *build_errors = errors;
+    }
+
+    /// Records the current state of the output folder, call it after the
+    /// first build so the next `notify_changes` only reports new changes.
+    pub fn record_output(&self, output_folder: &Path) {
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            *snapshot = OutputSnapshot::capture(output_folder, &snapshot);
+        }
+    }
+
+    /// Compares the output folder with the last recorded state and notifies
+    /// the browsers about the files that changed.
+    pub fn notify_changes(&self, output_folder: &Path) {
+        let Ok(mut snapshot) = self.snapshot.lock() else {
+            self.notify_reload();
+            return;
+        };
+        let current = OutputSnapshot::capture(output_folder, &snapshot);
+        let changed = current.changed_since(&snapshot);
+        *snapshot = current;
+        drop(snapshot);
+        if changed.is_empty() {
+            info!("Rebuild did not change the output, nothing to reload");
+            return;
+        }
+        info!("Rebuild changed {} output file(s)", changed.len());
+        for event in reload_events(&changed) {
+            self.broadcast(&event.to_string());
         }
     }
 
@@ -1504,6 +2659,11 @@ impl LiveReload {
 
         let stream = request.upgrade("websocket", response);
         let (tx, rx) = mpsc::channel::<String>();
+        if let Ok(build_errors) = self.build_errors.lock() {
+            if !build_errors.is_empty() {
+                let _ = tx.send(build_error_event(&build_errors));
+            }
+        }
         let client_id = self.register(tx);
         let live_reload = self.clone();
 
@@ -7,6 +7,7 @@ use serde::Serialize;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::sync::Mutex;
 use tera::{Context, Tera};
 
 #[derive(Debug, Clone)]
@@ -41,9 +42,19 @@ fn insert_typed(context: &mut Context, key: String, value: &str) {
     }
 }
 
+/// A shortcode that failed to render and was replaced by an error box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortcodeFailure {
+    pub name: String,
+    /// The shortcode as written, e.g. `<!-- .youtube id=123 -->`
+    pub shortcode: String,
+    pub message: String,
+}
+
 pub struct ShortcodeProcessor {
     pub shortcodes: HashMap<String, Shortcode>,
     pub pattern: Regex,
+    failures: Mutex<Vec<ShortcodeFailure>>,
 }
 
 impl ShortcodeProcessor {
@@ -54,6 +65,7 @@ impl ShortcodeProcessor {
         Self {
             shortcodes: HashMap::new(),
             pattern,
+            failures: Mutex::new(Vec::new()),
         }
     }
 
@@ -314,6 +326,11 @@ impl ShortcodeProcessor {
                 }
                 Err(e) => {
                     warn!("Shortcode '{shortcode_name}' failed to render: {e}");
+                    self.record_failure(ShortcodeFailure {
+                        name: shortcode_name.to_string(),
+                        shortcode: full_match.to_string(),
+                        message: e.clone(),
+                    });
                     // Render an error message in the HTML output
                     let escaped_error = e
                         .replace('&', "&amp;")
@@ -333,6 +350,27 @@ impl ShortcodeProcessor {
         result
     }
 
+    fn record_failure(&self, failure: ShortcodeFailure) {
+        let mut failures = self
+            .failures
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        // The same content is rendered in many pages, like its page and the lists
+        if !failures.contains(&failure) {
+            failures.push(failure);
+        }
+    }
+
+    /// Returns the shortcodes that failed to render since the last call
+    pub fn take_failures(&self) -> Vec<ShortcodeFailure> {
+        std::mem::take(
+            &mut *self
+                .failures
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        )
+    }
+
     fn render_shortcode(
         &self,
         name: &str,
@@ -1,31 +1,39 @@
+use crate::activitypub;
+use crate::atproto::comments::BskyThread;
 use crate::config::{Author, LanguageConfig, Marmite};
 use crate::content::{
-    check_for_duplicate_slugs, detect_language_from_path, is_iso_639_1_code, merge_frontmatter,
-    Content, ContentBuilder, GroupedContent, Kind, TranslationRef, ISO_639_1_CODES,
+    alias_output_path, check_for_duplicate_slugs, detect_language_from_path, is_iso_639_1_code,
+    merge_frontmatter, Content, ContentBuilder, GroupedContent, Kind, Schedule, TranslationRef,
+    ISO_639_1_CODES,
 };
+use crate::diagnostics::{apply_deny, Diagnostic, Report, Severity, Warning};
 use crate::embedded::{
     collect_ignore_missing_includes, generate_static, preprocess_template, Templates,
     EMBEDDED_STATIC,
 };
+use crate::error::Error;
 use crate::gallery::Gallery;
 use crate::highlight::{self, MarmiteHighlighter};
-use crate::image_resize;
+use crate::image_resize::{self, ResponsiveImage};
+use crate::incremental::{IncrementalBuild, SourceCache, SourceEntry};
+use crate::json_api;
 use crate::parser::fix_wikilinks;
-use crate::shortcodes::ShortcodeProcessor;
+use crate::shortcodes::{ShortcodeFailure, ShortcodeProcessor};
 use crate::tera_functions::{
-    DisplayName, GetDataBySlug, GetGallery, GetPages, GetPosts, Group, SourceLink, UrlFor,
+    DisplayName, GetDataBySlug, GetGallery, GetPages, GetPosts, Group, ResponsiveImageTag,
+    SourceLink, UrlFor,
 };
-use crate::{re, server, tera_filter};
-use chrono::Datelike;
+use crate::webmention::Webmentions;
+use crate::{server, tera_filter};
+use chrono::{Datelike, NaiveDateTime};
 use core::str;
 use fs_extra::dir::{copy as dircopy, CopyOptions};
 use glob::glob;
 use hotwatch::{Event, EventKind, Hotwatch};
 use log::{debug, error, info, warn};
 use rayon::prelude::*;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
 use std::{fs, process, sync::Arc, sync::Mutex};
 use tera::Value;
@@ -42,6 +50,7 @@ pub struct UrlCollection {
     pub streams: Vec<String>,
     pub archives: Vec<String>,
     pub languages: Vec<String>,
+    pub taxonomies: Vec<String>,
     pub feeds: Vec<String>,
     pub pagination: Vec<String>,
     pub file_mappings: Vec<String>,
@@ -60,24 +69,69 @@ pub struct Data {
     pub stream: GroupedContent,
     pub series: GroupedContent,
     pub language: GroupedContent,
+    /// Content grouped by the custom taxonomies, keyed by taxonomy name
+    pub taxonomies: BTreeMap<String, GroupedContent>,
     pub latest_timestamp: Option<i64>,
     pub config_path: String,
     pub force_render: bool,
     pub generated_urls: UrlCollection,
     pub galleries: HashMap<String, Gallery>,
+    /// Media images served with `srcset` variants, keyed by their path inside
+    /// the media folder
+    #[serde(skip)]
+    pub responsive_images: HashMap<String, ResponsiveImage>,
+    /// Names (without extension) of the outputs an incremental build must render,
+    /// `None` renders everything.
+    #[serde(skip)]
+    pub render_only: Option<HashSet<String>>,
+    /// Time of the build, content dated after it is scheduled
+    #[serde(skip)]
+    pub build_time: NaiveDateTime,
+    /// Publish scheduled content too, for previews
+    #[serde(skip)]
+    pub include_future: bool,
+    /// Scheduled and expired content left out of the build
+    #[serde(skip)]
+    pub scheduled: Vec<Content>,
+    /// Problems found while building, like broken links
+    #[serde(skip)]
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl Data {
+    /// Same as [`Data::try_new`], exiting when the config is not valid
+    #[must_use]
     pub fn new(config_content: &str, config_path: &Path) -> Self {
-        let site: Marmite = match serde_yaml::from_str::<Marmite>(config_content) {
-            Ok(site) => site,
-            Err(e) => {
-                error!("Failed to parse config YAML: {e:?}");
-                process::exit(1);
-            }
-        };
+        Self::try_new(config_content, config_path).unwrap_or_else(|e| {
+            error!("Failed to parse config YAML: {e}");
+            process::exit(1);
+        })
+    }
 
-        Data {
+    /// Site data with the config parsed from `config_content`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] when `config_content` is not a valid config.
+    pub fn try_new(config_content: &str, config_path: &Path) -> Result<Self, Error> {
+        let site: Marmite =
+            serde_yaml::from_str::<Marmite>(config_content).map_err(|e| Error::Config {
+                path: config_path.to_path_buf(),
+                message: e.to_string(),
+            })?;
+        site.validate_taxonomies()
+            .map_err(|message| Error::Config {
+                path: config_path.to_path_buf(),
+                message,
+            })?;
+
+        let taxonomies = site
+            .taxonomies
+            .keys()
+            .map(|name| (name.clone(), GroupedContent::new(Kind::Taxonomy)))
+            .collect();
+
+        Ok(Data {
             site,
             posts: Vec::new(),
             pages: Vec::new(),
@@ -87,15 +141,36 @@ impl Data {
             stream: GroupedContent::new(Kind::Stream),
             series: GroupedContent::new(Kind::Series),
             language: GroupedContent::new(Kind::Language),
+            taxonomies,
             latest_timestamp: None,
             config_path: config_path.to_string_lossy().to_string(),
             force_render: false,
             generated_urls: UrlCollection::default(),
             galleries: HashMap::new(),
-        }
+            responsive_images: HashMap::new(),
+            render_only: None,
+            build_time: chrono::Local::now().naive_local(),
+            include_future: false,
+            scheduled: Vec::new(),
+            diagnostics: Vec::new(),
+        })
     }
 
+    #[must_use]
     pub fn from_file(config_path: &Path) -> Self {
+        Self::try_from_file(config_path).unwrap_or_else(|e| {
+            error!("Failed to parse config YAML: {e}");
+            process::exit(1);
+        })
+    }
+
+    /// Site data with the config read from `config_path`, or the defaults
+    /// when it can not be read
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] when the file is not a valid config.
+    pub fn try_from_file(config_path: &Path) -> Result<Self, Error> {
         let config_str = fs::read_to_string(config_path).unwrap_or_else(|e| {
             info!(
                 "Unable to read '{}', assuming defaults.: {e:?}",
@@ -110,7 +185,29 @@ impl Data {
             info!("Config loaded from: {}", config_path.display());
         }
 
-        Self::new(&config_str, config_path)
+        Self::try_new(&config_str, config_path)
+    }
+
+    /// Whether the output `name` (without extension) must be rendered on this build
+    #[must_use]
+    pub fn should_render(&self, name: &str) -> bool {
+        self.render_only
+            .as_ref()
+            .is_none_or(|only| only.contains(name))
+    }
+
+    /// Records a warning found in the source file of the content `slug`
+    pub fn add_diagnostic(&mut self, warning: Warning, slug: Option<&str>) {
+        let file = slug.and_then(|slug| {
+            self.posts
+                .iter()
+                .chain(&self.pages)
+                .rev()
+                .find(|content| content.slug == slug)
+                .and_then(|content| content.source_path.clone())
+        });
+        self.diagnostics
+            .push(Diagnostic::new(warning, file.as_deref()));
     }
 
     pub fn sort_all(&mut self) {
@@ -122,6 +219,9 @@ impl Data {
         self.stream.sort_all();
         self.series.sort_all();
         self.language.sort_all();
+        for grouped in self.taxonomies.values_mut() {
+            grouped.sort_all();
+        }
     }
 
     /// takes content then classifies the content
@@ -174,6 +274,22 @@ impl Data {
                     .or_default()
                     .push(content.clone());
             }
+
+            // custom taxonomies by slugified term, as terms differing only
+            // in case or punctuation share the same pages
+            for (name, terms) in &content.taxonomies {
+                let grouped = self
+                    .taxonomies
+                    .entry(name.clone())
+                    .or_insert_with(|| GroupedContent::new(Kind::Taxonomy));
+                let mut slugs = HashSet::new();
+                for term in terms {
+                    let term_slug = crate::slugify::slugify(term);
+                    if slugs.insert(term_slug.clone()) {
+                        grouped.entry(term_slug).or_default().push(content.clone());
+                    }
+                }
+            }
         } else {
             self.pages.push(content);
         }
@@ -378,6 +494,43 @@ impl Data {
                 .add_url("archives", "archive.html".to_string());
         }
 
+        // Add custom taxonomy pages, pagination and feeds
+        for taxonomy in self.site.taxonomy_list() {
+            if let Some(grouped) = self.taxonomies.get(&taxonomy.name) {
+                for (term, contents) in grouped.iter() {
+                    let filename = taxonomy.term_filename(term);
+                    self.generated_urls
+                        .add_url("taxonomies", format!("{filename}.html"));
+
+                    // Always add -1 page (same as base page but with consistent naming)
+                    self.generated_urls
+                        .add_url("pagination", format!("{filename}-1.html"));
+                    if contents.len() > self.site.pagination {
+                        let total_pages = contents.len().div_ceil(self.site.pagination);
+                        for page_num in 2..=total_pages {
+                            self.generated_urls
+                                .add_url("pagination", format!("{filename}-{page_num}.html"));
+                        }
+                    }
+
+                    if taxonomy.feeds {
+                        self.generated_urls
+                            .add_url("feeds", format!("{filename}.rss"));
+                        if self.site.json_feed {
+                            self.generated_urls
+                                .add_url("feeds", format!("{filename}.json"));
+                        }
+                        if self.site.atom_feed {
+                            self.generated_urls
+                                .add_url("feeds", format!("{filename}.atom"));
+                        }
+                    }
+                }
+            }
+            self.generated_urls
+                .add_url("taxonomies", format!("{}.html", taxonomy.index));
+        }
+
         // Add languages group page (always rendered)
         self.generated_urls
             .add_url("languages", "languages.html".to_string());
@@ -472,6 +625,44 @@ impl Data {
             }
         }
 
+        // Add Atom feeds
+        if self.site.atom_feed {
+            // Stream feeds (includes index stream which covers main index feed)
+            for stream in self.stream.iter() {
+                let feed_slug = format!("{}.atom", crate::slugify::slugify(stream.0));
+                self.generated_urls.add_url("feeds", feed_slug);
+            }
+
+            // Series feeds
+            for series in self.series.iter() {
+                let feed_slug = format!("series-{}.atom", crate::slugify::slugify(series.0));
+                self.generated_urls.add_url("feeds", feed_slug);
+            }
+
+            // Tag feeds
+            // Filter to only process slugified keys (avoid duplicates from backward compatibility layer)
+            for tag in self
+                .tag
+                .iter()
+                .filter(|(key, _)| crate::slugify::slugify(key) == key.as_str())
+            {
+                let feed_slug = format!("tag-{}.atom", crate::slugify::slugify(tag.0));
+                self.generated_urls.add_url("feeds", feed_slug);
+            }
+
+            // Author feeds
+            for author in self.author.iter() {
+                let feed_slug = format!("author-{}.atom", crate::slugify::slugify(author.0));
+                self.generated_urls.add_url("feeds", feed_slug);
+            }
+
+            // Archive feeds
+            for archive in self.archive.iter() {
+                let feed_slug = format!("archive-{}.atom", archive.0);
+                self.generated_urls.add_url("feeds", feed_slug);
+            }
+        }
+
         // Add file mappings if they exist
         for mapping in &self.site.file_mapping {
             let destination = if mapping.dest.starts_with('/') {
@@ -484,9 +675,8 @@ impl Data {
 
         // Add redirect aliases
         for content in self.posts.iter().chain(&self.pages) {
-            for alias in &content.aliases {
-                self.generated_urls
-                    .add_url("redirects", format!("{alias}.html"));
+            for file in content.aliases.iter().filter_map(|a| alias_output_path(a)) {
+                self.generated_urls.add_url("redirects", file);
             }
         }
     }
@@ -501,9 +691,9 @@ struct ContentInfo {
     source_path: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     date: Option<String>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     tags: Vec<String>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     authors: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
@@ -562,62 +752,53 @@ pub(crate) fn build_site_with_config(
     cli_args: &Arc<crate::cli::Cli>,
     cross_site_data: Option<&crate::workspace::CrossSiteData>,
     path_prefix: &str,
-) -> Result<Data, Box<dyn std::error::Error>> {
+) -> Result<Data, Error> {
     let start_time = std::time::Instant::now();
 
     let config_str = serde_yaml::to_string(&site_config).unwrap_or_default();
     let config_path = input_folder.join(&cli_args.config);
-    let mut site_data = Data::new(&config_str, &config_path);
+    let mut site_data = Data::try_new(&config_str, &config_path)?;
     let content_folder = get_content_folder(&site_data.site, input_folder);
 
     let build_info_path = output_folder.join("marmite.json");
-    let latest_build_info = get_latest_build_info(&build_info_path)?;
+    let latest_build_info = get_latest_build_info(&build_info_path).map_err(|e| Error::Io {
+        path: build_info_path.clone(),
+        message: e.to_string(),
+    })?;
     if let Some(build_info) = &latest_build_info {
         site_data.latest_timestamp = Some(build_info.timestamp);
     }
     if cli_args.force {
         site_data.force_render = true;
     }
+    site_data.include_future = cli_args.include_future;
+    let mut incremental = start_incremental_build(
+        cli_args,
+        input_folder,
+        output_folder,
+        &content_folder,
+        &site_data.site,
+    );
 
     let highlighter = build_code_highlighter(&site_data.site);
 
     let fragments = collect_content_fragments(&content_folder);
     let folder_defaults = load_folder_frontmatter(&content_folder);
-    collect_content(
+    collect_content_with_cache(
         &content_folder,
         &mut site_data,
         &fragments,
         highlighter.as_deref(),
         &folder_defaults,
-    );
+        incremental.as_mut().map(|inc| &mut inc.sources),
+    )?;
 
     discover_translations(&mut site_data, &content_folder);
     rebuild_stream_index(&mut site_data);
     build_language_index(&mut site_data);
 
-    let state_path = input_folder.join(".marmite-atproto-state.json");
-    if state_path.exists() {
-        if let Ok(state_str) = fs::read_to_string(&state_path) {
-            if let Ok(state_json) = serde_json::from_str::<serde_json::Value>(&state_str) {
-                if let Some(posts_obj) = state_json.get("posts").and_then(|p| p.as_object()) {
-                    for post in &mut site_data.posts {
-                        if let Some(entry) = posts_obj.get(&post.slug) {
-                            if let Some(at_uri) = entry.get("at_uri").and_then(|u| u.as_str()) {
-                                post.at_uri = Some(at_uri.to_string());
-                            }
-                        }
-                    }
-                    for page in &mut site_data.pages {
-                        if let Some(entry) = posts_obj.get(&page.slug) {
-                            if let Some(at_uri) = entry.get("at_uri").and_then(|u| u.as_str()) {
-                                page.at_uri = Some(at_uri.to_string());
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
+    load_atproto_state(&mut site_data, input_folder);
+    load_webmentions(&mut site_data, input_folder);
 
     let media_path = content_folder.join(&site_data.site.media_path);
     site_data.galleries = crate::gallery::process_galleries(
@@ -627,51 +808,32 @@ pub(crate) fn build_site_with_config(
         site_data.site.gallery_thumb_size,
     );
 
+    prepare_responsive_images(&mut site_data, &media_path);
+
     site_data.sort_all();
-    detect_slug_collision(&site_data);
+    detect_slug_collision(&mut site_data);
     collect_back_links(&mut site_data);
     set_next_and_previous_links(&mut site_data);
     site_data.collect_all_urls();
-
-    if site_data.site.check_internal_links {
-        let broken = validate_internal_links(&site_data);
-        for (source, target) in &broken {
-            warn!("Broken internal link in \"{source}.html\": \"{target}.html\" does not exist");
-        }
-        if !broken.is_empty() {
-            warn!("Found {} broken internal link(s)", broken.len());
-            if site_data.site.strict_internal_links {
-                error!(
-                    "Build failed due to broken internal links (strict_internal_links is enabled)"
-                );
-                process::exit(1);
-            }
-        }
+    if let Some(incremental) = incremental.as_mut() {
+        site_data.render_only = incremental.plan(&site_data);
+        // Rendering the whole site must not skip content pages by mtime
+        site_data.force_render |= site_data.render_only.is_none();
     }
 
-    if site_data.site.check_media_links {
-        let broken = validate_media_links(&site_data, &content_folder);
-        for (source, target) in &broken {
-            warn!("Broken media link in \"{source}\": \"{target}\" does not exist");
-        }
-        if !broken.is_empty() {
-            warn!("Found {} broken media link(s)", broken.len());
-            if site_data.site.strict_internal_links {
-                error!("Build failed due to broken media links (strict_internal_links is enabled)");
-                process::exit(1);
-            }
-        }
-    }
+    check_links(&mut site_data, &content_folder)
+        .inspect_err(|_| print_report(&site_data, cli_args))?;
 
     let site_path = site_data.site.site_path.clone();
     let output_path = output_folder.join(site_path);
-    if let Err(e) = fs::create_dir_all(&output_path) {
-        error!("Unable to create output directory: {e:?}");
-        process::exit(1);
-    }
+    fs::create_dir_all(&output_path).map_err(|e| Error::Io {
+        path: output_path.clone(),
+        message: format!("Unable to create output directory: {e}"),
+    })?;
 
     let input_folder_arc = Arc::new(input_folder.to_path_buf());
     let output_folder_arc = Arc::new(output_folder.to_path_buf());
+    let shortcode_failures = Mutex::new(Vec::new());
 
     [
         "render_templates",
@@ -680,15 +842,15 @@ pub(crate) fn build_site_with_config(
         "copy_markdown_sources",
     ]
     .par_iter()
-    .for_each(|step| match *step {
+    .try_for_each(|step| match *step {
         "render_templates" => {
             let (tera, shortcode_processor) = initialize_tera(
                 input_folder_arc.as_path(),
                 &site_data,
                 cross_site_data,
                 path_prefix,
-            );
-            if let Err(e) = render_templates(
+            )?;
+            render_templates(
                 &content_folder,
                 &site_data,
                 &tera,
@@ -700,37 +862,47 @@ pub(crate) fn build_site_with_config(
                 highlighter.as_deref(),
                 cross_site_data,
                 false,
-            ) {
-                error!("Failed to render templates: {e:?}");
-                process::exit(1);
-            }
-        }
-        "handle_static_artifacts" => {
-            handle_static_artifacts(
-                input_folder_arc.as_path(),
-                &site_data,
-                &output_folder_arc,
-                &content_folder,
-            );
+            )
+            .map_err(Error::Template)?;
+            collect_shortcode_failures(shortcode_processor.as_ref(), &shortcode_failures);
+            Ok(())
         }
+        "handle_static_artifacts" if site_data.render_only.is_none() => handle_static_artifacts(
+            input_folder_arc.as_path(),
+            &site_data,
+            &output_folder_arc,
+            &content_folder,
+        ),
         "generate_search_index" => {
             if site_data.site.enable_search {
-                generate_search_index(&site_data, &output_folder_arc);
+                crate::search::generate_search_index(&site_data, &output_folder_arc);
             }
+            Ok(())
         }
         "copy_markdown_sources" if site_data.site.publish_md => {
             copy_markdown_sources(&site_data, &content_folder, &output_path);
+            Ok(())
         }
-        _ => {}
-    });
+        _ => Ok(()),
+    })?;
+    add_shortcode_diagnostics(&mut site_data, shortcode_failures);
 
-    let (tera, _) = initialize_tera(input_folder, &site_data, cross_site_data, path_prefix);
+    let (tera, _) = initialize_tera(input_folder, &site_data, cross_site_data, path_prefix)?;
     generate_sitemap(&site_data, &tera, &output_path);
+    remove_unpublished_outputs(&site_data, &content_folder, &output_path);
 
     if site_data.site.publish_urls_json {
         generate_urls_json(&site_data, &output_path, path_prefix);
     }
 
+    if site_data.site.json_api.enabled {
+        json_api::generate_json_api(&site_data, &output_path, path_prefix);
+    }
+
+    if site_data.site.activitypub.is_some() {
+        activitypub::generate(&site_data, input_folder, &output_path, path_prefix);
+    }
+
     if let Some(atproto) = &site_data.site.atproto {
         if let Some(pub_uri) = &atproto.publication_uri {
             let wk_dir = output_path.join(".well-known");
@@ -749,8 +921,10 @@ pub(crate) fn build_site_with_config(
 
     let end_time = start_time.elapsed().as_secs_f64();
     write_build_info(&output_path, &site_data, input_folder, end_time);
+    finish_incremental_build(incremental, input_folder, &output_path);
     debug!("Site generated in {end_time:.2}s");
     info!("Site generated at: {}/", output_folder.display());
+    finish_diagnostics(&mut site_data, cli_args)?;
     Ok(site_data)
 }
 
@@ -776,11 +950,11 @@ pub fn generate(
     };
 
     let rebuild = {
-        move || -> Result<(), Box<dyn std::error::Error>> {
+        move || -> Result<Vec<Diagnostic>, Box<dyn std::error::Error>> {
             let start_time = std::time::Instant::now();
-            let site_data = Arc::new(Mutex::new(Data::from_file(
+            let site_data = Arc::new(Mutex::new(Data::try_from_file(
                 moved_config_path.clone().as_path(),
-            )));
+            )?));
             let content_folder = get_content_folder(
                 &site_data
                     .lock()
@@ -812,18 +986,27 @@ pub fn generate(
             if moved_cli_args.force {
                 site_data.force_render = true;
             }
+            site_data.include_future = moved_cli_args.include_future;
+            let mut incremental = start_incremental_build(
+                &moved_cli_args,
+                &moved_input_folder,
+                &moved_output_folder,
+                &content_folder,
+                &site_data.site,
+            );
 
             let highlighter = build_code_highlighter(&site_data.site);
 
             let fragments = collect_content_fragments(&content_folder);
             let folder_defaults = load_folder_frontmatter(&content_folder);
-            collect_content(
+            collect_content_with_cache(
                 &content_folder,
                 &mut site_data,
                 &fragments,
                 highlighter.as_deref(),
                 &folder_defaults,
-            );
+                incremental.as_mut().map(|inc| &mut inc.sources),
+            )?;
 
             discover_translations(&mut site_data, &content_folder);
 
@@ -832,34 +1015,8 @@ pub fn generate(
             build_language_index(&mut site_data);
 
             // Load atproto state to populate at_uri on matching posts/pages
-            let state_path = moved_input_folder.join(".marmite-atproto-state.json");
-            if state_path.exists() {
-                if let Ok(state_str) = fs::read_to_string(&state_path) {
-                    if let Ok(state_json) = serde_json::from_str::<serde_json::Value>(&state_str) {
-                        if let Some(posts_obj) = state_json.get("posts").and_then(|p| p.as_object())
-                        {
-                            for post in &mut site_data.posts {
-                                if let Some(entry) = posts_obj.get(&post.slug) {
-                                    if let Some(at_uri) =
-                                        entry.get("at_uri").and_then(|u| u.as_str())
-                                    {
-                                        post.at_uri = Some(at_uri.to_string());
-                                    }
-                                }
-                            }
-                            for page in &mut site_data.pages {
-                                if let Some(entry) = posts_obj.get(&page.slug) {
-                                    if let Some(at_uri) =
-                                        entry.get("at_uri").and_then(|u| u.as_str())
-                                    {
-                                        page.at_uri = Some(at_uri.to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+            load_atproto_state(&mut site_data, &moved_input_folder);
+            load_webmentions(&mut site_data, &moved_input_folder);
 
             // Process galleries
             let media_path = content_folder.join(&site_data.site.media_path);
@@ -869,49 +1026,30 @@ pub fn generate(
                 site_data.site.gallery_create_thumbnails,
                 site_data.site.gallery_thumb_size,
             );
+            prepare_responsive_images(&mut site_data, &media_path);
 
             site_data.sort_all();
-            detect_slug_collision(&site_data); // Detect slug collision and warn user
+            detect_slug_collision(&mut site_data); // Detect slug collision and warn user
             collect_back_links(&mut site_data);
             set_next_and_previous_links(&mut site_data);
             site_data.collect_all_urls();
-
-            if site_data.site.check_internal_links {
-                let broken = validate_internal_links(&site_data);
-                for (source, target) in &broken {
-                    warn!(
-                        "Broken internal link in \"{source}.html\": \"{target}.html\" does not exist"
-                    );
-                }
-                if !broken.is_empty() {
-                    warn!("Found {} broken internal link(s)", broken.len());
-                    if site_data.site.strict_internal_links {
-                        error!("Build failed due to broken internal links (strict_internal_links is enabled)");
-                        process::exit(1);
-                    }
-                }
+            if let Some(incremental) = incremental.as_mut() {
+                site_data.render_only = incremental.plan(&site_data);
+                // Rendering the whole site must not skip content pages by mtime
+                site_data.force_render |= site_data.render_only.is_none();
             }
 
-            if site_data.site.check_media_links {
-                let broken = validate_media_links(&site_data, &content_folder);
-                for (source, target) in &broken {
-                    warn!("Broken media link in \"{source}\": \"{target}\" does not exist");
-                }
-                if !broken.is_empty() {
-                    warn!("Found {} broken media link(s)", broken.len());
-                    if site_data.site.strict_internal_links {
-                        error!("Build failed due to broken media links (strict_internal_links is enabled)");
-                        process::exit(1);
-                    }
-                }
-            }
+            check_links(&mut site_data, &content_folder)
+                .inspect_err(|_| print_report(&site_data, &moved_cli_args))?;
 
             let site_path = site_data.site.site_path.clone();
             let output_path = moved_output_folder.join(site_path);
-            if let Err(e) = fs::create_dir_all(&output_path) {
-                error!("Unable to create output directory: {e:?}");
-                process::exit(1);
-            }
+            let shortcode_failures = Mutex::new(Vec::new());
+            let template_errors = Mutex::new(Vec::new());
+            fs::create_dir_all(&output_path).map_err(|e| Error::Io {
+                path: output_path.clone(),
+                message: format!("Unable to create output directory: {e}"),
+            })?;
 
             [
                 "render_templates",
@@ -920,10 +1058,10 @@ pub fn generate(
                 "copy_markdown_sources",
             ]
             .par_iter()
-            .for_each(|step| match *step {
+            .try_for_each(|step| match *step {
                 "render_templates" => {
                     let (tera, shortcode_processor) =
-                        initialize_tera(&moved_input_folder, &site_data, None, "");
+                        initialize_tera(&moved_input_folder, &site_data, None, "")?;
                     if let Err(e) = render_templates(
                         &content_folder,
                         &site_data,
@@ -937,40 +1075,66 @@ pub fn generate(
                         None,
                         serve,
                     ) {
-                        error!("Failed to render templates: {e:?}");
+                        // The dev server keeps running with the pages that did render
                         if !serve {
-                            process::exit(1);
+                            return Err(Error::Template(e));
                         }
+                        error!("Failed to render templates: {e}");
+                        template_errors
+                            .lock()
+                            .unwrap_or_else(std::sync::PoisonError::into_inner)
+                            .push(e);
                     }
+                    collect_shortcode_failures(shortcode_processor.as_ref(), &shortcode_failures);
+                    Ok(())
                 }
-                "handle_static_artifacts" => {
+                // Partial incremental renders are only planned when static inputs are unchanged
+                "handle_static_artifacts" if site_data.render_only.is_none() => {
                     handle_static_artifacts(
                         &moved_input_folder,
                         &site_data,
                         &moved_output_folder,
                         &content_folder,
-                    );
+                    )
                 }
                 "generate_search_index" => {
                     if site_data.site.enable_search {
-                        generate_search_index(&site_data, &moved_output_folder);
+                        crate::search::generate_search_index(&site_data, &moved_output_folder);
                     }
+                    Ok(())
                 }
                 "copy_markdown_sources" if site_data.site.publish_md => {
                     copy_markdown_sources(&site_data, &content_folder, &output_path);
+                    Ok(())
                 }
-                _ => {}
-            });
+                _ => Ok(()),
+            })?;
+            add_shortcode_diagnostics(&mut site_data, shortcode_failures);
+            for message in template_errors
+                .into_inner()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+            {
+                add_template_diagnostic(&mut site_data, &moved_input_folder, message);
+            }
 
             // Generate sitemap after all templates are rendered
-            let (tera, _) = initialize_tera(&moved_input_folder, &site_data, None, "");
+            let (tera, _) = initialize_tera(&moved_input_folder, &site_data, None, "")?;
             generate_sitemap(&site_data, &tera, &output_path);
+            remove_unpublished_outputs(&site_data, &content_folder, &output_path);
 
             // Generate urls.json if enabled
             if site_data.site.publish_urls_json {
                 generate_urls_json(&site_data, &output_path, "");
             }
 
+            if site_data.site.json_api.enabled {
+                json_api::generate_json_api(&site_data, &output_path, "");
+            }
+
+            if site_data.site.activitypub.is_some() {
+                activitypub::generate(&site_data, &moved_input_folder, &output_path, "");
+            }
+
             // Generate standard.site verification file if configured
             if let Some(atproto) = &site_data.site.atproto {
                 if let Some(pub_uri) = &atproto.publication_uri {
@@ -990,14 +1154,20 @@ pub fn generate(
 
             let end_time = start_time.elapsed().as_secs_f64();
             write_build_info(&output_path, &site_data, &moved_input_folder, end_time);
+            finish_incremental_build(incremental, &moved_input_folder, &output_path);
             debug!("Site generated in {end_time:.2}s");
             info!("Site generated at: {}/", moved_output_folder.display());
-            Ok(())
+            finish_diagnostics(&mut site_data, &moved_cli_args)?;
+            Ok(std::mem::take(&mut site_data.diagnostics))
         }
     };
 
     // Initial site generation
-    rebuild()?;
+    let diagnostics = rebuild()?;
+    if let Some(live_reload) = &live_reload {
+        live_reload.record_output(output_folder);
+        live_reload.notify_build(&diagnostics, &[]);
+    }
 
     if watch {
         let mut hotwatch = match Hotwatch::new() {
@@ -1009,19 +1179,37 @@ pub fn generate(
         };
         let watch_folder = Arc::clone(input_folder).as_path().to_path_buf();
         let out_folder = Arc::clone(output_folder).as_path().to_path_buf();
+        // Writing the API audit log must not trigger a rebuild
+        let audit_log = Path::new(&Data::from_file(config_path).site.server.audit_log)
+            .file_name()
+            .map(std::ffi::OsStr::to_os_string);
         // Watch the input folder for changes
         let live_reload_watch = live_reload.clone();
         let watch_result = hotwatch.watch(watch_folder, move |event: Event| match event.kind {
             EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
                 for ev in &event.paths {
+                    if audit_log.is_some() && ev.file_name() == audit_log.as_deref()
+                        || crate::history::is_metadata_path(ev)
+                    {
+                        continue;
+                    }
                     if !ev.starts_with(
                         fs::canonicalize(out_folder.clone()).unwrap_or_else(|_| out_folder.clone()),
                     ) {
                         info!("Change detected. Rebuilding site...");
-                        if let Err(e) = rebuild() {
-                            error!("Failed to rebuild site: {e}");
-                        } else if let Some(live_reload) = &live_reload_watch {
-                            live_reload.notify_reload();
+                        match rebuild() {
+                            Err(e) => {
+                                error!("Failed to rebuild site: {e}");
+                                if let Some(live_reload) = &live_reload_watch {
+                                    live_reload.notify_build(&[], &[e.as_ref()]);
+                                }
+                            }
+                            Ok(diagnostics) => {
+                                if let Some(live_reload) = &live_reload_watch {
+                                    live_reload.notify_build(&diagnostics, &[]);
+                                    live_reload.notify_changes(&out_folder);
+                                }
+                            }
                         }
                     }
                 }
@@ -1038,13 +1226,16 @@ pub fn generate(
         // Keep the thread alive for watching
         if serve {
             info!("Starting built-in HTTP server...");
-            let serve_config = Data::from_file(config_path);
+            let mut serve_config = Data::from_file(config_path);
+            serve_config.site.override_from_cli_args(cli_args);
             let ctx = server::ServerContext {
                 output_folder: Arc::clone(output_folder),
                 input_folder: Arc::clone(input_folder),
                 config_path: Arc::clone(config_path),
                 enable_toolbar: serve_config.site.enable_toolbar,
                 watch_enabled: true,
+                activitypub: activitypub::Service::new(&serve_config.site, input_folder),
+                server_config: serve_config.site.server,
             };
             server::start(bind_address, &ctx, live_reload.as_ref());
         } else {
@@ -1056,9 +1247,47 @@ pub fn generate(
     Ok(())
 }
 
-fn get_latest_build_info(
-    build_info_path: &std::path::PathBuf,
-) -> Result<Option<BuildInfo>, std::io::Error> {
+/// Loads the dependency graph of the previous build when `--incremental` is set
+fn start_incremental_build(
+    cli_args: &crate::cli::Cli,
+    input_folder: &Path,
+    output_folder: &Path,
+    content_folder: &Path,
+    site: &Marmite,
+) -> Option<IncrementalBuild> {
+    if !cli_args.incremental {
+        return None;
+    }
+    let output_path = output_folder.join(&site.site_path);
+    let previous_build = get_latest_build_info(&output_path.join("marmite.json"))
+        .ok()
+        .flatten();
+    Some(IncrementalBuild::load(
+        input_folder,
+        &output_path,
+        content_folder,
+        site,
+        previous_build
+            .as_ref()
+            .map(|info| info.generated_at.as_str()),
+    ))
+}
+
+/// Saves the dependency graph tied to the `marmite.json` written by this build
+fn finish_incremental_build(
+    incremental: Option<IncrementalBuild>,
+    input_folder: &Path,
+    output_path: &Path,
+) {
+    if let Some(incremental) = incremental {
+        let build_info = get_latest_build_info(&output_path.join("marmite.json"))
+            .ok()
+            .flatten();
+        incremental.save(input_folder, build_info.map(|info| info.generated_at));
+    }
+}
+
+fn get_latest_build_info(build_info_path: &Path) -> Result<Option<BuildInfo>, std::io::Error> {
     if build_info_path.exists() {
         let build_info_json = fs::read_to_string(build_info_path)?;
         if let Ok(build_info) = BuildInfo::from_json(&build_info_json) {
@@ -1212,6 +1441,28 @@ fn collect_global_fragments(
     }
 }
 
+/// Plans the responsive variants of the media images and rewrites the
+/// `<img>` tags of posts and pages to point at them.
+fn prepare_responsive_images(site_data: &mut Data, media_path: &Path) {
+    if site_data.site.skip_image_resize || !media_path.is_dir() {
+        return;
+    }
+    let banner_paths =
+        image_resize::collect_banner_paths_from_content(&site_data.posts, &site_data.pages);
+    site_data.responsive_images =
+        image_resize::collect_responsive_images(media_path, &site_data.site, &banner_paths);
+    if site_data.responsive_images.is_empty() {
+        return;
+    }
+    for content in site_data.posts.iter_mut().chain(site_data.pages.iter_mut()) {
+        content.html = image_resize::rewrite_img_tags(
+            &content.html,
+            &site_data.responsive_images,
+            &site_data.site.media_path,
+        );
+    }
+}
+
 #[allow(clippy::used_underscore_items)]
 fn collect_back_links(site_data: &mut Data) {
     let other_contents = site_data
@@ -1244,6 +1495,165 @@ fn _collect_back_links(contents: &mut [Content], other_contents: &[Content]) {
     }
 }
 
+/// Runs the enabled link checks, logging and storing what was found in
+/// `site_data.diagnostics`. Fails when `strict_internal_links` is enabled.
+fn check_links(site_data: &mut Data, content_folder: &Path) -> Result<(), Error> {
+    let mut broken = Vec::new();
+
+    if site_data.site.check_internal_links {
+        let internal = validate_internal_links(site_data);
+        if !internal.is_empty() {
+            warn!("Found {} broken internal link(s)", internal.len());
+            for (source, target) in internal {
+                broken.push((
+                    source.clone(),
+                    Warning::BrokenInternalLink { source, target },
+                ));
+            }
+        }
+    }
+
+    if site_data.site.check_media_links {
+        let media = validate_media_links(site_data, content_folder);
+        if !media.is_empty() {
+            warn!("Found {} broken media link(s)", media.len());
+            for (source, target) in media {
+                broken.push((source.clone(), Warning::BrokenMediaLink { source, target }));
+            }
+        }
+    }
+
+    let first = site_data.diagnostics.len();
+    for (source, warning) in broken {
+        warn!("{warning}");
+        site_data.add_diagnostic(warning, Some(&source));
+    }
+    if site_data.diagnostics.len() > first && site_data.site.strict_internal_links {
+        let broken = &mut site_data.diagnostics[first..];
+        for diagnostic in broken.iter_mut() {
+            diagnostic.severity = Severity::Error;
+        }
+        return Err(Error::BrokenLinks(broken.to_vec()));
+    }
+    Ok(())
+}
+
+fn collect_shortcode_failures(
+    processor: Option<&ShortcodeProcessor>,
+    failures: &Mutex<Vec<ShortcodeFailure>>,
+) {
+    if let Some(processor) = processor {
+        failures
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .extend(processor.take_failures());
+    }
+}
+
+/// Records a diagnostic when the content at `path` was skipped because of
+/// its frontmatter
+fn add_frontmatter_diagnostic(site_data: &mut Data, path: &Path, message: String) {
+    let line = fs::read_to_string(path)
+        .ok()
+        .and_then(|file_content| crate::parser::frontmatter_error_line(&file_content, &message));
+    let warning = Warning::InvalidFrontmatter {
+        source: path.display().to_string(),
+        message,
+    };
+    site_data
+        .diagnostics
+        .push(Diagnostic::new(warning, Some(path)).with_line(line));
+}
+
+/// Template name and line of a Tera error report
+pub(crate) fn template_error_location(message: &str) -> Option<(String, usize)> {
+    let re = regex::Regex::new(crate::re::CAPTURE_TEMPLATE_AND_LINE_FROM_TERA_ERROR)
+        .expect("Template error regex should compile");
+    let caps = re.captures(message)?;
+    Some((caps[1].to_string(), caps[2].parse().ok()?))
+}
+
+/// Records a template that failed to render while serving, located in the
+/// `templates` folder when it is not an embedded one
+fn add_template_diagnostic(site_data: &mut Data, input_folder: &Path, message: String) {
+    let (template, line) = template_error_location(&message)
+        .map_or((None, None), |(template, line)| {
+            (Some(template), Some(line))
+        });
+    let file = template
+        .as_ref()
+        .and_then(|template| {
+            let templates_path = site_data.site.get_templates_path(input_folder).ok()?;
+            Some(templates_path.join(template))
+        })
+        .filter(|file| file.is_file());
+    let warning = Warning::TemplateError { template, message };
+    let line = line.filter(|_| file.is_some());
+    site_data
+        .diagnostics
+        .push(Diagnostic::new(warning, file.as_deref()).with_line(line));
+}
+
+/// Reports each failed shortcode in the contents it was written in
+fn add_shortcode_diagnostics(site_data: &mut Data, failures: Mutex<Vec<ShortcodeFailure>>) {
+    let failures = failures
+        .into_inner()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    for failure in failures {
+        let sources: Vec<String> = site_data
+            .posts
+            .iter()
+            .chain(&site_data.pages)
+            .filter(|content| content.html.contains(&failure.shortcode))
+            .map(|content| content.slug.clone())
+            .collect();
+        if sources.is_empty() {
+            // Written in a template
+            site_data.add_diagnostic(
+                Warning::BrokenShortcode {
+                    source: None,
+                    name: failure.name,
+                    message: failure.message,
+                },
+                None,
+            );
+            continue;
+        }
+        for source in sources {
+            site_data.add_diagnostic(
+                Warning::BrokenShortcode {
+                    source: Some(source.clone()),
+                    name: failure.name.clone(),
+                    message: failure.message.clone(),
+                },
+                Some(&source),
+            );
+        }
+    }
+}
+
+/// Prints the diagnostics when `--report` is set
+fn print_report(site_data: &Data, cli_args: &crate::cli::Cli) {
+    if cli_args.report == Some(crate::cli::ReportFormat::Json) {
+        match serde_json::to_string_pretty(&Report::new(&site_data.diagnostics)) {
+            Ok(json) => println!("{json}"),
+            Err(e) => error!("Failed to serialize the build report: {e}"),
+        }
+    }
+}
+
+/// Applies `--deny` and prints the `--report`, fails when denied
+/// diagnostics were found
+fn finish_diagnostics(site_data: &mut Data, cli_args: &crate::cli::Cli) -> Result<(), Error> {
+    let denied = apply_deny(&mut site_data.diagnostics, &cli_args.deny);
+    print_report(site_data, cli_args);
+    if denied.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Denied(denied))
+    }
+}
+
 fn validate_internal_links(site_data: &Data) -> Vec<(String, String)> {
     let mut valid_slugs: std::collections::HashSet<String> = std::collections::HashSet::new();
 
@@ -1434,14 +1844,39 @@ fn set_next_and_previous_links(site_data: &mut Data) {
     }
 }
 
-#[allow(clippy::cast_possible_wrap, clippy::too_many_lines)]
 pub(crate) fn collect_content(
     content_dir: &std::path::PathBuf,
     site_data: &mut Data,
     fragments: &HashMap<String, String>,
     highlighter: Option<&MarmiteHighlighter>,
     folder_defaults: &HashMap<std::path::PathBuf, frontmatter_gen::Frontmatter>,
-) {
+) -> Result<(), Error> {
+    collect_content_with_cache(
+        content_dir,
+        site_data,
+        fragments,
+        highlighter,
+        folder_defaults,
+        None,
+    )
+}
+
+/// Same as `collect_content` but reuses the content parsed on a previous
+/// incremental build for files whose hash did not change, and records
+/// every parsed file into the `cache` for the next build.
+///
+/// Files that can not be read are skipped, the ones with an invalid front
+/// matter, bad dates included, are skipped with a diagnostic.
+#[allow(clippy::cast_possible_wrap, clippy::too_many_lines)]
+pub(crate) fn collect_content_with_cache(
+    content_dir: &std::path::PathBuf,
+    site_data: &mut Data,
+    fragments: &HashMap<String, String>,
+    highlighter: Option<&MarmiteHighlighter>,
+    folder_defaults: &HashMap<std::path::PathBuf, frontmatter_gen::Frontmatter>,
+    mut cache: Option<&mut SourceCache>,
+) -> Result<(), Error> {
+    let previous_cache = cache.as_deref();
     let contents = WalkDir::new(content_dir)
         .into_iter()
         .filter_map(Result::ok)
@@ -1474,7 +1909,10 @@ pub(crate) fn collect_content(
                         entry.path().display(),
                         e
                     );
-                    return Err(format!("Failed to get file metadata: {e}"));
+                    return Err(Error::Io {
+                        path: entry.path().to_path_buf(),
+                        message: format!("Failed to get file metadata: {e}"),
+                    });
                 }
             };
             let modified_time = if let Ok(modified_time) = file_metadata.modified() {
@@ -1490,6 +1928,24 @@ pub(crate) fn collect_content(
             } else {
                 None
             };
+            let source_hash = if previous_cache.is_some() {
+                match fs::read(entry.path()) {
+                    Ok(bytes) => Some(crate::incremental::sha256_hex(&bytes)),
+                    Err(e) => {
+                        return Err(Error::Io {
+                            path: entry.path().to_path_buf(),
+                            message: e.to_string(),
+                        })
+                    }
+                }
+            } else {
+                None
+            };
+            if let (Some(cache), Some(hash)) = (previous_cache, &source_hash) {
+                if let Some(content) = cache.get(entry.path(), hash, modified_time) {
+                    return Ok((content, source_hash, true));
+                }
+            }
             let defaults = {
                 let mut dir = entry.path().parent();
                 loop {
@@ -1513,11 +1969,25 @@ pub(crate) fn collect_content(
                 defaults,
                 Some(content_dir),
             )
+            .map(|content| (content, source_hash, false))
         })
         .collect::<Vec<_>>();
     for content in contents {
         match content {
-            Ok(mut content) => {
+            Ok((mut content, source_hash, reused)) => {
+                if let (Some(cache), Some(hash), Some(path)) = (
+                    cache.as_deref_mut(),
+                    source_hash,
+                    content.source_path.clone(),
+                ) {
+                    let entry = SourceEntry {
+                        hash,
+                        modified_time: content.modified_time,
+                        content: content.clone(),
+                    };
+                    cache.record(&path, entry, reused);
+                }
+
                 // Language-from-path detection is deferred to discover_translations
                 // Pass 3, where it only applies inside validated translation groups.
 
@@ -1543,11 +2013,26 @@ pub(crate) fn collect_content(
                     }
                 }
 
+                match content.schedule(site_data.build_time) {
+                    Schedule::Published => {}
+                    Schedule::Scheduled(_) if site_data.include_future => {}
+                    Schedule::Scheduled(date) | Schedule::Expired(date) => {
+                        info!("Skipping {} scheduled or expired at {date}", content.slug);
+                        site_data.scheduled.push(content);
+                        continue;
+                    }
+                }
+
                 site_data.push_content(content);
             }
-            Err(e) => {
-                error!("Failed to process content: {e:?}");
+            Err(Error::Frontmatter { path, message }) => {
+                error!("Failed to process content {}: {message}", path.display());
+                add_frontmatter_diagnostic(site_data, &path, message);
+            }
+            Err(Error::Io { path, message }) => {
+                error!("Failed to process content {}: {message}", path.display());
             }
+            Err(e) => return Err(e),
         }
     }
 
@@ -1568,6 +2053,7 @@ pub(crate) fn collect_content(
             .entry(lang.clone())
             .or_insert_with(|| crate::config::LanguageConfig { display_name: lang });
     }
+    Ok(())
 }
 
 #[allow(clippy::too_many_lines)]
@@ -1605,6 +2091,60 @@ pub fn build_language_index(site_data: &mut Data) {
     site_data.language.sort_all();
 }
 
+/// Sets the AT-URI of the contents published by `marmite atproto publish`,
+/// and the Bluesky thread of the announced posts when `atproto.comments` is on
+fn load_atproto_state(site_data: &mut Data, input_folder: &Path) {
+    let state_path = input_folder.join(".marmite-atproto-state.json");
+    let Some(entries) = fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|state| serde_json::from_str::<serde_json::Value>(&state).ok())
+        .and_then(|mut state| state.get_mut("posts").map(serde_json::Value::take))
+    else {
+        return;
+    };
+    let comments = site_data
+        .site
+        .atproto
+        .as_ref()
+        .is_some_and(|atproto| atproto.comments);
+    let mut replies = if comments {
+        crate::atproto::comments::load(input_folder)
+    } else {
+        HashMap::new()
+    };
+    for content in site_data.posts.iter_mut().chain(site_data.pages.iter_mut()) {
+        let Some(entry) = entries.get(&content.slug) else {
+            continue;
+        };
+        if let Some(at_uri) = entry.get("at_uri").and_then(|u| u.as_str()) {
+            content.at_uri = Some(at_uri.to_string());
+        }
+        if let Some(uri) = entry.get("bsky_uri").and_then(|u| u.as_str()) {
+            if comments {
+                content.bsky = Some(BskyThread {
+                    uri: uri.to_string(),
+                    url: crate::atproto::comments::post_url(uri).unwrap_or_default(),
+                    replies: replies.remove(&content.slug).unwrap_or_default(),
+                });
+            }
+        }
+    }
+}
+
+/// Sets the mentions saved by `marmite webmention fetch` when the
+/// `webmention` section is configured
+fn load_webmentions(site_data: &mut Data, input_folder: &Path) {
+    if site_data.site.webmention.is_none() {
+        return;
+    }
+    let mut received = crate::webmention::load(input_folder);
+    for content in site_data.posts.iter_mut().chain(site_data.pages.iter_mut()) {
+        if let Some(mentions) = received.remove(&content.slug) {
+            content.webmentions = Some(Webmentions::new(mentions));
+        }
+    }
+}
+
 fn discover_translations(site_data: &mut Data, content_dir: &Path) {
     if site_data.site.language.is_empty() {
         site_data.site.language = "en".to_string();
@@ -1985,6 +2525,7 @@ fn resolve_frontmatter_translations(
 
     let mut updates: Vec<(usize, bool, String, String, String, String)> = Vec::new();
     let mut bidirectional_adds: Vec<(usize, bool, TranslationRef)> = Vec::new();
+    let mut missing: Vec<Warning> = Vec::new();
 
     let all_contents: Vec<(usize, bool, &Content)> = site_data
         .posts
@@ -2045,19 +2586,27 @@ fn resolve_frontmatter_translations(
                         },
                     ));
                 } else {
-                    warn!(
-                        "Translation reference '{}' in '{}' not found",
-                        tr.slug, content.slug
-                    );
+                    let warning = Warning::MissingTranslation {
+                        source: content.slug.clone(),
+                        target: tr.slug.clone(),
+                    };
+                    warn!("{warning}");
+                    missing.push(warning);
                 }
             }
         }
     }
 
     apply_translation_resolutions(site_data, updates, bidirectional_adds);
+    for warning in missing {
+        if let Warning::MissingTranslation { source, .. } = &warning {
+            let source = source.clone();
+            site_data.add_diagnostic(warning, Some(&source));
+        }
+    }
 }
 
-fn detect_slug_collision(site_data: &Data) {
+fn detect_slug_collision(site_data: &mut Data) {
     if let Err(duplicate) = check_for_duplicate_slugs(
         &site_data
             .posts
@@ -2065,12 +2614,11 @@ fn detect_slug_collision(site_data: &Data) {
             .chain(&site_data.pages)
             .collect::<Vec<_>>(),
     ) {
-        error!(
-            "Duplicate slug found: '{duplicate}' \
-            - try setting `title` or `slug` as a unique text, \
-            or leave both empty so filename will be assumed. \
-            - The latest content rendered will overwrite the previous one."
-        );
+        let warning = Warning::SlugCollision {
+            slug: duplicate.clone(),
+        };
+        error!("{warning}");
+        site_data.add_diagnostic(warning, Some(&duplicate));
     }
 }
 
@@ -2080,7 +2628,7 @@ fn initialize_tera(
     site_data: &Data,
     cross_site_data: Option<&crate::workspace::CrossSiteData>,
     path_prefix: &str,
-) -> (Tera, Option<ShortcodeProcessor>) {
+) -> Result<(Tera, Option<ShortcodeProcessor>), Error> {
     let mut tera = Tera::default();
     tera.autoescape_on(Vec::<&str>::new());
     let all_site_prefixes = cross_site_data
@@ -2161,6 +2709,12 @@ fn initialize_tera(
             site_data: site_data.clone(),
         },
     );
+    tera.register_function(
+        "responsive_image",
+        ResponsiveImageTag {
+            site_data: site_data.clone(),
+        },
+    );
     tera.register_filter(
         "default_date_format",
         tera_filter::DefaultDateFormat {
@@ -2174,7 +2728,7 @@ fn initialize_tera(
     tera.register_filter("slice", tera_filter::slice);
     tera.register_filter("date", tera_filter::date);
 
-    let templates_path = site_data.site.get_templates_path(input_folder);
+    let templates_path = site_data.site.get_templates_path(input_folder)?;
     let mandatory_templates = ["base.html", "list.html", "group.html", "content.html"];
 
     // Phase 1: Collect all template content (user templates override embedded defaults)
@@ -2218,8 +2772,9 @@ fn initialize_tera(
     // Verify mandatory templates exist
     for tpl_name in &mandatory_templates {
         if !all_templates.iter().any(|(n, _)| n == *tpl_name) {
-            error!("Failed to load template: {tpl_name}");
-            process::exit(1);
+            return Err(Error::Template(format!(
+                "Failed to load template: {tpl_name}"
+            )));
         }
     }
 
@@ -2247,9 +2802,9 @@ fn initialize_tera(
         .iter()
         .map(|(n, c)| (n.as_str(), c.as_str()))
         .collect();
-    if let Err(e) = tera.add_raw_templates(template_refs) {
-        error!("Failed to load templates: {e}");
-    }
+    // Tera drops the whole batch on a syntax error, nothing could be rendered
+    tera.add_raw_templates(template_refs)
+        .map_err(|e| Error::Template(format!("Failed to load templates: {e}")))?;
 
     // Initialize shortcode processor if enabled
     let shortcode_processor = if site_data.site.enable_shortcodes {
@@ -2263,7 +2818,7 @@ fn initialize_tera(
     };
 
     debug!("{:#?}", &tera);
-    (tera, shortcode_processor)
+    Ok((tera, shortcode_processor))
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -2304,7 +2859,7 @@ fn render_templates(
     handle_series_pages(&site_data, &global_context, tera, output_dir)?;
     // If site_data.stream.map does not contain the index stream
     // we will render empty index.html from list.html template
-    if !site_data.stream.map.contains_key("index") {
+    if !site_data.stream.map.contains_key("index") && site_data.should_render("index") {
         handle_default_empty_site(&global_context, tera, output_dir)?;
     }
 
@@ -2312,18 +2867,22 @@ fn render_templates(
 
     // Pages are treated as a list of content, no stream separation is needed
     // pages are usually just static pages that user will link in the menu.
-    handle_list_page(
-        &global_context,
-        &site_data.site.pages_title,
-        &site_data.pages,
-        &site_data,
-        tera,
-        output_dir,
-        "pages",
-    )?;
+    if site_data.should_render("pages") {
+        handle_list_page(
+            &global_context,
+            &site_data.site.pages_title,
+            &site_data.pages,
+            &site_data,
+            tera,
+            output_dir,
+            "pages",
+        )?;
+    }
 
     // Check and guarantees that page 404 was generated even if _404.md is removed
-    handle_404(content_dir, &global_context, tera, output_dir, highlighter)?;
+    if site_data.should_render("404") {
+        handle_404(content_dir, &global_context, tera, output_dir, highlighter)?;
+    }
 
     // Render individual content-slug.html from content.html template
     // content is rendered as last step so it gives the user the ability to
@@ -2364,6 +2923,7 @@ fn handle_group_pages(
         "streams",
         "series",
         "languages",
+        "taxonomies",
     ]
     .par_iter()
     .map(|step| -> Result<(), String> {
@@ -2386,6 +2946,9 @@ fn handle_group_pages(
             "languages" => {
                 handle_language_list_page(output_dir, site_data, global_context, tera)?;
             }
+            "taxonomies" => {
+                handle_taxonomy_pages(output_dir, site_data, global_context, tera)?;
+            }
             _ => {}
         }
         Ok(())
@@ -2411,6 +2974,9 @@ fn handle_stream_pages(
         .par_iter()
         .map(|(stream, stream_contents)| -> Result<(), String> {
             let stream_slug = crate::slugify::slugify(stream);
+            if !site_data.should_render(&stream_slug) {
+                return Ok(());
+            }
             let title = if *stream == "index" {
                 String::new()
             } else {
@@ -2446,21 +3012,13 @@ fn handle_stream_pages(
             // Skip generating feeds for draft stream
             if *stream != "draft" {
                 // Render {stream}.rss for each stream
-                crate::feed::generate_rss(
+                crate::feed::generate_feeds(
                     stream_contents,
                     output_dir,
                     &stream_slug,
                     &site_data.site,
+                    &site_data.site.feed.stream_settings(stream),
                 )?;
-
-                if site_data.site.json_feed {
-                    crate::feed::generate_json(
-                        stream_contents,
-                        output_dir,
-                        &stream_slug,
-                        &site_data.site,
-                    )?;
-                }
             }
             Ok(())
         })
@@ -2484,6 +3042,9 @@ fn handle_series_pages(
         .par_iter()
         .map(|(series, series_contents)| -> Result<(), String> {
             let series_slug = format!("series-{}", crate::slugify::slugify(series));
+            if !site_data.should_render(&series_slug) {
+                return Ok(());
+            }
             let title = site_data
                 .site
                 .series_content_title
@@ -2503,16 +3064,13 @@ fn handle_series_pages(
             )?;
 
             // Generate RSS feed for series
-            crate::feed::generate_rss(series_contents, output_dir, &series_slug, &site_data.site)?;
-
-            if site_data.site.json_feed {
-                crate::feed::generate_json(
-                    series_contents,
-                    output_dir,
-                    &series_slug,
-                    &site_data.site,
-                )?;
-            }
+            crate::feed::generate_feeds(
+                series_contents,
+                output_dir,
+                &series_slug,
+                &site_data.site,
+                &site_data.site.feed.series_settings(series),
+            )?;
 
             Ok(())
         })
@@ -2550,6 +3108,9 @@ fn handle_stream_list_page(
     global_context: &Context,
     tera: &Tera,
 ) -> Result<(), String> {
+    if !site_data.should_render("streams") {
+        return Ok(());
+    }
     let mut stream_list_context = global_context.clone();
     stream_list_context.insert("title", &site_data.site.streams_title);
     stream_list_context.insert("current_page", "streams.html");
@@ -2570,6 +3131,9 @@ fn handle_series_list_page(
     global_context: &Context,
     tera: &Tera,
 ) -> Result<(), String> {
+    if !site_data.should_render("series") {
+        return Ok(());
+    }
     let mut series_list_context = global_context.clone();
     series_list_context.insert("title", &site_data.site.series_title);
     series_list_context.insert("current_page", "series.html");
@@ -2590,6 +3154,9 @@ fn handle_language_list_page(
     global_context: &Context,
     tera: &Tera,
 ) -> Result<(), String> {
+    if !site_data.should_render("languages") {
+        return Ok(());
+    }
     let mut lang_list_context = global_context.clone();
     lang_list_context.insert("title", &site_data.site.languages_title);
     lang_list_context.insert("current_page", "languages.html");
@@ -2616,6 +3183,11 @@ fn handle_author_pages(
         .collect::<Vec<_>>()
         .par_iter()
         .map(|(username, _)| -> Result<(), String> {
+            let author_slug = crate::slugify::slugify(username);
+            let filename = format!("author-{}", &author_slug);
+            if !site_data.should_render(&filename) {
+                return Ok(());
+            }
             let default_author = Author {
                 name: (*username).clone(),
                 bio: None,
@@ -2630,7 +3202,6 @@ fn handle_author_pages(
             };
             author_context.insert("author", &author);
 
-            let author_slug = crate::slugify::slugify(username);
             let mut author_posts = site_data
                 .posts
                 .iter()
@@ -2650,7 +3221,6 @@ fn handle_author_pages(
                 }
             });
 
-            let filename = format!("author-{}", &author_slug);
             handle_list_page(
                 &author_context,
                 &author.name,
@@ -2662,22 +3232,23 @@ fn handle_author_pages(
             )?;
 
             // Render author-{name}.rss for each stream
-            crate::feed::generate_rss(
+            crate::feed::generate_feeds(
                 &author_posts,
                 output_dir,
-                &filename.clone(),
+                &filename,
                 &site_data.site,
+                &site_data.site.feed.settings(),
             )?;
 
-            if site_data.site.json_feed {
-                crate::feed::generate_json(&author_posts, output_dir, &filename, &site_data.site)?;
-            }
-
             Ok(())
         })
         .reduce_with(|r1, r2| if r1.is_err() { r1 } else { r2 })
         .unwrap_or(Ok(()))?;
 
+    if !site_data.should_render("authors") {
+        return Ok(());
+    }
+
     // Render authors.html group page
     let mut authors_list_context = global_context.clone();
     authors_list_context.insert("title", &site_data.site.authors_title);
@@ -2888,8 +3459,8 @@ fn handle_static_artifacts(
     site_data: &Data,
     output_folder: &Arc<std::path::PathBuf>,
     content_dir: &std::path::Path,
-) {
-    let static_source = site_data.site.get_static_path(input_folder);
+) -> Result<(), Error> {
+    let static_source = site_data.site.get_static_path(input_folder)?;
     let has_theme = site_data.site.theme.is_some();
 
     if has_theme && static_source.is_dir() {
@@ -2897,10 +3468,10 @@ fn handle_static_artifacts(
         let mut options = CopyOptions::new();
         options.overwrite = true;
 
-        if let Err(e) = dircopy(&static_source, &**output_folder, &options) {
-            error!("Failed to copy static directory: {e:?}");
-            process::exit(1);
-        }
+        dircopy(&static_source, &**output_folder, &options).map_err(|e| Error::Io {
+            path: static_source.clone(),
+            message: format!("Failed to copy static directory: {e}"),
+        })?;
 
         info!(
             "Copied '{}' to '{}/'",
@@ -2920,10 +3491,10 @@ fn handle_static_artifacts(
             let mut options = CopyOptions::new();
             options.overwrite = true;
 
-            if let Err(e) = dircopy(&user_static, &**output_folder, &options) {
-                error!("Failed to copy user static directory: {e:?}");
-                process::exit(1);
-            }
+            dircopy(&user_static, &**output_folder, &options).map_err(|e| Error::Io {
+                path: user_static.clone(),
+                message: format!("Failed to copy user static directory: {e}"),
+            })?;
 
             info!(
                 "Copied '{}' on top of embedded static to '{}/'",
@@ -2945,10 +3516,14 @@ fn handle_static_artifacts(
                         let mut options = CopyOptions::new();
                         options.overwrite = true;
 
-                        if let Err(e) = dircopy(&source_folder, &**output_folder, &options) {
-                            error!("Failed to copy extra static folders directory: {e:?}");
-                            process::exit(1);
-                        }
+                        dircopy(&source_folder, &**output_folder, &options).map_err(|e| {
+                            Error::Io {
+                                path: source_folder.clone(),
+                                message: format!(
+                                    "Failed to copy extra static folders directory: {e}"
+                                ),
+                            }
+                        })?;
 
                         info!(
                             "Copied extra static folders '{}' to '{}/'",
@@ -2967,10 +3542,10 @@ fn handle_static_artifacts(
         let mut options = CopyOptions::new();
         options.overwrite = true; // Overwrite files if they already exist
 
-        if let Err(e) = dircopy(&media_source, &**output_folder, &options) {
-            error!("Failed to copy media directory: {e:?}");
-            process::exit(1);
-        }
+        dircopy(&media_source, &**output_folder, &options).map_err(|e| Error::Io {
+            path: media_source.clone(),
+            message: format!("Failed to copy media directory: {e}"),
+        })?;
 
         info!(
             "Copied '{}' to '{}/'",
@@ -3047,6 +3622,12 @@ fn handle_static_artifacts(
                 );
             }
         }
+        image_resize::process_responsive_images(
+            &media_source,
+            &output_folder.join(&site_data.site.media_path),
+            &site_data.site,
+            &site_data.responsive_images,
+        );
     }
 
     // Handle file mappings
@@ -3107,77 +3688,7 @@ fn handle_static_artifacts(
 
     // Generate code highlighting CSS based on the site settings
     write_code_highlight_css(&site_data.site, output_folder);
-}
-
-fn generate_search_index(site_data: &Data, output_folder: &Arc<std::path::PathBuf>) {
-    let remove_html_tags = |html: &str| -> String {
-        // Remove HTML tags, Liquid tags, and Jinja tags
-        let re = Regex::new(re::MATCH_HTML_OR_TEMPLATE_TAGS)
-            .map_err(|e| format!("Failed to create regex: {e}"))
-            .unwrap_or_else(|e| {
-                error!("Regex compilation failed: {e}");
-                // Return a simple regex that won't crash but may not work perfectly
-                Regex::new(re::MATCH_HTML_TAGS).expect("Basic regex should compile")
-            });
-        re.replace_all(html, "")
-            .replace('\n', " ")
-            .split_whitespace()
-            .collect::<Vec<_>>()
-            .join(" ")
-    };
-
-    let convert_items_to_json = |item: &Content| {
-        serde_json::json!({
-            "title": item.title,
-            "description": item.description,
-            "tags": item.tags,
-            "slug": item.slug,
-            "html": remove_html_tags(&item.html),
-        })
-    };
-
-    // Merge posts and pages into a single list, filtering out draft content
-    let all_content_json = site_data
-        .posts
-        .iter()
-        .filter(|content| {
-            content
-                .stream
-                .as_ref()
-                .is_none_or(|stream| stream != "draft")
-        })
-        .map(convert_items_to_json)
-        .collect::<Vec<_>>()
-        .into_iter()
-        .chain(
-            site_data
-                .pages
-                .iter()
-                .filter(|content| {
-                    content
-                        .stream
-                        .as_ref()
-                        .is_none_or(|stream| stream != "draft")
-                })
-                .map(convert_items_to_json)
-                .collect::<Vec<_>>(),
-        )
-        .collect::<Vec<_>>();
-
-    let search_json_path = output_folder
-        .join(site_data.site.static_path.clone())
-        .join("search_index.json");
-    if let Err(e) = fs::write(
-        search_json_path,
-        serde_json::to_string(&all_content_json).unwrap_or_else(|e| {
-            error!("Failed to serialize search index: {e}");
-            "[]".to_string()
-        }),
-    ) {
-        error!("Failed to write search_index.json: {e:?}");
-    } else {
-        info!("Generated search_index.json");
-    }
+    Ok(())
 }
 
 fn copy_markdown_sources(site_data: &Data, content_folder: &Path, output_path: &Path) {
@@ -3581,6 +4092,23 @@ pub(crate) fn create_urls_json(site_data: &Data, path_prefix: &str) -> serde_jso
         ),
     );
 
+    // Add custom taxonomies
+    let taxonomies: Vec<String> = site_data
+        .generated_urls
+        .taxonomies
+        .iter()
+        .map(|url| generate_url(url.trim_start_matches('/')))
+        .collect();
+    output.insert(
+        "taxonomies".to_string(),
+        serde_json::Value::Array(
+            taxonomies
+                .iter()
+                .map(|url| serde_json::Value::String(url.clone()))
+                .collect(),
+        ),
+    );
+
     // Add feeds
     let feeds: Vec<String> = site_data
         .generated_urls
@@ -3699,6 +4227,10 @@ pub(crate) fn create_urls_json(site_data: &Data, path_prefix: &str) -> serde_jso
         "languages".to_string(),
         serde_json::Value::Number(serde_json::Number::from(languages.len())),
     );
+    summary.insert(
+        "taxonomies".to_string(),
+        serde_json::Value::Number(serde_json::Number::from(taxonomies.len())),
+    );
     summary.insert(
         "feeds".to_string(),
         serde_json::Value::Number(serde_json::Number::from(feeds.len())),
@@ -3774,6 +4306,30 @@ fn handle_list_page(
     tera: &Tera,
     output_dir: &Path,
     output_filename: &str,
+) -> Result<(), String> {
+    handle_list_page_with_template(
+        global_context,
+        title,
+        all_content,
+        site_data,
+        tera,
+        output_dir,
+        output_filename,
+        "list.html",
+    )
+}
+
+/// Same as `handle_list_page` rendering `template` instead of `list.html`
+#[allow(clippy::too_many_arguments)]
+fn handle_list_page_with_template(
+    global_context: &Context,
+    title: &str,
+    all_content: &[Content],
+    site_data: &Data,
+    tera: &Tera,
+    output_dir: &Path,
+    output_filename: &str,
+    template: &str,
 ) -> Result<(), String> {
     let per_page = &site_data.site.pagination;
     let total_content = all_content.len();
@@ -3790,7 +4346,7 @@ fn handle_list_page(
         context.insert("total_content", &1);
         context.insert("current_page_number", &1);
         render_html(
-            &format!("custom_{output_filename},list.html"),
+            &format!("custom_{output_filename},{template}"),
             &format!("{output_filename}.html"),
             tera,
             &context,
@@ -3857,7 +4413,7 @@ fn handle_list_page(
             );
 
             // Render the HTML file for this page
-            let templates = format!("custom_{output_filename},list.html");
+            let templates = format!("custom_{output_filename},{template}");
             render_html(&templates, &filename, tera, &context, output_dir)?;
             // If there isn't an item in site_data.pages with the same slug as output_filename
             // we will render a {output_filename}.html with the same content as {output_filename}-1.html
@@ -3924,7 +4480,14 @@ fn handle_redirect_aliases(site_data: &Data, output_dir: &Path) -> Result<(), St
 
     for content in site_data.posts.iter().chain(&site_data.pages) {
         for alias in &content.aliases {
-            if all_slugs.contains(alias) {
+            let Some(file) = alias_output_path(alias) else {
+                warn!(
+                    "Redirect alias \"{}\" in \"{}\" is not a valid path, skipping",
+                    alias, content.slug
+                );
+                continue;
+            };
+            if all_slugs.contains(file.trim_end_matches(".html")) {
                 warn!(
                     "Redirect alias \"{}\" in \"{}\" conflicts with an existing content slug, skipping",
                     alias, content.slug
@@ -3932,7 +4495,7 @@ fn handle_redirect_aliases(site_data: &Data, output_dir: &Path) -> Result<(), St
                 continue;
             }
 
-            if let Some(other_slug) = seen_aliases.get(alias) {
+            if let Some(other_slug) = seen_aliases.get(&file) {
                 warn!(
                     "Duplicate redirect alias \"{}\" defined in \"{}\" and \"{}\", skipping duplicate",
                     alias, other_slug, content.slug
@@ -3940,14 +4503,18 @@ fn handle_redirect_aliases(site_data: &Data, output_dir: &Path) -> Result<(), St
                 continue;
             }
 
-            seen_aliases.insert(alias.clone(), content.slug.clone());
+            seen_aliases.insert(file.clone(), content.slug.clone());
 
             let target_url = url_for.resolve(&format!("{}.html", content.slug), false);
             let redirect_html = generate_redirect_html(&target_url);
-            let output_file = output_dir.join(format!("{alias}.html"));
+            let output_file = output_dir.join(&file);
+            if let Some(parent) = output_file.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create folder for redirect {file}: {e}"))?;
+            }
             fs::write(&output_file, redirect_html)
-                .map_err(|e| format!("Failed to write redirect alias {alias}.html: {e}"))?;
-            info!("Generated redirect: {alias}.html -> {}.html", content.slug);
+                .map_err(|e| format!("Failed to write redirect alias {file}: {e}"))?;
+            info!("Generated redirect: {file} -> {}.html", content.slug);
         }
     }
 
@@ -4021,9 +4588,11 @@ fn handle_content_pages(
         .chain(&site_data.pages)
         .collect::<Vec<_>>()
         .par_iter()
-        .filter(|content| {
+        .filter(|content| match &site_data.render_only {
+            // incremental builds render exactly the planned outputs
+            Some(only) => only.contains(&content.slug),
             // render only if force_render or content is newer than the latest timestamp
-            force_render || content.modified_time.unwrap_or(i64::MAX) > last_build
+            None => force_render || content.modified_time.unwrap_or(i64::MAX) > last_build,
         })
         .map(|content| -> Result<(), String> {
             let mut content_context = global_context.clone();
@@ -4089,7 +4658,9 @@ fn should_force_render(
         return true;
     }
 
-    let templates_path = site_data.site.get_templates_path(input_folder);
+    let Ok(templates_path) = site_data.site.get_templates_path(input_folder) else {
+        return true;
+    };
     let templates_modified = WalkDir::new(&templates_path)
         .into_iter()
         .filter_map(Result::ok)
@@ -4145,7 +4716,25 @@ fn should_force_render(
         .as_ref()
         .is_none_or(|info| info.config != site_data.site);
 
-    templates_modified || fragments_modified || config_modified
+    // Content can be published or hidden without its file changing, e.g. a
+    // scheduled post reaching its date, which changes the neighbours of other pages
+    let published_changed = latest_build_info.as_ref().is_none_or(|info| {
+        let previous: HashSet<&str> = info
+            .posts
+            .iter()
+            .chain(&info.pages)
+            .map(|content| content.slug.as_str())
+            .collect();
+        let current: HashSet<&str> = site_data
+            .posts
+            .iter()
+            .chain(&site_data.pages)
+            .map(|content| content.slug.as_str())
+            .collect();
+        previous != current
+    });
+
+    templates_modified || fragments_modified || config_modified || published_changed
 }
 
 #[allow(clippy::similar_names)]
@@ -4172,7 +4761,8 @@ fn handle_404(
             highlighter,
             None,
             None,
-        )?;
+        )
+        .map_err(|e| e.to_string())?;
         content.html.clone_from(&custom_content.html);
         content.title.clone_from(&custom_content.title);
     }
@@ -4215,6 +4805,9 @@ fn handle_tag_pages(
             debug!("Tag slug: '{tag_slug}' -> Original tag: '{original_tag}'");
 
             let filename = format!("tag-{tag_slug}");
+            if !site_data.should_render(&filename) {
+                return Ok(());
+            }
             // Filter out draft content
             let filtered_contents: Vec<Content> = tagged_contents
                 .iter()
@@ -4234,26 +4827,22 @@ fn handle_tag_pages(
                 &filename,
             )?;
             // Render tag-{tag}.rss for each stream
-            crate::feed::generate_rss(
+            crate::feed::generate_feeds(
                 &filtered_contents,
                 output_dir,
-                &filename.clone(),
+                &filename,
                 &site_data.site,
+                &site_data.site.feed.tag_settings(&original_tag),
             )?;
-
-            if site_data.site.json_feed {
-                crate::feed::generate_json(
-                    &filtered_contents,
-                    output_dir,
-                    &filename,
-                    &site_data.site,
-                )?;
-            }
             Ok(())
         })
         .reduce_with(|r1, r2| if r1.is_err() { r1 } else { r2 })
         .unwrap_or(Ok(()))?;
 
+    if !site_data.should_render("tags") {
+        return Ok(());
+    }
+
     // Render tags.html group page
     let mut tag_list_context = global_context.clone();
     tag_list_context.insert("title", &site_data.site.tags_title);
@@ -4269,6 +4858,97 @@ fn handle_tag_pages(
     Ok(())
 }
 
+/// The term of `taxonomy` as written in the frontmatter of `contents`,
+/// the groups of custom taxonomies are keyed by its slug
+pub fn taxonomy_term(contents: &[Content], taxonomy: &str, slug: &str) -> String {
+    contents
+        .iter()
+        .find_map(|content| {
+            content
+                .taxonomies
+                .get(taxonomy)?
+                .iter()
+                .find(|term| crate::slugify::slugify(term) == slug)
+                .cloned()
+        })
+        .unwrap_or_else(|| slug.to_string())
+}
+
+/// Renders `{prefix}-{term}.html` list pages with their feeds and the
+/// `{index}.html` group page of every custom taxonomy
+fn handle_taxonomy_pages(
+    output_dir: &Path,
+    site_data: &Data,
+    global_context: &Context,
+    tera: &Tera,
+) -> Result<(), String> {
+    for taxonomy in site_data.site.taxonomy_list() {
+        let Some(grouped) = site_data.taxonomies.get(&taxonomy.name) else {
+            continue;
+        };
+        grouped
+            .iter()
+            .collect::<Vec<_>>()
+            .par_iter()
+            .map(|(term, term_contents)| -> Result<(), String> {
+                let filename = taxonomy.term_filename(term);
+                if !site_data.should_render(&filename) {
+                    return Ok(());
+                }
+                // Filter out draft content
+                let filtered_contents: Vec<Content> = term_contents
+                    .iter()
+                    .filter(|content| content.stream.as_deref() != Some("draft"))
+                    .cloned()
+                    .collect();
+                handle_list_page_with_template(
+                    global_context,
+                    &taxonomy
+                        .content_title
+                        .replace("$term", &taxonomy_term(term_contents, &taxonomy.name, term)),
+                    &filtered_contents,
+                    site_data,
+                    tera,
+                    output_dir,
+                    &filename,
+                    &taxonomy.template,
+                )?;
+                if taxonomy.feeds {
+                    crate::feed::generate_feeds(
+                        &filtered_contents,
+                        output_dir,
+                        &filename,
+                        &site_data.site,
+                        &site_data.site.feed.settings(),
+                    )?;
+                }
+                Ok(())
+            })
+            .reduce_with(|r1, r2| if r1.is_err() { r1 } else { r2 })
+            .unwrap_or(Ok(()))?;
+
+        if !site_data.should_render(&taxonomy.index) {
+            continue;
+        }
+
+        // Render {index}.html group page
+        let filename = format!("{}.html", taxonomy.index);
+        let mut index_context = global_context.clone();
+        index_context.insert("title", &taxonomy.title);
+        index_context.insert("current_page", &filename);
+        index_context.insert("kind", &taxonomy.name);
+        index_context.insert("taxonomy", &taxonomy);
+        render_html(
+            &taxonomy.index_template,
+            &filename,
+            tera,
+            &index_context,
+            output_dir,
+        )?;
+    }
+    Ok(())
+}
+
 fn handle_archive_pages(
     output_dir: &Path,
     site_data: &Data,
@@ -4282,6 +4962,9 @@ fn handle_archive_pages(
         .par_iter()
         .map(|(year, archive_contents)| -> Result<(), String> {
             let filename = format!("archive-{year}");
+            if !site_data.should_render(&filename) {
+                return Ok(());
+            }
             // Filter out draft content
             let filtered_contents: Vec<Content> = archive_contents
                 .iter()
@@ -4298,26 +4981,22 @@ fn handle_archive_pages(
                 &filename,
             )?;
             // Render archive-{year}.rss for each stream
-            crate::feed::generate_rss(
+            crate::feed::generate_feeds(
                 &filtered_contents,
                 output_dir,
-                &filename.clone(),
+                &filename,
                 &site_data.site,
+                &site_data.site.feed.settings(),
             )?;
-
-            if site_data.site.json_feed {
-                crate::feed::generate_json(
-                    &filtered_contents,
-                    output_dir,
-                    &filename,
-                    &site_data.site,
-                )?;
-            }
             Ok(())
         })
         .reduce_with(|r1, r2| if r1.is_err() { r1 } else { r2 })
         .unwrap_or(Ok(()))?;
 
+    if !site_data.should_render("archive") {
+        return Ok(());
+    }
+
     // Render archive.html group page
     let mut archive_context = global_context.clone();
     archive_context.insert("title", &site_data.site.archives_title);
@@ -4345,6 +5024,7 @@ impl UrlCollection {
             "streams" => self.streams.push(url),
             "archives" => self.archives.push(url),
             "languages" => self.languages.push(url),
+            "taxonomies" => self.taxonomies.push(url),
             "feeds" => self.feeds.push(url),
             "pagination" => self.pagination.push(url),
             "file_mappings" => self.file_mappings.push(url),
@@ -4353,6 +5033,7 @@ impl UrlCollection {
         }
     }
 
+    #[must_use]
     pub fn get_all_urls(&self) -> Vec<String> {
         let mut all_urls = Vec::new();
         all_urls.extend(self.posts.iter().cloned());
@@ -4363,6 +5044,7 @@ impl UrlCollection {
         all_urls.extend(self.streams.iter().cloned());
         all_urls.extend(self.archives.iter().cloned());
         all_urls.extend(self.languages.iter().cloned());
+        all_urls.extend(self.taxonomies.iter().cloned());
         all_urls.extend(self.feeds.iter().cloned());
         all_urls.extend(self.pagination.iter().cloned());
         all_urls.extend(self.file_mappings.iter().cloned());
@@ -4372,6 +5054,7 @@ impl UrlCollection {
         all_urls
     }
 
+    #[must_use]
     pub fn total_count(&self) -> usize {
         self.posts.len()
             + self.pages.len()
@@ -4381,6 +5064,7 @@ impl UrlCollection {
             + self.streams.len()
             + self.archives.len()
             + self.languages.len()
+            + self.taxonomies.len()
             + self.feeds.len()
             + self.pagination.len()
             + self.file_mappings.len()
@@ -4760,17 +5444,21 @@ pub fn show_urls(
 
     // Override site config with CLI arguments
     site_data.site.override_from_cli_args(args);
+    site_data.include_future = args.include_future;
 
     // Collect content fragments and process content
     let fragments = collect_content_fragments(&content_folder);
     let folder_defaults = load_folder_frontmatter(&content_folder);
-    collect_content(
+    if let Err(e) = collect_content(
         &content_folder,
         &mut site_data,
         &fragments,
         None,
         &folder_defaults,
-    );
+    ) {
+        error!("{e}");
+        process::exit(1);
+    }
     site_data.sort_all();
 
     // Collect all URLs including pagination, feeds, and file mappings
@@ -4786,6 +5474,110 @@ pub fn show_urls(
     }
 }
 
+/// Removes the pages of scheduled and expired content left by previous builds,
+/// e.g. a post generated before its `expires_at` or with `--include-future`
+fn remove_unpublished_outputs(site_data: &Data, content_folder: &Path, output_path: &Path) {
+    for content in &site_data.scheduled {
+        let published = site_data
+            .posts
+            .iter()
+            .chain(&site_data.pages)
+            .any(|other| other.slug == content.slug);
+        if published {
+            continue;
+        }
+        // the page and the markdown source copied by `publish_md`
+        let mut outputs = vec![output_path.join(format!("{}.html", content.slug))];
+        if let Some(relative_path) = content
+            .source_path
+            .as_ref()
+            .and_then(|source_path| source_path.strip_prefix(content_folder).ok())
+        {
+            outputs.push(output_path.join(relative_path));
+        }
+        for path in outputs {
+            if path.exists() {
+                match fs::remove_file(&path) {
+                    Ok(()) => info!("Removed unpublished {}", path.display()),
+                    Err(e) => error!("Failed to remove {}: {e:?}", path.display()),
+                }
+            }
+        }
+    }
+}
+
+/// Report of the scheduled and expired content left out of the build
+pub(crate) fn create_scheduled_json(site_data: &Data) -> serde_json::Value {
+    let url_for = UrlFor {
+        base_url: site_data.site.url.clone(),
+        ..Default::default()
+    };
+    let use_abs = !site_data.site.url.is_empty();
+    let date_format = "%Y-%m-%d %H:%M:%S";
+
+    let mut scheduled = Vec::new();
+    let mut expired = Vec::new();
+    for content in &site_data.scheduled {
+        let mut entry = serde_json::json!({
+            "title": content.title,
+            "url": url_for.resolve(&format!("{}.html", content.slug), use_abs),
+            "source": content
+                .source_path
+                .as_ref()
+                .map(|path| path.display().to_string()),
+        });
+        match content.schedule(site_data.build_time) {
+            Schedule::Scheduled(date) => {
+                entry["publish_at"] = date.format(date_format).to_string().into();
+                scheduled.push((date, entry));
+            }
+            Schedule::Expired(date) => {
+                entry["expires_at"] = date.format(date_format).to_string().into();
+                expired.push((date, entry));
+            }
+            Schedule::Published => {}
+        }
+    }
+    // next to be published first, most recently expired first
+    scheduled.sort_by_key(|(date, _)| *date);
+    expired.sort_by_key(|(date, _)| std::cmp::Reverse(*date));
+
+    serde_json::json!({
+        "build_time": site_data.build_time.format(date_format).to_string(),
+        "scheduled": scheduled.into_iter().map(|(_, entry)| entry).collect::<Vec<_>>(),
+        "expired": expired.into_iter().map(|(_, entry)| entry).collect::<Vec<_>>(),
+    })
+}
+
+/// Show the scheduled and expired content in JSON format
+pub fn show_scheduled(
+    config_path: &Arc<std::path::PathBuf>,
+    input_folder: &Arc<std::path::PathBuf>,
+    args: &Arc<crate::cli::Cli>,
+) {
+    let mut site_data = Data::from_file(config_path.as_path());
+    let content_folder = get_content_folder(&site_data.site, input_folder.as_path());
+    site_data.site.override_from_cli_args(args);
+
+    let fragments = collect_content_fragments(&content_folder);
+    let folder_defaults = load_folder_frontmatter(&content_folder);
+    if let Err(e) = collect_content(
+        &content_folder,
+        &mut site_data,
+        &fragments,
+        None,
+        &folder_defaults,
+    ) {
+        error!("{e}");
+        process::exit(1);
+    }
+
+    match serde_json::to_string_pretty(&create_scheduled_json(&site_data)) {
+        Ok(json_string) => println!("{json_string}"),
+        Err(e) => error!("Failed to serialize scheduled content to JSON: {e}"),
+    }
+}
+
 #[cfg(test)]
 #[path = "tests/site.rs"]
 mod tests;
@@ -4,6 +4,7 @@ use tera::{Kwargs, State, TeraResult, Value};
 use url::Url;
 
 use crate::content::Content;
+use crate::image_resize;
 use crate::site::Data;
 use crate::workspace::CrossSiteData;
 
@@ -171,7 +172,8 @@ impl tera::Function<TeraResult<Value>> for UrlFor {
 }
 
 /// Tera template function that takes a `kind` argument and returns the grouped content
-/// based on the kind. The function is used to group the content by tags or archive.
+/// based on the kind. The function is used to group the content by tags, archive or
+/// the name of a custom taxonomy.
 /// The grouped content is built using the `site_data` field from the `Group` struct.
 /// and converted to an `IndexMap` to preserve the order of insertion that is
 /// determined by the iter on `GroupedContent`.
@@ -191,7 +193,7 @@ fn get_grouped_content_from_data<'a>(
         "stream" => Some(&data.stream),
         "series" => Some(&data.series),
         "language" => Some(&data.language),
-        _ => None,
+        taxonomy => data.taxonomies.get(taxonomy),
     }
 }
 
@@ -284,11 +286,15 @@ impl tera::Function<TeraResult<Value>> for Group {
             "stream" => &self.site_data.stream,
             "series" => &self.site_data.series,
             "language" => &self.site_data.language,
-            _ => return Err(tera::Error::message("Invalid `kind` argument")),
+            taxonomy => match self.site_data.taxonomies.get(taxonomy) {
+                Some(grouped) => grouped,
+                None => return Err(tera::Error::message("Invalid `kind` argument")),
+            },
         };
 
         // Convert to vector for sorting.
-        // For tags, filter out backward-compat duplicate keys and recover original names.
+        // For tags, filter out backward-compat duplicate keys and recover original names,
+        // custom taxonomies are keyed by slug too.
         let mut group_list: Vec<(String, Vec<Content>)> = grouped_content
             .iter()
             .filter(|(key, _)| kind != "tag" || crate::slugify::slugify(key) == key.as_str())
@@ -305,6 +311,11 @@ impl tera::Function<TeraResult<Value>> for Group {
                         })
                         .unwrap_or_else(|| name.clone());
                     (original_name, posts.clone())
+                } else if self.site_data.taxonomies.contains_key(kind) {
+                    (
+                        crate::site::taxonomy_term(&posts, kind, name),
+                        posts.clone(),
+                    )
                 } else {
                     (name.clone(), posts.clone())
                 }
@@ -841,6 +852,43 @@ impl tera::Function<TeraResult<Value>> for GetGallery {
     }
 }
 
+/// Tera function rendering a media image as a responsive `<picture>` when
+/// it has `srcset` variants, or as a plain `<img>` otherwise.
+/// Takes a `src` argument plus optional `alt` and `class`.
+pub struct ResponsiveImageTag {
+    pub site_data: Data,
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+impl tera::Function<TeraResult<Value>> for ResponsiveImageTag {
+    fn call(&self, kwargs: Kwargs, _: &State) -> TeraResult<Value> {
+        let src: &str = kwargs.must_get("src")?;
+        let alt: &str = kwargs.get::<&str>("alt")?.unwrap_or("");
+        let class: Option<&str> = kwargs.get::<&str>("class")?;
+
+        let class = class.map_or_else(String::new, |class| {
+            format!(r#" class="{}""#, escape_attribute(class))
+        });
+        let tag = format!(
+            r#"<img src="{}" alt="{}"{class} />"#,
+            escape_attribute(src),
+            escape_attribute(alt)
+        );
+
+        Ok(Value::safe_string(&image_resize::rewrite_img_tags(
+            &tag,
+            &self.site_data.responsive_images,
+            &self.site_data.site.media_path,
+        )))
+    }
+}
+
 #[cfg(test)]
 #[path = "tests/tera_functions.rs"]
 mod tests;
@@ -0,0 +1,387 @@
+use super::*;
+use crate::content::ContentBuilder;
+use chrono::NaiveDate;
+use std::sync::mpsc;
+
+const CONFIG: &str = "name: Blog
+url: https://blog.test
+default_author: bob
+authors:
+  alice:
+    name: Alice <A>
+    bio: Writes about Rust
+    avatar: media/alice.png
+    links:
+      - [Site, https://alice.test]
+  bob:
+    name: Bob
+activitypub: {}
+";
+
+fn post(tags: &[&str]) -> Content {
+    ContentBuilder::new()
+        .title("Hello <world>".to_string())
+        .slug("hello".to_string())
+        .html("<p>Hello</p>".to_string())
+        .tags(tags.iter().map(ToString::to_string).collect())
+        .authors(vec!["alice".to_string()])
+        .date(
+            NaiveDate::from_ymd_opt(2024, 1, 2)
+                .unwrap()
+                .and_hms_opt(10, 0, 0)
+                .unwrap(),
+        )
+        .build()
+}
+
+#[test]
+fn test_actors_and_webfinger() {
+    let data = Data::new(CONFIG, Path::new("marmite.yaml"));
+    let site = &data.site;
+    let published = actors(site, site.activitypub.as_ref().unwrap());
+    // The default author is the main actor
+    let usernames: Vec<&str> = published.iter().map(|a| a.username.as_str()).collect();
+    assert_eq!(usernames, ["bob", "alice"]);
+
+    let urls = Urls::new(site, "");
+    let jrd = webfinger(&urls, "blog.test", "alice");
+    assert_eq!(jrd["subject"], "acct:alice@blog.test");
+    assert_eq!(jrd["links"][0]["type"], ACTIVITY_JSON);
+    assert_eq!(
+        jrd["links"][0]["href"],
+        "https://blog.test/activitypub/alice.json"
+    );
+
+    let actor = actor_document(&urls, &published[1], "PEM");
+    assert_eq!(actor["id"], "https://blog.test/activitypub/alice.json");
+    assert_eq!(actor["name"], "Alice <A>");
+    assert_eq!(actor["summary"], "<p>Writes about Rust</p>");
+    assert_eq!(actor["url"], "https://blog.test/author-alice.html");
+    assert_eq!(actor["inbox"], "https://blog.test/activitypub/alice/inbox");
+    assert_eq!(
+        actor["endpoints"]["sharedInbox"],
+        "https://blog.test/activitypub/inbox"
+    );
+    assert_eq!(
+        actor["publicKey"]["id"],
+        "https://blog.test/activitypub/alice.json#main-key"
+    );
+    assert_eq!(actor["publicKey"]["owner"], actor["id"]);
+    assert_eq!(actor["icon"]["url"], "https://blog.test/media/alice.png");
+    assert_eq!(actor["attachment"][0]["name"], "Site");
+
+    // Only the listed authors, in their order
+    let data = Data::new(
+        &CONFIG.replace("activitypub: {}", "activitypub:\n  authors: [alice, carol]"),
+        Path::new("marmite.yaml"),
+    );
+    let published = actors(&data.site, data.site.activitypub.as_ref().unwrap());
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].username, "alice");
+}
+
+#[test]
+fn test_note_and_article_objects() {
+    let data = Data::new(CONFIG, Path::new("marmite.yaml"));
+    let urls = Urls::new(&data.site, "");
+    let note = object(&data.site, &urls, &post(&["Rust Lang"]), "alice");
+    assert_eq!(note["type"], "Note");
+    assert_eq!(
+        note["id"],
+        "https://blog.test/activitypub/objects/hello.json"
+    );
+    assert_eq!(
+        note["attributedTo"],
+        "https://blog.test/activitypub/alice.json"
+    );
+    assert_eq!(note["url"], "https://blog.test/hello.html");
+    assert_eq!(note["published"], "2024-01-02T10:00:00Z");
+    assert_eq!(note["to"][0], PUBLIC);
+    assert_eq!(note["tag"][0]["name"], "#RustLang");
+    assert_eq!(
+        note["tag"][0]["href"],
+        "https://blog.test/tag-rust-lang.html"
+    );
+    let content = note["content"].as_str().unwrap();
+    assert!(content.contains("<strong>Hello &lt;world&gt;</strong>"));
+    assert!(content.contains("<a href=\"https://blog.test/hello.html\">"));
+    assert!(content.contains(">#RustLang</a>"));
+
+    let data = Data::new(
+        &CONFIG.replace("activitypub: {}", "activitypub:\n  object_type: article"),
+        Path::new("marmite.yaml"),
+    );
+    let article = object(&data.site, &urls, &post(&[]), "alice");
+    assert_eq!(article["type"], "Article");
+    assert_eq!(article["name"], "Hello <world>");
+    assert_eq!(article["content"], "<p>Hello</p>");
+    assert_eq!(create(&article)["object"]["id"], article["id"]);
+}
+
+#[test]
+fn test_check_signature() {
+    let body = br#"{"type":"Follow"}"#;
+    let now = Utc::now();
+    let date = now.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    let mut headers = HashMap::from([
+        ("host".to_string(), "blog.test".to_string()),
+        ("date".to_string(), date.clone()),
+        ("digest".to_string(), digest(body)),
+        (
+            "signature".to_string(),
+            "keyId=\"https://remote.test/users/carol#main-key\",algorithm=\"rsa-sha256\",\
+             headers=\"(request-target) host date digest\",signature=\"c2ln\""
+                .to_string(),
+        ),
+    ]);
+    let target = "post /activitypub/inbox";
+    let signature = check_signature(&headers, target, body, now).unwrap();
+    assert_eq!(signature.key_id, "https://remote.test/users/carol#main-key");
+    assert_eq!(signature.signature, "c2ln");
+    assert_eq!(
+        signature.signing_string,
+        format!(
+            "(request-target): post /activitypub/inbox\nhost: blog.test\ndate: {date}\ndigest: {}",
+            digest(body)
+        )
+    );
+
+    assert!(check_signature(&headers, target, b"{}", now).is_err());
+    let later = now + chrono::Duration::days(1);
+    assert!(check_signature(&headers, target, body, later).is_err());
+    headers.insert(
+        "signature".to_string(),
+        "keyId=\"k\",headers=\"(request-target) host date\",signature=\"c2ln\"".to_string(),
+    );
+    assert!(check_signature(&headers, target, body, now)
+        .unwrap_err()
+        .contains("digest"));
+}
+
+#[test]
+fn test_service_content_type() {
+    let data = Data::new(CONFIG, Path::new("marmite.yaml"));
+    let service = Service::new(&data.site, Path::new(".")).unwrap();
+    assert!(service.key.is_none());
+    assert_eq!(
+        service.content_type(".well-known/webfinger"),
+        Some(JRD_JSON)
+    );
+    assert_eq!(
+        service.content_type("activitypub/webfinger/alice.json"),
+        Some(JRD_JSON)
+    );
+    assert_eq!(
+        service.content_type("activitypub/alice.json"),
+        Some(ACTIVITY_JSON)
+    );
+    assert_eq!(service.content_type("api/index.json"), None);
+
+    let data = Data::new("name: Blog\nactivitypub: {}\n", Path::new("marmite.yaml"));
+    assert!(Service::new(&data.site, Path::new(".")).is_none());
+}
+
+#[test]
+fn test_remote_agent_connects_only_to_public_addresses() {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let _ = request.respond(tiny_http::Response::empty(200));
+        }
+    });
+    // Resolved by the agent itself, as a rebinding DNS answer would be
+    for url in [
+        format!("http://localhost:{port}/users/carol"),
+        format!("http://127.0.0.1:{port}/users/carol"),
+    ] {
+        let error = remote_agent(false).get(&url).call().unwrap_err();
+        assert!(
+            error.to_string().contains("not a public address"),
+            "{error}"
+        );
+        assert_eq!(remote_agent(true).get(&url).call().unwrap().status(), 200);
+    }
+}
+
+#[test]
+fn test_remote_addresses() {
+    assert!(check_remote("http://example.com/users/carol")
+        .unwrap_err()
+        .contains("https"));
+    for url in [
+        "https://127.0.0.1/users/carol",
+        "https://10.0.0.1/users/carol",
+        "https://192.168.1.1/users/carol",
+        "https://169.254.169.254/latest",
+        "https://100.64.0.1/users/carol",
+        "https://[::1]/users/carol",
+        "https://[fd00::1]/users/carol",
+        "https://[::ffff:127.0.0.1]/users/carol",
+    ] {
+        assert!(check_remote(url).is_err(), "{url}");
+    }
+    assert!(check_remote("https://93.184.215.14/users/carol").is_ok());
+    assert!(same_origin(
+        "https://remote.test/users/carol#main-key",
+        "https://remote.test/users/carol"
+    ));
+    assert!(!same_origin(
+        "https://evil.test/key#main-key",
+        "https://remote.test/users/carol"
+    ));
+}
+
+/// A remote server hosting `carol`, publishing `key_id` as her key and
+/// recording the activities posted to her inbox
+fn start_remote(
+    key_id: &'static str,
+    public_pem: String,
+    received: mpsc::Sender<(String, String)>,
+) -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let actor = format!("{base}/users/carol");
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let response = match request.url() {
+                "/users/carol" => Response::from_string(
+                    json!({
+                        "id": actor,
+                        "type": "Person",
+                        "inbox": format!("{actor}/inbox"),
+                        "publicKey": {
+                            "id": format!("{actor}{key_id}"),
+                            "owner": actor,
+                            "publicKeyPem": public_pem,
+                        },
+                    })
+                    .to_string(),
+                ),
+                "/users/carol/inbox" => {
+                    let mut body = String::new();
+                    let _ = request.as_reader().read_to_string(&mut body);
+                    let signature = request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv("Signature"))
+                        .map(|h| h.value.to_string())
+                        .unwrap_or_default();
+                    let _ = received.send((signature, body));
+                    Response::from_string("").with_status_code(202)
+                }
+                _ => Response::from_string("Not found").with_status_code(404),
+            };
+            let _ = request.respond(response);
+        }
+    });
+    base
+}
+
+/// Serves the inboxes of `service`
+fn start_site(service: Service, server: tiny_http::Server) {
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let response = service
+                .handle(&mut request)
+                .unwrap_or_else(|| error_reply(404, "Not found"));
+            let _ = request.respond(response);
+        }
+    });
+}
+
+fn post_signed(url: &str, activity: &Value, key: &ActorKey, key_id: &str) -> u16 {
+    let body = activity.to_string();
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut request = agent.post(url).header("Content-Type", ACTIVITY_JSON);
+    for (name, value) in signed_headers(key, key_id, "post", url, Some(body.as_bytes())).unwrap() {
+        request = request.header(name, value);
+    }
+    request.send(body.as_bytes()).unwrap().status().as_u16()
+}
+
+#[test]
+fn test_inbox_follow_and_undo() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let data = Data::new(
+        &format!("name: Blog\nurl: {base}\nauthors:\n  alice:\n    name: Alice\nactivitypub:\n  inbox: true\n"),
+        Path::new("marmite.yaml"),
+    );
+    let mut service = Service::new(&data.site, temp.path()).unwrap();
+    service.allow_local = true;
+    service.agent = remote_agent(true);
+    // The remote actors sign with the key of the site, any RSA key will do
+    let key = ActorKey::load(temp.path()).unwrap();
+    let (sender, received) = mpsc::channel();
+    let remote = start_remote("#main-key", key.public_pem.clone(), sender);
+    let (other_sender, _) = mpsc::channel();
+    let impostor = start_remote("#other-key", key.public_pem.clone(), other_sender);
+    start_site(service, server);
+
+    let carol = format!("{remote}/users/carol");
+    let actor_url = format!("{base}/activitypub/alice.json");
+    let follow = json!({
+        "id": format!("{carol}#follow-1"),
+        "type": "Follow",
+        "actor": carol,
+        "object": actor_url,
+    });
+    let inbox = format!("{base}/activitypub/alice/inbox");
+    // The key must be the one of the actor, on the server of the actor
+    assert_eq!(
+        post_signed(&inbox, &follow, &key, &format!("{carol}#other-key")),
+        401
+    );
+    assert_eq!(
+        post_signed(
+            &inbox,
+            &follow,
+            &key,
+            &format!("{impostor}/users/carol#other-key")
+        ),
+        401
+    );
+    assert!(load_followers(temp.path()).followers.is_empty());
+
+    assert_eq!(
+        post_signed(&inbox, &follow, &key, &format!("{carol}#main-key")),
+        202
+    );
+    let followers = load_followers(temp.path()).followers;
+    assert_eq!(followers.len(), 1);
+    assert_eq!(followers[0].actor, carol);
+    assert_eq!(followers[0].following, "alice");
+    assert!(temp
+        .path()
+        .join(".marmite")
+        .join("activitypub-followers.json")
+        .exists());
+    let (signature, accept) = received.recv_timeout(Duration::from_secs(30)).unwrap();
+    assert!(signature.contains(&format!("keyId=\"{actor_url}#main-key\"")));
+    let accept: Value = serde_json::from_str(&accept).unwrap();
+    assert_eq!(accept["type"], "Accept");
+    assert_eq!(accept["actor"], actor_url.as_str());
+    assert_eq!(accept["object"]["id"], follow["id"]);
+
+    let undo = json!({
+        "id": format!("{carol}#undo-1"),
+        "type": "Undo",
+        "actor": carol,
+        "object": follow,
+    });
+    assert_eq!(
+        post_signed(
+            &format!("{base}/activitypub/inbox"),
+            &undo,
+            &key,
+            &format!("{carol}#main-key")
+        ),
+        202
+    );
+    assert!(load_followers(temp.path()).followers.is_empty());
+}
@@ -0,0 +1,150 @@
+use super::*;
+use crate::diagnostics::{Severity, Warning};
+use tempfile::TempDir;
+
+fn write_site(config: &str) -> TempDir {
+    let temp = TempDir::new().unwrap();
+    let content = temp.path().join("content");
+    fs::create_dir_all(&content).unwrap();
+    fs::write(temp.path().join("marmite.yaml"), config).unwrap();
+    fs::write(
+        content.join("2024-01-01-hello.md"),
+        "# Hello\n\nSee [the missing page](missing.html).",
+    )
+    .unwrap();
+    fs::write(content.join("about.md"), "# About\n\nAbout page.").unwrap();
+    temp
+}
+
+#[test]
+fn test_load_missing_folder() {
+    let temp = TempDir::new().unwrap();
+    let result = Site::load(temp.path().join("missing"));
+    assert!(matches!(result, Err(Error::Io { .. })));
+}
+
+#[test]
+fn test_load_invalid_config() {
+    let temp = write_site("name: [unclosed");
+    let result = Site::load(temp.path());
+    assert!(matches!(result, Err(Error::Config { .. })));
+}
+
+#[test]
+fn test_load_invalid_taxonomy() {
+    let temp = write_site("name: Test\ntaxonomies:\n  topic:\n    prefix: ../x\n");
+    let result = Site::load(temp.path());
+    assert!(
+        matches!(result, Err(Error::Config { message, .. }) if message.contains("prefix '../x'"))
+    );
+}
+
+#[test]
+fn test_load_missing_theme() {
+    let temp = write_site("name: Test\ntheme: missing-theme");
+    let result = Site::load(temp.path());
+    assert!(matches!(result, Err(Error::ThemeNotFound(path)) if path.ends_with("missing-theme")));
+}
+
+#[test]
+fn test_load_without_config_uses_defaults() {
+    let temp = TempDir::new().unwrap();
+    let site = Site::load(temp.path()).unwrap();
+    assert_eq!(site.config().name, "Home");
+    assert_eq!(site.input_folder(), temp.path());
+}
+
+#[test]
+fn test_build_returns_report() {
+    let temp = write_site("name: Test");
+    let output = temp.path().join("public");
+    let report = Site::load(temp.path())
+        .unwrap()
+        .build(&BuildOptions {
+            output_folder: Some(output.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+
+    assert_eq!(report.output_folder, output);
+    assert_eq!(report.data.posts.len(), 1);
+    assert_eq!(report.data.pages.len(), 1);
+    assert!(report
+        .urls
+        .posts
+        .iter()
+        .any(|url| url.ends_with("hello.html")));
+    assert!(report.diagnostics.is_empty());
+    assert!(output.join("hello.html").exists());
+    assert!(output.join("marmite.json").exists());
+}
+
+#[test]
+fn test_build_reports_broken_links_as_warnings() {
+    let temp = write_site("name: Test\ncheck_internal_links: true");
+    let report = Site::load(temp.path())
+        .unwrap()
+        .build(&BuildOptions::default())
+        .unwrap();
+
+    assert_eq!(report.diagnostics.len(), 1);
+    let diagnostic = &report.diagnostics[0];
+    assert_eq!(
+        diagnostic.warning,
+        Warning::BrokenInternalLink {
+            source: "hello".to_string(),
+            target: "missing".to_string(),
+        }
+    );
+    assert!(diagnostic
+        .file
+        .as_ref()
+        .unwrap()
+        .ends_with("2024-01-01-hello.md"));
+    assert_eq!(diagnostic.line, Some(3));
+    assert!(temp.path().join("site").join("hello.html").exists());
+}
+
+#[test]
+fn test_build_denied_diagnostics_is_an_error() {
+    let temp = write_site("name: Test\ncheck_internal_links: true");
+    let result = Site::load(temp.path()).unwrap().build(&BuildOptions {
+        deny: vec!["broken-internal-link".to_string()],
+        ..Default::default()
+    });
+
+    assert!(matches!(result, Err(Error::Denied(denied)) if denied[0].severity == Severity::Error));
+    // the site is still written
+    assert!(temp.path().join("site").join("hello.html").exists());
+}
+
+#[test]
+fn test_build_skips_invalid_date() {
+    let temp = write_site("name: Test");
+    let post = temp.path().join("content").join("bad-date.md");
+    fs::write(&post, "---\ndate: not a date\n---\n# Bad date\n").unwrap();
+    let site = Site::load(temp.path()).unwrap();
+    let report = site.build(&BuildOptions::default()).unwrap();
+
+    assert_eq!(report.data.posts.len(), 1);
+    assert_eq!(report.diagnostics.len(), 1);
+    assert_eq!(report.diagnostics[0].code(), "invalid-frontmatter");
+    assert_eq!(report.diagnostics[0].file.as_deref(), Some(post.as_path()));
+
+    let result = site.build(&BuildOptions {
+        deny: vec!["invalid-frontmatter".to_string()],
+        ..Default::default()
+    });
+    assert!(matches!(result, Err(Error::Denied(denied)) if denied.len() == 1));
+}
+
+#[test]
+fn test_build_strict_broken_links_is_an_error() {
+    let temp = write_site("name: Test\ncheck_internal_links: true\nstrict_internal_links: true");
+    let result = Site::load(temp.path())
+        .unwrap()
+        .build(&BuildOptions::default());
+
+    assert!(matches!(result, Err(Error::BrokenLinks(links)) if links.len() == 1));
+    assert!(!temp.path().join("site").join("hello.html").exists());
+}
@@ -0,0 +1,92 @@
+use super::*;
+
+fn reply(
+    uri: &str,
+    handle: &str,
+    created_at: &str,
+    replies: serde_json::Value,
+) -> serde_json::Value {
+    serde_json::json!({
+        "$type": "app.bsky.feed.defs#threadViewPost",
+        "post": {
+            "uri": uri,
+            "author": {"did": "did:plc:x", "handle": handle, "displayName": ""},
+            "record": {"text": format!("Hi from {handle}"), "createdAt": created_at},
+            "likeCount": 2
+        },
+        "replies": replies
+    })
+}
+
+#[test]
+fn test_post_url() {
+    assert_eq!(
+        post_url("at://did:plc:me/app.bsky.feed.post/3k2a").as_deref(),
+        Some("https://bsky.app/profile/did:plc:me/post/3k2a")
+    );
+    assert_eq!(
+        post_url("at://did:plc:me/site.standard.document/3k2a"),
+        None
+    );
+}
+
+#[test]
+fn test_flatten_orders_and_nests_replies() {
+    let thread = serde_json::json!({
+        "post": {"uri": "at://did:plc:me/app.bsky.feed.post/root"},
+        "replies": [
+            reply(
+                "at://did:plc:b/app.bsky.feed.post/b",
+                "bob.test",
+                "2024-01-02T00:00:00Z",
+                serde_json::json!([]),
+            ),
+            {"$type": "app.bsky.feed.defs#blockedPost", "uri": "at://did:plc:x/app.bsky.feed.post/x", "blocked": true},
+            reply(
+                "at://did:plc:a/app.bsky.feed.post/a",
+                "alice.test",
+                "2024-01-01T00:00:00Z",
+                serde_json::json!([reply(
+                    "at://did:plc:c/app.bsky.feed.post/c",
+                    "carol.test",
+                    "2024-01-03T00:00:00Z",
+                    serde_json::json!([]),
+                )]),
+            ),
+        ]
+    });
+    let mut replies = Vec::new();
+    flatten(&thread, 0, &mut replies);
+    let found: Vec<(&str, usize)> = replies
+        .iter()
+        .map(|r| (r.handle.as_str(), r.depth))
+        .collect();
+    assert_eq!(
+        found,
+        vec![("alice.test", 0), ("carol.test", 1), ("bob.test", 0)]
+    );
+    assert_eq!(replies[0].display_name, "alice.test");
+    assert_eq!(replies[0].text, "Hi from alice.test");
+    assert_eq!(replies[0].like_count, 2);
+    assert_eq!(replies[0].url, "https://bsky.app/profile/did:plc:a/post/a");
+}
+
+#[test]
+fn test_flatten_escapes_replies() {
+    let mut thread = serde_json::json!({"replies": [reply(
+        "at://did:plc:a/app.bsky.feed.post/a",
+        "alice.test",
+        "2024-01-01T00:00:00Z",
+        serde_json::json!([]),
+    )]});
+    thread["replies"][0]["post"]["record"]["text"] =
+        serde_json::json!("<script>\"x\" & y</script>");
+    thread["replies"][0]["post"]["author"]["displayName"] = serde_json::json!("<b>Alice</b>");
+    let mut replies = Vec::new();
+    flatten(&thread, 0, &mut replies);
+    assert_eq!(
+        replies[0].text,
+        "&lt;script&gt;&quot;x&quot; &amp; y&lt;/script&gt;"
+    );
+    assert_eq!(replies[0].display_name, "&lt;b&gt;Alice&lt;/b&gt;");
+}
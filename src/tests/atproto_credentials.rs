@@ -0,0 +1,35 @@
+use super::*;
+
+#[test]
+fn test_seal_and_open() {
+    let json = r#"{"me.test":{"pds_url":"https://pds.test","identifier":"me.test"}}"#;
+    let text = seal(json, "correct horse").unwrap();
+    assert!(!text.contains("pds.test"));
+
+    let sealed: Sealed = serde_json::from_str(&text).unwrap();
+    assert_eq!(sealed.iterations, ITERATIONS);
+    assert_eq!(open(&sealed, "correct horse").unwrap(), json);
+    assert!(open(&sealed, "wrong horse").is_err());
+
+    // Every seal has its own salt and nonce
+    let again: Sealed = serde_json::from_str(&seal(json, "correct horse").unwrap()).unwrap();
+    assert_ne!(again.salt, sealed.salt);
+    assert_ne!(again.ciphertext, sealed.ciphertext);
+}
+
+#[test]
+fn test_credential_without_password() {
+    let cred: Credential = serde_json::from_str(
+        r#"{"pds_url":"https://pds.test","identifier":"me.test","password":"pw"}"#,
+    )
+    .unwrap();
+    assert_eq!(cred.password.as_deref(), Some("pw"));
+    assert!(cred.oauth.is_none());
+    let json = serde_json::to_value(Credential {
+        password: None,
+        ..cred
+    })
+    .unwrap();
+    assert!(json.get("password").is_none());
+    assert!(json.get("oauth").is_none());
+}
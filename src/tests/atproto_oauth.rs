@@ -0,0 +1,99 @@
+use super::*;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_FIXED};
+
+fn decode(part: &str) -> serde_json::Value {
+    serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(part).unwrap()).unwrap()
+}
+
+#[test]
+fn test_challenge() {
+    // RFC 7636, appendix B
+    assert_eq!(
+        challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+        "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+    );
+}
+
+#[test]
+fn test_client_id() {
+    assert_eq!(
+        client_id("http://127.0.0.1:8080/callback"),
+        "http://localhost?redirect_uri=http%3A%2F%2F127.0.0.1%3A8080%2Fcallback\
+         &scope=atproto%20transition%3Ageneric"
+    );
+}
+
+#[test]
+fn test_dpop_proof() {
+    let key = DpopKey::generate().unwrap();
+    let key = DpopKey::decode(&key.encode()).unwrap();
+    let proof = key
+        .proof(
+            "POST",
+            "https://pds.test/xrpc/com.atproto.repo.createRecord?x=1",
+            Some("n1"),
+            Some("token"),
+        )
+        .unwrap();
+    let parts: Vec<&str> = proof.split('.').collect();
+    assert_eq!(parts.len(), 3);
+
+    let header = decode(parts[0]);
+    assert_eq!(header["typ"], "dpop+jwt");
+    assert_eq!(header["alg"], "ES256");
+    assert_eq!(header["jwk"], key.jwk());
+    let claims = decode(parts[1]);
+    assert_eq!(claims["htm"], "POST");
+    assert_eq!(
+        claims["htu"],
+        "https://pds.test/xrpc/com.atproto.repo.createRecord"
+    );
+    assert_eq!(claims["nonce"], "n1");
+    assert_eq!(claims["ath"], sha256_b64("token"));
+    assert!(claims["jti"].as_str().is_some_and(|jti| jti.len() >= 32));
+
+    // The signature verifies with the public key of the JWK
+    let jwk = key.jwk();
+    let mut point = vec![4u8];
+    for coordinate in ["x", "y"] {
+        point.extend(
+            BASE64_URL_SAFE_NO_PAD
+                .decode(jwk[coordinate].as_str().unwrap())
+                .unwrap(),
+        );
+    }
+    let signature = BASE64_URL_SAFE_NO_PAD.decode(parts[2]).unwrap();
+    UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, &point)
+        .verify(format!("{}.{}", parts[0], parts[1]).as_bytes(), &signature)
+        .unwrap();
+
+    let proof = key
+        .proof("GET", "https://as.test/token", None, None)
+        .unwrap();
+    let claims = decode(proof.split('.').nth(1).unwrap());
+    assert!(claims.get("nonce").is_none());
+    assert!(claims.get("ath").is_none());
+}
+
+#[test]
+fn test_parse_tokens() {
+    let body = |token_type: &str, scope: &str| {
+        serde_json::json!({
+            "access_token": "at",
+            "refresh_token": "rt",
+            "token_type": token_type,
+            "expires_in": 60,
+            "sub": "did:plc:me",
+            "scope": scope,
+        })
+    };
+    let tokens = parse_tokens(
+        body("DPoP", "atproto transition:generic"),
+        Some("did:plc:me"),
+    )
+    .unwrap();
+    assert_eq!(tokens.refresh_token, "rt");
+    assert!(parse_tokens(body("Bearer", "atproto"), None).is_err());
+    assert!(parse_tokens(body("DPoP", "transition:generic"), None).is_err());
+    assert!(parse_tokens(body("DPoP", "atproto"), Some("did:plc:other")).is_err());
+}
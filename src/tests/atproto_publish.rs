@@ -0,0 +1,200 @@
+use super::*;
+use crate::content::ContentBuilder;
+
+fn document(slug: &str, hash: &str) -> LocalDocument {
+    LocalDocument {
+        content: ContentBuilder::new().slug(slug.to_string()).build(),
+        hash: hash.to_string(),
+    }
+}
+
+fn record(slug: &str, site: &str) -> client::RecordItem {
+    client::RecordItem {
+        uri: format!("at://did:plc:me/site.standard.document/{slug}"),
+        value: serde_json::json!({"site": site, "path": format!("/{slug}.html")}),
+    }
+}
+
+fn entry(slug: &str, hash: &str) -> StateEntry {
+    StateEntry {
+        content_hash: hash.to_string(),
+        at_uri: format!("at://did:plc:me/site.standard.document/{slug}"),
+        last_published: String::new(),
+        bsky_uri: None,
+    }
+}
+
+#[test]
+fn test_remote_documents_keeps_the_publication() {
+    let mut other_path = record("nested", "at://pub");
+    other_path.value["path"] = serde_json::json!("/blog/nested.html");
+    let records = vec![
+        record("mine", "at://pub"),
+        record("theirs", "at://other"),
+        other_path,
+    ];
+    let remote = remote_documents(&records, "at://pub");
+    assert_eq!(remote.len(), 1);
+    assert_eq!(
+        remote["mine"],
+        "at://did:plc:me/site.standard.document/mine"
+    );
+}
+
+#[test]
+fn test_plan_reconciles_with_the_pds() {
+    let local = vec![
+        document("new", "1"),
+        document("same", "2"),
+        document("changed", "3"),
+        document("lost", "4"),
+    ];
+    let mut state = PublishState::default();
+    state.posts.insert("same".to_string(), entry("same", "2"));
+    state
+        .posts
+        .insert("changed".to_string(), entry("changed", "old"));
+    // Published before but deleted on the PDS since
+    state.posts.insert("lost".to_string(), entry("lost", "4"));
+    let remote = remote_documents(
+        &[
+            record("same", "at://pub"),
+            record("changed", "at://pub"),
+            record("removed", "at://pub"),
+            record("drafted", "at://pub"),
+        ],
+        "at://pub",
+    );
+
+    let steps = plan(&local, &state, &remote, false, false, false);
+    let uri = |slug: &str| format!("at://did:plc:me/site.standard.document/{slug}");
+    assert_eq!(
+        steps,
+        vec![
+            ("new".to_string(), PostAction::Publish { announce: false }),
+            ("same".to_string(), PostAction::Skip),
+            (
+                "changed".to_string(),
+                PostAction::Update {
+                    at_uri: uri("changed")
+                }
+            ),
+            ("lost".to_string(), PostAction::Publish { announce: false }),
+            (
+                "drafted".to_string(),
+                PostAction::Delete {
+                    at_uri: uri("drafted")
+                }
+            ),
+            (
+                "removed".to_string(),
+                PostAction::Delete {
+                    at_uri: uri("removed")
+                }
+            ),
+        ]
+    );
+
+    let kept = plan(&local, &state, &remote, true, true, false);
+    assert_eq!(kept.len(), 4);
+    assert_eq!(
+        kept[1],
+        (
+            "same".to_string(),
+            PostAction::Update {
+                at_uri: uri("same")
+            }
+        )
+    );
+}
+
+#[test]
+fn test_is_publishable() {
+    let post = |stream: &str| {
+        ContentBuilder::new()
+            .stream(stream.to_string())
+            .date(
+                chrono::NaiveDate::from_ymd_opt(2024, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+            )
+            .build()
+    };
+    let page = ContentBuilder::new().build();
+    let mut atproto = AtprotoConfig::default();
+    assert!(is_publishable(&post("index"), &atproto));
+    assert!(is_publishable(&post("notes"), &atproto));
+    assert!(!is_publishable(&post("draft"), &atproto));
+    assert!(!is_publishable(&page, &atproto));
+
+    atproto.publish_pages = true;
+    atproto.streams = vec!["notes".to_string()];
+    assert!(is_publishable(&page, &atproto));
+    assert!(is_publishable(&post("notes"), &atproto));
+    assert!(!is_publishable(&post("index"), &atproto));
+}
+
+#[test]
+fn test_plan_announces_new_posts_once() {
+    let dated = |slug: &str| LocalDocument {
+        content: ContentBuilder::new()
+            .slug(slug.to_string())
+            .date(
+                chrono::NaiveDate::from_ymd_opt(2024, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+            )
+            .build(),
+        hash: String::new(),
+    };
+    let local = vec![dated("new"), dated("lost"), document("page", "")];
+    let mut state = PublishState::default();
+    state.posts.insert(
+        "lost".to_string(),
+        StateEntry {
+            bsky_uri: Some("at://did:plc:me/app.bsky.feed.post/1".to_string()),
+            ..entry("lost", "")
+        },
+    );
+    let steps = plan(&local, &state, &HashMap::new(), false, false, true);
+    let announced: Vec<bool> = steps
+        .iter()
+        .map(|(_, action)| matches!(action, PostAction::Publish { announce: true }))
+        .collect();
+    assert_eq!(announced, vec![true, false, false]);
+}
+
+#[test]
+fn test_announcement_record() {
+    let marmite: crate::config::Marmite =
+        serde_yaml::from_str("name: Blog\nurl: https://blog.test/\nlanguage: pt\n").unwrap();
+    let content = ContentBuilder::new()
+        .slug("hello".to_string())
+        .title("Hello".to_string())
+        .description("First post".to_string())
+        .build();
+    let thumb = serde_json::json!({"$type": "blob", "mimeType": "image/png", "size": 3});
+    let record = announcement_record(
+        &content,
+        &marmite,
+        Some("New: {title} {url}"),
+        Some(thumb.clone()),
+    );
+    assert_eq!(record["$type"], "app.bsky.feed.post");
+    assert_eq!(record["text"], "New: Hello https://blog.test/hello.html");
+    assert_eq!(record["langs"], serde_json::json!(["pt"]));
+    let external = &record["embed"]["external"];
+    assert_eq!(external["uri"], "https://blog.test/hello.html");
+    assert_eq!(external["title"], "Hello");
+    assert_eq!(external["description"], "First post");
+    assert_eq!(external["thumb"], thumb);
+    assert_eq!(record["facets"][0]["index"]["byteStart"], 11);
+    assert_eq!(record["facets"][0]["index"]["byteEnd"], 39);
+
+    let plain = announcement_record(&content, &marmite, None, None);
+    assert_eq!(plain["text"], "Hello");
+    assert!(plain["facets"].is_null());
+    assert!(plain["embed"]["external"]["thumb"].is_null());
+}
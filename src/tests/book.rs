@@ -0,0 +1,242 @@
+use super::*;
+use crate::content::{ContentBuilder, GroupedContent, Kind};
+use chrono::NaiveDate;
+use std::io::Read;
+use tempfile::TempDir;
+
+fn chapter(slug: &str, day: u32, html: &str) -> Content {
+    ContentBuilder::new()
+        .title(format!("Part {day}"))
+        .slug(slug.to_string())
+        .html(html.to_string())
+        .series("python-tutorial".to_string())
+        .tags(vec!["Python".to_string()])
+        .authors(vec!["alice".to_string()])
+        .date(
+            NaiveDate::from_ymd_opt(2024, 1, day)
+                .unwrap()
+                .and_hms_opt(10, 0, 0)
+                .unwrap(),
+        )
+        .build()
+}
+
+fn site(config: &str) -> Data {
+    let mut data = Data::new(config, Path::new("marmite.yaml"));
+    let first = ContentBuilder::new()
+        .banner_image("media/cover.png".to_string())
+        .build();
+    let first = Content {
+        banner_image: first.banner_image,
+        ..chapter(
+            "part-1",
+            1,
+            "<h1>Getting Started</h1>\n<h2 id=\"install\">Install</h2>\n\
+             <p><img src=\"media/photo.png\" alt=\"photo\"> &nbsp;&copy; R&D</p>\n\
+             <p><a href=\"part-2.html#loops\">next</a> <a href=\"tags.html\">tags</a></p>",
+        )
+    };
+    let second = chapter(
+        "part-2",
+        2,
+        "<h2 id=\"install\">Install</h2><h3 id=\"loops\">Loops</h3><p>Line<br>break</p>",
+    );
+    let mut series = GroupedContent::new(Kind::Series);
+    series
+        .entry("python-tutorial".to_string())
+        .or_default()
+        .extend([second.clone(), first.clone()]);
+    data.series = series;
+    let mut tags = GroupedContent::new(Kind::Tag);
+    tags.entry("Python".to_string())
+        .or_default()
+        .extend([second, first]);
+    data.tag = tags;
+    data.sort_all();
+    data
+}
+
+fn media_folder() -> TempDir {
+    let temp = TempDir::new().unwrap();
+    let media = temp.path().join("media");
+    fs::create_dir_all(media.join("sub")).unwrap();
+    fs::write(media.join("photo.png"), "photo").unwrap();
+    fs::write(media.join("cover.png"), "cover").unwrap();
+    fs::create_dir_all(temp.path().join("guide/media")).unwrap();
+    fs::write(temp.path().join("guide/media/chart.svg"), "<svg/>").unwrap();
+    temp
+}
+
+#[test]
+fn test_select_series() {
+    let data = site(
+        "name: Blog\nurl: https://example.com\nauthors:\n  alice:\n    name: Alice\n\
+         series:\n  python-tutorial:\n    display_name: Learn Python\n    description: A book\n",
+    );
+    let book = select(&data, Some("Python Tutorial"), None).unwrap();
+    assert_eq!(book.title, "Learn Python");
+    assert_eq!(book.description.as_deref(), Some("A book"));
+    assert_eq!(book.slug, "python-tutorial");
+    assert_eq!(book.authors, vec!["Alice"]);
+    assert_eq!(book.publisher, "Blog");
+    assert_eq!(
+        book.identifier,
+        "https://example.com/series-python-tutorial.html"
+    );
+    assert_eq!(book.cover.as_deref(), Some("media/cover.png"));
+    let slugs: Vec<_> = book.chapters.iter().map(|c| c.slug.as_str()).collect();
+    assert_eq!(slugs, vec!["part-1", "part-2"]);
+    assert_eq!(book.modified.format("%d").to_string(), "02");
+}
+
+#[test]
+fn test_select_tag_and_errors() {
+    let data = site("name: Blog\n");
+    let book = select(&data, None, Some("python")).unwrap();
+    assert_eq!(book.title, "Python");
+    assert_eq!(book.identifier, "urn:marmite:tag:python");
+    assert_eq!(book.chapters[0].slug, "part-1");
+    assert!(select(&data, Some("missing"), None).is_err());
+    assert!(select(&data, None, None).is_err());
+}
+
+#[test]
+fn test_nested_list() {
+    let entries = vec![
+        (0, "a.xhtml".to_string(), "A".to_string()),
+        (2, "a.xhtml#x".to_string(), "X".to_string()),
+        (2, "a.xhtml#y".to_string(), "Y".to_string()),
+        (0, "b.xhtml".to_string(), "B".to_string()),
+    ];
+    assert_eq!(
+        nested_list(&entries),
+        "<ol>\n<li><a href=\"a.xhtml\">A</a>\n<ol>\n<li><a href=\"a.xhtml#x\">X</a></li>\n\
+         <li><a href=\"a.xhtml#y\">Y</a></li>\n</ol>\n</li>\n<li><a href=\"b.xhtml\">B</a></li>\n</ol>\n"
+    );
+    let entries = vec![
+        (0, "a".to_string(), "A".to_string()),
+        (3, "x".to_string(), "X".to_string()),
+        (1, "y".to_string(), "Y".to_string()),
+    ];
+    assert_eq!(
+        nested_list(&entries),
+        "<ol>\n<li><a href=\"a\">A</a>\n<ol>\n<li><a href=\"x\">X</a></li>\n\
+         <li><a href=\"y\">Y</a></li>\n</ol>\n</li>\n</ol>\n"
+    );
+    assert_eq!(nested_list(&[]), "<ol>\n</ol>\n");
+}
+
+#[test]
+fn test_to_xhtml() {
+    assert_eq!(
+        to_xhtml("<p>a<br>b &nbsp;&amp;&#169; R&D &unknown;<img src=\"x.png\" alt=\"\"/></p><hr>"),
+        "<p>a<br />b &#160;&amp;&#169; R&amp;D &amp;unknown;<img src=\"x.png\" alt=\"\" /></p><hr />"
+    );
+    assert!(xml_error(&to_xhtml("<p>a<br>b</p>")).is_none());
+    assert!(xml_error("<p><b>a</p></b>").is_some());
+}
+
+#[test]
+fn test_images() {
+    let folder = media_folder();
+    let mut images = Images::new(folder.path(), "media", "https://example.com");
+    assert_eq!(
+        images.add("media/photo.png").map(|i| i.href.clone()),
+        Some("images/photo.png".to_string())
+    );
+    assert_eq!(
+        images
+            .add("https://example.com/media/photo.png")
+            .map(|i| i.href.clone()),
+        Some("images/photo.png".to_string())
+    );
+    assert_eq!(
+        images.add("/media/guide/chart.svg").map(|i| i.media_type),
+        Some("image/svg+xml")
+    );
+    assert!(images.add("https://other.com/photo.png").is_none());
+    assert!(images.add("media/missing.png").is_none());
+    assert_eq!(images.embedded.len(), 2);
+}
+
+#[test]
+fn test_write_epub() {
+    let folder = media_folder();
+    let data = site("name: Blog\nurl: https://example.com\n");
+    let book = select(&data, Some("python-tutorial"), None).unwrap();
+    let path = folder.path().join("book.epub");
+    let mut images = Images::new(folder.path(), "media", "https://example.com");
+    write_epub(&book, &mut images, &path).unwrap();
+
+    let mut archive = zip::ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+    let names: Vec<String> = archive.file_names().map(ToString::to_string).collect();
+    assert_eq!(names[0], "mimetype");
+    assert_eq!(
+        archive.by_index(0).unwrap().compression(),
+        CompressionMethod::Stored
+    );
+    for name in [
+        "META-INF/container.xml",
+        "OEBPS/content.opf",
+        "OEBPS/nav.xhtml",
+        "OEBPS/cover.xhtml",
+        "OEBPS/chapter-1.xhtml",
+        "OEBPS/chapter-2.xhtml",
+        "OEBPS/images/photo.png",
+        "OEBPS/images/cover.png",
+    ] {
+        assert!(names.iter().any(|n| n == name), "{name} missing: {names:?}");
+    }
+    let read = |archive: &mut zip::ZipArchive<fs::File>, name: &str| {
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    };
+    let opf = read(&mut archive, "OEBPS/content.opf");
+    assert!(opf.contains("<dc:title>python-tutorial</dc:title>"));
+    assert!(opf.contains("<meta property=\"dcterms:modified\">2024-01-02T10:00:00Z</meta>"));
+    assert!(opf
+        .contains("href=\"images/cover.png\" media-type=\"image/png\" properties=\"cover-image\""));
+    assert!(opf.find("idref=\"chapter-1\"") < opf.find("idref=\"chapter-2\""));
+
+    let nav = read(&mut archive, "OEBPS/nav.xhtml");
+    assert!(nav.contains("<li><a href=\"chapter-1.xhtml\">Part 1</a>\n<ol>\n<li><a href=\"chapter-1.xhtml#install\">Install</a></li>"));
+    assert!(nav.contains("<li><a href=\"chapter-2.xhtml#loops\">Loops</a></li>"));
+    assert!(xml_error(&nav).is_none());
+
+    let first = read(&mut archive, "OEBPS/chapter-1.xhtml");
+    assert!(first.contains("<img src=\"images/photo.png\" alt=\"photo\" />"));
+    assert!(first.contains("href=\"chapter-2.xhtml#loops\""));
+    assert!(first.contains("href=\"https://example.com/tags.html\""));
+    assert!(xml_error(&first).is_none(), "{first}");
+    let second = read(&mut archive, "OEBPS/chapter-2.xhtml");
+    assert!(second.contains("<h1>Part 2</h1>"));
+}
+
+#[test]
+fn test_write_print() {
+    let folder = media_folder();
+    let data = site("name: Blog\n");
+    let book = select(&data, Some("python-tutorial"), None).unwrap();
+    let path = folder.path().join("book.html");
+    let mut images = Images::new(folder.path(), "media", "");
+    write_print(&book, &mut images, &path).unwrap();
+
+    let html = fs::read_to_string(&path).unwrap();
+    assert!(html.contains("@page"));
+    assert!(html.contains(&format!(
+        "src=\"data:image/png;base64,{}\"",
+        BASE64_STANDARD.encode("photo")
+    )));
+    assert!(html.contains("<article class=\"chapter\" id=\"part-1\">"));
+    // Headings keep unique ids across chapters
+    assert!(html.contains("id=\"part-1-install\""));
+    assert!(html.contains("id=\"part-2-install\""));
+    assert!(html.contains("<a href=\"#part-2-loops\">next</a>"));
+    assert!(html.contains("<li><a href=\"#part-2-loops\">Loops</a></li>"));
+    assert!(html.contains("<a href=\"tags.html\">tags</a>"));
+}
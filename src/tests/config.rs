@@ -17,8 +17,13 @@ fn test_cli() -> crate::cli::Cli {
         generate_config: false,
         init_site: false,
         force: false,
+        incremental: false,
         shortcodes: false,
         show_urls: false,
+        include_future: false,
+        show_scheduled: false,
+        report: None,
+        deny: Vec::new(),
         skill: false,
         skill_install: false,
         skill_install_claude: false,
@@ -52,6 +57,7 @@ fn test_cli() -> crate::cli::Cli {
             colorscheme: None,
             toc: None,
             json_feed: None,
+            atom_feed: None,
             show_next_prev_links: None,
             publish_md: None,
             source_repository: None,
@@ -59,6 +65,7 @@ fn test_cli() -> crate::cli::Cli {
             theme: None,
             build_sitemap: None,
             publish_urls_json: None,
+            json_api: None,
             enable_shortcodes: None,
             shortcode_pattern: None,
             skip_image_resize: None,
@@ -67,6 +74,7 @@ fn test_cli() -> crate::cli::Cli {
             strict_internal_links: None,
             check_media_links: None,
             native_mermaid_render: None,
+            read_only: None,
         },
         subcommand: None,
     }
@@ -325,3 +333,125 @@ mermaid_config:
         80
     );
 }
+
+#[test]
+fn test_feed_config_defaults() {
+    let config: Marmite = serde_yaml::from_str("name: Test").unwrap();
+    let settings = config.feed.settings();
+    assert_eq!(settings.items, 15);
+    assert_eq!(settings.content, FeedContent::Full);
+    assert!(settings.exclude_tags.is_empty());
+    assert!(!settings.updated);
+}
+
+#[test]
+fn test_feed_config_overrides() {
+    let config: Marmite = serde_yaml::from_str(
+        r"
+feed:
+  items: 20
+  content: summary
+  exclude_tags: [private]
+  streams:
+    newsletter:
+      items: 50
+  series:
+    tutorial:
+      content: excerpt
+      excerpt_length: 100
+  tags:
+    Rust:
+      content: full
+      exclude_tags: []
+      updated: true
+",
+    )
+    .unwrap();
+
+    let newsletter = config.feed.stream_settings("newsletter");
+    assert_eq!(newsletter.items, 50);
+    assert_eq!(newsletter.content, FeedContent::Summary);
+    assert_eq!(newsletter.exclude_tags, vec!["private".to_string()]);
+
+    let tutorial = config.feed.series_settings("tutorial");
+    assert_eq!(tutorial.items, 20);
+    assert_eq!(tutorial.content, FeedContent::Excerpt);
+    assert_eq!(tutorial.excerpt_length, 100);
+
+    // tags are matched by slug
+    let rust = config.feed.tag_settings("rust");
+    assert_eq!(rust.content, FeedContent::Full);
+    assert!(rust.exclude_tags.is_empty());
+    assert!(rust.updated);
+
+    assert_eq!(config.feed.stream_settings("index"), config.feed.settings());
+}
+
+#[test]
+fn test_taxonomy_defaults_and_overrides() {
+    let config: Marmite = serde_yaml::from_str(
+        "
+taxonomies:
+  category: {}
+  difficulty:
+    key: level
+    prefix: level
+    index: levels
+    title: Levels
+    content_title: 'Level: $term'
+    template: custom_list.html
+    feeds: false
+",
+    )
+    .unwrap();
+
+    let taxonomies = config.taxonomy_list();
+    assert_eq!(taxonomies.len(), 2);
+
+    let category = &taxonomies[0];
+    assert_eq!(category.name, "category");
+    assert_eq!(category.key, "category");
+    assert_eq!(category.prefix, "category");
+    assert_eq!(category.index, "category-index");
+    assert_eq!(category.title, "category");
+    assert_eq!(category.content_title, "$term");
+    assert_eq!(category.template, "list.html");
+    assert_eq!(category.index_template, "group.html");
+    assert!(category.feeds);
+    assert_eq!(category.term_filename("Rust Tips"), "category-rust-tips");
+
+    let difficulty = &taxonomies[1];
+    assert_eq!(difficulty.key, "level");
+    assert_eq!(difficulty.index, "levels");
+    assert_eq!(difficulty.title, "Levels");
+    assert_eq!(difficulty.template, "custom_list.html");
+    assert!(!difficulty.feeds);
+    assert_eq!(difficulty.term_filename("Hard"), "level-hard");
+}
+
+#[test]
+fn test_taxonomies_can_not_replace_built_in_pages() {
+    let validate = |taxonomies: &str| {
+        serde_yaml::from_str::<Marmite>(&format!("taxonomies:\n{taxonomies}"))
+            .unwrap()
+            .validate_taxonomies()
+    };
+    assert!(validate("  category: {}\n  level:\n    index: levels\n").is_ok());
+
+    for taxonomy in [
+        "  tag: {}\n",
+        "  topic:\n    prefix: author\n",
+        "  topic:\n    index: tags\n",
+        "  topic:\n    index: index\n",
+        "  topic:\n    prefix: ../x\n",
+        "  topic:\n    prefix: My Topic\n",
+        "  topic:\n    index: /etc/index\n",
+        "  ../x: {}\n",
+    ] {
+        assert!(validate(taxonomy).is_err(), "{taxonomy}");
+    }
+    assert_eq!(
+        validate("  topic:\n    prefix: ../x\n").unwrap_err(),
+        "taxonomy 'topic': prefix '../x' must be a slug like 'x'"
+    );
+}
@@ -221,6 +221,33 @@ fn test_get_tags_with_no_tags() {
     assert!(tags.is_empty());
 }
 
+#[test]
+fn test_get_taxonomies_from_frontmatter() {
+    let site: Marmite = serde_yaml::from_str(
+        "taxonomies:\n  category: {}\n  difficulty:\n    key: level\n  product: {}\n",
+    )
+    .unwrap();
+    let mut frontmatter = Frontmatter::new();
+    frontmatter.insert(
+        "category".to_string(),
+        Value::String("Rust, CLI".to_string()),
+    );
+    frontmatter.insert(
+        "level".to_string(),
+        Value::Array(vec![Value::String("easy".to_string())]),
+    );
+    frontmatter.insert(
+        "difficulty".to_string(),
+        Value::String("ignored".to_string()),
+    );
+
+    let taxonomies = get_taxonomies(&frontmatter, &site);
+    assert_eq!(taxonomies.len(), 2);
+    assert_eq!(taxonomies["category"], vec!["Rust", "CLI"]);
+    assert_eq!(taxonomies["difficulty"], vec!["easy"]);
+    assert!(!taxonomies.contains_key("product"));
+}
+
 #[test]
 fn test_get_tags_with_empty_str() {
     let mut frontmatter = Frontmatter::new();
@@ -239,7 +266,7 @@ fn test_get_date_from_frontmatter() {
     );
     let path = Path::new("myfile.md");
 
-    let date = get_date(&frontmatter, path).unwrap();
+    let date = get_date(&frontmatter, path).unwrap().unwrap();
     assert_eq!(
         date,
         NaiveDate::from_ymd_opt(2024, 1, 1)
@@ -255,7 +282,7 @@ fn test_get_date_from_frontmatter_without_time() {
     frontmatter.insert("date".to_string(), Value::String("2024-01-01".to_string()));
     let path = Path::new("myfile.md");
 
-    let date = get_date(&frontmatter, path).unwrap();
+    let date = get_date(&frontmatter, path).unwrap().unwrap();
     assert_eq!(
         date,
         NaiveDate::from_ymd_opt(2024, 1, 1)
@@ -270,7 +297,7 @@ fn test_get_date_from_filename() {
     let frontmatter = Frontmatter::new();
     let path = Path::new("2024-01-01-myfile.md");
 
-    let date = get_date(&frontmatter, path).unwrap();
+    let date = get_date(&frontmatter, path).unwrap().unwrap();
     assert_eq!(
         date,
         NaiveDate::from_ymd_opt(2024, 1, 1)
@@ -285,10 +312,22 @@ fn test_get_date_no_date() {
     let frontmatter = Frontmatter::new();
     let path = Path::new("myfile.md");
 
-    let date = get_date(&frontmatter, path);
+    let date = get_date(&frontmatter, path).unwrap();
     assert!(date.is_none());
 }
 
+#[test]
+fn test_get_date_invalid() {
+    let mut frontmatter = Frontmatter::new();
+    frontmatter.insert("date".to_string(), Value::String("not a date".to_string()));
+    let path = Path::new("myfile.md");
+
+    let result = get_date(&frontmatter, path);
+    assert!(
+        matches!(result, Err(Error::Frontmatter { path, .. }) if path == Path::new("myfile.md"))
+    );
+}
+
 #[test]
 fn test_slugify_simple_text() {
     let text = "Simple Text";
@@ -948,6 +987,26 @@ fn test_get_aliases_empty() {
     assert!(aliases.is_empty());
 }
 
+#[test]
+fn test_alias_output_path() {
+    assert_eq!(
+        alias_output_path("old-post"),
+        Some("old-post.html".to_string())
+    );
+    assert_eq!(alias_output_path("/old.HTML"), Some("old.HTML".to_string()));
+    assert_eq!(
+        alias_output_path("/2019/05/03/hello/"),
+        Some("2019/05/03/hello/index.html".to_string())
+    );
+    assert_eq!(
+        alias_output_path("blog/post.htm"),
+        Some("blog/post.htm".to_string())
+    );
+    assert_eq!(alias_output_path("/"), None);
+    assert_eq!(alias_output_path("../escape"), None);
+    assert_eq!(alias_output_path("a\\b"), None);
+}
+
 #[test]
 fn test_get_aliases_with_empty_string() {
     let mut frontmatter = Frontmatter::new();
@@ -2044,3 +2103,79 @@ fn test_clone_content_duplicate_slug() {
     assert!(result.is_err());
     assert!(result.unwrap_err().contains("already exists"));
 }
+
+#[test]
+fn test_get_schedule_date_from_frontmatter() {
+    let mut frontmatter = Frontmatter::new();
+    frontmatter.insert(
+        "publish_at".to_string(),
+        Value::String("2030-05-01 09:30".to_string()),
+    );
+    let path = Path::new("myfile.md");
+
+    assert_eq!(
+        get_schedule_date(&frontmatter, "publish_at", path).unwrap(),
+        NaiveDate::from_ymd_opt(2030, 5, 1)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+    );
+    assert_eq!(
+        get_schedule_date(&frontmatter, "expires_at", path).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_get_schedule_date_invalid() {
+    let mut frontmatter = Frontmatter::new();
+    frontmatter.insert(
+        "publish_at".to_string(),
+        Value::String("next monday".to_string()),
+    );
+    let path = Path::new("myfile.md");
+
+    let result = get_schedule_date(&frontmatter, "publish_at", path);
+    assert!(
+        matches!(result, Err(Error::Frontmatter { message, .. }) if message.contains("publish_at"))
+    );
+}
+
+#[test]
+fn test_content_schedule() {
+    let date = |day| {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    };
+    let now = date(10);
+
+    let past = ContentBuilder::new().date(date(1)).build();
+    assert_eq!(past.schedule(now), Schedule::Published);
+
+    let page = ContentBuilder::new().build();
+    assert_eq!(page.schedule(now), Schedule::Published);
+
+    let future = ContentBuilder::new().date(date(20)).build();
+    assert_eq!(future.schedule(now), Schedule::Scheduled(date(20)));
+
+    // publish_at takes precedence over the date
+    let embargoed = ContentBuilder::new()
+        .date(date(1))
+        .publish_at(date(15))
+        .build();
+    assert_eq!(embargoed.schedule(now), Schedule::Scheduled(date(15)));
+    let released = ContentBuilder::new()
+        .date(date(20))
+        .publish_at(date(5))
+        .build();
+    assert_eq!(released.schedule(now), Schedule::Published);
+
+    let expired = ContentBuilder::new()
+        .date(date(1))
+        .expires_at(date(10))
+        .build();
+    assert_eq!(expired.schedule(now), Schedule::Expired(date(10)));
+    let expiring = ContentBuilder::new().expires_at(date(11)).build();
+    assert_eq!(expiring.schedule(now), Schedule::Published);
+}
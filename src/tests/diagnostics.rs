@@ -0,0 +1,232 @@
+use super::*;
+use tempfile::TempDir;
+
+fn broken_link(target: &str) -> Warning {
+    Warning::BrokenInternalLink {
+        source: "post".to_string(),
+        target: target.to_string(),
+    }
+}
+
+#[test]
+fn test_diagnostic_finds_line() {
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("post.md");
+    fs::write(&file, "# Post\n\nSee [other](other.html)\n").unwrap();
+
+    let diagnostic = Diagnostic::new(broken_link("other"), Some(&file));
+    assert_eq!(diagnostic.line, Some(3));
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!(
+        diagnostic.to_string(),
+        format!(
+            "{}:3: Broken internal link in \"post.html\": \"other.html\" does not exist",
+            file.display()
+        )
+    );
+
+    let not_found = Diagnostic::new(broken_link("missing"), Some(&file));
+    assert_eq!(not_found.line, None);
+
+    let without_file = Diagnostic::new(broken_link("other"), None);
+    assert_eq!(without_file.line, None);
+    assert!(without_file.to_string().starts_with("Broken internal link"));
+}
+
+#[test]
+fn test_media_diagnostic_matches_file_name() {
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("post.md");
+    fs::write(&file, "# Post\n\n![photo](@/photo.png)\n").unwrap();
+
+    let warning = Warning::BrokenMediaLink {
+        source: "post".to_string(),
+        target: "media/post/photo.png".to_string(),
+    };
+    assert_eq!(Diagnostic::new(warning, Some(&file)).line, Some(3));
+}
+
+#[test]
+fn test_diagnostic_line_comes_from_the_markdown_nodes() {
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("post.md");
+    fs::write(
+        &file,
+        "---\ntitle: other.html\ntranslations:\n  pt: post-pt\n---\n\
+        Talking about other.html and the .youtube shortcode\n\n\
+        ```\n[other](other.html)\n<!-- .youtube id=1 -->\n```\n\n\
+        See [other](./other.html#intro) and [site](https://example.com/#top)\n\n\
+        <!-- .youtube id=1 -->\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        Diagnostic::new(broken_link("other"), Some(&file)).line,
+        Some(13)
+    );
+    let external = Warning::BrokenExternalLink {
+        source: "post".to_string(),
+        target: "https://example.com/".to_string(),
+        status: Some(404),
+        error: None,
+    };
+    assert_eq!(Diagnostic::new(external, Some(&file)).line, Some(13));
+    let shortcode = Warning::BrokenShortcode {
+        source: Some("post".to_string()),
+        name: "youtube".to_string(),
+        message: "missing id".to_string(),
+    };
+    assert_eq!(Diagnostic::new(shortcode, Some(&file)).line, Some(15));
+    let translation = Warning::MissingTranslation {
+        source: "post".to_string(),
+        target: "post-pt".to_string(),
+    };
+    assert_eq!(Diagnostic::new(translation, Some(&file)).line, Some(4));
+
+    let mentioned_only = temp.path().join("mentioned.md");
+    fs::write(
+        &mentioned_only,
+        "Rename other.html soon\n\n    [other](other.html)\n",
+    )
+    .unwrap();
+    assert_eq!(
+        Diagnostic::new(broken_link("other"), Some(&mentioned_only)).line,
+        None
+    );
+}
+
+#[test]
+fn test_diagnostic_serializes_code_and_fields() {
+    let diagnostic = Diagnostic::new(
+        Warning::BrokenShortcode {
+            source: Some("post".to_string()),
+            name: "youtube".to_string(),
+            message: "missing id".to_string(),
+        },
+        None,
+    );
+    let json = serde_json::to_value(&diagnostic).unwrap();
+    assert_eq!(json["code"], "broken-shortcode");
+    assert_eq!(json["name"], "youtube");
+    assert_eq!(json["severity"], "warning");
+    assert_eq!(json["line"], serde_json::Value::Null);
+    assert_eq!(
+        json["message"],
+        "Shortcode 'youtube' failed to render: missing id"
+    );
+}
+
+#[test]
+fn test_every_warning_code_can_be_denied() {
+    let warnings = [
+        broken_link("x"),
+        Warning::BrokenMediaLink {
+            source: String::new(),
+            target: String::new(),
+        },
+        Warning::SlugCollision {
+            slug: String::new(),
+        },
+        Warning::MissingTranslation {
+            source: String::new(),
+            target: String::new(),
+        },
+        Warning::BrokenShortcode {
+            source: None,
+            name: String::new(),
+            message: String::new(),
+        },
+        Warning::InvalidFrontmatter {
+            source: String::new(),
+            message: String::new(),
+        },
+        Warning::TemplateError {
+            template: None,
+            message: String::new(),
+        },
+        Warning::BrokenExternalLink {
+            source: String::new(),
+            target: String::new(),
+            status: None,
+            error: None,
+        },
+        Warning::Lint {
+            rule: String::new(),
+            detail: String::new(),
+        },
+    ];
+    assert_eq!(DENY_CODES.len(), warnings.len() + 1);
+    for warning in warnings {
+        assert!(DENY_CODES.contains(&warning.code()));
+        let json = serde_json::to_value(&warning).unwrap();
+        assert_eq!(json["code"], warning.code());
+    }
+}
+
+#[test]
+fn test_apply_deny() {
+    let mut diagnostics = vec![
+        Diagnostic::new(broken_link("a"), None),
+        Diagnostic::new(
+            Warning::SlugCollision {
+                slug: "a".to_string(),
+            },
+            None,
+        ),
+    ];
+
+    assert!(apply_deny(&mut diagnostics, &[]).is_empty());
+
+    let denied = apply_deny(&mut diagnostics, &["slug-collision".to_string()]);
+    assert_eq!(denied.len(), 1);
+    assert_eq!(denied[0].code(), "slug-collision");
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[1].severity, Severity::Error);
+
+    let report = Report::new(&diagnostics);
+    assert_eq!((report.warnings, report.errors), (1, 1));
+
+    let denied = apply_deny(&mut diagnostics, &["warnings".to_string()]);
+    assert_eq!(denied.len(), 2);
+}
+
+#[test]
+fn test_diagnostic_with_line() {
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("post.md");
+    fs::write(&file, "---\ntitle: [\n---\n").unwrap();
+
+    let warning = Warning::InvalidFrontmatter {
+        source: file.display().to_string(),
+        message: "YAML parse error at line 1".to_string(),
+    };
+    let diagnostic = Diagnostic::new(warning.clone(), Some(&file)).with_line(Some(2));
+    assert_eq!(diagnostic.line, Some(2));
+    assert_eq!(diagnostic.code(), "invalid-frontmatter");
+    assert!(diagnostic.to_string().ends_with(&format!(
+        ":2: Invalid frontmatter in \"{}\": YAML parse error at line 1",
+        file.display()
+    )));
+    assert_eq!(
+        Diagnostic::new(warning, Some(&file)).with_line(None).line,
+        None
+    );
+}
+
+#[test]
+fn test_template_error_display() {
+    let located = Warning::TemplateError {
+        template: Some("content.html".to_string()),
+        message: "Variable `missing` not found".to_string(),
+    };
+    assert_eq!(located.code(), "template-error");
+    assert_eq!(
+        located.to_string(),
+        "Template 'content.html' failed to render: Variable `missing` not found"
+    );
+    let unknown = Warning::TemplateError {
+        template: None,
+        message: "boom".to_string(),
+    };
+    assert_eq!(unknown.to_string(), "Templates failed to render: boom");
+}
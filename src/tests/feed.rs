@@ -1,4 +1,5 @@
 use super::*;
+use crate::config::FeedConfig;
 use crate::content::ContentBuilder;
 use std::path::PathBuf;
 
@@ -29,7 +30,13 @@ fn test_generate_json() {
     let (contents, output_path, config) = setup_test_environment();
     let filename = "test_feed";
 
-    let result = generate_json(&contents, &output_path, filename, &config);
+    let result = generate_json(
+        &contents,
+        &output_path,
+        filename,
+        &config,
+        &config.feed.settings(),
+    );
     assert!(result.is_ok());
 
     let feed_path = output_path.join(format!("{filename}.json"));
@@ -47,7 +54,9 @@ fn test_generate_json() {
     let content = &contents[0];
     assert_eq!(item.title, content.title);
     assert_eq!(item.url, format!("{}/{}.html", config.url, content.slug));
-    assert_eq!(item.content_html, content.html);
+    assert_eq!(item.content_html, Some(content.html.clone()));
+    assert!(item.content_text.is_none());
+    assert!(item.date_modified.is_none());
     assert_eq!(item.summary, content.description.clone().unwrap());
     let date_format = "%Y-%m-%dT%H:%M:%S-00:00"; // Loose RFC3339 format
     assert_eq!(
@@ -65,7 +74,13 @@ fn test_generate_rss() {
     let (contents, output_path, config) = setup_test_environment();
     let filename = "test_rss";
 
-    let result = generate_rss(&contents, &output_path, filename, &config);
+    let result = generate_rss(
+        &contents,
+        &output_path,
+        filename,
+        &config,
+        &config.feed.settings(),
+    );
     assert!(result.is_ok());
 
     // Check that the RSS file was created
@@ -94,7 +109,13 @@ fn test_generate_rss_with_https_config() {
     config.https = Some(true);
     let filename = "test_rss_https";
 
-    let result = generate_rss(&contents, &output_path, filename, &config);
+    let result = generate_rss(
+        &contents,
+        &output_path,
+        filename,
+        &config,
+        &config.feed.settings(),
+    );
     assert!(result.is_ok());
 
     let feed_path = output_path.join(format!("{filename}.rss"));
@@ -109,7 +130,13 @@ fn test_generate_rss_with_http_config() {
     config.https = Some(false);
     let filename = "test_rss_http";
 
-    let result = generate_rss(&contents, &output_path, filename, &config);
+    let result = generate_rss(
+        &contents,
+        &output_path,
+        filename,
+        &config,
+        &config.feed.settings(),
+    );
     assert!(result.is_ok());
 
     let feed_path = output_path.join(format!("{filename}.rss"));
@@ -123,7 +150,13 @@ fn test_generate_rss_with_full_url() {
     config.url = "https://fullurl.com".to_string();
     let filename = "test_rss_full_url";
 
-    let result = generate_rss(&contents, &output_path, filename, &config);
+    let result = generate_rss(
+        &contents,
+        &output_path,
+        filename,
+        &config,
+        &config.feed.settings(),
+    );
     assert!(result.is_ok());
 
     let feed_path = output_path.join(format!("{filename}.rss"));
@@ -137,7 +170,13 @@ fn test_generate_rss_with_card_image() {
     config.card_image = "site-image.png".to_string();
     let filename = "test_rss_image";
 
-    let result = generate_rss(&contents, &output_path, filename, &config);
+    let result = generate_rss(
+        &contents,
+        &output_path,
+        filename,
+        &config,
+        &config.feed.settings(),
+    );
     assert!(result.is_ok());
 
     let feed_path = output_path.join(format!("{filename}.rss"));
@@ -183,7 +222,13 @@ fn test_generate_feeds_filter_draft_content() {
     let config = Marmite::new();
 
     // Test RSS feed filtering
-    let rss_result = generate_rss(&contents, output_path, "test_filter", &config);
+    let rss_result = generate_rss(
+        &contents,
+        output_path,
+        "test_filter",
+        &config,
+        &config.feed.settings(),
+    );
     assert!(rss_result.is_ok());
 
     let rss_path = output_path.join("test_filter.rss");
@@ -195,7 +240,13 @@ fn test_generate_feeds_filter_draft_content() {
     assert_eq!(channel.items()[0].title(), Some("Published Post"));
 
     // Test JSON feed filtering
-    let json_result = generate_json(&contents, output_path, "test_filter_json", &config);
+    let json_result = generate_json(
+        &contents,
+        output_path,
+        "test_filter_json",
+        &config,
+        &config.feed.settings(),
+    );
     assert!(json_result.is_ok());
 
     let json_path = output_path.join("test_filter_json.json");
@@ -243,7 +294,13 @@ fn test_generate_json_with_authors_config() {
 
     config.authors.insert("alice".to_string(), alice_author);
 
-    let result = generate_json(&contents, output_path, "test_authors", &config);
+    let result = generate_json(
+        &contents,
+        output_path,
+        "test_authors",
+        &config,
+        &config.feed.settings(),
+    );
     assert!(result.is_ok());
 
     let json_path = output_path.join("test_authors.json");
@@ -275,7 +332,13 @@ fn test_generate_feeds_empty_content() {
     let config = Marmite::new();
 
     // Test RSS with empty content
-    let rss_result = generate_rss(&contents, output_path, "empty_rss", &config);
+    let rss_result = generate_rss(
+        &contents,
+        output_path,
+        "empty_rss",
+        &config,
+        &config.feed.settings(),
+    );
     assert!(rss_result.is_ok());
 
     let rss_path = output_path.join("empty_rss.rss");
@@ -284,7 +347,13 @@ fn test_generate_feeds_empty_content() {
     assert_eq!(channel.items().len(), 0);
 
     // Test JSON with empty content
-    let json_result = generate_json(&contents, output_path, "empty_json", &config);
+    let json_result = generate_json(
+        &contents,
+        output_path,
+        "empty_json",
+        &config,
+        &config.feed.settings(),
+    );
     assert!(json_result.is_ok());
 
     let json_path = output_path.join("empty_json.json");
@@ -292,3 +361,321 @@ fn test_generate_feeds_empty_content() {
     let feed: JsonFeed = serde_json::from_str(&json_content).expect("Failed to parse JSON");
     assert_eq!(feed.items.len(), 0);
 }
+
+#[test]
+fn test_generate_atom() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path();
+    let (contents, _, mut config) = setup_test_environment();
+    config.url = "https://example.com".to_string();
+    config.tagline = "A tagline".to_string();
+    config.card_image = "site-image.png".to_string();
+
+    let result = generate_atom(
+        &contents,
+        output_path,
+        "index",
+        &config,
+        &config.feed.settings(),
+    );
+    assert!(result.is_ok());
+
+    let feed_path = output_path.join("index.atom");
+    let atom_content = std::fs::read_to_string(feed_path).expect("Failed to read Atom feed");
+    let feed = atom_syndication::Feed::read_from(atom_content.as_bytes())
+        .expect("Failed to parse Atom feed");
+
+    assert_eq!(feed.title().as_str(), config.name);
+    assert_eq!(feed.id(), "https://example.com/index.atom");
+    assert_eq!(feed.subtitle().unwrap().as_str(), "A tagline");
+    assert_eq!(feed.logo(), Some("https://example.com/site-image.png"));
+    assert_eq!(feed.lang(), Some("en"));
+    assert!(feed
+        .links()
+        .iter()
+        .any(|link| link.rel() == "self" && link.href() == "https://example.com/index.atom"));
+    assert_eq!(feed.entries().len(), contents.len());
+
+    let entry = &feed.entries()[0];
+    let content = &contents[0];
+    assert_eq!(entry.title().as_str(), content.title);
+    assert_eq!(entry.id(), "https://example.com/test-title.html");
+    assert_eq!(
+        entry.links()[0].href(),
+        "https://example.com/test-title.html"
+    );
+    assert_eq!(entry.summary().unwrap().as_str(), "Test Description");
+    assert_eq!(
+        entry.content().unwrap().value(),
+        Some("<p>Test Content</p>")
+    );
+    assert_eq!(entry.content().unwrap().lang(), Some("en"));
+    assert_eq!(
+        entry.published().unwrap().to_rfc3339(),
+        "2021-01-01T00:00:00+00:00"
+    );
+    assert_eq!(entry.updated(), entry.published().unwrap());
+    assert_eq!(feed.updated(), entry.updated());
+    assert_eq!(entry.authors()[0].name(), "rochacbruno");
+    let terms: Vec<&str> = entry.categories().iter().map(|c| c.term()).collect();
+    assert_eq!(terms, vec!["tag1", "tag2"]);
+}
+
+#[test]
+fn test_generate_atom_with_authors_config() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path();
+    let contents = vec![ContentBuilder::new()
+        .title("Test Post".to_string())
+        .slug("test".to_string())
+        .html("<p>Test content</p>".to_string())
+        .date(
+            chrono::NaiveDateTime::parse_from_str("2021-01-01 00:00:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap(),
+        )
+        .authors(vec!["alice".to_string(), "unknown".to_string()])
+        .build()];
+
+    let mut config = Marmite::new();
+    config.default_author = "alice".to_string();
+    config.authors.insert(
+        "alice".to_string(),
+        crate::config::Author {
+            name: "Alice Smith".to_string(),
+            bio: None,
+            links: Some(vec![(
+                "website".to_string(),
+                "https://alice.example.com".to_string(),
+            )]),
+            avatar: None,
+        },
+    );
+
+    generate_atom(
+        &contents,
+        output_path,
+        "authors",
+        &config,
+        &config.feed.settings(),
+    )
+    .unwrap();
+
+    let atom_content = std::fs::read_to_string(output_path.join("authors.atom")).unwrap();
+    let feed = atom_syndication::Feed::read_from(atom_content.as_bytes()).unwrap();
+
+    assert_eq!(feed.authors()[0].name(), "Alice Smith");
+    let authors = feed.entries()[0].authors();
+    assert_eq!(authors.len(), 2);
+    assert_eq!(authors[0].name(), "Alice Smith");
+    assert_eq!(authors[0].uri(), Some("https://alice.example.com"));
+    assert_eq!(authors[1].name(), "unknown");
+    assert_eq!(authors[1].uri(), None);
+}
+
+#[test]
+fn test_generate_atom_filters_drafts_and_content_language() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path();
+    let mut translated = ContentBuilder::new()
+        .title("Publicado".to_string())
+        .slug("publicado".to_string())
+        .html("<p>Conteúdo</p>".to_string())
+        .date(
+            chrono::NaiveDateTime::parse_from_str("2021-01-01 00:00:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap(),
+        )
+        .build();
+    translated.language = Some("pt".to_string());
+    let contents = vec![
+        translated,
+        ContentBuilder::new()
+            .title("Draft Post".to_string())
+            .slug("draft".to_string())
+            .html("<p>Draft content</p>".to_string())
+            .date(
+                chrono::NaiveDateTime::parse_from_str("2021-01-02 00:00:00", "%Y-%m-%d %H:%M:%S")
+                    .unwrap(),
+            )
+            .stream("draft".to_string())
+            .build(),
+        ContentBuilder::new()
+            .title("Undated".to_string())
+            .slug("undated".to_string())
+            .build(),
+    ];
+
+    generate_atom(
+        &contents,
+        output_path,
+        "filtered",
+        &Marmite::new(),
+        &FeedConfig::default().settings(),
+    )
+    .unwrap();
+
+    let atom_content = std::fs::read_to_string(output_path.join("filtered.atom")).unwrap();
+    let feed = atom_syndication::Feed::read_from(atom_content.as_bytes()).unwrap();
+    assert_eq!(feed.entries().len(), 1);
+    assert_eq!(feed.entries()[0].title().as_str(), "Publicado");
+    assert_eq!(feed.entries()[0].content().unwrap().lang(), Some("pt"));
+    // the feed author covers entries without authors
+    assert_eq!(feed.authors()[0].name(), Marmite::new().name);
+}
+
+fn dated_post(slug: &str, day: u32, tags: &[&str]) -> Content {
+    ContentBuilder::new()
+        .title(format!("Post {slug}"))
+        .slug(slug.to_string())
+        .html(format!(
+            "<p>The <strong>body</strong> of {slug} with some more words</p>"
+        ))
+        .date(
+            chrono::NaiveDate::from_ymd_opt(2021, 1, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        )
+        .tags(tags.iter().map(ToString::to_string).collect())
+        .build()
+}
+
+#[test]
+fn test_feed_items_and_exclude_tags() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path();
+    let mut contents: Vec<Content> = (1..=25)
+        .rev()
+        .map(|day| dated_post(&format!("post-{day}"), day, &[]))
+        .collect();
+    contents[0].tags = vec!["Private".to_string()];
+    let settings = FeedSettings {
+        items: 20,
+        exclude_tags: vec!["private".to_string()],
+        ..FeedConfig::default().settings()
+    };
+
+    generate_rss(&contents, output_path, "index", &Marmite::new(), &settings).unwrap();
+
+    let channel =
+        rss::Channel::read_from(&std::fs::read(output_path.join("index.rss")).unwrap()[..])
+            .unwrap();
+    assert_eq!(channel.items().len(), 20);
+    // excluded tags are matched by slug
+    assert_eq!(channel.items()[0].title(), Some("Post post-24"));
+}
+
+#[test]
+fn test_feed_summary_and_excerpt_content() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path();
+    let mut described = dated_post("described", 2, &[]);
+    described.description = Some("A short description".to_string());
+    let contents = vec![described, dated_post("plain", 1, &[])];
+    let mut config = Marmite::new();
+    config.url = "https://example.com".to_string();
+
+    let summary = FeedSettings {
+        content: FeedContent::Summary,
+        ..config.feed.settings()
+    };
+    generate_json(&contents, output_path, "summary", &config, &summary).unwrap();
+    let feed: JsonFeed =
+        serde_json::from_str(&std::fs::read_to_string(output_path.join("summary.json")).unwrap())
+            .unwrap();
+    assert!(feed.items.iter().all(|item| item.content_html.is_none()));
+    assert_eq!(
+        feed.items[0].content_text.as_deref(),
+        Some("A short description")
+    );
+    // without a description the summary falls back to the excerpt
+    assert_eq!(
+        feed.items[1].content_text.as_deref(),
+        Some("The body of plain with some more words")
+    );
+
+    let excerpt = FeedSettings {
+        content: FeedContent::Excerpt,
+        excerpt_length: 20,
+        ..config.feed.settings()
+    };
+    generate_rss(&contents, output_path, "excerpt", &config, &excerpt).unwrap();
+    let channel =
+        rss::Channel::read_from(&std::fs::read(output_path.join("excerpt.rss")).unwrap()[..])
+            .unwrap();
+    assert_eq!(channel.items()[0].description(), Some("The body of..."));
+    assert!(channel.items()[0].content().is_none());
+
+    generate_atom(&contents, output_path, "excerpt", &config, &excerpt).unwrap();
+    let atom_content = std::fs::read_to_string(output_path.join("excerpt.atom")).unwrap();
+    let atom = atom_syndication::Feed::read_from(atom_content.as_bytes()).unwrap();
+    assert!(atom.entries()[0].content().is_none());
+    assert_eq!(
+        atom.entries()[0].summary().unwrap().as_str(),
+        "The body of..."
+    );
+}
+
+#[test]
+fn test_feed_updated_from_modified_time() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path();
+    let mut edited = dated_post("edited", 1, &[]);
+    // 2021-03-01 00:00:00 UTC
+    edited.modified_time = Some(1_614_556_800);
+    let mut older_file = dated_post("older-file", 2, &[]);
+    // 2020-01-01, before the date of the post
+    older_file.modified_time = Some(1_577_836_800);
+    let contents = vec![older_file, edited];
+    let config = Marmite::new();
+    let settings = FeedSettings {
+        updated: true,
+        ..config.feed.settings()
+    };
+
+    generate_json(&contents, output_path, "updated", &config, &settings).unwrap();
+    let feed: JsonFeed =
+        serde_json::from_str(&std::fs::read_to_string(output_path.join("updated.json")).unwrap())
+            .unwrap();
+    assert_eq!(
+        feed.items[0].date_modified.as_deref(),
+        Some("2021-01-02T00:00:00-00:00")
+    );
+    assert_eq!(
+        feed.items[1].date_modified.as_deref(),
+        Some("2021-03-01T00:00:00-00:00")
+    );
+
+    generate_atom(&contents, output_path, "updated", &config, &settings).unwrap();
+    let atom_content = std::fs::read_to_string(output_path.join("updated.atom")).unwrap();
+    let atom = atom_syndication::Feed::read_from(atom_content.as_bytes()).unwrap();
+    assert_eq!(
+        atom.entries()[1].updated().to_rfc3339(),
+        "2021-03-01T00:00:00+00:00"
+    );
+    assert_eq!(
+        atom.entries()[1].published().unwrap().to_rfc3339(),
+        "2021-01-01T00:00:00+00:00"
+    );
+    assert_eq!(atom.updated().to_rfc3339(), "2021-03-01T00:00:00+00:00");
+
+    generate_rss(&contents, output_path, "updated", &config, &settings).unwrap();
+    let channel =
+        rss::Channel::read_from(&std::fs::read(output_path.join("updated.rss")).unwrap()[..])
+            .unwrap();
+    assert_eq!(
+        channel.last_build_date(),
+        Some("Mon, 01 Mar 2021 00:00:00 GMT")
+    );
+}
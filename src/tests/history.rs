@@ -0,0 +1,192 @@
+use super::*;
+use tempfile::TempDir;
+
+fn write_post(input: &Path, content: &str) -> PathBuf {
+    let file = input.join("content").join("post.md");
+    fs::create_dir_all(file.parent().unwrap()).unwrap();
+    fs::write(&file, content).unwrap();
+    file
+}
+
+#[test]
+fn test_record_and_read_revision() {
+    let temp = TempDir::new().unwrap();
+    let file = write_post(temp.path(), "# First\n");
+
+    let revision = record(temp.path(), "post", &file, "edit", 10)
+        .unwrap()
+        .unwrap();
+    assert_eq!(revision.slug, "post");
+    assert_eq!(revision.action, "edit");
+    assert_eq!(revision.path, "content/post.md");
+    assert_eq!(revision.size, 8);
+    assert!(revision.id.ends_with("-edit"));
+
+    let (read_revision, content) = read(temp.path(), "post", &revision.id).unwrap();
+    assert_eq!(read_revision, revision);
+    assert_eq!(content, "# First\n");
+}
+
+#[test]
+fn test_list_is_newest_first_and_pruned() {
+    let temp = TempDir::new().unwrap();
+    let file = write_post(temp.path(), "v1\n");
+
+    let mut ids = Vec::new();
+    for version in 1..=4 {
+        fs::write(&file, format!("v{version}\n")).unwrap();
+        let revision = record(temp.path(), "post", &file, "edit", 3)
+            .unwrap()
+            .unwrap();
+        ids.push(revision.id);
+    }
+
+    let revisions = list(temp.path(), "post");
+    let listed: Vec<&str> = revisions.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(listed, vec![&ids[3], &ids[2], &ids[1]]);
+    assert!(read(temp.path(), "post", &ids[0]).is_err());
+    assert_eq!(read(temp.path(), "post", &ids[3]).unwrap().1, "v4\n");
+}
+
+#[test]
+fn test_record_disabled_with_zero_limit() {
+    let temp = TempDir::new().unwrap();
+    let file = write_post(temp.path(), "text\n");
+
+    assert!(record(temp.path(), "post", &file, "edit", 0)
+        .unwrap()
+        .is_none());
+    assert!(list(temp.path(), "post").is_empty());
+    assert!(!temp.path().join(HISTORY_DIR).exists());
+}
+
+#[test]
+fn test_unsafe_names_are_rejected() {
+    let temp = TempDir::new().unwrap();
+    let file = write_post(temp.path(), "text\n");
+
+    assert!(record(temp.path(), "../post", &file, "edit", 10).is_err());
+    assert!(read(temp.path(), "post", "../../content/post").is_err());
+    assert!(read(temp.path(), "post", ".hidden").is_err());
+    assert!(list(temp.path(), "..").is_empty());
+}
+
+#[test]
+fn test_original_path_stays_in_input_folder() {
+    let temp = TempDir::new().unwrap();
+    let mut revision = Revision {
+        id: "1-delete".to_string(),
+        slug: "post".to_string(),
+        action: "delete".to_string(),
+        path: "content/post.md".to_string(),
+        timestamp: 1,
+        size: 0,
+    };
+    assert_eq!(
+        original_path(temp.path(), &revision).unwrap(),
+        temp.path().join("content/post.md")
+    );
+
+    revision.path = "../outside.md".to_string();
+    assert!(original_path(temp.path(), &revision).is_err());
+    revision.path = "/etc/passwd".to_string();
+    assert!(original_path(temp.path(), &revision).is_err());
+}
+
+#[test]
+fn test_is_metadata_path() {
+    assert!(is_metadata_path(Path::new(
+        "site/.marmite/history/post/1.json"
+    )));
+    assert!(is_metadata_path(Path::new("site/.git/index")));
+    assert!(!is_metadata_path(Path::new("site/content/post.md")));
+    assert!(!is_metadata_path(Path::new("site/content/.gitkeep")));
+    assert!(is_metadata_path(Path::new(
+        "site/.marmite-build-graph.json"
+    )));
+    assert!(!is_metadata_path(Path::new(
+        "site/content/.marmite-notes.md/post.md"
+    )));
+}
+
+#[test]
+fn test_unified_diff_of_equal_texts_is_empty() {
+    assert_eq!(unified_diff("a\nb\n", "a\nb\n", "old", "new"), "");
+}
+
+#[test]
+fn test_unified_diff_changed_line() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+    assert_eq!(
+        unified_diff(old, new, "a/post.md", "b/post.md"),
+        "--- a/post.md\n+++ b/post.md\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+    );
+}
+
+#[test]
+fn test_unified_diff_separate_hunks() {
+    let old: String = (1..=20).map(|n| format!("{n}\n")).collect();
+    let new: String = (1..=20)
+        .filter(|n| *n != 18)
+        .map(|n| {
+            if n == 2 {
+                "two\n".to_string()
+            } else {
+                format!("{n}\n")
+            }
+        })
+        .collect();
+    let diff = unified_diff(&old, &new, "old", "new");
+    assert!(diff.contains("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n"));
+    assert!(diff.contains("@@ -15,6 +15,5 @@\n 15\n 16\n 17\n-18\n 19\n 20\n"));
+}
+
+#[test]
+fn test_unified_diff_from_and_to_empty() {
+    assert_eq!(
+        unified_diff("", "new\n", "old", "new"),
+        "--- old\n+++ new\n@@ -0,0 +1,1 @@\n+new\n"
+    );
+    assert_eq!(
+        unified_diff("gone\n", "", "old", "new"),
+        "--- old\n+++ new\n@@ -1,1 +0,0 @@\n-gone\n"
+    );
+}
+
+#[test]
+fn test_commit_only_given_paths() {
+    let temp = TempDir::new().unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(temp.path())
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.name", "Marmite"]);
+    git(&["config", "user.email", "marmite@example.com"]);
+
+    let file = write_post(temp.path(), "text\n");
+    fs::write(temp.path().join("other.md"), "other\n").unwrap();
+    commit(temp.path(), &[&file], "Create post").unwrap();
+
+    let log = Command::new("git")
+        .arg("-C")
+        .arg(temp.path())
+        .args(["log", "--format=%s", "--name-only"])
+        .output()
+        .unwrap();
+    let log = String::from_utf8_lossy(&log.stdout);
+    assert!(log.contains("Create post"));
+    assert!(log.contains("content/post.md"));
+    assert!(!log.contains("other.md"));
+
+    fs::remove_file(&file).unwrap();
+    commit(temp.path(), &[&file], "Delete post").unwrap();
+    assert!(commit(temp.path(), &[&file], "Nothing to commit").is_err());
+}
@@ -424,3 +424,210 @@ fn test_process_media_images_banner_from_frontmatter() {
     let resized_img = image::open(media_path.join("hero.jpg")).unwrap();
     assert!(resized_img.width() >= 1023 && resized_img.width() <= 1024);
 }
+
+fn responsive_config(widths: &[u64], formats: &[&str]) -> Marmite {
+    let mut config = Marmite::new();
+    let mut extra = HashMap::new();
+    extra.insert(
+        "responsive_image_widths".to_string(),
+        Value::Sequence(widths.iter().map(|w| Value::Number((*w).into())).collect()),
+    );
+    extra.insert(
+        "responsive_image_formats".to_string(),
+        Value::Sequence(
+            formats
+                .iter()
+                .map(|f| Value::String((*f).to_string()))
+                .collect(),
+        ),
+    );
+    config.extra = Some(extra);
+    config
+}
+
+#[test]
+fn test_get_responsive_settings() {
+    assert!(get_responsive_settings(&Marmite::new()).is_none());
+
+    let settings =
+        get_responsive_settings(&responsive_config(&[960, 480, 0, 480], &["WebP", "gif"])).unwrap();
+    assert_eq!(settings.widths, vec![480, 960]);
+    assert_eq!(settings.formats, vec!["webp".to_string()]);
+
+    // formats default to AVIF and WebP
+    let mut config = responsive_config(&[480], &[]);
+    config
+        .extra
+        .as_mut()
+        .unwrap()
+        .remove("responsive_image_formats");
+    let settings = get_responsive_settings(&config).unwrap();
+    assert_eq!(
+        settings.formats,
+        vec!["avif".to_string(), "webp".to_string()]
+    );
+
+    assert!(get_responsive_settings(&responsive_config(&[0], &["webp"])).is_none());
+}
+
+#[test]
+fn test_split_media_src() {
+    assert_eq!(
+        split_media_src("media/cat.jpg", "media"),
+        Some(("media/", "cat.jpg"))
+    );
+    assert_eq!(
+        split_media_src("../media/photos/cat.jpg", "media"),
+        Some(("../media/", "photos/cat.jpg"))
+    );
+    assert_eq!(
+        split_media_src("/blog/media/cat.jpg", "media"),
+        None,
+        "only paths starting at the media folder are matched"
+    );
+    assert_eq!(
+        split_media_src("https://example.com/media/cat.jpg", "media"),
+        None
+    );
+    assert_eq!(split_media_src("media/cat.jpg?v=1", "media"), None);
+    assert_eq!(split_media_src("images/cat.jpg", "media"), None);
+}
+
+#[test]
+fn test_collect_responsive_images() {
+    let temp_dir = TempDir::new().unwrap();
+    let media_path = temp_dir.path();
+    fs::create_dir_all(media_path.join("photos")).unwrap();
+
+    let img: ImageBuffer<Rgb<u8>, Vec<u8>> = ImageBuffer::new(1200, 800);
+    img.save(media_path.join("photos/large.jpg")).unwrap();
+    img.save(media_path.join("hero.png")).unwrap();
+    let small: ImageBuffer<Rgb<u8>, Vec<u8>> = ImageBuffer::new(300, 100);
+    small.save(media_path.join("small.webp")).unwrap();
+    small.save(media_path.join("animation.gif")).unwrap();
+
+    let mut config = responsive_config(&[400, 1000], &["avif", "webp"]);
+    let extra = config.extra.as_mut().unwrap();
+    extra.insert("max_image_width".to_string(), Value::Number(800.into()));
+    extra.insert("banner_image_width".to_string(), Value::Number(1100.into()));
+    let banner_paths = HashSet::from(["hero.png".to_string()]);
+
+    let images = collect_responsive_images(media_path, &config, &banner_paths);
+    assert_eq!(images.len(), 3, "GIFs are left out");
+
+    let large = &images["photos/large.jpg"];
+    assert_eq!((large.width, large.height), (800, 533));
+    assert_eq!(large.widths, vec![400, 800]);
+    assert_eq!(
+        large.variants(),
+        vec![
+            (400, "jpg".to_string()),
+            (400, "avif".to_string()),
+            (800, "avif".to_string()),
+            (400, "webp".to_string()),
+            (800, "webp".to_string()),
+        ]
+    );
+    assert_eq!(
+        large.variant_path(400, "avif"),
+        "_responsive/photos/large-400w.avif"
+    );
+
+    let hero = &images["hero.png"];
+    assert_eq!(hero.widths, vec![400, 1000, 1100]);
+
+    let small = &images["small.webp"];
+    assert_eq!(small.widths, vec![300]);
+    assert_eq!(small.formats, vec!["avif".to_string()]);
+}
+
+#[test]
+fn test_rewrite_img_tags() {
+    let images = HashMap::from([(
+        "cat.jpg".to_string(),
+        ResponsiveImage {
+            path: "cat.jpg".to_string(),
+            width: 800,
+            height: 600,
+            widths: vec![400, 800],
+            formats: vec!["avif".to_string()],
+        },
+    )]);
+    let html = r#"<p><img src="media/cat.jpg" alt="Cat" /></p>
+<p><img src="media/dog.jpg" alt="Dog"></p>
+<p><img src="https://example.com/media/cat.jpg"></p>
+<p><img src="media/cat.jpg" srcset="media/cat.jpg 1x"></p>"#;
+
+    let rewritten = rewrite_img_tags(html, &images, "media");
+    let lines: Vec<&str> = rewritten.lines().collect();
+    assert_eq!(
+        lines[0],
+        concat!(
+            r#"<p><picture><source type="image/avif" srcset="media/_responsive/cat-400w.avif 400w, media/_responsive/cat-800w.avif 800w" sizes="(max-width: 800px) 100vw, 800px">"#,
+            r#"<img src="media/cat.jpg" alt="Cat" srcset="media/_responsive/cat-400w.jpg 400w, media/cat.jpg 800w" sizes="(max-width: 800px) 100vw, 800px" width="800" height="600" /></picture></p>"#
+        )
+    );
+    assert_eq!(&lines[1..], &html.lines().collect::<Vec<_>>()[1..]);
+
+    // explicit dimensions are kept
+    let sized = rewrite_img_tags(
+        r#"<img src="./media/cat.jpg" width="200">"#,
+        &images,
+        "media",
+    );
+    assert!(sized.contains(r#"srcset="./media/_responsive/cat-400w.avif 400w"#));
+    assert!(!sized.contains(r#"width="800""#));
+}
+
+#[test]
+fn test_process_responsive_images() {
+    let temp_dir = TempDir::new().unwrap();
+    let source = temp_dir.path().join("source");
+    let output = temp_dir.path().join("output");
+    fs::create_dir_all(&source).unwrap();
+    fs::create_dir_all(&output).unwrap();
+
+    let img: ImageBuffer<Rgb<u8>, Vec<u8>> = ImageBuffer::new(40, 20);
+    img.save(source.join("pic.png")).unwrap();
+
+    let config = responsive_config(&[10], &["webp"]);
+    let images = collect_responsive_images(&source, &config, &HashSet::new());
+    process_responsive_images(&source, &output, &config, &images);
+
+    let variant = output.join("_responsive/pic-10w.png");
+    assert_eq!(image::open(&variant).unwrap().dimensions(), (10, 5));
+    assert_eq!(
+        image::open(output.join("_responsive/pic-10w.webp"))
+            .unwrap()
+            .dimensions(),
+        (10, 5)
+    );
+    assert_eq!(
+        image::open(output.join("_responsive/pic-40w.webp"))
+            .unwrap()
+            .dimensions(),
+        (40, 20)
+    );
+    assert!(output.join(RESPONSIVE_STATE_FILE_NAME).exists());
+
+    // unchanged images are not processed again
+    fs::write(&variant, b"cached").unwrap();
+    process_responsive_images(&source, &output, &config, &images);
+    assert_eq!(fs::read(&variant).unwrap(), b"cached");
+
+    // missing variants are generated again
+    fs::remove_file(output.join("_responsive/pic-10w.webp")).unwrap();
+    process_responsive_images(&source, &output, &config, &images);
+    assert!(output.join("_responsive/pic-10w.webp").exists());
+    assert_eq!(image::open(&variant).unwrap().dimensions(), (10, 5));
+}
+
+#[test]
+fn test_save_variant_avif() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("nested/pic.avif");
+    let img = DynamicImage::ImageRgb8(ImageBuffer::new(8, 4));
+
+    save_variant(&img, &path, "avif").unwrap();
+    assert!(fs::metadata(&path).unwrap().len() > 0);
+}
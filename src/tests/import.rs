@@ -0,0 +1,450 @@
+use super::*;
+use tempfile::TempDir;
+
+const WXR: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0"
+    xmlns:excerpt="http://wordpress.org/export/1.2/excerpt/"
+    xmlns:content="http://purl.org/rss/1.0/modules/content/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+    <title>Old Blog</title>
+    <link>https://old.example.com</link>
+    <wp:base_site_url>https://old.example.com</wp:base_site_url>
+    <item>
+        <title>Tom &amp; Jerry&#8217;s day</title>
+        <link>https://old.example.com/2019/05/03/tom-and-jerry/</link>
+        <dc:creator><![CDATA[alice]]></dc:creator>
+        <content:encoded><![CDATA[<!-- wp:paragraph -->
+<p>Hello <a href="https://old.example.com/about/">about</a></p>
+<!-- /wp:paragraph -->
+<img src="https://old.example.com/wp-content/uploads/2019/05/cat.jpg" srcset="https://old.example.com/wp-content/uploads/2019/05/cat-300x200.jpg 300w" alt="cat" />]]></content:encoded>
+        <excerpt:encoded><![CDATA[<p>A short <b>excerpt</b></p>]]></excerpt:encoded>
+        <wp:post_id>10</wp:post_id>
+        <wp:post_date><![CDATA[2019-05-03 10:20:30]]></wp:post_date>
+        <wp:post_name><![CDATA[tom-and-jerry]]></wp:post_name>
+        <wp:status><![CDATA[publish]]></wp:status>
+        <wp:post_type><![CDATA[post]]></wp:post_type>
+        <category domain="category" nicename="uncategorized"><![CDATA[Uncategorized]]></category>
+        <category domain="category" nicename="news"><![CDATA[News]]></category>
+        <category domain="post_tag" nicename="cartoons"><![CDATA[Cartoons]]></category>
+        <wp:postmeta>
+            <wp:meta_key><![CDATA[_thumbnail_id]]></wp:meta_key>
+            <wp:meta_value><![CDATA[20]]></wp:meta_value>
+        </wp:postmeta>
+    </item>
+    <item>
+        <title>About</title>
+        <link>https://old.example.com/about/</link>
+        <content:encoded><![CDATA[About me]]></content:encoded>
+        <wp:post_id>11</wp:post_id>
+        <wp:post_name><![CDATA[about]]></wp:post_name>
+        <wp:status><![CDATA[publish]]></wp:status>
+        <wp:post_type><![CDATA[page]]></wp:post_type>
+    </item>
+    <item>
+        <title>Unfinished</title>
+        <link>https://old.example.com/?p=12</link>
+        <content:encoded><![CDATA[Soon]]></content:encoded>
+        <wp:post_id>12</wp:post_id>
+        <wp:post_date><![CDATA[0000-00-00 00:00:00]]></wp:post_date>
+        <wp:post_name><![CDATA[]]></wp:post_name>
+        <wp:status><![CDATA[draft]]></wp:status>
+        <wp:post_type><![CDATA[post]]></wp:post_type>
+    </item>
+    <item>
+        <title>Deleted</title>
+        <wp:post_id>13</wp:post_id>
+        <wp:status><![CDATA[trash]]></wp:status>
+        <wp:post_type><![CDATA[post]]></wp:post_type>
+    </item>
+    <item>
+        <title>cover</title>
+        <wp:post_id>20</wp:post_id>
+        <wp:post_type><![CDATA[attachment]]></wp:post_type>
+        <wp:status><![CDATA[inherit]]></wp:status>
+        <wp:attachment_url><![CDATA[https://old.example.com/wp-content/uploads/2019/05/cover.png]]></wp:attachment_url>
+    </item>
+    <item>
+        <title>Home</title>
+        <wp:post_id>30</wp:post_id>
+        <wp:post_type><![CDATA[nav_menu_item]]></wp:post_type>
+        <wp:status><![CDATA[publish]]></wp:status>
+    </item>
+</channel>
+</rss>"#;
+
+fn write(path: &Path, text: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+#[test]
+fn test_parse_wxr() {
+    let contents = parse_wxr(WXR).unwrap();
+    assert_eq!(contents.len(), 3);
+
+    let post = &contents[0];
+    assert_eq!(post.title, "Tom & Jerry\u{2019}s day");
+    assert_eq!(post.slug, "tom-and-jerry");
+    assert_eq!(
+        post.date,
+        parse_date("2019-05-03 10:20:30"),
+        "post_date is used"
+    );
+    assert_eq!(post.tags, vec!["Cartoons", "News"]);
+    assert_eq!(post.authors, vec!["alice"]);
+    assert_eq!(post.description.as_deref(), Some("A short excerpt"));
+    assert_eq!(
+        post.card_image.as_deref(),
+        Some("https://old.example.com/wp-content/uploads/2019/05/cover.png")
+    );
+    assert_eq!(post.aliases, vec!["/2019/05/03/tom-and-jerry/"]);
+    assert_eq!(
+        post.media_base,
+        MediaBase::Remote("https://old.example.com".to_string())
+    );
+    assert!(!post.body.contains("wp:paragraph"));
+    assert!(!post.body.contains("srcset"));
+
+    let page = &contents[1];
+    assert_eq!(page.slug, "about");
+    assert_eq!(page.date, None);
+    assert_eq!(page.stream, None);
+
+    let draft = &contents[2];
+    assert_eq!(draft.slug, "unfinished");
+    assert_eq!(draft.stream.as_deref(), Some("draft"));
+    assert!(draft.date.is_some());
+    assert!(draft.aliases.is_empty(), "Query URLs are not aliases");
+}
+
+#[test]
+fn test_parse_wxr_rejects_invalid_xml() {
+    assert!(parse_wxr("<rss><channel><item></channel>").is_err());
+}
+
+#[test]
+fn test_convert_wordpress() {
+    let html = "<!-- wp:paragraph -->\n<p>Intro</p>\n<!-- /wp:paragraph -->\n\n\n\n\
+        [caption id=\"a\" align=\"alignnone\"]<img src=\"a.jpg\" sizes=\"100vw\" /> A cat[/caption]\n\
+        [code lang=\"rust\"]\nfn main() -&gt; () {}\n[/code]";
+    assert_eq!(
+        convert_wordpress(html),
+        "<p>Intro</p>\n\n<img src=\"a.jpg\" /> A cat\n\n```rust\nfn main() -> () {}\n```"
+    );
+}
+
+#[test]
+fn test_convert_liquid() {
+    let body = "{% highlight ruby linenos %}\nputs 1\n{% endhighlight %}\n\
+        [next]({% post_url 2019-01-02-Next-Post %})\n\
+        ![img]({{ site.baseurl }}/assets/a.png)\n\
+        ![img]({{ \"/assets/b.png\" | relative_url }})\n\
+        {% raw %}{{ kept }}{% endraw %}";
+    assert_eq!(
+        convert_liquid(body),
+        "```ruby\nputs 1\n```\n[next](next-post.html)\n![img](/assets/a.png)\n\
+        ![img](/assets/b.png)\n{{ kept }}"
+    );
+}
+
+#[test]
+fn test_convert_hugo_shortcodes() {
+    let body = "{{< figure src=\"cat.jpg\" caption=\"A cat\" >}}\n\
+        {{< highlight go \"linenos=table\" >}}\nfmt.Println()\n{{< /highlight >}}\n\
+        [a]({{< ref \"posts/first-post.md\" >}}) [b]({{< relref \"/posts/bundle/index.md#intro\" >}})";
+    assert_eq!(
+        convert_hugo_shortcodes(body),
+        "![A cat](cat.jpg)\n```go\nfmt.Println()\n```\n\
+        [a](first-post.html) [b](bundle.html#intro)"
+    );
+}
+
+#[test]
+fn test_expand_permalink() {
+    let mut vars = date_vars(&parse_date("2024-03-05 08:00:00").unwrap());
+    vars.insert("title", "my-post".to_string());
+    vars.insert("categories", String::new());
+    vars.insert("output_ext", ".html".to_string());
+    assert_eq!(
+        expand_permalink(jekyll_permalink("date"), &vars),
+        "/2024/03/05/my-post.html"
+    );
+    assert_eq!(
+        expand_permalink("/:year/:i_month/:title/:unknown/", &vars),
+        "/2024/3/my-post/:unknown/"
+    );
+}
+
+#[test]
+fn test_read_jekyll() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write(
+        &root.join("_config.yml"),
+        "title: Blog\npermalink: pretty\n",
+    );
+    write(
+        &root.join("_posts/2020-02-03-hello-world.md"),
+        "---\nlayout: post\ntitle: Hello\ncategories: [Life, Code]\ntags: ruby jekyll\n\
+        author: bob\nredirect_from: /old-hello/\nimage: /assets/hello.png\n---\nHi there\n",
+    );
+    write(
+        &root.join("_posts/2020-02-04-secret.md"),
+        "---\npublished: false\n---\nSecret\n",
+    );
+    write(
+        &root.join("_drafts/idea.md"),
+        "---\ntitle: Idea\n---\nSome day\n",
+    );
+    write(
+        &root.join("about.md"),
+        "---\ntitle: About\npermalink: /about-me/\n---\nMe\n",
+    );
+    write(&root.join("docs/guide.md"), "---\n---\nGuide\n");
+    write(&root.join("README.md"), "# Readme\n");
+    write(&root.join("index.md"), "---\nlayout: home\n---\n");
+    write(&root.join("notes.md"), "No frontmatter, not a page\n");
+    write(&root.join("_site/copy.md"), "---\n---\nGenerated\n");
+
+    let contents = read_jekyll(root).unwrap();
+    let slugs: Vec<&str> = contents.iter().map(|c| c.slug.as_str()).collect();
+    assert_eq!(
+        slugs,
+        vec!["hello-world", "secret", "idea", "about", "guide"]
+    );
+
+    let post = &contents[0];
+    assert_eq!(post.title, "Hello");
+    assert_eq!(post.date, parse_date("2020-02-03"));
+    assert_eq!(post.tags, vec!["ruby", "jekyll", "Life", "Code"]);
+    assert_eq!(post.authors, vec!["bob"]);
+    assert_eq!(post.card_image.as_deref(), Some("/assets/hello.png"));
+    assert_eq!(
+        post.aliases,
+        vec!["/life/code/2020/02/03/hello-world/", "/old-hello/"]
+    );
+    assert_eq!(post.body, "Hi there\n");
+
+    assert_eq!(contents[1].stream.as_deref(), Some("draft"));
+    assert_eq!(contents[2].stream.as_deref(), Some("draft"));
+    assert_eq!(contents[3].date, None);
+    assert_eq!(contents[3].aliases, vec!["/about-me/"]);
+    assert_eq!(contents[4].title, "Guide");
+    assert_eq!(contents[4].aliases, vec!["/docs/guide.html"]);
+}
+
+#[test]
+fn test_read_jekyll_requires_posts_folder() {
+    let temp = TempDir::new().unwrap();
+    assert!(read_jekyll(temp.path()).is_err());
+}
+
+#[test]
+fn test_read_hugo() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    write(
+        &root.join("hugo.toml"),
+        "title = \"Blog\"\n[permalinks]\nposts = \"/:year/:month/:slug/\"\n",
+    );
+    write(&root.join("content/_index.md"), "---\ntitle: Home\n---\n");
+    write(
+        &root.join("content/posts/first.md"),
+        "+++\ntitle = \"First Post\"\ndate = 2021-06-07T10:00:00-03:00\ntags = [\"go\"]\n\
+        categories = [\"dev\"]\nseries = [\"Learning\"]\naliases = [\"/old/first/\"]\n+++\nBody\n",
+    );
+    write(
+        &root.join("content/notes/bundle/index.md"),
+        "---\ntitle: Bundle\ndate: 2021-06-08\ndraft: true\nimages: [cover.jpg]\n---\n\
+        {{< figure src=\"photo.jpg\" alt=\"Photo\" >}}\n",
+    );
+    write(
+        &root.join("content/about.md"),
+        "---\ntitle: About\n---\nMe\n",
+    );
+
+    let contents = read_hugo(root).unwrap();
+    let slugs: Vec<&str> = contents.iter().map(|c| c.slug.as_str()).collect();
+    assert_eq!(slugs, vec!["about", "bundle", "first"]);
+
+    let about = &contents[0];
+    assert_eq!(about.date, None);
+    assert_eq!(about.aliases, vec!["/about/"]);
+
+    let bundle = &contents[1];
+    assert_eq!(bundle.stream.as_deref(), Some("draft"));
+    assert_eq!(bundle.card_image.as_deref(), Some("cover.jpg"));
+    assert_eq!(bundle.aliases, vec!["/notes/bundle/"]);
+    assert_eq!(bundle.body.trim(), "![Photo](photo.jpg)");
+    assert_eq!(
+        bundle.media_base,
+        MediaBase::Local {
+            site: root.to_path_buf(),
+            root: root.join("static"),
+            dir: root.join("content/notes/bundle"),
+        }
+    );
+
+    let first = &contents[2];
+    assert_eq!(first.title, "First Post");
+    assert_eq!(first.date, parse_date("2021-06-07 10:00:00"));
+    assert_eq!(first.tags, vec!["go", "dev"]);
+    assert_eq!(first.series.as_deref(), Some("Learning"));
+    assert_eq!(first.aliases, vec!["/2021/06/first-post/", "/old/first/"]);
+
+    // The content folder can be given directly
+    assert_eq!(read_hugo(&root.join("content")).unwrap().len(), 3);
+}
+
+#[test]
+fn test_media_references_and_rewrite() {
+    let body = "![a](media.png) <img src=\"/img/a.png\"> [doc](/files/doc.pdf?x=1) \
+        [page](/about/) <a href='https://example.com/b.JPG'>b</a> ![a again](media.png)";
+    assert_eq!(
+        media_references(body),
+        vec![
+            "media.png",
+            "/img/a.png",
+            "/files/doc.pdf?x=1",
+            "https://example.com/b.JPG"
+        ]
+    );
+
+    let replacements = HashMap::from([
+        ("/img/a.png".to_string(), "media/post/a.png".to_string()),
+        ("a.png".to_string(), "wrong".to_string()),
+    ]);
+    assert_eq!(
+        rewrite_references("<img src=\"/img/a.png\"> ![x](a.png)", &replacements),
+        "<img src=\"media/post/a.png\"> ![x](wrong)"
+    );
+}
+
+#[test]
+fn test_import_local_media() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("source");
+    write(&source.join("static/img/logo.png"), "logo");
+    write(&source.join("post/photo one.jpg"), "photo");
+    write(&source.join("post/other/photo-one.jpg"), "another photo");
+    let content_folder = temp.path().join("site/content");
+
+    let mut content = Imported {
+        title: "Post".to_string(),
+        slug: "post".to_string(),
+        date: None,
+        description: None,
+        tags: Vec::new(),
+        authors: Vec::new(),
+        series: None,
+        stream: None,
+        card_image: Some("/img/logo.png".to_string()),
+        aliases: Vec::new(),
+        link: None,
+        body: "![a](photo%20one.jpg) ![b](other/photo-one.jpg) ![c](/img/logo.png) \
+            ![d](missing.png) ![e](https://example.com/x.png)"
+            .to_string(),
+        media_base: MediaBase::Local {
+            site: source.clone(),
+            root: source.join("static"),
+            dir: source.join("post"),
+        },
+    };
+    assert_eq!(
+        import_media(&mut content.clone(), &content_folder, "media", true),
+        3
+    );
+    assert!(!content_folder.exists(), "Dry runs write nothing");
+
+    assert_eq!(
+        import_media(&mut content, &content_folder, "media", false),
+        3
+    );
+    assert_eq!(
+        content.body,
+        "![a](media/post/photo-one.jpg) ![b](media/post/photo-one-2.jpg) \
+        ![c](media/post/logo.png) ![d](missing.png) ![e](https://example.com/x.png)"
+    );
+    assert_eq!(content.card_image.as_deref(), Some("media/post/logo.png"));
+    assert_eq!(
+        fs::read_to_string(content_folder.join("media/post/photo-one-2.jpg")).unwrap(),
+        "another photo"
+    );
+}
+
+#[test]
+fn test_import_local_media_stays_inside_the_site() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("source");
+    write(&temp.path().join("secret.png"), "secret");
+    write(&source.join("post/photo.jpg"), "photo");
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(temp.path().join("secret.png"), source.join("post/link.png"))
+        .unwrap();
+    let content_folder = temp.path().join("site/content");
+
+    let body = "![a](photo.jpg) ![b](../../secret.png) ![c](/../secret.png) ![d](link.png)";
+    let mut content = Imported {
+        title: "Post".to_string(),
+        slug: "post".to_string(),
+        date: None,
+        description: None,
+        tags: Vec::new(),
+        authors: Vec::new(),
+        series: None,
+        stream: None,
+        card_image: None,
+        aliases: Vec::new(),
+        link: None,
+        body: body.to_string(),
+        media_base: MediaBase::Local {
+            site: source.clone(),
+            root: source.join("static"),
+            dir: source.join("post"),
+        },
+    };
+    assert_eq!(
+        import_media(&mut content, &content_folder, "media", false),
+        1
+    );
+    assert_eq!(
+        content.body,
+        "![a](media/post/photo.jpg) ![b](../../secret.png) ![c](/../secret.png) ![d](link.png)"
+    );
+    assert!(!content_folder.join("media/post/secret.png").exists());
+    assert!(!content_folder.join("media/post/link.png").exists());
+}
+
+#[test]
+fn test_prepare_and_to_markdown() {
+    let mut contents = parse_wxr(WXR).unwrap();
+    let mut copy = contents[0].clone();
+    copy.link = None;
+    contents.push(copy);
+    prepare(&mut contents, Some("blog"));
+
+    assert_eq!(contents[0].stream.as_deref(), Some("blog"));
+    assert_eq!(contents[1].stream, None, "Pages keep the default stream");
+    assert_eq!(contents[2].stream.as_deref(), Some("draft"));
+    assert_eq!(contents[3].slug, "tom-and-jerry-2");
+    assert!(contents[0].body.contains("href=\"about.html\""));
+    // `/about/` is written as `about/index.html`, not the page itself
+    assert_eq!(contents[1].aliases, vec!["/about/"]);
+
+    let markdown = contents[0].to_markdown();
+    let (frontmatter, body) = parse_front_matter(&markdown).unwrap();
+    assert_eq!(
+        text(&frontmatter, "title").unwrap(),
+        "Tom & Jerry\u{2019}s day"
+    );
+    assert_eq!(text(&frontmatter, "date").unwrap(), "2019-05-03 10:20:30");
+    assert_eq!(text(&frontmatter, "stream").unwrap(), "blog");
+    assert_eq!(
+        terms(&frontmatter, "aliases", |c| c == ','),
+        vec!["/2019/05/03/tom-and-jerry/"]
+    );
+    assert!(frontmatter.get("series").is_none());
+    assert!(body.trim_start().starts_with("<p>Hello"));
+}
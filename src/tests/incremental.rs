@@ -0,0 +1,209 @@
+use super::*;
+use crate::content::ContentBuilder;
+use tempfile::TempDir;
+
+fn post(slug: &str, html: &str, tags: &[&str]) -> Content {
+    ContentBuilder::new()
+        .title(slug.to_string())
+        .slug(slug.to_string())
+        .html(html.to_string())
+        .tags(tags.iter().map(ToString::to_string).collect())
+        .date(
+            chrono::NaiveDateTime::parse_from_str("2024-01-01 00:00:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap(),
+        )
+        .build()
+}
+
+fn site_data(posts: Vec<Content>) -> Data {
+    let mut data = Data::new("name: Test", Path::new("marmite.yaml"));
+    for content in &posts {
+        data.stream
+            .entry("index".to_string())
+            .or_default()
+            .push(content.clone());
+        for tag in &content.tags {
+            data.tag
+                .entry(tag.clone())
+                .or_default()
+                .push(content.clone());
+        }
+    }
+    data.posts = posts;
+    data
+}
+
+fn build_with_previous(previous: &Data) -> IncrementalBuild {
+    let graph = BuildGraph {
+        contents: content_hashes(previous),
+        outputs: output_dependencies(previous),
+        ..Default::default()
+    };
+    IncrementalBuild {
+        previous: Some(graph.clone()),
+        graph,
+        sources: SourceCache::default(),
+    }
+}
+
+#[test]
+fn test_sha256_hex() {
+    assert_eq!(
+        sha256_hex(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn test_is_content_source() {
+    let content = Path::new("/site/content");
+    assert!(is_content_source(
+        Path::new("/site/content/post.md"),
+        content
+    ));
+    assert!(is_content_source(
+        Path::new("/site/content/posts/post.md"),
+        content
+    ));
+    assert!(!is_content_source(
+        Path::new("/site/content/_hero.md"),
+        content
+    ));
+    assert!(!is_content_source(
+        Path::new("/site/content/media/image.png"),
+        content
+    ));
+    assert!(!is_content_source(Path::new("/site/README.md"), content));
+}
+
+#[test]
+fn test_plan_without_previous_graph_renders_everything() {
+    let data = site_data(vec![post("one", "<p>one</p>", &[])]);
+    let mut build = IncrementalBuild {
+        previous: None,
+        graph: BuildGraph::default(),
+        sources: SourceCache::default(),
+    };
+    assert!(build.plan(&data).is_none());
+}
+
+#[test]
+fn test_plan_unchanged_content_renders_nothing() {
+    let data = site_data(vec![post("one", "<p>one</p>", &["rust"])]);
+    let mut build = build_with_previous(&data);
+    assert_eq!(build.plan(&data), Some(HashSet::new()));
+}
+
+#[test]
+fn test_plan_body_change_renders_dependent_outputs() {
+    let previous = site_data(vec![
+        post("one", "<p>one</p>", &["rust"]),
+        post("two", "<p>two</p>", &["python"]),
+    ]);
+    let mut build = build_with_previous(&previous);
+
+    let current = site_data(vec![
+        post("one", "<p>one changed</p>", &["rust"]),
+        post("two", "<p>two</p>", &["python"]),
+    ]);
+    let render = build.plan(&current).unwrap();
+
+    assert!(render.contains("one"));
+    assert!(render.contains("index"));
+    assert!(render.contains("tag-rust"));
+    assert!(!render.contains("two"));
+    assert!(!render.contains("tag-python"));
+}
+
+#[test]
+fn test_plan_metadata_change_renders_everything() {
+    let previous = site_data(vec![post("one", "<p>one</p>", &["rust"])]);
+    let mut build = build_with_previous(&previous);
+
+    let current = site_data(vec![post("one", "<p>one</p>", &["rust", "python"])]);
+    assert!(build.plan(&current).is_none());
+}
+
+#[test]
+fn test_plan_new_content_renders_everything() {
+    let previous = site_data(vec![post("one", "<p>one</p>", &[])]);
+    let mut build = build_with_previous(&previous);
+
+    let current = site_data(vec![
+        post("one", "<p>one</p>", &[]),
+        post("two", "<p>two</p>", &[]),
+    ]);
+    assert!(build.plan(&current).is_none());
+}
+
+#[test]
+fn test_plan_changed_fingerprint_renders_everything() {
+    let data = site_data(vec![post("one", "<p>one</p>", &[])]);
+    let mut build = build_with_previous(&data);
+    build.graph.render_fingerprint = "templates changed".to_string();
+    assert!(build.plan(&data).is_none());
+}
+
+#[test]
+fn test_source_cache_requires_same_hash_and_mtime() {
+    let path = Path::new("/site/content/one.md");
+    let mut previous = BTreeMap::new();
+    previous.insert(
+        path.to_string_lossy().to_string(),
+        SourceEntry {
+            hash: "abc".to_string(),
+            modified_time: Some(1),
+            content: post("one", "<p>one</p>", &[]),
+        },
+    );
+    let cache = SourceCache {
+        previous,
+        ..Default::default()
+    };
+
+    assert_eq!(cache.get(path, "abc", Some(1)).unwrap().slug, "one");
+    assert!(cache.get(path, "abc", Some(2)).is_none());
+    assert!(cache.get(path, "def", Some(1)).is_none());
+    assert!(cache
+        .get(Path::new("/site/content/two.md"), "abc", Some(1))
+        .is_none());
+}
+
+#[test]
+fn test_load_discards_graph_from_another_build() {
+    let temp = TempDir::new().unwrap();
+    let input = temp.path();
+    let output = input.join("site");
+    fs::create_dir_all(&output).unwrap();
+    let site = Marmite::new();
+
+    let build = IncrementalBuild::load(input, &output, input, &site, Some("first"));
+    assert!(build.previous.is_none());
+    build.save(input, Some("first".to_string()));
+    assert!(input.join(GRAPH_FILE).exists());
+
+    let build = IncrementalBuild::load(input, &output, input, &site, Some("first"));
+    assert!(build.previous.is_some());
+
+    let build = IncrementalBuild::load(input, &output, input, &site, Some("second"));
+    assert!(build.previous.is_none());
+}
+
+#[test]
+fn test_folder_stamps_skip_hidden_files() {
+    let temp = TempDir::new().unwrap();
+    let folder = temp.path();
+    fs::create_dir_all(folder.join("media")).unwrap();
+    fs::create_dir_all(folder.join(".git")).unwrap();
+    fs::write(folder.join("media").join("photo.png"), "png").unwrap();
+    fs::write(folder.join(".git").join("HEAD"), "ref").unwrap();
+    fs::write(folder.join(".marmite-audit.log"), "{}").unwrap();
+    fs::write(folder.join("media").join(".DS_Store"), "").unwrap();
+
+    let stamps = folder_stamps(folder, &folder.join("content"), &[]);
+    assert_eq!(stamps.len(), 1);
+    assert!(stamps[0].starts_with(&format!(
+        "{}:",
+        Path::new("media").join("photo.png").display()
+    )));
+}
@@ -0,0 +1,148 @@
+use super::*;
+use crate::content::{ContentBuilder, Kind};
+use chrono::NaiveDate;
+use tempfile::TempDir;
+
+fn post(slug: &str, day: u32, tags: &[&str]) -> Content {
+    ContentBuilder::new()
+        .title(format!("Post {slug}"))
+        .slug(slug.to_string())
+        .html(format!("<p>{slug}</p>"))
+        .tags(tags.iter().map(ToString::to_string).collect())
+        .date(
+            NaiveDate::from_ymd_opt(2024, 1, day)
+                .unwrap()
+                .and_hms_opt(10, 0, 0)
+                .unwrap(),
+        )
+        .build()
+}
+
+fn site(config: &str) -> Data {
+    let mut data = Data::new(config, Path::new("marmite.yaml"));
+    data.posts = vec![
+        post("third", 3, &["Rust"]),
+        post("second", 2, &["Rust", "web"]),
+        post("first", 1, &[]),
+    ];
+    let mut tags = GroupedContent::new(Kind::Tag);
+    tags.entry("Rust".to_string())
+        .or_default()
+        .extend([data.posts[0].clone(), data.posts[1].clone()]);
+    tags.entry("rust".to_string())
+        .or_default()
+        .extend([data.posts[0].clone(), data.posts[1].clone()]);
+    tags.entry("web".to_string())
+        .or_default()
+        .push(data.posts[1].clone());
+    data.tag = tags;
+    data
+}
+
+fn read(dir: &Path, path: &str) -> Value {
+    serde_json::from_str(&fs::read_to_string(dir.join(path)).unwrap()).unwrap()
+}
+
+#[test]
+fn test_generate_paginated_posts() {
+    let temp = TempDir::new().unwrap();
+    let data = site("name: Site\njson_api:\n  enabled: true\n  per_page: 2\n");
+    generate_json_api(&data, temp.path(), "");
+
+    let first = read(temp.path(), "api/posts/page/1.json");
+    assert_eq!(first["version"], SCHEMA_VERSION);
+    assert_eq!(first["total_pages"], 2);
+    assert_eq!(first["total_items"], 3);
+    assert_eq!(first["previous"], Value::Null);
+    assert_eq!(first["next"], "/api/posts/page/2.json");
+    assert_eq!(first["items"][0]["slug"], "third");
+    assert_eq!(first["items"][0]["kind"], "post");
+    assert_eq!(first["items"][0]["date"], "2024-01-03T10:00:00");
+    assert_eq!(first["items"][0]["url"], "/third.html");
+    assert_eq!(first["items"][0]["api_url"], "/api/posts/third.json");
+    assert!(first["items"][0].get("html").is_none());
+
+    let second = read(temp.path(), "api/posts/page/2.json");
+    assert_eq!(second["previous"], "/api/posts/page/1.json");
+    assert_eq!(second["items"].as_array().unwrap().len(), 1);
+
+    let pages = read(temp.path(), "api/pages/page/1.json");
+    assert_eq!(pages["total_items"], 0);
+    assert_eq!(pages["items"], serde_json::json!([]));
+}
+
+#[test]
+fn test_generate_content_detail_with_markdown() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("post.md");
+    fs::write(&source, "---\ntitle: Post\n---\n\n# Hello\n\nWorld\n").unwrap();
+    let mut data = site("name: Site\nurl: https://example.com\njson_api:\n  enabled: true\n");
+    data.posts[0].source_path = Some(source);
+    data.posts[0].previous = Some(Box::new(data.posts[1].clone()));
+    let output = temp.path().join("site");
+    generate_json_api(&data, &output, "");
+
+    let detail = read(&output, "api/posts/third.json");
+    assert_eq!(detail["version"], SCHEMA_VERSION);
+    assert_eq!(detail["title"], "Post third");
+    assert_eq!(detail["html"], "<p>third</p>");
+    assert_eq!(detail["markdown"], "# Hello\n\nWorld\n");
+    assert_eq!(detail["url"], "https://example.com/third.html");
+    assert_eq!(detail["previous"], "second");
+    assert_eq!(detail["next"], Value::Null);
+
+    let index = read(&output, "api/index.json");
+    assert_eq!(index["site"]["name"], "Site");
+    assert_eq!(index["counts"]["posts"], 3);
+    assert_eq!(
+        index["endpoints"]["tags"],
+        "https://example.com/api/tags.json"
+    );
+}
+
+#[test]
+fn test_generate_groups_keep_written_names() {
+    let temp = TempDir::new().unwrap();
+    let data = site("name: Site\njson_api:\n  enabled: true\n  path: data/v1\n");
+    generate_json_api(&data, temp.path(), "blog");
+
+    let tags = read(temp.path(), "data/v1/tags.json");
+    let items = tags["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["name"], "Rust");
+    assert_eq!(items[0]["slug"], "rust");
+    assert_eq!(items[0]["count"], 2);
+    assert_eq!(items[0]["url"], "/blog/tag-rust.html");
+    assert_eq!(items[0]["api_url"], "/blog/data/v1/tags/rust.json");
+
+    let rust = read(temp.path(), "data/v1/tags/rust.json");
+    assert_eq!(rust["name"], "Rust");
+    assert_eq!(rust["items"][1]["slug"], "second");
+    assert_eq!(
+        read(temp.path(), "data/v1/series.json")["items"],
+        serde_json::json!([])
+    );
+}
+
+#[test]
+fn test_generate_replaces_previous_files() {
+    let temp = TempDir::new().unwrap();
+    let mut data = site("name: Site\njson_api:\n  enabled: true\n");
+    generate_json_api(&data, temp.path(), "");
+    assert!(temp.path().join("api/posts/first.json").exists());
+
+    data.posts.pop();
+    generate_json_api(&data, temp.path(), "");
+    assert!(!temp.path().join("api/posts/first.json").exists());
+    assert!(temp.path().join("api/posts/second.json").exists());
+}
+
+#[test]
+fn test_generate_refuses_paths_outside_output() {
+    let temp = TempDir::new().unwrap();
+    let output = temp.path().join("site");
+    fs::create_dir_all(&output).unwrap();
+    let data = site("name: Site\njson_api:\n  enabled: true\n  path: ../outside\n");
+    generate_json_api(&data, &output, "");
+    assert!(!temp.path().join("outside").exists());
+}
@@ -16,8 +16,13 @@ fn create_test_cli(overrides: impl FnOnce(&mut cli::Cli)) -> cli::Cli {
         generate_config: false,
         init_site: false,
         force: false,
+        incremental: false,
         shortcodes: false,
         show_urls: false,
+        include_future: false,
+        show_scheduled: false,
+        report: None,
+        deny: Vec::new(),
         skill: false,
         skill_install: false,
         skill_install_claude: false,
@@ -51,6 +56,7 @@ fn create_test_cli(overrides: impl FnOnce(&mut cli::Cli)) -> cli::Cli {
             colorscheme: None,
             toc: None,
             json_feed: None,
+            atom_feed: None,
             show_next_prev_links: None,
             publish_md: None,
             source_repository: None,
@@ -58,6 +64,7 @@ fn create_test_cli(overrides: impl FnOnce(&mut cli::Cli)) -> cli::Cli {
             theme: None,
             build_sitemap: None,
             publish_urls_json: None,
+            json_api: None,
             enable_shortcodes: None,
             shortcode_pattern: None,
             skip_image_resize: None,
@@ -66,6 +73,7 @@ fn create_test_cli(overrides: impl FnOnce(&mut cli::Cli)) -> cli::Cli {
             strict_internal_links: None,
             check_media_links: None,
             native_mermaid_render: None,
+            read_only: None,
         },
         subcommand: None,
     };
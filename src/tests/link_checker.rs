@@ -0,0 +1,117 @@
+use super::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Local HTTP stand-in: `/ok` answers 200, `/missing` 404 and `/no-head`
+/// answers 405 to `HEAD` and 200 to `GET`. Returns its base URL and a
+/// counter of the requests it received.
+fn start_server() -> (String, Arc<AtomicUsize>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&requests);
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let status = match (request.method(), request.url()) {
+                (_, "/ok") | (tiny_http::Method::Get, "/no-head") => 200,
+                (_, "/no-head") => 405,
+                _ => 404,
+            };
+            let _ = request.respond(tiny_http::Response::empty(status));
+        }
+    });
+    (base, requests)
+}
+
+fn test_config() -> LinkCheckerConfig {
+    LinkCheckerConfig {
+        timeout: 5,
+        domain_delay: 0,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_check_statuses() {
+    let (base, _) = start_server();
+    let checker = LinkChecker::new(&test_config());
+
+    let ok = checker.check(&format!("{base}/ok"));
+    assert!(ok.ok);
+    assert_eq!(ok.status, Some(200));
+
+    let missing = checker.check(&format!("{base}/missing"));
+    assert!(!missing.ok);
+    assert_eq!(missing.status, Some(404));
+
+    // falls back to GET when HEAD is not allowed
+    let no_head = checker.check(&format!("{base}/no-head"));
+    assert!(no_head.ok);
+    assert_eq!(no_head.status, Some(200));
+
+    let unreachable = checker.check("http://127.0.0.1:1/closed");
+    assert!(!unreachable.ok);
+    assert_eq!(unreachable.status, None);
+    assert!(unreachable.error.is_some());
+}
+
+#[test]
+fn test_refresh_cache_only_checks_stale_links() {
+    let (base, requests) = start_server();
+    let checker = LinkChecker::new(&test_config());
+    let urls = vec![format!("{base}/ok"), format!("{base}/missing")];
+    let mut cache = LinkCache::default();
+
+    assert_eq!(refresh_cache(&checker, &urls, &mut cache, 3600, false), 2);
+    assert!(cache.links[&urls[0]].ok);
+    assert!(!cache.links[&urls[1]].ok);
+    let after_first_run = requests.load(Ordering::SeqCst);
+
+    // fresh entries are not checked again
+    assert_eq!(refresh_cache(&checker, &urls, &mut cache, 3600, false), 0);
+    assert_eq!(requests.load(Ordering::SeqCst), after_first_run);
+
+    // stale entries are
+    cache.links.get_mut(&urls[0]).unwrap().checked_at -= 7200;
+    assert_eq!(refresh_cache(&checker, &urls, &mut cache, 3600, false), 1);
+
+    assert_eq!(refresh_cache(&checker, &urls, &mut cache, 3600, true), 2);
+}
+
+#[test]
+fn test_link_cache_roundtrip() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let path = temp.path().join("cache.json");
+    let mut cache = LinkCache::default();
+    cache.links.insert(
+        "https://example.com".to_string(),
+        LinkStatus {
+            ok: true,
+            status: Some(200),
+            error: None,
+            checked_at: 100,
+        },
+    );
+    cache.save(&path);
+
+    let loaded = LinkCache::load(&path);
+    assert_eq!(loaded.links, cache.links);
+    assert!(loaded.is_fresh("https://example.com", 150, 60));
+    assert!(!loaded.is_fresh("https://example.com", 200, 60));
+    assert!(!loaded.is_fresh("https://other.com", 150, 60));
+    assert!(LinkCache::load(&temp.path().join("missing.json"))
+        .links
+        .is_empty());
+}
+
+#[test]
+fn test_rate_limiter_spaces_requests_per_domain() {
+    let limiter = RateLimiter::new(Duration::from_millis(100));
+    let start = Instant::now();
+    limiter.wait("http://a.example/1");
+    limiter.wait("http://b.example/1");
+    assert!(start.elapsed() < Duration::from_millis(100));
+    limiter.wait("http://a.example/2");
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
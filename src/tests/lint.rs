@@ -0,0 +1,185 @@
+use super::*;
+use crate::content::ContentBuilder;
+use tempfile::TempDir;
+
+fn content(slug: &str, title: &str, html: &str) -> Content {
+    ContentBuilder::new()
+        .title(title.to_string())
+        .slug(slug.to_string())
+        .html(html.to_string())
+        .description("A description".to_string())
+        .build()
+}
+
+fn rules(violations: &[Violation]) -> Vec<&str> {
+    violations.iter().map(|v| v.rule).collect()
+}
+
+#[test]
+fn test_level_defaults_and_overrides() {
+    let data = Data::new(
+        "name: Site\nlint:\n  rules:\n    missing-card-image: error\n    long-title: off\n",
+        Path::new("marmite.yaml"),
+    );
+    let config = &data.site.lint;
+    assert_eq!(config.max_title_length, 70);
+    assert_eq!(level(config, "missing-card-image"), LintLevel::Error);
+    assert_eq!(level(config, "long-title"), LintLevel::Off);
+    assert_eq!(level(config, "missing-description"), LintLevel::Warning);
+    assert_eq!(level(config, "invalid-frontmatter"), LintLevel::Error);
+    assert_eq!(level(config, "no-such-rule"), LintLevel::Off);
+}
+
+#[test]
+fn test_check_content() {
+    let data = Data::new(
+        "name: Site\nauthors:\n  alice:\n    name: Alice\nlint:\n  max_title_length: 10\n",
+        Path::new("marmite.yaml"),
+    );
+    let mut post = content(
+        "post",
+        "A title longer than ten",
+        "<h2>Intro</h2><h4 id=\"deep\"><a href=\"#deep\"></a>Deep</h4><h2>Next</h2>\
+         <p><img src=\"media/a.png\" alt=\"A cat\"><img src=\"media/b.png\" alt=\"\"><img src=\"c.png\"></p>",
+    );
+    post.description = None;
+    post.authors = vec!["alice".to_string(), "bob".to_string()];
+    let violations = check_content(&post, &data);
+    assert_eq!(
+        rules(&violations),
+        vec![
+            "missing-description",
+            "missing-card-image",
+            "long-title",
+            "unknown-author",
+            "heading-level-skip",
+            "image-missing-alt",
+            "image-missing-alt",
+        ]
+    );
+    assert_eq!(
+        violations[3].message,
+        "Author \"bob\" is not in the authors of marmite.yaml"
+    );
+    assert_eq!(violations[4].message, "Heading \"Deep\" is h4 after h2");
+    assert_eq!(
+        violations[5].message,
+        "Image \"media/b.png\" has no alt text"
+    );
+
+    let mut clean = content("clean", "Short", "<h1>Short</h1><h2>Part</h2><h3>Sub</h3>");
+    clean.card_image = Some("media/card.png".to_string());
+    assert!(check_content(&clean, &data).is_empty());
+}
+
+#[test]
+fn test_check_duplicate_titles() {
+    let a = content("a", "Hello World", "");
+    let b = content("b", "hello world ", "");
+    let c = content("c", "Other", "");
+    let violations = check_duplicate_titles(&[&a, &b, &c]);
+    assert_eq!(violations.len(), 2);
+    assert_eq!(
+        violations[0].message,
+        "Title \"Hello World\" is also used by b"
+    );
+    assert_eq!(
+        violations[1].message,
+        "Title \"hello world \" is also used by a"
+    );
+}
+
+#[test]
+fn test_check_tag_case() {
+    let tagged = |slug: &str, tags: &[&str]| Content {
+        tags: tags.iter().map(ToString::to_string).collect(),
+        ..content(slug, slug, "")
+    };
+    let a = tagged("a", &["Rust", "web"]);
+    let b = tagged("b", &["Rust"]);
+    let c = tagged("c", &["rust", "Web"]);
+    let violations = check_tag_case(&[&a, &b, &c]);
+    let found: Vec<(&str, &str)> = violations
+        .iter()
+        .map(|v| (v.rule, v.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                "inconsistent-tag-case",
+                "Tag \"rust\" is written \"Rust\" elsewhere, both become one tag"
+            ),
+            // A tie keeps the first spelling in order
+            (
+                "inconsistent-tag-case",
+                "Tag \"web\" is written \"Web\" elsewhere, both become one tag"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_lint_locates_and_grades_violations() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("post.md");
+    fs::write(
+        &path,
+        "---\ntitle: Post\nauthors: carol\n---\n# Post\n\n![](media/photo.png)\n",
+    )
+    .unwrap();
+    let mut data = Data::new(
+        "name: Site\nlint:\n  rules:\n    image-missing-alt: error\n    unknown-author: off\n",
+        Path::new("marmite.yaml"),
+    );
+    let mut post = content(
+        "post",
+        "Post",
+        "<h1>Post</h1>\n<p><img src=\"media/photo.png\" alt=\"\" /></p>",
+    );
+    post.description = None;
+    post.card_image = Some("media/photo.png".to_string());
+    post.authors = vec!["carol".to_string()];
+    post.source_path = Some(path.clone());
+    data.pages.push(post);
+    data.diagnostics.push(Diagnostic::new(
+        Warning::InvalidFrontmatter {
+            source: "broken.md".to_string(),
+            message: "bad".to_string(),
+        },
+        Some(&temp.path().join("broken.md")),
+    ));
+
+    let diagnostics = lint(&data);
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.file.clone(), d.line, d.severity, d.message.clone()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                Some(temp.path().join("broken.md")),
+                None,
+                Severity::Error,
+                "Invalid frontmatter, the file is skipped: bad [invalid-frontmatter]".to_string()
+            ),
+            (
+                Some(path.clone()),
+                None,
+                Severity::Warning,
+                "Missing description [missing-description]".to_string()
+            ),
+            (
+                Some(path),
+                Some(7),
+                Severity::Error,
+                "Image \"media/photo.png\" has no alt text [image-missing-alt]".to_string()
+            ),
+        ]
+    );
+    assert_eq!(diagnostics[2].code(), "lint");
+    let json = serde_json::to_value(&diagnostics[2]).unwrap();
+    assert_eq!(json["rule"], "image-missing-alt");
+    assert_eq!(json["severity"], "error");
+}
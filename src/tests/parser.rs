@@ -394,11 +394,18 @@ fn create_test_site_data() -> Data {
         stream: GroupedContent::new(Kind::Stream),
         series: GroupedContent::new(Kind::Series),
         language: GroupedContent::new(Kind::Language),
+        taxonomies: std::collections::BTreeMap::new(),
         latest_timestamp: None,
         config_path: String::new(),
         force_render: false,
         generated_urls: crate::site::UrlCollection::default(),
         galleries: HashMap::new(),
+        responsive_images: HashMap::new(),
+        render_only: None,
+        build_time: chrono::NaiveDateTime::default(),
+        include_future: false,
+        scheduled: Vec::new(),
+        diagnostics: Vec::new(),
     }
 }
 
@@ -555,3 +562,70 @@ fn test_render_native_mermaid_invalid_config_falls_back() {
     // Falls back to defaults on invalid theme
     assert!(result.contains("<svg") || result.contains("language-mermaid"));
 }
+
+#[test]
+fn test_frontmatter_error_line_yaml() {
+    let content = "---\ntitle: Hello\ntags: [a, b\ndate: 2024-01-01\n---\n# Hi\n";
+    let message = parse_front_matter(content).unwrap_err();
+    assert_eq!(frontmatter_error_line(content, &message), Some(4));
+    assert_eq!(
+        frontmatter_error_line(&format!("\n\n{content}"), &message),
+        Some(6)
+    );
+}
+
+#[test]
+fn test_frontmatter_error_line_toml() {
+    let content = "+++\ntitle = \"x\n+++\nbody\n";
+    let message = parse_front_matter(content).unwrap_err();
+    assert_eq!(frontmatter_error_line(content, &message), Some(2));
+}
+
+#[test]
+fn test_frontmatter_error_line_without_line() {
+    assert_eq!(frontmatter_error_line("---\n", "Invalid frontmatter"), None);
+}
+
+#[test]
+fn test_parse_front_matter_toml_with_colons() {
+    let content = "+++\ntitle = \"Post\"\ndate = 2021-06-07T10:00:00-03:00\n[extra]\nurl = \"/a/:slug/\"\n+++\nbody\n";
+    let (frontmatter, body) = parse_front_matter(content).unwrap();
+    assert_eq!(
+        frontmatter.get("title"),
+        Some(&frontmatter_gen::Value::String("Post".to_string()))
+    );
+    assert!(frontmatter.get("date").is_some());
+    assert!(frontmatter.get("extra").is_some());
+    assert_eq!(body.trim(), "body");
+}
+
+#[test]
+fn test_parse_front_matter_empty() {
+    let (frontmatter, body) = parse_front_matter("---\n---\nbody\n").unwrap();
+    assert!(frontmatter.is_empty());
+    assert_eq!(body.trim(), "body");
+}
+
+#[test]
+fn test_get_external_links() {
+    let html = r#"<a href="https://a.test/post#top">a</a> <a href='http://b.test/?x=1&amp;y=2'>b</a>
+        <a href="./local.html">local</a> <a href="https://a.test/post">again</a>
+        <img src = "https://c.test/img.png"> <a href="mailto:me@example.com">mail</a>"#;
+    assert_eq!(
+        get_external_links(html),
+        vec![
+            "https://a.test/post",
+            "http://b.test/?x=1&y=2",
+            "https://c.test/img.png"
+        ]
+    );
+    assert!(get_external_links("<p>No links here</p>").is_empty());
+}
+
+#[test]
+fn test_escape_html() {
+    assert_eq!(
+        escape_html(r#"<a href="x">Tom & Jerry</a>"#),
+        "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&lt;/a&gt;"
+    );
+}
@@ -7,6 +7,7 @@ fn test_all_patterns_compile() {
     Regex::new(MATCH_HTML_OR_TEMPLATE_TAGS).unwrap();
     Regex::new(MATCH_HTML_TAGS).unwrap();
     Regex::new(CAPTURE_SLUG_ANCHOR_FROM_HREF).unwrap();
+    Regex::new(CAPTURE_EXTERNAL_URL_FROM_HREF_OR_SRC).unwrap();
     Regex::new(CAPTURE_LEVEL_ID_CONTENT_FROM_H_TAG).unwrap();
     Regex::new(MATCH_COMRAK_HEADING_ANCHOR).unwrap();
     Regex::new(CAPTURE_LINK_AND_TEXT_FROM_A_TAG).unwrap();
@@ -23,6 +24,7 @@ fn test_all_patterns_compile() {
     Regex::new(CAPTURE_DATE_PREFIX_FROM_TEXT).unwrap();
     Regex::new(REPLACE_AT_MEDIA_REF_IN_HTML).unwrap();
     Regex::new(CAPTURE_MERMAID_BLOCK).unwrap();
+    Regex::new(CAPTURE_NAME_VALUE_FROM_SIGNATURE_HEADER).unwrap();
 }
 
 #[test]
@@ -256,3 +258,33 @@ fn test_capture_mermaid_block_no_match_for_other_langs() {
     let html = r#"<pre class="marmite-code"><code class="marmite-code-inner language-rust">fn main() {}</code></pre>"#;
     assert!(re.captures(html).is_none());
 }
+
+#[test]
+fn test_capture_name_value_from_signature_header() {
+    let re = Regex::new(CAPTURE_NAME_VALUE_FROM_SIGNATURE_HEADER).unwrap();
+    let header =
+        r#"keyId="https://a.test/u#main-key",headers="(request-target) host",signature="c2k=""#;
+    let params: Vec<(&str, &str)> = re
+        .captures_iter(header)
+        .map(|cap| (cap.get(1).unwrap().as_str(), cap.get(2).unwrap().as_str()))
+        .collect();
+    assert_eq!(
+        params,
+        [
+            ("keyId", "https://a.test/u#main-key"),
+            ("headers", "(request-target) host"),
+            ("signature", "c2k="),
+        ]
+    );
+}
+
+#[test]
+fn test_capture_external_url_from_href_or_src() {
+    let re = Regex::new(CAPTURE_EXTERNAL_URL_FROM_HREF_OR_SRC).unwrap();
+    let html = r#"<a href="https://a.test/x">a</a> <img src = 'http://b.test/i.png'> <a href="/local">l</a>"#;
+    let urls: Vec<&str> = re
+        .captures_iter(html)
+        .map(|caps| caps.get(1).unwrap().as_str())
+        .collect();
+    assert_eq!(urls, vec!["https://a.test/x", "http://b.test/i.png"]);
+}
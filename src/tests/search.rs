@@ -0,0 +1,179 @@
+use super::*;
+use crate::content::ContentBuilder;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn content(slug: &str, title: &str, html: &str, tags: &[&str]) -> Content {
+    ContentBuilder::new()
+        .title(title.to_string())
+        .slug(slug.to_string())
+        .html(html.to_string())
+        .tags(tags.iter().map(ToString::to_string).collect())
+        .build()
+}
+
+fn scores(shard: &SearchShard, term: &str) -> Vec<(String, u32)> {
+    shard.terms[term]
+        .chunks(2)
+        .map(|pair| (shard.documents[pair[0] as usize].slug.clone(), pair[1]))
+        .collect()
+}
+
+#[test]
+fn test_stemmer_algorithm() {
+    assert!(matches!(stemmer_algorithm("en"), Some(Algorithm::English)));
+    assert!(matches!(
+        stemmer_algorithm("pt-BR"),
+        Some(Algorithm::Portuguese)
+    ));
+    assert!(matches!(
+        stemmer_algorithm("de_DE"),
+        Some(Algorithm::German)
+    ));
+    assert!(stemmer_algorithm("ja").is_none());
+}
+
+#[test]
+fn test_normalize_removes_diacritics() {
+    assert_eq!(normalize("Ação"), "acao");
+    assert_eq!(normalize("Crème"), "creme");
+    assert_eq!(normalize("rust"), "rust");
+}
+
+#[test]
+fn test_tokenize() {
+    assert_eq!(
+        tokenize("Hello, World! It's a re-write in Rust 2024."),
+        vec!["hello", "world", "it", "re", "write", "in", "rust", "2024"]
+    );
+}
+
+#[test]
+fn test_analyzer_stems_per_language() {
+    let english = Analyzer::new("en");
+    assert_eq!(
+        english.analyze("Running searches"),
+        vec![
+            ("run".to_string(), "running".to_string()),
+            ("search".to_string(), "searches".to_string())
+        ]
+    );
+
+    let portuguese = Analyzer::new("pt");
+    assert_eq!(portuguese.analyze("correndo")[0].0, "corr");
+
+    // unknown languages are only normalized
+    let unknown = Analyzer::new("xx");
+    assert_eq!(unknown.analyze("Running")[0].0, "running");
+}
+
+#[test]
+fn test_html_to_text() {
+    assert_eq!(
+        html_to_text("<h1>Title</h1>\n<p>Fish &amp; chips {{ var }}</p>"),
+        "Title Fish & chips"
+    );
+}
+
+#[test]
+fn test_build_shard_ranks_title_above_body() {
+    let in_title = content("in-title", "Marmite recipes", "<p>Tasty food</p>", &[]);
+    let in_body = content(
+        "in-body",
+        "Breakfast",
+        "<p>Toast with marmite and butter</p>",
+        &[],
+    );
+    let unrelated = content("unrelated", "Lunch", "<p>Rice and beans</p>", &[]);
+    let shard = build_shard(
+        "en",
+        &[&in_title, &in_body, &unrelated],
+        &SearchWeights::default(),
+    );
+
+    let marmite = scores(&shard, "marmit");
+    assert_eq!(marmite.len(), 2);
+    let title_score = marmite.iter().find(|(s, _)| s == "in-title").unwrap().1;
+    let body_score = marmite.iter().find(|(s, _)| s == "in-body").unwrap().1;
+    assert!(title_score > body_score);
+}
+
+#[test]
+fn test_build_shard_indexes_tags_and_aliases() {
+    let post = content("post", "Happy days", "<p>Some text</p>", &["python"]);
+    let shard = build_shard("en", &[&post], &SearchWeights::default());
+
+    assert!(shard.terms.contains_key("python"));
+    assert_eq!(shard.aliases.get("happy"), Some(&"happi".to_string()));
+    // words whose stem is a prefix of the word do not need an alias
+    assert!(!shard.aliases.contains_key("days"));
+}
+
+#[test]
+fn test_build_shard_zero_weight_skips_field() {
+    let post = content("post", "Title", "<p>Body words</p>", &["tagged"]);
+    let weights = SearchWeights {
+        tags: 0.0,
+        ..Default::default()
+    };
+    let shard = build_shard("en", &[&post], &weights);
+    assert!(!shard.terms.contains_key("tag"));
+    assert!(shard.terms.contains_key("bodi"));
+}
+
+#[test]
+fn test_build_shard_excerpt() {
+    let long = content(
+        "long",
+        "Long",
+        &format!("<p>{}</p>", "word ".repeat(100)),
+        &[],
+    );
+    let mut described = content("described", "Described", "<p>Body</p>", &[]);
+    described.description = Some("The description".to_string());
+    let shard = build_shard("en", &[&long, &described], &SearchWeights::default());
+
+    assert!(shard.documents[0].excerpt.ends_with("..."));
+    assert!(shard.documents[0].excerpt.len() <= EXCERPT_LENGTH + 3);
+    assert_eq!(shard.documents[1].excerpt, "The description");
+}
+
+#[test]
+fn test_build_index_shards_by_language_and_skips_drafts() {
+    let mut data = Data::new("name: Test\nlanguage: en", Path::new("marmite.yaml"));
+    let mut portuguese = content("pt-post", "Olá", "<p>Texto</p>", &[]);
+    portuguese.language = Some("pt".to_string());
+    let mut draft = content("draft", "Draft", "<p>Secret</p>", &[]);
+    draft.stream = Some("draft".to_string());
+    data.posts = vec![
+        content("post", "Hello", "<p>Text</p>", &[]),
+        portuguese,
+        draft,
+    ];
+
+    let index = build_index(&data);
+    assert_eq!(index.keys().collect::<Vec<_>>(), vec!["en", "pt"]);
+    assert_eq!(index["en"].documents.len(), 1);
+    assert_eq!(index["pt"].documents[0].slug, "pt-post");
+    assert!(!index["en"].terms.contains_key("secret"));
+}
+
+#[test]
+fn test_generate_search_index_writes_manifest_and_shards() {
+    let temp = TempDir::new().unwrap();
+    let mut data = Data::new("name: Test", Path::new("marmite.yaml"));
+    data.posts = vec![content("post", "Hello", "<p>Text</p>", &[])];
+
+    generate_search_index(&data, temp.path());
+
+    let search_dir = temp.path().join("static").join("search");
+    let manifest: SearchManifest =
+        serde_json::from_str(&fs::read_to_string(search_dir.join("index.json")).unwrap()).unwrap();
+    assert_eq!(manifest.default_language, "en");
+    assert_eq!(manifest.languages["en"], "en.json");
+
+    let shard: SearchShard =
+        serde_json::from_str(&fs::read_to_string(search_dir.join("en.json")).unwrap()).unwrap();
+    assert_eq!(shard.documents[0].slug, "post");
+    assert!(shard.terms.contains_key("hello"));
+}
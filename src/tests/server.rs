@@ -118,3 +118,317 @@ fn test_content_type_for_nested_path() {
         Some("image/svg+xml")
     );
 }
+
+fn headers(pairs: &[(&str, &str)]) -> Vec<Header> {
+    pairs
+        .iter()
+        .map(|(name, value)| Header::from_bytes(*name, *value).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_basic_auth_user() {
+    // admin:pa55
+    let valid = headers(&[("Authorization", "Basic YWRtaW46cGE1NQ==")]);
+    assert_eq!(
+        basic_auth_user(&valid, "admin:pa55"),
+        Some("admin".to_string())
+    );
+    assert_eq!(basic_auth_user(&valid, "admin:other"), None);
+    assert_eq!(
+        basic_auth_user(&headers(&[("Authorization", "Basic !!")]), "admin:pa55"),
+        None
+    );
+    assert_eq!(basic_auth_user(&[], "admin:pa55"), None);
+}
+
+#[test]
+fn test_api_user_with_token() {
+    let config = ServerConfig {
+        api_token: Some("s3cret".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        api_user(&headers(&[("Authorization", "Bearer s3cret")]), &config),
+        Some("token".to_string())
+    );
+    assert_eq!(
+        api_user(
+            &headers(&[("Cookie", "a=b; marmite_token=s3cret")]),
+            &config
+        ),
+        Some("token".to_string())
+    );
+    assert_eq!(
+        api_user(&headers(&[("X-Marmite-Token", "s3cre")]), &config),
+        None
+    );
+    assert_eq!(api_user(&[], &config), None);
+    assert_eq!(
+        api_user(&[], &ServerConfig::default()),
+        Some("anonymous".to_string())
+    );
+}
+
+#[test]
+fn test_local_origins() {
+    assert_eq!(
+        local_origins("0.0.0.0:8000".parse().unwrap()),
+        vec![
+            "http://localhost:8000",
+            "http://127.0.0.1:8000",
+            "http://[::1]:8000"
+        ]
+    );
+    assert_eq!(
+        local_origins("127.0.0.1:80".parse().unwrap()),
+        vec!["http://localhost", "http://127.0.0.1", "http://[::1]"]
+    );
+    assert_eq!(
+        local_origins("192.168.0.10:8000".parse().unwrap()),
+        vec!["http://192.168.0.10:8000"]
+    );
+}
+
+#[test]
+fn test_is_allowed_origin() {
+    let local = local_origins("0.0.0.0:8000".parse().unwrap());
+    let allowed = vec!["https://editor.example.com/".to_string()];
+    let check = |pairs: &[(&str, &str)]| is_allowed_origin(&headers(pairs), &local, &allowed);
+
+    assert!(check(&[("Host", "localhost:8000")]), "no origin, e.g. curl");
+    assert!(check(&[
+        ("Host", "localhost:8000"),
+        ("Origin", "http://localhost:8000")
+    ]));
+    assert!(check(&[
+        ("Host", "localhost:8000"),
+        ("Origin", "https://editor.example.com")
+    ]));
+    assert!(!check(&[
+        ("Host", "localhost:8000"),
+        ("Origin", "http://localhost:9000")
+    ]));
+    assert!(!check(&[
+        ("Host", "localhost:8000"),
+        ("Origin", "https://evil.example.com")
+    ]));
+    assert!(!check(&[("Host", "localhost:8000"), ("Origin", "null")]));
+    // DNS rebinding: the Host matches the Origin but neither is the server
+    assert!(!check(&[
+        ("Host", "rebind.example.com:8000"),
+        ("Origin", "http://rebind.example.com:8000")
+    ]));
+    // Browsers always say where they come from
+    assert!(!check(&[
+        ("Host", "localhost:8000"),
+        ("Referer", "http://localhost:8000/__marmite__/editor/post")
+    ]));
+    assert!(!check(&[
+        ("Host", "localhost:8000"),
+        ("Sec-Fetch-Site", "cross-site")
+    ]));
+}
+
+#[test]
+fn test_is_api_path() {
+    assert!(is_api_path("/__marmite__/content/post", &Method::Delete));
+    assert!(is_api_path("/__marmite__/config", &Method::Get));
+    assert!(is_api_path(
+        "/__marmite__/file/content/post.md",
+        &Method::Put
+    ));
+    assert!(is_api_path("/__marmite__/init", &Method::Post));
+    assert!(!is_api_path("/__marmite__/init", &Method::Get));
+    assert!(!is_api_path("/__marmite__/file/", &Method::Get));
+    assert!(!is_api_path("/__marmite__/toolbar.js", &Method::Get));
+    assert!(!is_api_path("/post.html", &Method::Get));
+}
+
+fn paths(items: &[&str]) -> Vec<String> {
+    items.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn test_reload_events_swaps_stylesheets() {
+    let events = reload_events(&paths(&["/static/custom.css"]));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["event"], "css");
+    assert_eq!(events[0]["paths"], json!(["/static/custom.css"]));
+}
+
+#[test]
+fn test_reload_events_targets_changed_pages() {
+    let events = reload_events(&paths(&[
+        "/index.html",
+        "/marmite.json",
+        "/my-post.html",
+        "/sitemap.xml",
+        "/static/search/index.json",
+        "/static/search/en.json",
+    ]));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["event"], "reload");
+    assert_eq!(events[0]["paths"], json!(["/index.html", "/my-post.html"]));
+}
+
+#[test]
+fn test_reload_events_css_and_pages() {
+    let events = reload_events(&paths(&["/my-post.html", "/static/marmite.css"]));
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event"], "css");
+    assert_eq!(events[1]["event"], "reload");
+    assert_eq!(events[1]["paths"], json!(["/my-post.html"]));
+}
+
+#[test]
+fn test_reload_events_other_assets_reload_everything() {
+    let events = reload_events(&paths(&["/my-post.html", "/static/marmite.js"]));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["event"], "reload");
+    assert!(events[0].get("paths").is_none());
+}
+
+#[test]
+fn test_reload_events_ignores_data_files() {
+    assert!(reload_events(&paths(&["/marmite.json", "/index.rss"])).is_empty());
+    assert!(reload_events(&[]).is_empty());
+}
+
+#[test]
+fn test_output_snapshot_changed_since() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("static")).unwrap();
+    fs::write(root.join("index.html"), "<h1>Home</h1>").unwrap();
+    fs::write(root.join("old.html"), "<h1>Old</h1>").unwrap();
+    fs::write(root.join("static/style.css"), "body {}").unwrap();
+
+    let first = OutputSnapshot::capture(root, &OutputSnapshot::default());
+    assert_eq!(
+        first.changed_since(&OutputSnapshot::default()),
+        paths(&["/index.html", "/old.html", "/static/style.css"])
+    );
+    assert!(OutputSnapshot::capture(root, &first)
+        .changed_since(&first)
+        .is_empty());
+
+    // Rewriting a file with the same content is not a change
+    fs::write(root.join("index.html"), "<h1>Home</h1>").unwrap();
+    fs::write(root.join("static/style.css"), "body { color: red }").unwrap();
+    fs::remove_file(root.join("old.html")).unwrap();
+    fs::write(root.join("new.html"), "<h1>New</h1>").unwrap();
+
+    let second = OutputSnapshot::capture(root, &first);
+    assert_eq!(
+        second.changed_since(&first),
+        paths(&["/new.html", "/old.html", "/static/style.css"])
+    );
+}
+
+#[test]
+fn test_live_reload_script_handles_targeted_events() {
+    assert!(LIVE_RELOAD_SCRIPT.contains(r#"payload.event === "css""#));
+    assert!(LIVE_RELOAD_SCRIPT.contains("sessionStorage"));
+}
+
+fn shortcode_diagnostic() -> Diagnostic {
+    Diagnostic::new(
+        crate::diagnostics::Warning::BrokenShortcode {
+            source: Some("post".to_string()),
+            name: "youtube".to_string(),
+            message: "missing id".to_string(),
+        },
+        None,
+    )
+}
+
+#[test]
+fn test_overlay_errors_skip_link_warnings() {
+    let link = Diagnostic::new(
+        crate::diagnostics::Warning::BrokenInternalLink {
+            source: "post".to_string(),
+            target: "missing".to_string(),
+        },
+        None,
+    );
+    let errors = overlay_errors(&[link.clone(), shortcode_diagnostic()]);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["code"], "broken-shortcode");
+    assert_eq!(
+        errors[0]["message"],
+        "Shortcode 'youtube' failed to render: missing id"
+    );
+
+    // Denied warnings fail the build, so they are shown
+    let mut denied = link;
+    denied.severity = Severity::Error;
+    assert_eq!(overlay_errors(&[denied]).len(), 1);
+}
+
+#[test]
+fn test_failure_errors() {
+    let template = Error::Template(
+        "error: Variable `missing` not found\n --> content.html:12:4\n  |".to_string(),
+    );
+    let errors = failure_errors(&template);
+    assert_eq!(errors[0]["code"], "template-error");
+    assert_eq!(errors[0]["file"], "content.html");
+    assert_eq!(errors[0]["line"], 12);
+
+    let denied = Error::Denied(vec![shortcode_diagnostic()]);
+    assert_eq!(failure_errors(&denied)[0]["code"], "broken-shortcode");
+
+    let io = Error::Io {
+        path: PathBuf::from("site/index.html"),
+        message: "permission denied".to_string(),
+    };
+    let errors = failure_errors(&io);
+    assert_eq!(errors[0]["code"], "build-error");
+    assert_eq!(errors[0]["file"], "site/index.html");
+
+    let other = std::io::Error::other("disk full");
+    let errors = failure_errors(&other);
+    assert_eq!(errors[0]["message"], "disk full");
+    assert_eq!(errors[0]["file"], serde_json::Value::Null);
+}
+
+#[test]
+fn test_notify_build_sends_errors_and_clears_them() {
+    let live_reload = LiveReload::new();
+    let (tx, rx) = mpsc::channel();
+    live_reload.register(tx);
+    let next = || serde_json::from_str::<serde_json::Value>(&rx.try_recv().unwrap()).unwrap();
+
+    live_reload.notify_build(&[], &[]);
+    assert!(rx.try_recv().is_err(), "a clean build sends nothing");
+
+    live_reload.notify_build(&[shortcode_diagnostic()], &[]);
+    let payload = next();
+    assert_eq!(payload["event"], "build-error");
+    assert_eq!(payload["errors"][0]["code"], "broken-shortcode");
+
+    let failure = Error::Template("boom".to_string());
+    live_reload.notify_build(&[], &[&failure]);
+    assert_eq!(next()["errors"][0]["code"], "template-error");
+
+    live_reload.notify_build(&[], &[]);
+    assert_eq!(next()["event"], "build-ok");
+    live_reload.notify_build(&[], &[]);
+    assert!(rx.try_recv().is_err());
+}
+
+#[test]
+fn test_file_etag_follows_content() {
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("post.md");
+    assert_eq!(file_etag(&file), None);
+
+    fs::write(&file, "first").unwrap();
+    let first = file_etag(&file).unwrap();
+    assert!(first.starts_with('"') && first.ends_with('"'));
+    assert_eq!(file_etag(&file).unwrap(), first);
+
+    fs::write(&file, "second").unwrap();
+    assert_ne!(file_etag(&file).unwrap(), first);
+}
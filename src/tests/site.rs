@@ -1795,3 +1795,167 @@ fn test_validate_media_links_subfolder_media() {
     let broken = validate_media_links(&data, &content_folder);
     assert!(broken.is_empty());
 }
+
+// --- scheduled publishing tests ---
+
+fn write_scheduled_site(content_dir: &Path) {
+    fs::create_dir_all(content_dir).unwrap();
+    fs::write(
+        content_dir.join("published.md"),
+        "---\ndate: 2024-01-01\n---\n# Published\n\nBody.",
+    )
+    .unwrap();
+    fs::write(
+        content_dir.join("future.md"),
+        "---\ndate: 2030-01-01\n---\n# Future\n\nBody.",
+    )
+    .unwrap();
+    fs::write(
+        content_dir.join("expired.md"),
+        "---\ndate: 2024-01-01\nexpires_at: 2024-06-01\n---\n# Expired\n\nBody.",
+    )
+    .unwrap();
+}
+
+fn collect_scheduled_site(content_dir: &Path, include_future: bool) -> Data {
+    let mut site_data = Data::new("", Path::new("marmite.yaml"));
+    site_data.build_time = NaiveDate::from_ymd_opt(2025, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    site_data.include_future = include_future;
+    collect_content(
+        &content_dir.to_path_buf(),
+        &mut site_data,
+        &HashMap::new(),
+        None,
+        &HashMap::new(),
+    )
+    .unwrap();
+    site_data.sort_all();
+    site_data
+}
+
+#[test]
+fn test_collect_content_holds_scheduled_content() {
+    use tempfile::TempDir;
+
+    let temp = TempDir::new().unwrap();
+    write_scheduled_site(temp.path());
+
+    let site_data = collect_scheduled_site(temp.path(), false);
+    let slugs: Vec<&str> = site_data.posts.iter().map(|p| p.slug.as_str()).collect();
+    assert_eq!(slugs, vec!["published"]);
+    assert_eq!(site_data.scheduled.len(), 2);
+    assert!(!site_data.stream.map["index"]
+        .iter()
+        .any(|content| content.slug == "future"));
+
+    // --include-future publishes scheduled content but not expired content
+    let site_data = collect_scheduled_site(temp.path(), true);
+    let slugs: Vec<&str> = site_data.posts.iter().map(|p| p.slug.as_str()).collect();
+    assert_eq!(slugs, vec!["future", "published"]);
+    assert_eq!(site_data.scheduled[0].slug, "expired");
+}
+
+#[test]
+fn test_collect_content_skips_invalid_schedule_date() {
+    use tempfile::TempDir;
+
+    let temp = TempDir::new().unwrap();
+    write_scheduled_site(temp.path());
+    fs::write(
+        temp.path().join("bad.md"),
+        "---\ndate: 2024-01-01\npublish_at: next monday\n---\n# Bad\n\nBody.",
+    )
+    .unwrap();
+
+    let site_data = collect_scheduled_site(temp.path(), false);
+    let slugs: Vec<&str> = site_data.posts.iter().map(|p| p.slug.as_str()).collect();
+    assert_eq!(slugs, vec!["published"]);
+    assert_eq!(site_data.diagnostics.len(), 1);
+    assert_eq!(site_data.diagnostics[0].code(), "invalid-frontmatter");
+    assert!(site_data.diagnostics[0]
+        .file
+        .as_ref()
+        .unwrap()
+        .ends_with("bad.md"));
+}
+
+#[test]
+fn test_create_scheduled_json() {
+    use tempfile::TempDir;
+
+    let temp = TempDir::new().unwrap();
+    write_scheduled_site(temp.path());
+    let mut site_data = collect_scheduled_site(temp.path(), false);
+    site_data.site.url = "https://example.com".to_string();
+
+    let report = create_scheduled_json(&site_data);
+    assert_eq!(report["build_time"], "2025-01-01 00:00:00");
+    assert_eq!(
+        report["scheduled"][0]["url"],
+        "https://example.com/future.html"
+    );
+    assert_eq!(report["scheduled"][0]["publish_at"], "2030-01-01 00:00:00");
+    assert_eq!(
+        report["expired"][0]["url"],
+        "https://example.com/expired.html"
+    );
+    assert_eq!(report["expired"][0]["expires_at"], "2024-06-01 00:00:00");
+}
+
+#[test]
+fn test_remove_unpublished_outputs() {
+    use tempfile::TempDir;
+
+    let temp = TempDir::new().unwrap();
+    let content_dir = temp.path().join("content");
+    let output_dir = temp.path().join("site");
+    write_scheduled_site(&content_dir);
+    fs::create_dir_all(&output_dir).unwrap();
+    for file in ["published.html", "expired.html", "expired.md"] {
+        fs::write(output_dir.join(file), "old").unwrap();
+    }
+
+    let site_data = collect_scheduled_site(&content_dir, false);
+    remove_unpublished_outputs(&site_data, &content_dir, &output_dir);
+
+    assert!(output_dir.join("published.html").exists());
+    assert!(!output_dir.join("expired.html").exists());
+    assert!(!output_dir.join("expired.md").exists());
+    // sources are never touched
+    assert!(content_dir.join("expired.md").exists());
+}
+
+#[test]
+fn test_template_error_location() {
+    let mut tera = Tera::default();
+    tera.add_raw_template("content.html", "<p>\n{{ missing.value }}\n</p>")
+        .unwrap();
+    let message = tera
+        .render("content.html", &Context::new())
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        template_error_location(&message),
+        Some(("content.html".to_string(), 2))
+    );
+    assert_eq!(template_error_location("Template 'x' not found"), None);
+}
+
+#[test]
+fn test_add_frontmatter_diagnostic() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let file = temp_dir.path().join("post.md");
+    fs::write(&file, "---\ntitle: Post\ntags: [a, b\n---\n# Post\n").unwrap();
+    let message =
+        crate::parser::parse_front_matter(&fs::read_to_string(&file).unwrap()).unwrap_err();
+
+    let mut data = Data::new("", Path::new("test.yaml"));
+    add_frontmatter_diagnostic(&mut data, &file, message);
+    let diagnostic = &data.diagnostics[0];
+    assert_eq!(diagnostic.code(), "invalid-frontmatter");
+    assert_eq!(diagnostic.file.as_deref(), Some(file.as_path()));
+    assert_eq!(diagnostic.line, Some(3));
+}
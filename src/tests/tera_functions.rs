@@ -800,6 +800,52 @@ fn test_merge_grouped_content_unknown_site() {
     assert!(merged.is_empty());
 }
 
+#[test]
+fn test_group_function_taxonomy() {
+    use crate::content::ContentBuilder;
+    use chrono::NaiveDate;
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    let mut site_data = Data::new(
+        "taxonomies:\n  category: {}\n  product: {}\n",
+        Path::new("marmite.yaml"),
+    );
+    for (slug, category) in [("one", "Rust"), ("two", "Rust"), ("three", "Python")] {
+        let post = ContentBuilder::new()
+            .title(slug.to_string())
+            .slug(slug.to_string())
+            .date(
+                NaiveDate::from_ymd_opt(2024, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+            )
+            .taxonomies(BTreeMap::from([(
+                "category".to_string(),
+                vec![category.to_string()],
+            )]))
+            .build();
+        site_data.push_content(post);
+    }
+
+    let mut tera = tera::Tera::default();
+    tera.register_function(
+        "group",
+        Group {
+            site_data,
+            cross_site_data: None,
+        },
+    );
+    tera.add_raw_template(
+        "test",
+        r#"{% for name, items in group(kind="category") %}{{ name }}={{ items | length }};{% endfor %}|{{ group(kind="product") | length }}"#,
+    )
+    .unwrap();
+    let result = tera.render("test", &tera::Context::new()).unwrap();
+    assert_eq!(result, "Rust=2;Python=1;|0");
+}
+
 #[test]
 fn test_group_function_language() {
     let site_data = create_test_data();
@@ -911,3 +957,39 @@ fn test_language_display_name() {
     assert_eq!(display.resolve("pt"), "Portugues");
     assert_eq!(display.resolve("fr"), "fr");
 }
+
+#[test]
+fn test_responsive_image_function() {
+    use crate::image_resize::ResponsiveImage;
+    use std::path::Path;
+
+    let mut site_data = Data::new("", Path::new("marmite.yaml"));
+    site_data.responsive_images.insert(
+        "hero.jpg".to_string(),
+        ResponsiveImage {
+            path: "hero.jpg".to_string(),
+            width: 800,
+            height: 400,
+            widths: vec![400, 800],
+            formats: vec!["webp".to_string()],
+        },
+    );
+    let mut tera = tera::Tera::default();
+    tera.register_function("responsive_image", ResponsiveImageTag { site_data });
+    tera.add_raw_template(
+        "test",
+        r#"{{ responsive_image(src="media/hero.jpg", alt="A \"hero\"") }}|{{ responsive_image(src="media/other.jpg", class="banner") }}"#,
+    )
+    .unwrap();
+    let result = tera.render("test", &tera::Context::new()).unwrap();
+    let (hero, other) = result.split_once('|').unwrap();
+
+    assert!(hero.starts_with("<picture>"));
+    assert!(hero.contains(r#"<source type="image/webp" srcset="media/_responsive/hero-400w.webp 400w, media/_responsive/hero-800w.webp 800w""#));
+    assert!(hero.contains(r#"alt="A &quot;hero&quot;""#));
+    assert!(hero.contains(r#"width="800" height="400""#));
+    assert_eq!(
+        other,
+        r#"<img src="media/other.jpg" alt="" class="banner" />"#
+    );
+}
@@ -0,0 +1,202 @@
+use super::*;
+use tempfile::TempDir;
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut data = Vec::new();
+    image::RgbImage::new(width, height)
+        .write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png)
+        .unwrap();
+    data
+}
+
+fn config(yaml: &str) -> Marmite {
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+#[test]
+fn test_boundary() {
+    assert_eq!(
+        boundary("multipart/form-data; boundary=----abc"),
+        Some("----abc".to_string())
+    );
+    assert_eq!(
+        boundary("Multipart/Form-Data; charset=utf-8; boundary=\"x y\""),
+        Some("x y".to_string())
+    );
+    assert_eq!(boundary("application/json"), None);
+    assert_eq!(boundary("multipart/form-data"), None);
+}
+
+#[test]
+fn test_parse_multipart() {
+    let body = b"preamble\r\n--XYZ\r\n\
+        Content-Disposition: form-data; name=\"slug\"\r\n\r\n\
+        my-post\r\n--XYZ\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a.png\"\r\n\
+        Content-Type: image/png\r\n\r\n\
+        \x89PNG\r\n--XY\r\n--XYZ--\r\n";
+    let parts = parse_multipart(body, "XYZ").unwrap();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].name, "slug");
+    assert_eq!(parts[0].filename, None);
+    assert_eq!(parts[0].data, b"my-post");
+    assert_eq!(parts[1].name, "file");
+    assert_eq!(parts[1].filename.as_deref(), Some("a.png"));
+    assert_eq!(parts[1].data, b"\x89PNG\r\n--XY");
+}
+
+#[test]
+fn test_parse_multipart_rejects_malformed_bodies() {
+    assert!(parse_multipart(b"no boundary here", "XYZ").is_err());
+    assert!(parse_multipart(
+        b"--XYZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nunterminated",
+        "XYZ"
+    )
+    .is_err());
+}
+
+#[test]
+fn test_safe_filename() {
+    assert_eq!(
+        safe_filename("My Photo.Banner.JPG"),
+        Some("my-photo.banner.jpg".to_string())
+    );
+    assert_eq!(
+        safe_filename("../../etc/cat.png"),
+        Some("cat.png".to_string())
+    );
+    assert_eq!(
+        safe_filename("C:\\Users\\me\\dog.jpeg"),
+        Some("dog.jpeg".to_string())
+    );
+    assert_eq!(safe_filename("no-extension"), None);
+    assert_eq!(safe_filename(".png"), None);
+    assert_eq!(safe_filename("photo.p g"), None);
+}
+
+#[test]
+fn test_check_image() {
+    assert!(check_image("photo.png", &png(4, 4)).is_ok());
+    assert!(check_image("notes.txt", b"hello").is_err());
+    assert!(check_image("fake.png", b"not really a png").is_err());
+    assert!(check_image("photo.jpg", &png(4, 4)).is_err());
+    assert!(check_image("logo.svg", b"plain text").is_err());
+}
+
+#[test]
+fn test_check_image_refuses_svg() {
+    let svg = b"<svg xmlns=\"http://www.w3.org/2000/svg\"><script>alert(1)</script></svg>";
+    let error = check_image("logo.svg", svg).unwrap_err();
+    assert!(error.contains("SVG"));
+    assert!(check_image("LOGO.SVG", b"<svg/>").is_err());
+}
+
+#[test]
+fn test_store_image_in_slug_folder() {
+    let temp = TempDir::new().unwrap();
+    let content = temp.path().join("content");
+    let config = config("name: Site\n");
+
+    let upload = store_image(
+        temp.path(),
+        &content,
+        &config,
+        Some("my-post"),
+        "cute-cat.png",
+        None,
+        &png(8, 6),
+    )
+    .unwrap();
+    assert_eq!(upload.path, "content/media/my-post/cute-cat.png");
+    assert_eq!(upload.url, "media/my-post/cute-cat.png");
+    assert_eq!(upload.markdown, "![cute cat](media/my-post/cute-cat.png)");
+    assert_eq!((upload.width, upload.height, upload.resized), (8, 6, false));
+    assert!(content.join("media/my-post/cute-cat.png").is_file());
+
+    // Existing files are not overwritten
+    let second = store_image(
+        temp.path(),
+        &content,
+        &config,
+        Some("my-post"),
+        "cute-cat.png",
+        Some("Another cat"),
+        &png(8, 6),
+    )
+    .unwrap();
+    assert_eq!(second.url, "media/my-post/cute-cat-2.png");
+    assert_eq!(
+        second.markdown,
+        "![Another cat](media/my-post/cute-cat-2.png)"
+    );
+
+    let third = store_image(
+        temp.path(),
+        &content,
+        &config,
+        None,
+        "cat.png",
+        Some("a [cat](javascript:x) \\\n\n  on a mat]"),
+        &png(8, 6),
+    )
+    .unwrap();
+    assert_eq!(
+        third.markdown,
+        "![a \\[cat\\](javascript:x) \\\\ on a mat\\]](media/cat.png)"
+    );
+
+    assert!(store_image(
+        temp.path(),
+        &content,
+        &config,
+        Some("../outside"),
+        "cat.png",
+        None,
+        &png(8, 6),
+    )
+    .is_err());
+}
+
+#[test]
+fn test_store_image_applies_resize_settings() {
+    let temp = TempDir::new().unwrap();
+    let content = temp.path().join("content");
+    let config = config("name: Site\nextra:\n  max_image_width: 100\n  banner_image_width: 200\n");
+
+    let photo = store_image(
+        temp.path(),
+        &content,
+        &config,
+        None,
+        "wide.png",
+        None,
+        &png(400, 100),
+    )
+    .unwrap();
+    assert_eq!(photo.url, "media/wide.png");
+    assert_eq!((photo.width, photo.height, photo.resized), (100, 25, true));
+
+    let banner = store_image(
+        temp.path(),
+        &content,
+        &config,
+        None,
+        "post.banner.png",
+        None,
+        &png(400, 100),
+    )
+    .unwrap();
+    assert_eq!((banner.width, banner.resized), (200, true));
+
+    let small = store_image(
+        temp.path(),
+        &content,
+        &config,
+        None,
+        "small.png",
+        None,
+        &png(50, 50),
+    )
+    .unwrap();
+    assert!(!small.resized);
+}
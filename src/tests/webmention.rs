@@ -0,0 +1,152 @@
+use super::*;
+
+fn sent(hash: &str, endpoint: Option<&str>, error: Option<&str>) -> Notification {
+    Notification {
+        hash: hash.to_string(),
+        endpoint: endpoint.map(ToString::to_string),
+        status: None,
+        error: error.map(ToString::to_string),
+        sent_at: 0,
+    }
+}
+
+#[test]
+fn test_pending() {
+    let links = vec![
+        "https://a.test/".to_string(),
+        "https://b.test/".to_string(),
+        "https://c.test/".to_string(),
+    ];
+    assert_eq!(pending(&links, "h1", None, false), links);
+
+    let state = BTreeMap::from([
+        (
+            "https://a.test/".to_string(),
+            sent("h1", Some("https://a.test/wm"), None),
+        ),
+        (
+            "https://b.test/".to_string(),
+            sent("h1", None, Some("timeout")),
+        ),
+        (
+            "https://gone.test/".to_string(),
+            sent("h1", Some("https://gone.test/wm"), None),
+        ),
+        ("https://plain.test/".to_string(), sent("h1", None, None)),
+    ]);
+    // Failed and new links, and the removed link that had an endpoint
+    assert_eq!(
+        pending(&links, "h1", Some(&state), false),
+        vec!["https://b.test/", "https://c.test/", "https://gone.test/"]
+    );
+    // A changed post notifies every link again
+    assert_eq!(
+        pending(&links, "h2", Some(&state), false),
+        vec![
+            "https://a.test/",
+            "https://b.test/",
+            "https://c.test/",
+            "https://gone.test/"
+        ]
+    );
+    assert_eq!(pending(&links, "h1", Some(&state), true).len(), 4);
+}
+
+#[test]
+fn test_find_endpoint() {
+    let page = "https://example.test/post/1";
+    assert_eq!(
+        find_endpoint(
+            &["<https://example.test/style.css>; rel=\"stylesheet\", </wm?x=1>; rel=\"other webmention\""],
+            "<link rel=\"webmention\" href=\"/html\">",
+            page
+        ),
+        Some("https://example.test/wm?x=1".to_string())
+    );
+    assert_eq!(
+        find_endpoint(
+            &[],
+            "<!-- <link rel=\"webmention\" href=\"/commented\"> -->\
+             <a href=\"/first\" rel=\"webmention\">x</a><link rel=\"webmention\" href=\"/second\">",
+            page
+        ),
+        Some("https://example.test/first".to_string())
+    );
+    assert_eq!(
+        find_endpoint(&[], "<link rel='webmention' href='wm?a=1&amp;b=2'>", page),
+        Some("https://example.test/post/wm?a=1&b=2".to_string())
+    );
+    // An empty href is the page itself
+    assert_eq!(
+        find_endpoint(&[], "<link rel=\"webmention\" href=\"\">", page),
+        Some(page.to_string())
+    );
+    assert_eq!(
+        find_endpoint(&[], "<link rel=\"webmentions\" href=\"/no\">", page),
+        None
+    );
+}
+
+#[test]
+fn test_target_slug() {
+    assert_eq!(
+        target_slug("https://blog.test/first.html#reply", "https://blog.test/"),
+        Some("first".to_string())
+    );
+    assert_eq!(
+        target_slug("https://blog.test/docs/guide/?x=1", "https://blog.test"),
+        Some("docs/guide".to_string())
+    );
+    assert_eq!(
+        target_slug("https://other.test/first.html", "https://blog.test"),
+        None
+    );
+    assert_eq!(target_slug("https://blog.test/", "https://blog.test"), None);
+    assert_eq!(
+        target_slug("https://any.test/about.html", ""),
+        Some("about".to_string())
+    );
+}
+
+#[test]
+fn test_parse_and_merge_jf2() {
+    let feed = serde_json::json!({"type": "feed", "children": [
+        {
+            "type": "entry",
+            "author": {"name": "Ann <b>", "url": "https://ann.test", "photo": "javascript:alert(1)"},
+            "url": "https://ann.test/like",
+            "wm-source": "https://ann.test/like",
+            "wm-target": "https://blog.test/first.html",
+            "wm-property": "like-of",
+            "wm-received": "2024-01-02T00:00:00Z"
+        },
+        {
+            "type": "entry",
+            "author": {"name": "Bob"},
+            "wm-source": "https://bob.test/reply",
+            "wm-target": "https://blog.test/first.html",
+            "wm-property": "in-reply-to",
+            "published": "2024-01-01T00:00:00Z",
+            "content": {"text": "Nice <post>", "html": "<p>Nice</p>"}
+        },
+        {"wm-source": "https://eve.test", "wm-target": "https://elsewhere.test/x.html"}
+    ]});
+    let fetched = parse_jf2(&feed, "https://blog.test");
+    assert_eq!(fetched.len(), 2);
+    let (slug, like) = &fetched[0];
+    assert_eq!(slug, "first");
+    assert_eq!(like.kind, "like");
+    assert_eq!(like.author_name, "Ann &lt;b&gt;");
+    assert_eq!(like.author_photo, None);
+    assert_eq!(like.published.as_deref(), Some("2024-01-02T00:00:00Z"));
+    assert_eq!(fetched[1].1.content, "Nice &lt;post&gt;");
+    assert_eq!(fetched[1].1.url, "https://bob.test/reply");
+
+    let mut saved = BTreeMap::new();
+    merge(&mut saved, fetched.clone());
+    merge(&mut saved, fetched);
+    let mentions = Webmentions::new(saved.remove("first").unwrap());
+    assert_eq!(mentions.likes.len(), 1);
+    assert_eq!(mentions.replies.len(), 1);
+    assert!(mentions.reposts.is_empty() && mentions.mentions.is_empty());
+}
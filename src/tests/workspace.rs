@@ -102,15 +102,15 @@ fn test_deep_merge_yaml_scalars() {
     let merged = deep_merge_yaml(base, overlay);
     let map = merged.as_mapping().unwrap();
     assert_eq!(
-        map.get(&serde_yaml::Value::String("a".into())),
+        map.get(serde_yaml::Value::String("a".into())),
         Some(&serde_yaml::Value::Number(1.into()))
     );
     assert_eq!(
-        map.get(&serde_yaml::Value::String("b".into())),
+        map.get(serde_yaml::Value::String("b".into())),
         Some(&serde_yaml::Value::Number(3.into()))
     );
     assert_eq!(
-        map.get(&serde_yaml::Value::String("c".into())),
+        map.get(serde_yaml::Value::String("c".into())),
         Some(&serde_yaml::Value::Number(4.into()))
     );
 }
@@ -123,26 +123,26 @@ fn test_deep_merge_yaml_nested() {
     let top = merged
         .as_mapping()
         .unwrap()
-        .get(&serde_yaml::Value::String("top".into()))
+        .get(serde_yaml::Value::String("top".into()))
         .unwrap()
         .as_mapping()
         .unwrap();
     assert_eq!(
-        top.get(&serde_yaml::Value::String("a".into())),
+        top.get(serde_yaml::Value::String("a".into())),
         Some(&serde_yaml::Value::Number(1.into()))
     );
     assert_eq!(
-        top.get(&serde_yaml::Value::String("b".into())),
+        top.get(serde_yaml::Value::String("b".into())),
         Some(&serde_yaml::Value::Number(3.into()))
     );
     assert_eq!(
-        top.get(&serde_yaml::Value::String("c".into())),
+        top.get(serde_yaml::Value::String("c".into())),
         Some(&serde_yaml::Value::Number(4.into()))
     );
     assert!(merged
         .as_mapping()
         .unwrap()
-        .get(&serde_yaml::Value::String("other".into()))
+        .get(serde_yaml::Value::String("other".into()))
         .is_some());
 }
 
@@ -256,8 +256,13 @@ fn test_cli() -> crate::cli::Cli {
         generate_config: false,
         init_site: false,
         force: false,
+        incremental: false,
         shortcodes: false,
         show_urls: false,
+        include_future: false,
+        show_scheduled: false,
+        report: None,
+        deny: Vec::new(),
         skill: false,
         skill_install: false,
         skill_install_claude: false,
@@ -291,6 +296,7 @@ fn test_cli() -> crate::cli::Cli {
             colorscheme: None,
             toc: None,
             json_feed: None,
+            atom_feed: None,
             show_next_prev_links: None,
             publish_md: None,
             source_repository: None,
@@ -298,6 +304,7 @@ fn test_cli() -> crate::cli::Cli {
             theme: None,
             build_sitemap: None,
             publish_urls_json: None,
+            json_api: None,
             enable_shortcodes: None,
             shortcode_pattern: None,
             skip_image_resize: None,
@@ -306,6 +313,7 @@ fn test_cli() -> crate::cli::Cli {
             strict_internal_links: None,
             check_media_links: None,
             native_mermaid_render: None,
+            read_only: None,
         },
         subcommand: None,
     }
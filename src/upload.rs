@@ -0,0 +1,256 @@
+//! Media uploads of the dev server API.
+//!
+//! `POST /__marmite__/media` takes a `multipart/form-data` body with one or
+//! more `file` fields, an optional `slug` and an optional `alt` text. Images
+//! are stored in the media folder, or in `media/{slug}/` where the content
+//! looks for its banner and other media, resized with the `image_resize`
+//! settings, and returned with the markdown to insert them.
+use crate::config::Marmite;
+use crate::image_resize;
+use crate::slugify::slugify;
+use image::ImageReader;
+use serde::Serialize;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+/// Raster formats accepted by the upload, checked against the file content
+const RASTER_EXTENSIONS: [&str; 8] = ["jpg", "jpeg", "png", "webp", "gif", "bmp", "tiff", "avif"];
+
+/// A field of a `multipart/form-data` body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    pub name: String,
+    pub filename: Option<String>,
+    pub data: Vec<u8>,
+}
+
+/// An image stored by the upload
+#[derive(Debug, Clone, Serialize)]
+pub struct Upload {
+    /// Path relative to the input folder
+    pub path: String,
+    /// Path used by the content to link the image, e.g. `media/photo.jpg`
+    pub url: String,
+    pub markdown: String,
+    pub width: u32,
+    pub height: u32,
+    pub resized: bool,
+    pub size: u64,
+}
+
+/// The boundary of a `multipart/form-data` content type
+pub fn boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';');
+    if !params
+        .next()?
+        .trim()
+        .eq_ignore_ascii_case("multipart/form-data")
+    {
+        return None;
+    }
+    params.find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').to_string())
+            .filter(|boundary| !boundary.is_empty())
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Value of a `name="value"` parameter of a `Content-Disposition` header
+fn disposition_param(disposition: &str, name: &str) -> Option<String> {
+    disposition.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// Splits a `multipart/form-data` body into its fields
+pub fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<Part>, String> {
+    let delimiter = format!("--{boundary}");
+    let start = find(body, delimiter.as_bytes()).ok_or("Missing multipart boundary")?;
+    let mut rest = &body[start + delimiter.len()..];
+    let separator = format!("\r\n--{boundary}");
+    let mut parts = Vec::new();
+
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        rest = rest
+            .strip_prefix(b"\r\n")
+            .ok_or("Malformed multipart body")?;
+        let headers_end = find(rest, b"\r\n\r\n").ok_or("Malformed multipart headers")?;
+        let headers = String::from_utf8_lossy(&rest[..headers_end]);
+        let data_start = headers_end + 4;
+        let data_end = find(&rest[data_start..], separator.as_bytes())
+            .ok_or("Unterminated multipart body")?
+            + data_start;
+
+        let disposition = headers
+            .lines()
+            .find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.trim()
+                    .eq_ignore_ascii_case("content-disposition")
+                    .then_some(value)
+            })
+            .ok_or("Multipart field without Content-Disposition")?;
+        parts.push(Part {
+            name: disposition_param(disposition, "name").unwrap_or_default(),
+            filename: disposition_param(disposition, "filename"),
+            data: rest[data_start..data_end].to_vec(),
+        });
+        rest = &rest[data_end + separator.len()..];
+    }
+}
+
+/// A file name safe for the media folder: the last path component with each
+/// dot-separated part slugified, so `My Photo.Banner.JPG` becomes
+/// `my-photo.banner.jpg` and still matches the banner convention
+pub fn safe_filename(filename: &str) -> Option<String> {
+    let name = filename.rsplit(['/', '\\']).next()?;
+    let (stem, ext) = name.rsplit_once('.')?;
+    let stem: Vec<String> = stem
+        .split('.')
+        .map(slugify)
+        .filter(|part| !part.is_empty())
+        .collect();
+    let ext = ext.to_lowercase();
+    if stem.is_empty() || ext.is_empty() || !ext.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(format!("{}.{ext}", stem.join(".")))
+}
+
+/// Refuses anything but raster images whose content matches their
+/// extension. SVG documents are refused too, they can run scripts on the
+/// origin of the server that serves them.
+pub fn check_image(filename: &str, data: &[u8]) -> Result<(), String> {
+    let ext = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+    if ext == "svg" {
+        return Err(format!(
+            "'{filename}' is an SVG image, which can not be uploaded, add it to the media folder instead"
+        ));
+    }
+    if !RASTER_EXTENSIONS.contains(&ext.as_str()) {
+        return Err(format!("'{filename}' is not an image"));
+    }
+    let reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    let matches_extension = reader
+        .format()
+        .is_some_and(|format| format.extensions_str().contains(&ext.as_str()));
+    if !matches_extension {
+        return Err(format!("'{filename}' is not a valid {ext} image"));
+    }
+    reader
+        .into_dimensions()
+        .map(|_| ())
+        .map_err(|e| format!("'{filename}' is not a valid image: {e}"))
+}
+
+/// Slugs name the media subfolder, so they must be a single path component
+pub fn check_slug(slug: &str) -> Result<(), String> {
+    if slug.is_empty() || slug.starts_with('.') || slug.contains(['/', '\\']) {
+        return Err(format!("Invalid slug '{slug}'"));
+    }
+    Ok(())
+}
+
+/// Alt text that can not end the image markdown early: brackets and
+/// backslashes are escaped and line breaks become spaces
+fn escape_alt(alt: &str) -> String {
+    let mut escaped = String::with_capacity(alt.len());
+    for (index, line) in alt
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .enumerate()
+    {
+        if index > 0 {
+            escaped.push(' ');
+        }
+        for c in line.chars() {
+            if matches!(c, '\\' | '[' | ']') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Stores an image checked by [`check_image`] in the media folder of the
+/// content folder, in the `slug` subfolder when given. Existing files are
+/// kept, the new one gets a `-2`, `-3`... suffix.
+pub fn store_image(
+    input_folder: &Path,
+    content_folder: &Path,
+    config: &Marmite,
+    slug: Option<&str>,
+    filename: &str,
+    alt: Option<&str>,
+    data: &[u8],
+) -> Result<Upload, String> {
+    let mut url_dir = config.media_path.clone();
+    if let Some(slug) = slug {
+        check_slug(slug)?;
+        url_dir = format!("{url_dir}/{slug}");
+    }
+    let dir = content_folder.join(&url_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+
+    let (stem, ext) = filename.rsplit_once('.').unwrap_or((filename, ""));
+    let mut name = filename.to_string();
+    let mut counter = 1;
+    while dir.join(&name).exists() {
+        counter += 1;
+        name = format!("{stem}-{counter}.{ext}");
+    }
+    let path = dir.join(&name);
+    fs::write(&path, data).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+
+    let (width, height, resized) =
+        image_resize::resize_uploaded_image(&path, config).map_err(|e| {
+            let _ = fs::remove_file(&path);
+            format!("Failed to process '{filename}': {e}")
+        })?;
+    let size = fs::metadata(&path).map_or(0, |meta| meta.len());
+
+    let url = format!("{url_dir}/{name}");
+    let alt = alt.map_or_else(
+        || stem.split('.').next().unwrap_or(stem).replace('-', " "),
+        str::to_string,
+    );
+    Ok(Upload {
+        path: path
+            .strip_prefix(input_folder)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/"),
+        markdown: format!("![{}]({url})", escape_alt(&alt)),
+        url,
+        width,
+        height,
+        resized,
+        size,
+    })
+}
+
+#[cfg(test)]
+#[path = "tests/upload.rs"]
+mod tests;
@@ -0,0 +1,541 @@
+//! Webmention sending and receiving behind `marmite <folder> webmention`.
+//!
+//! `webmention send` notifies the sites linked by new or changed posts: the
+//! endpoint of each link is discovered from its `Link` header or its HTML,
+//! and the post URL is sent to it as the source. What was sent is kept in
+//! `.marmite-webmention-state.json`, so a link is only notified again when
+//! the post changes or the last attempt failed.
+//!
+//! `webmention fetch` reads the mentions received by the site from a JF2
+//! feed, like the one of webmention.io, or from a JSON file, and saves them
+//! to `.marmite-webmentions.json`. The build reads them into
+//! `content.webmentions` when the `webmention` section is configured.
+use crate::cli::{Cli, WebmentionCommand};
+use crate::config::{Marmite, WebmentionConfig};
+use crate::content::Content;
+use crate::error::Error;
+use crate::parser::escape_html;
+use crate::parser::get_external_links;
+use crate::site::{collect_content, collect_content_fragments, get_content_folder, Data};
+use chrono::Utc;
+use log::{info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use ureq::ResponseExt;
+
+/// Result of notifying one link of a post
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    /// Hash of the post HTML that was notified
+    pub hash: String,
+    /// Webmention endpoint of the link, `None` when it has none
+    pub endpoint: Option<String>,
+    /// HTTP status of the endpoint
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    /// Unix timestamp
+    pub sent_at: i64,
+}
+
+/// Links notified by each post, by slug and link
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SendState {
+    pub posts: BTreeMap<String, BTreeMap<String, Notification>>,
+}
+
+/// A mention received by a content
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Webmention {
+    /// `like`, `repost`, `reply`, `bookmark` or `mention`
+    pub kind: String,
+    /// Page that mentions the content
+    pub source: String,
+    /// Page to link to, the source unless the feed has a better one
+    pub url: String,
+    pub author_name: String,
+    pub author_url: Option<String>,
+    pub author_photo: Option<String>,
+    /// Text of replies and mentions, HTML-escaped
+    pub content: String,
+    pub published: Option<String>,
+}
+
+/// Mentions of a content grouped for the templates
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Webmentions {
+    pub likes: Vec<Webmention>,
+    pub reposts: Vec<Webmention>,
+    pub replies: Vec<Webmention>,
+    /// Mentions and bookmarks
+    pub mentions: Vec<Webmention>,
+}
+
+impl Webmentions {
+    #[must_use]
+    pub fn new(received: Vec<Webmention>) -> Self {
+        let mut webmentions = Self::default();
+        for mention in received {
+            match mention.kind.as_str() {
+                "like" => webmentions.likes.push(mention),
+                "repost" => webmentions.reposts.push(mention),
+                "reply" => webmentions.replies.push(mention),
+                _ => webmentions.mentions.push(mention),
+            }
+        }
+        webmentions
+    }
+}
+
+/// Received mentions as saved by `webmention fetch`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ReceivedFile {
+    mentions: BTreeMap<String, Vec<Webmention>>,
+}
+
+fn state_path(input_folder: &Path) -> PathBuf {
+    input_folder.join(".marmite-webmention-state.json")
+}
+
+fn received_path(input_folder: &Path) -> PathBuf {
+    input_folder.join(".marmite-webmentions.json")
+}
+
+fn load_state(input_folder: &Path) -> SendState {
+    fs::read_to_string(state_path(input_folder))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Mentions saved by `marmite webmention fetch`, by slug
+#[must_use]
+pub fn load(input_folder: &Path) -> BTreeMap<String, Vec<Webmention>> {
+    fs::read_to_string(received_path(input_folder))
+        .ok()
+        .and_then(|json| serde_json::from_str::<ReceivedFile>(&json).ok())
+        .map(|file| file.mentions)
+        .unwrap_or_default()
+}
+
+fn hash(html: &str) -> String {
+    Sha256::digest(html.as_bytes())
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            use std::fmt::Write;
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Links of a post to notify: new links, links that failed, every link when
+/// the post changed, and the links removed from the post that had an
+/// endpoint, so their site can update the mention
+fn pending(
+    links: &[String],
+    hash: &str,
+    sent: Option<&BTreeMap<String, Notification>>,
+    force: bool,
+) -> Vec<String> {
+    let previous = |link: &str| sent.and_then(|sent| sent.get(link));
+    let mut pending: Vec<String> = links
+        .iter()
+        .filter(|link| {
+            force || previous(link).is_none_or(|sent| sent.hash != hash || sent.error.is_some())
+        })
+        .cloned()
+        .collect();
+    for (link, sent) in sent.into_iter().flatten() {
+        if !links.contains(link) && (sent.endpoint.is_some() || sent.error.is_some()) {
+            pending.push(link.clone());
+        }
+    }
+    pending
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    Regex::new(&format!(r#"(?i)\s{name}\s*=\s*["']([^"']*)["']"#))
+        .expect("Attribute regex should compile")
+        .captures(tag)
+        .map(|cap| cap[1].replace("&amp;", "&"))
+}
+
+fn has_webmention_rel(rel: &str) -> bool {
+    rel.split_whitespace()
+        .any(|rel| rel.eq_ignore_ascii_case("webmention"))
+}
+
+/// Endpoint advertised by a page, from its `Link` headers first and then
+/// from the first `<link>` or `<a>` with `rel="webmention"`, resolved
+/// against the page URL
+fn find_endpoint(link_headers: &[&str], html: &str, page_url: &str) -> Option<String> {
+    let header = link_headers
+        .iter()
+        .flat_map(|header| header.split(','))
+        .find_map(|value| {
+            let (target, params) = value.trim().split_once(';')?;
+            let rel = params.split(';').find_map(|param| {
+                let (key, value) = param.trim().split_once('=')?;
+                key.trim()
+                    .eq_ignore_ascii_case("rel")
+                    .then(|| value.trim().trim_matches('"').to_string())
+            })?;
+            has_webmention_rel(&rel).then(|| {
+                target
+                    .trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+        });
+    let endpoint = header.or_else(|| {
+        let comments = Regex::new(r"(?s)<!--.*?-->").expect("Comment regex should compile");
+        let html = comments.replace_all(html, "");
+        Regex::new(r"(?i)<(?:link|a)\s[^>]*>")
+            .expect("Link tag regex should compile")
+            .find_iter(&html)
+            .find(|tag| attribute(tag.as_str(), "rel").is_some_and(|rel| has_webmention_rel(&rel)))
+            .and_then(|tag| attribute(tag.as_str(), "href"))
+    })?;
+    // An empty href is the page itself
+    url::Url::parse(page_url)
+        .and_then(|base| base.join(&endpoint))
+        .map(|url| url.to_string())
+        .ok()
+}
+
+struct Sender {
+    agent: ureq::Agent,
+}
+
+impl Sender {
+    fn new(config: &WebmentionConfig) -> Self {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(config.timeout)))
+            .http_status_as_error(false)
+            .user_agent(concat!(
+                "marmite/",
+                env!("CARGO_PKG_VERSION"),
+                " webmention"
+            ))
+            .build()
+            .into();
+        Self { agent }
+    }
+
+    /// Webmention endpoint of a page, following redirects
+    fn discover(&self, target: &str) -> Result<Option<String>, String> {
+        let mut response = self.agent.get(target).call().map_err(|e| e.to_string())?;
+        let page_url = response.get_uri().to_string();
+        let headers: Vec<&str> = response
+            .headers()
+            .get_all("Link")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        if let Some(endpoint) = find_endpoint(&headers, "", &page_url) {
+            return Ok(Some(endpoint));
+        }
+        let is_html = response
+            .headers()
+            .get("Content-Type")
+            .and_then(|value| value.to_str().ok())
+            .is_none_or(|value| value.contains("html"));
+        if !is_html || response.status().as_u16() >= 400 {
+            return Ok(None);
+        }
+        let html = response
+            .body_mut()
+            .read_to_string()
+            .map_err(|e| e.to_string())?;
+        Ok(find_endpoint(&[], &html, &page_url))
+    }
+
+    /// Notifies the links of a post, returning the result of each
+    fn notify(&self, source: &str, target: &str, hash: &str) -> Notification {
+        let mut sent = Notification {
+            hash: hash.to_string(),
+            endpoint: None,
+            status: None,
+            error: None,
+            sent_at: Utc::now().timestamp(),
+        };
+        match self.discover(target) {
+            Ok(Some(endpoint)) => {
+                let result = self
+                    .agent
+                    .post(&endpoint)
+                    .send_form([("source", source), ("target", target)]);
+                match result {
+                    Ok(response) => {
+                        let status = response.status().as_u16();
+                        sent.status = Some(status);
+                        if !(200..300).contains(&status) {
+                            sent.error = Some(format!("The endpoint answered {status}"));
+                        }
+                    }
+                    Err(e) => sent.error = Some(e.to_string()),
+                }
+                sent.endpoint = Some(endpoint);
+            }
+            Ok(None) => {}
+            Err(e) => sent.error = Some(format!("Could not discover the endpoint: {e}")),
+        }
+        sent
+    }
+}
+
+fn load_site(args: &Cli, input_folder: &Path) -> Result<Data, Error> {
+    let mut site_data = Data::from_file(&input_folder.join(&args.config));
+    site_data
+        .site
+        .override_from_cli_args(&std::sync::Arc::new(args.clone()));
+    let content_folder = get_content_folder(&site_data.site, input_folder);
+    let fragments = collect_content_fragments(&content_folder);
+    let folder_defaults = crate::site::load_folder_frontmatter(&content_folder);
+    collect_content(
+        &content_folder,
+        &mut site_data,
+        &fragments,
+        None,
+        &folder_defaults,
+    )?;
+    Ok(site_data)
+}
+
+/// Absolute URL of a content
+fn content_url(site: &Marmite, content: &Content) -> String {
+    format!("{}/{}.html", site.url.trim_end_matches('/'), content.slug)
+}
+
+/// Sends the mentions of the new and changed posts
+fn send(
+    args: &Cli,
+    input_folder: &Path,
+    dry_run: bool,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let site_data = load_site(args, input_folder)?;
+    let site = &site_data.site;
+    if site.url.is_empty() {
+        return Err("Set url in marmite.yaml, mentions are sent with the URL of each post".into());
+    }
+    let config = site.webmention.clone().unwrap_or_default();
+    let ignore = config
+        .ignore
+        .iter()
+        .map(|pattern| Regex::new(pattern))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid webmention ignore pattern: {e}"))?;
+    let own = site.url.trim_end_matches('/');
+
+    let mut state = load_state(input_folder);
+    // (post, hash, links to notify) for each post with something to send
+    let mut plan: Vec<(&Content, String, Vec<String>)> = Vec::new();
+    let mut links_of: HashMap<&str, Vec<String>> = HashMap::new();
+    for post in site_data
+        .posts
+        .iter()
+        .filter(|post| post.stream.as_deref() != Some("draft"))
+    {
+        let links: Vec<String> = get_external_links(&post.html)
+            .into_iter()
+            .filter(|link| !link.starts_with(own))
+            .filter(|link| !ignore.iter().any(|re| re.is_match(link)))
+            .collect();
+        let hash = hash(&post.html);
+        let pending = pending(&links, &hash, state.posts.get(&post.slug), force);
+        links_of.insert(&post.slug, links);
+        if !pending.is_empty() {
+            plan.push((post, hash, pending));
+        }
+    }
+
+    let total: usize = plan.iter().map(|(_, _, links)| links.len()).sum();
+    for (post, _, links) in &plan {
+        for link in links {
+            info!("  {} -> {link}", post.slug);
+        }
+    }
+    info!(
+        "Plan: {total} link(s) to notify from {} post(s)",
+        plan.len()
+    );
+    if dry_run {
+        info!("[dry-run] done — no mentions sent");
+        return Ok(());
+    }
+
+    let sender = Sender::new(&config);
+    let (mut delivered, mut no_endpoint, mut failed) = (0, 0, 0);
+    for (post, hash, links) in &plan {
+        let source = content_url(site, post);
+        let entry = state.posts.entry(post.slug.clone()).or_default();
+        for link in links {
+            let sent = sender.notify(&source, link, hash);
+            match (&sent.endpoint, &sent.error) {
+                (_, Some(error)) => {
+                    failed += 1;
+                    warn!(
+                        "Could not send the mention of {link} from {}: {error}",
+                        post.slug
+                    );
+                }
+                (Some(_), None) => delivered += 1,
+                (None, None) => no_endpoint += 1,
+            }
+            let removed = !links_of[post.slug.as_str()].contains(link);
+            if removed && sent.error.is_none() {
+                entry.remove(link);
+            } else {
+                entry.insert(link.clone(), sent);
+            }
+        }
+        // Removed links without an endpoint have nothing to update
+        entry.retain(|link, sent| {
+            links_of[post.slug.as_str()].contains(link)
+                || sent.endpoint.is_some()
+                || sent.error.is_some()
+        });
+    }
+    // Posts that are gone are not tracked anymore
+    state
+        .posts
+        .retain(|slug, _| links_of.contains_key(slug.as_str()));
+    fs::write(
+        state_path(input_folder),
+        serde_json::to_string_pretty(&state)?,
+    )?;
+    info!("Sent {delivered} mention(s), {no_endpoint} link(s) without endpoint, {failed} failed");
+    Ok(())
+}
+
+/// Slug of the content a mention targets, from its URL on the site
+fn target_slug(target: &str, site_url: &str) -> Option<String> {
+    let path = match site_url.trim_end_matches('/') {
+        "" => url::Url::parse(target).ok()?.path().to_string(),
+        site_url => target.strip_prefix(site_url)?.to_string(),
+    };
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let slug = path.trim_matches('/');
+    let slug = slug.strip_suffix(".html").unwrap_or(slug);
+    (!slug.is_empty()).then(|| slug.to_string())
+}
+
+/// Keeps `http(s)` URLs only, so a mention cannot inject a script link
+fn web_url(value: &serde_json::Value) -> Option<String> {
+    value
+        .as_str()
+        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+        .map(escape_html)
+}
+
+/// Mentions of a JF2 feed, or of a JSON array of JF2 entries, by slug
+fn parse_jf2(feed: &serde_json::Value, site_url: &str) -> Vec<(String, Webmention)> {
+    let entries = feed
+        .get("children")
+        .unwrap_or(feed)
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let target = entry["wm-target"].as_str().or(entry["target"].as_str())?;
+            let source = web_url(&entry["wm-source"]).or_else(|| web_url(&entry["source"]))?;
+            let kind = match entry["wm-property"].as_str().unwrap_or_default() {
+                "like-of" => "like",
+                "repost-of" => "repost",
+                "in-reply-to" => "reply",
+                "bookmark-of" => "bookmark",
+                _ => "mention",
+            };
+            let author = &entry["author"];
+            let text = entry["content"]["text"]
+                .as_str()
+                .or(entry["content"].as_str())
+                .unwrap_or_default();
+            let mention = Webmention {
+                kind: kind.to_string(),
+                url: web_url(&entry["url"]).unwrap_or_else(|| source.clone()),
+                source,
+                author_name: escape_html(author["name"].as_str().unwrap_or_default()),
+                author_url: web_url(&author["url"]),
+                author_photo: web_url(&author["photo"]),
+                content: escape_html(text),
+                published: entry["published"]
+                    .as_str()
+                    .or(entry["wm-received"].as_str())
+                    .map(escape_html),
+            };
+            Some((target_slug(target, site_url)?, mention))
+        })
+        .collect()
+}
+
+/// Adds the fetched mentions to the saved ones, a mention from the same
+/// source and of the same kind replaces the saved one
+fn merge(
+    saved: &mut BTreeMap<String, Vec<Webmention>>,
+    fetched: Vec<(String, Webmention)>,
+) -> usize {
+    let count = fetched.len();
+    for (slug, mention) in fetched {
+        let mentions = saved.entry(slug).or_default();
+        mentions.retain(|m| !(m.source == mention.source && m.kind == mention.kind));
+        mentions.push(mention);
+    }
+    for mentions in saved.values_mut() {
+        mentions.sort_by(|a, b| a.published.cmp(&b.published));
+    }
+    count
+}
+
+/// Saves the mentions received by the site
+fn fetch(args: &Cli, input_folder: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let site_data = Data::from_file(&input_folder.join(&args.config));
+    let source = site_data
+        .site
+        .webmention
+        .as_ref()
+        .and_then(|config| config.source.clone())
+        .ok_or(
+            "Set webmention.source in marmite.yaml to the URL or file of the received mentions",
+        )?;
+    let feed: serde_json::Value = if source.starts_with("http://") || source.starts_with("https://")
+    {
+        ureq::get(&source)
+            .call()
+            .map_err(|e| format!("Could not fetch the mentions: {e}"))?
+            .body_mut()
+            .read_json()?
+    } else {
+        serde_json::from_str(&fs::read_to_string(input_folder.join(&source))?)?
+    };
+
+    let mut saved = load(input_folder);
+    let fetched = merge(&mut saved, parse_jf2(&feed, &site_data.site.url));
+    let json = serde_json::to_string_pretty(&ReceivedFile { mentions: saved })?;
+    fs::write(received_path(input_folder), json)?;
+    info!("Fetched {fetched} mention(s)");
+    Ok(())
+}
+
+pub fn run(cmd: &WebmentionCommand, args: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let input_folder = args
+        .input_folder
+        .as_deref()
+        .ok_or("Input folder is required for webmention")?;
+    match cmd {
+        WebmentionCommand::Send { dry_run, force } => send(args, input_folder, *dry_run, *force),
+        WebmentionCommand::Fetch => fetch(args, input_folder),
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/webmention.rs"]
+mod tests;
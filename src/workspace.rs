@@ -168,7 +168,7 @@ fn preprocess_all_sites(
             &fragments,
             None,
             &folder_defaults,
-        );
+        )?;
         site_data.sort_all();
         site_data.collect_all_urls();
 
@@ -534,9 +534,14 @@ fn make_rebuild_fn(
                 Ok(data) => data,
                 Err(e) => {
                     error!("Failed to preprocess workspace: {e}");
+                    if let Some(lr) = &live_reload {
+                        lr.notify_build(&[], &[e.as_ref()]);
+                    }
                     return;
                 }
             };
+        let mut diagnostics = Vec::new();
+        let mut failures = Vec::new();
 
         let default_site_name = ws_config_clone.resolved_default_site().map(String::from);
 
@@ -573,7 +578,7 @@ fn make_rebuild_fn(
                 site_entry.resolved_output_path()
             };
 
-            if let Err(e) = site::build_site_with_config(
+            match site::build_site_with_config(
                 &merged_config,
                 &site_input,
                 &site_output,
@@ -581,7 +586,11 @@ fn make_rebuild_fn(
                 Some(&cross_site_data),
                 path_prefix,
             ) {
-                error!("Failed to rebuild site '{}': {e}", site_entry.name);
+                Ok(site_data) => diagnostics.extend(site_data.diagnostics),
+                Err(e) => {
+                    error!("Failed to rebuild site '{}': {e}", site_entry.name);
+                    failures.push(e);
+                }
             }
         }
 
@@ -601,11 +610,21 @@ fn make_rebuild_fn(
         }
 
         if let Some(lr) = &live_reload {
-            lr.notify_reload();
+            let failures: Vec<&(dyn std::error::Error + 'static)> =
+                failures.iter().map(|e| e as _).collect();
+            lr.notify_build(&diagnostics, &failures);
+            lr.notify_changes(&output_root_owned);
         }
     }))
 }
 
+/// Changes to the output, the content history or git do not rebuild
+fn is_ignored_change(path: &Path, out_folder: &Path) -> bool {
+    crate::history::is_metadata_path(path)
+        || path
+            .starts_with(fs::canonicalize(out_folder).unwrap_or_else(|_| out_folder.to_path_buf()))
+}
+
 #[allow(clippy::too_many_arguments)]
 fn handle_workspace_watch_serve(
     ws_config: &WorkspaceConfig,
@@ -617,11 +636,13 @@ fn handle_workspace_watch_serve(
     bind_address: &str,
     cli_args: &Arc<Cli>,
 ) {
-    let live_reload = if watch && serve {
-        Some(crate::server::LiveReload::new())
-    } else {
-        None
-    };
+    let live_reload = (watch && serve).then(|| {
+        let live_reload = crate::server::LiveReload::new();
+        live_reload.record_output(output_root);
+        live_reload
+    });
+    let mut defaults = ws_config.defaults.clone().unwrap_or_default();
+    defaults.override_from_cli_args(cli_args);
 
     if watch {
         let mut hotwatch = match hotwatch::Hotwatch::new() {
@@ -647,17 +668,14 @@ fn handle_workspace_watch_serve(
             let watch_result = hotwatch.watch(site_input.clone(), move |event: hotwatch::Event| {
                 use hotwatch::EventKind;
                 match event.kind {
-                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
-                        let in_output = event.paths.iter().any(|p| {
-                            p.starts_with(
-                                fs::canonicalize(&out_folder)
-                                    .unwrap_or_else(|_| out_folder.clone()),
-                            )
-                        });
-                        if !in_output {
-                            if let Ok(rebuild_fn) = rebuild_clone.lock() {
-                                rebuild_fn();
-                            }
+                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                        if !event
+                            .paths
+                            .iter()
+                            .any(|p| is_ignored_change(p, &out_folder)) =>
+                    {
+                        if let Ok(rebuild_fn) = rebuild_clone.lock() {
+                            rebuild_fn();
                         }
                     }
                     _ => {}
@@ -701,6 +719,8 @@ fn handle_workspace_watch_serve(
                 config_path: Arc::new(ws_config_path.to_path_buf()),
                 enable_toolbar: true,
                 watch_enabled: true,
+                activitypub: None,
+                server_config: defaults.server.clone(),
             };
             crate::server::start(bind_address, &ctx, live_reload.as_ref());
         } else {
@@ -716,6 +736,8 @@ fn handle_workspace_watch_serve(
             config_path: Arc::new(ws_config_path.to_path_buf()),
             enable_toolbar: true,
             watch_enabled: false,
+            activitypub: None,
+            server_config: defaults.server,
         };
         crate::server::start(bind_address, &ctx, None);
     }
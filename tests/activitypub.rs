@@ -0,0 +1,190 @@
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use rsa::pkcs8::{DecodePrivateKey, EncodePrivateKey, EncodePublicKey, LineEnding};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// Kills the server when the test ends, even on failure
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Posts an activity signed with `key_pem` as `key_id`
+fn post_signed(url: &str, activity: &serde_json::Value, key_pem: &str, key_id: &str) -> u16 {
+    let body = activity.to_string();
+    let parsed = url::Url::parse(url).unwrap();
+    let host = format!("{}:{}", parsed.host_str().unwrap(), parsed.port().unwrap());
+    let date = chrono::Utc::now()
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string();
+    let digest = format!("SHA-256={}", BASE64_STANDARD.encode(Sha256::digest(&body)));
+    let signing_string = format!(
+        "(request-target): post {}\nhost: {host}\ndate: {date}\ndigest: {digest}",
+        parsed.path()
+    );
+    let der = rsa::RsaPrivateKey::from_pkcs8_pem(key_pem)
+        .unwrap()
+        .to_pkcs8_der()
+        .unwrap();
+    let pair = ring::signature::RsaKeyPair::from_pkcs8(der.as_bytes()).unwrap();
+    let mut signature = vec![0; pair.public().modulus_len()];
+    pair.sign(
+        &ring::signature::RSA_PKCS1_SHA256,
+        &ring::rand::SystemRandom::new(),
+        signing_string.as_bytes(),
+        &mut signature,
+    )
+    .unwrap();
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .build()
+        .into();
+    agent
+        .post(url)
+        .header("Content-Type", "application/activity+json")
+        .header("Date", &date)
+        .header("Digest", &digest)
+        .header(
+            "Signature",
+            format!(
+                "keyId=\"{key_id}\",algorithm=\"rsa-sha256\",headers=\"(request-target) host date digest\",signature=\"{}\"",
+                BASE64_STANDARD.encode(signature)
+            ),
+        )
+        .send(body.as_bytes())
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+fn get(url: &str) -> (String, serde_json::Value) {
+    let mut response = ureq::get(url).call().unwrap();
+    let content_type = response
+        .headers()
+        .get("Content-Type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    (content_type, response.body_mut().read_json().unwrap())
+}
+
+#[test]
+fn test_actors_outbox_and_follows() {
+    let temp = TempDir::new().unwrap();
+    let input = temp.path();
+    fs::create_dir_all(input.join("content")).unwrap();
+    let port = free_port();
+    let base = format!("http://127.0.0.1:{port}");
+    fs::write(
+        input.join("marmite.yaml"),
+        format!(
+            "name: Blog\nurl: {base}\ndefault_author: alice\nauthors:\n  alice:\n    name: Alice\n\
+             activitypub:\n  inbox: true\n"
+        ),
+    )
+    .unwrap();
+    fs::write(
+        input.join("content/2024-01-01-first.md"),
+        "---\ntags: rust\n---\n# First\n\nHello",
+    )
+    .unwrap();
+    fs::write(
+        input.join("content/2024-01-02-second.md"),
+        "# Second\n\nWorld",
+    )
+    .unwrap();
+    fs::write(input.join("content/draft-S-wip.md"), "# Wip\n\nNot yet").unwrap();
+
+    let bind = format!("127.0.0.1:{port}");
+    let _server = Server(
+        Command::new(env!("CARGO_BIN_EXE_marmite"))
+            .args([input.to_str().unwrap(), "--serve", "--bind", &bind])
+            .env_remove("MARMITE_ACTIVITYPUB_KEY")
+            .spawn()
+            .expect("Failed to start marmite"),
+    );
+    let start = Instant::now();
+    while TcpStream::connect(&bind).is_err() {
+        assert!(
+            start.elapsed() < Duration::from_secs(180),
+            "server did not start"
+        );
+        std::thread::sleep(Duration::from_millis(200));
+    }
+
+    let (content_type, jrd) = get(&format!(
+        "{base}/.well-known/webfinger?resource=acct:alice@127.0.0.1:{port}"
+    ));
+    assert!(content_type.starts_with("application/jrd+json"));
+    let actor_url = format!("{base}/activitypub/alice.json");
+    assert_eq!(jrd["links"][0]["href"], actor_url.as_str());
+
+    let (content_type, actor) = get(&actor_url);
+    assert_eq!(content_type, "application/activity+json");
+    assert_eq!(actor["type"], "Person");
+    let (_, outbox) = get(actor["outbox"].as_str().unwrap());
+    assert_eq!(outbox["totalItems"], 2);
+    assert_eq!(
+        outbox["orderedItems"][0]["object"]["url"],
+        format!("{base}/second.html")
+    );
+    let (_, note) = get(&format!("{base}/activitypub/objects/first.json"));
+    assert_eq!(note["type"], "Note");
+    assert_eq!(note["tag"][0]["name"], "#rust");
+    assert!(
+        fs::read_to_string(temp.path().join("site/.well-known/webfinger"))
+            .unwrap()
+            .contains("acct:alice@")
+    );
+
+    // The remote actor signs with its own key, any RSA key will do
+    let key_pem = fs::read_to_string(input.join(".marmite-activitypub-key.pem")).unwrap();
+    let public_pem = rsa::RsaPrivateKey::from_pkcs8_pem(&key_pem)
+        .unwrap()
+        .to_public_key()
+        .to_public_key_pem(LineEnding::LF)
+        .unwrap();
+    assert_eq!(actor["publicKey"]["publicKeyPem"], public_pem.as_str());
+    // Actors must be on public https servers, so the inbox does not fetch
+    // local addresses
+    let carol = "http://127.0.0.1:1/users/carol";
+    let inbox = format!("{base}/activitypub/alice/inbox");
+    let follow = serde_json::json!({
+        "id": format!("{carol}#follow-1"),
+        "type": "Follow",
+        "actor": carol,
+        "object": actor_url,
+    });
+
+    let unsigned = ureq::post(&inbox)
+        .config()
+        .http_status_as_error(false)
+        .build()
+        .send(follow.to_string().as_bytes())
+        .unwrap();
+    assert_eq!(unsigned.status(), 401);
+    assert_eq!(
+        post_signed(&inbox, &follow, &key_pem, &format!("{carol}#main-key")),
+        401
+    );
+    let (_, followers) = get(&format!("{base}/activitypub/alice/followers.json"));
+    assert_eq!(followers["totalItems"], 0);
+    assert!(!input.join(".marmite/activitypub-followers.json").exists());
+}
@@ -0,0 +1,662 @@
+use base64::prelude::{Engine as _, BASE64_URL_SAFE_NO_PAD};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+const PUBLICATION: &str = "at://did:plc:me/site.standard.publication/blog";
+
+/// Records of a mock PDS and AppView, with the XRPC methods it received
+#[derive(Default)]
+struct Pds {
+    /// Collection, rkey and value of each record
+    records: Vec<(String, String, serde_json::Value)>,
+    /// Threads returned by `getPostThread`, by post URI
+    threads: HashMap<String, serde_json::Value>,
+    calls: Vec<String>,
+    next: usize,
+    /// URL of the server, also the OAuth issuer
+    base: String,
+    oauth: OAuth,
+}
+
+/// Pushed request and tokens of the mock authorization server
+#[derive(Default)]
+struct OAuth {
+    challenge: String,
+    state: String,
+    redirect_uri: String,
+    access_token: String,
+    /// Only the last refresh token is accepted
+    refresh_token: String,
+    issued: usize,
+}
+
+struct Reply {
+    status: u16,
+    body: serde_json::Value,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl Reply {
+    fn json(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            body,
+            headers: Vec::new(),
+        }
+    }
+
+    /// A DPoP nonce the client must retry with
+    fn use_nonce(status: u16, nonce: &str) -> Self {
+        Self {
+            status,
+            body: serde_json::json!({"error": "use_dpop_nonce"}),
+            headers: vec![
+                ("DPoP-Nonce", nonce.to_string()),
+                (
+                    "WWW-Authenticate",
+                    "DPoP error=\"use_dpop_nonce\"".to_string(),
+                ),
+            ],
+        }
+    }
+}
+
+/// Claims of a DPoP proof
+fn claims(proof: Option<&String>) -> serde_json::Value {
+    proof
+        .and_then(|proof| proof.split('.').nth(1))
+        .and_then(|claims| BASE64_URL_SAFE_NO_PAD.decode(claims).ok())
+        .and_then(|claims| serde_json::from_slice(&claims).ok())
+        .unwrap_or_default()
+}
+
+impl Pds {
+    fn uri(collection: &str, rkey: &str) -> String {
+        format!("at://did:plc:me/{collection}/{rkey}")
+    }
+
+    fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self
+            .collection("site.standard.document")
+            .iter()
+            .map(|value| value["path"].as_str().unwrap().to_string())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    fn collection(&self, collection: &str) -> Vec<serde_json::Value> {
+        self.records
+            .iter()
+            .filter(|record| record.0 == collection)
+            .map(|record| record.2.clone())
+            .collect()
+    }
+
+    fn handle(
+        &mut self,
+        method: &str,
+        query: &HashMap<String, String>,
+        body: &[u8],
+    ) -> serde_json::Value {
+        self.calls.push(method.to_string());
+        let json: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+        let collection = json["collection"].as_str().unwrap_or_default().to_string();
+        match method {
+            "com.atproto.server.createSession" => serde_json::json!({
+                "accessJwt": "jwt",
+                "did": "did:plc:me",
+                "handle": "me.test"
+            }),
+            "com.atproto.repo.listRecords" => {
+                let records: Vec<_> = self
+                    .records
+                    .iter()
+                    .filter(|record| Some(&record.0) == query.get("collection"))
+                    .map(|(collection, rkey, value)| {
+                        serde_json::json!({"uri": Self::uri(collection, rkey), "value": value})
+                    })
+                    .collect();
+                serde_json::json!({"records": records})
+            }
+            "com.atproto.repo.createRecord" => {
+                self.next += 1;
+                let rkey = format!("r{}", self.next);
+                self.records
+                    .push((collection.clone(), rkey.clone(), json["record"].clone()));
+                serde_json::json!({"uri": Self::uri(&collection, &rkey)})
+            }
+            "com.atproto.repo.putRecord" => {
+                let rkey = json["rkey"].as_str().unwrap();
+                for record in &mut self.records {
+                    if record.0 == collection && record.1 == rkey {
+                        record.2 = json["record"].clone();
+                    }
+                }
+                serde_json::json!({"uri": Self::uri(&collection, rkey)})
+            }
+            "com.atproto.repo.deleteRecord" => {
+                let rkey = json["rkey"].as_str().unwrap();
+                self.records
+                    .retain(|record| !(record.0 == collection && record.1 == rkey));
+                serde_json::json!({})
+            }
+            "com.atproto.repo.uploadBlob" => serde_json::json!({"blob": {
+                "$type": "blob",
+                "ref": {"$link": "bafkreiblob"},
+                "mimeType": "image/png",
+                "size": body.len()
+            }}),
+            "app.bsky.feed.getPostThread" => {
+                let uri = query.get("uri").cloned().unwrap_or_default();
+                serde_json::json!({"thread": self.threads.get(&uri).cloned().unwrap_or_default()})
+            }
+            _ => serde_json::json!({"error": "MethodNotImplemented"}),
+        }
+    }
+}
+
+impl Pds {
+    fn issue_tokens(&mut self) -> Reply {
+        self.oauth.issued += 1;
+        self.oauth.access_token = format!("at{}", self.oauth.issued);
+        self.oauth.refresh_token = format!("rt{}", self.oauth.issued);
+        Reply::json(
+            200,
+            serde_json::json!({
+                "access_token": self.oauth.access_token,
+                "refresh_token": self.oauth.refresh_token,
+                "token_type": "DPoP",
+                // Expired right away, so every publish refreshes
+                "expires_in": 0,
+                "sub": "did:plc:me",
+                "scope": "atproto transition:generic"
+            }),
+        )
+    }
+
+    /// Answers the OAuth discovery and authorization server endpoints
+    fn authorize(
+        &mut self,
+        path: &str,
+        query: &HashMap<String, String>,
+        headers: &HashMap<String, String>,
+        body: &[u8],
+    ) -> Option<Reply> {
+        let base = self.base.clone();
+        let form: HashMap<String, String> =
+            url::form_urlencoded::parse(body).into_owned().collect();
+        let reply = match path {
+            "/.well-known/oauth-protected-resource" => Reply::json(
+                200,
+                serde_json::json!({"resource": base, "authorization_servers": [base]}),
+            ),
+            "/.well-known/oauth-authorization-server" => Reply::json(
+                200,
+                serde_json::json!({
+                    "issuer": base,
+                    "authorization_endpoint": format!("{base}/oauth/authorize"),
+                    "token_endpoint": format!("{base}/oauth/token"),
+                    "pushed_authorization_request_endpoint": format!("{base}/oauth/par")
+                }),
+            ),
+            "/oauth/par" | "/oauth/token" if claims(headers.get("dpop"))["nonce"] != "as-nonce" => {
+                Reply::use_nonce(400, "as-nonce")
+            }
+            "/oauth/par" => {
+                self.calls.push("oauth.par".to_string());
+                self.oauth.challenge = form["code_challenge"].clone();
+                self.oauth.state = form["state"].clone();
+                self.oauth.redirect_uri = form["redirect_uri"].clone();
+                Reply::json(
+                    201,
+                    serde_json::json!({"request_uri": "urn:ietf:params:oauth:request_uri:1", "expires_in": 60}),
+                )
+            }
+            "/oauth/authorize" => {
+                assert_eq!(query["request_uri"], "urn:ietf:params:oauth:request_uri:1");
+                Reply {
+                    status: 302,
+                    body: serde_json::json!({}),
+                    headers: vec![(
+                        "Location",
+                        format!(
+                            "{}?code=code&state={}&iss={}",
+                            self.oauth.redirect_uri,
+                            urlencoding::encode(&self.oauth.state),
+                            urlencoding::encode(&base)
+                        ),
+                    )],
+                }
+            }
+            "/oauth/token" => {
+                let grant = form["grant_type"].clone();
+                self.calls.push(format!("oauth.token {grant}"));
+                let valid = match grant.as_str() {
+                    "authorization_code" => {
+                        let verifier = form["code_verifier"].as_bytes();
+                        form["code"] == "code"
+                            && BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(verifier))
+                                == self.oauth.challenge
+                    }
+                    _ => form["refresh_token"] == self.oauth.refresh_token,
+                };
+                if valid {
+                    self.issue_tokens()
+                } else {
+                    Reply::json(400, serde_json::json!({"error": "invalid_grant"}))
+                }
+            }
+            _ => return None,
+        };
+        Some(reply)
+    }
+
+    /// Checks the DPoP-bound access token of an XRPC call
+    fn check_dpop(&self, headers: &HashMap<String, String>) -> Option<Reply> {
+        let token = headers.get("authorization")?.strip_prefix("DPoP ")?;
+        let proof = claims(headers.get("dpop"));
+        if proof["nonce"] != "pds-nonce" {
+            return Some(Reply::use_nonce(401, "pds-nonce"));
+        }
+        let ath = BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()));
+        if token != self.oauth.access_token || proof["ath"] != ath.as_str() {
+            return Some(Reply::json(
+                401,
+                serde_json::json!({"error": "InvalidToken"}),
+            ));
+        }
+        None
+    }
+}
+
+/// Serves the XRPC calls of `atproto publish` and the OAuth flow from a
+/// shared [`Pds`]
+fn start_pds(pds: Arc<Mutex<Pds>>) -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    pds.lock().unwrap().base = base.clone();
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let url = request.url().to_string();
+            let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+            let method = path.trim_start_matches("/xrpc/");
+            let query: HashMap<String, String> = query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| {
+                    (
+                        key.to_string(),
+                        urlencoding::decode(value).unwrap().into_owned(),
+                    )
+                })
+                .collect();
+            let headers: HashMap<String, String> = request
+                .headers()
+                .iter()
+                .map(|header| {
+                    (
+                        header.field.as_str().as_str().to_ascii_lowercase(),
+                        header.value.as_str().to_string(),
+                    )
+                })
+                .collect();
+            let mut body = Vec::new();
+            let _ = request.as_reader().read_to_end(&mut body);
+            let reply = {
+                let mut pds = pds.lock().unwrap();
+                pds.authorize(path, &query, &headers, &body)
+                    .or_else(|| pds.check_dpop(&headers))
+                    .unwrap_or_else(|| Reply::json(200, pds.handle(method, &query, &body)))
+            };
+            let mut response = tiny_http::Response::from_string(reply.body.to_string())
+                .with_status_code(reply.status)
+                .with_header(
+                    "Content-Type: application/json"
+                        .parse::<tiny_http::Header>()
+                        .unwrap(),
+                );
+            for (name, value) in reply.headers {
+                response.add_header(tiny_http::Header::from_bytes(name, value).unwrap());
+            }
+            let _ = request.respond(response);
+        }
+    });
+    base
+}
+
+/// `marmite <input> atproto <args>` against the mock PDS, with its own home
+fn command(temp: &Path, pds_url: &str, args: &[&str]) -> Command {
+    let home = temp.join("home");
+    let mut command = Command::new("cargo");
+    command
+        .args(["run", "--quiet", "--", temp.join("input").to_str().unwrap()])
+        .arg("atproto")
+        .args(args)
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("ATPROTO_PDS_URL", pds_url)
+        .env("ATPROTO_APPVIEW_URL", pds_url)
+        .env_remove("MARMITE_CREDENTIALS_FILE")
+        .env_remove("MARMITE_CREDENTIALS_KEY");
+    command
+}
+
+/// Runs `marmite <input> atproto <args>` with credentials for the mock PDS
+fn atproto(temp: &Path, pds_url: &str, args: &[&str]) -> Output {
+    let home = temp.join("home");
+    let config_dir = if cfg!(target_os = "macos") {
+        home.join("Library/Application Support")
+    } else {
+        home.join(".config")
+    };
+    fs::create_dir_all(config_dir.join("marmite")).unwrap();
+    fs::write(
+        config_dir.join("marmite/credentials.json"),
+        serde_json::json!({"me.test": {
+            "pds_url": pds_url,
+            "identifier": "me.test",
+            "password": "secret"
+        }})
+        .to_string(),
+    )
+    .unwrap();
+    let output = command(temp, pds_url, args)
+        .output()
+        .expect("Failed to execute marmite");
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+#[test]
+fn test_publish_reconciles_with_the_pds() {
+    let temp = TempDir::new().unwrap();
+    let content = temp.path().join("input/content");
+    fs::create_dir_all(&content).unwrap();
+    fs::write(
+        temp.path().join("input/marmite.yaml"),
+        format!(
+            "name: Blog\nurl: https://blog.test\natproto:\n  handle: me.test\n  \
+             publication_uri: {PUBLICATION}\n  publish_pages: true\n"
+        ),
+    )
+    .unwrap();
+    fs::write(content.join("2024-01-01-first.md"), "# First\n\nHello").unwrap();
+    fs::write(
+        content.join("2024-01-02-idea.md"),
+        "---\nstream: draft\n---\n# Idea\n\nLater",
+    )
+    .unwrap();
+    fs::write(content.join("about.md"), "# About\n\nMe").unwrap();
+
+    let pds = Arc::new(Mutex::new(Pds::default()));
+    {
+        let mut pds = pds.lock().unwrap();
+        pds.records.push((
+            "site.standard.document".to_string(),
+            "gone".to_string(),
+            serde_json::json!({"site": PUBLICATION, "path": "/removed.html"}),
+        ));
+        pds.records.push((
+            "site.standard.document".to_string(),
+            "theirs".to_string(),
+            serde_json::json!({"site": "at://did:plc:me/site.standard.publication/other", "path": "/other.html"}),
+        ));
+    }
+    let pds_url = start_pds(Arc::clone(&pds));
+    let state = temp.path().join("input/.marmite-atproto-state.json");
+
+    let output = atproto(temp.path(), &pds_url, &["publish", "--dry-run"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("publish: first"), "{stderr}");
+    assert!(stderr.contains("publish: about"), "{stderr}");
+    assert!(stderr.contains("delete: removed (at://did:plc:me/site.standard.document/gone)"));
+    assert!(stderr.contains("Plan: 2 to publish, 0 to update, 1 to delete, 0 unchanged"));
+    assert!(!stderr.contains("idea"));
+    assert!(!state.exists());
+    assert_eq!(pds.lock().unwrap().paths().len(), 2);
+
+    atproto(temp.path(), &pds_url, &["publish"]);
+    assert_eq!(
+        pds.lock().unwrap().paths(),
+        vec!["/about.html", "/first.html", "/other.html"]
+    );
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&state).unwrap()).unwrap();
+    assert!(saved["posts"]["first"]["at_uri"].is_string());
+    assert!(saved["posts"]["about"]["at_uri"].is_string());
+
+    // Moving a post to the draft stream removes its record
+    fs::write(
+        content.join("2024-01-01-first.md"),
+        "---\nstream: draft\n---\n# First\n\nHello",
+    )
+    .unwrap();
+    let output = atproto(temp.path(), &pds_url, &["publish"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Published 0, updated 0, deleted 1, skipped 1 documents"),
+        "{stderr}"
+    );
+    assert_eq!(
+        pds.lock().unwrap().paths(),
+        vec!["/about.html", "/other.html"]
+    );
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&state).unwrap()).unwrap();
+    assert!(saved["posts"].get("first").is_none());
+    assert!(pds
+        .lock()
+        .unwrap()
+        .calls
+        .iter()
+        .all(|call| call != "com.atproto.repo.putRecord"));
+}
+
+#[test]
+fn test_announce_and_render_replies_as_comments() {
+    let temp = TempDir::new().unwrap();
+    let content = temp.path().join("input/content");
+    fs::create_dir_all(content.join("media")).unwrap();
+    fs::write(
+        temp.path().join("input/marmite.yaml"),
+        format!(
+            "name: Blog\nurl: https://blog.test\natproto:\n  handle: me.test\n  \
+             publication_uri: {PUBLICATION}\n  announce: true\n  \
+             announce_text: \"New post: {{title}} {{url}}\"\n  comments: true\n"
+        ),
+    )
+    .unwrap();
+    fs::write(content.join("media/card.png"), "png").unwrap();
+    fs::write(
+        content.join("2024-01-01-first.md"),
+        "---\ndescription: The first one\ncard_image: media/card.png\n---\n# First\n\nHello",
+    )
+    .unwrap();
+
+    let pds = Arc::new(Mutex::new(Pds::default()));
+    let pds_url = start_pds(Arc::clone(&pds));
+
+    let output = atproto(temp.path(), &pds_url, &["publish"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("announce: first"), "{stderr}");
+    let posts = pds.lock().unwrap().collection("app.bsky.feed.post");
+    assert_eq!(posts.len(), 1);
+    assert_eq!(
+        posts[0]["text"],
+        "New post: First https://blog.test/first.html"
+    );
+    let external = &posts[0]["embed"]["external"];
+    assert_eq!(external["uri"], "https://blog.test/first.html");
+    assert_eq!(external["description"], "The first one");
+    assert_eq!(external["thumb"]["size"], 3);
+
+    let state: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(temp.path().join("input/.marmite-atproto-state.json")).unwrap(),
+    )
+    .unwrap();
+    let bsky_uri = state["posts"]["first"]["bsky_uri"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Editing the post updates the document without a second announcement
+    fs::write(
+        content.join("2024-01-01-first.md"),
+        "---\ndescription: The first one\ncard_image: media/card.png\n---\n# First\n\nEdited",
+    )
+    .unwrap();
+    atproto(temp.path(), &pds_url, &["publish"]);
+    assert_eq!(
+        pds.lock().unwrap().collection("app.bsky.feed.post").len(),
+        1
+    );
+
+    pds.lock().unwrap().threads.insert(
+        bsky_uri.clone(),
+        serde_json::json!({
+            "post": {"uri": bsky_uri},
+            "replies": [{
+                "post": {
+                    "uri": "at://did:plc:bob/app.bsky.feed.post/r1",
+                    "author": {"handle": "bob.test", "displayName": "Bob"},
+                    "record": {"text": "Great <post>", "createdAt": "2024-01-02T10:00:00Z"},
+                    "likeCount": 1
+                },
+                "replies": []
+            }]
+        }),
+    );
+    let output = atproto(temp.path(), &pds_url, &["comments"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Fetched 1 replies of 1 posts, 0 failed"),
+        "{stderr}"
+    );
+
+    let output_dir = temp.path().join("output");
+    let build = Command::new("cargo")
+        .args([
+            "run",
+            "--quiet",
+            "--",
+            temp.path().join("input").to_str().unwrap(),
+            output_dir.to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute marmite");
+    assert!(build.status.success());
+    let html = fs::read_to_string(output_dir.join("first.html")).unwrap();
+    assert!(html.contains("Great &lt;post&gt;"));
+    assert!(html.contains("https://bsky.app/profile/did:plc:bob/post/r1"));
+    assert!(html.contains(&format!(
+        "href=\"https://bsky.app/profile/did:plc:me/post/{}\"",
+        bsky_uri.rsplit('/').next().unwrap()
+    )));
+}
+
+#[test]
+fn test_oauth_session_is_encrypted_and_refreshed() {
+    let temp = TempDir::new().unwrap();
+    let content = temp.path().join("input/content");
+    fs::create_dir_all(&content).unwrap();
+    fs::write(
+        temp.path().join("input/marmite.yaml"),
+        format!(
+            "name: Blog\nurl: https://blog.test\natproto:\n  handle: me.test\n  \
+             publication_uri: {PUBLICATION}\n"
+        ),
+    )
+    .unwrap();
+    fs::write(content.join("2024-01-01-first.md"), "# First\n\nHello").unwrap();
+
+    let pds = Arc::new(Mutex::new(Pds::default()));
+    let pds_url = start_pds(Arc::clone(&pds));
+    let store = temp.path().join("credentials.enc");
+    let encrypted = |args: &[&str]| {
+        let mut command = command(temp.path(), &pds_url, args);
+        command
+            .env("MARMITE_CREDENTIALS_FILE", &store)
+            .env("MARMITE_CREDENTIALS_KEY", "s3cret");
+        command
+    };
+
+    let mut auth = encrypted(&["auth", "--oauth"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute marmite");
+    let mut lines = BufReader::new(auth.stderr.take().unwrap()).lines();
+    let mut seen = Vec::new();
+    let authorize_url = loop {
+        let line = lines
+            .next()
+            .unwrap_or_else(|| panic!("No authorization URL in {seen:?}"))
+            .unwrap();
+        if line
+            .trim()
+            .starts_with(&format!("{pds_url}/oauth/authorize?"))
+        {
+            break line.trim().to_string();
+        }
+        seen.push(line);
+    };
+    // The browser follows the redirect back to the callback of marmite
+    let page = ureq::get(&authorize_url)
+        .call()
+        .unwrap()
+        .body_mut()
+        .read_to_string()
+        .unwrap();
+    assert!(page.contains("authorized"), "{page}");
+    let rest: Vec<String> = lines.map(Result::unwrap).collect();
+    assert!(auth.wait().unwrap().success(), "{rest:?}");
+
+    let sealed = fs::read_to_string(&store).unwrap();
+    assert!(sealed.contains("ciphertext"));
+    assert!(!sealed.contains("rt1"));
+
+    // Each publish refreshes the expired tokens, with the rotated refresh token
+    for _ in 0..2 {
+        let output = encrypted(&["publish"]).output().unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    {
+        let pds = pds.lock().unwrap();
+        assert_eq!(pds.oauth.issued, 3);
+        assert_eq!(pds.paths(), vec!["/first.html"]);
+        assert_eq!(
+            pds.calls
+                .iter()
+                .filter(|call| call.starts_with("oauth."))
+                .collect::<Vec<_>>(),
+            vec![
+                "oauth.par",
+                "oauth.token authorization_code",
+                "oauth.token refresh_token",
+                "oauth.token refresh_token"
+            ]
+        );
+    }
+
+    // The store cannot be opened without its key
+    let output = command(temp.path(), &pds_url, &["publish"])
+        .env("MARMITE_CREDENTIALS_FILE", &store)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("MARMITE_CREDENTIALS_KEY"));
+}
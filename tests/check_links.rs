@@ -0,0 +1,99 @@
+use std::fs;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+/// Serves 200 on `/ok` and 404 everywhere else, counting the requests
+fn start_server() -> (String, Arc<AtomicUsize>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&requests);
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let status = if request.url() == "/ok" { 200 } else { 404 };
+            let _ = request.respond(tiny_http::Response::empty(status));
+        }
+    });
+    (base, requests)
+}
+
+fn check_links(input_dir: &std::path::Path, extra: &[&str]) -> std::process::Output {
+    let mut args = vec![
+        "run",
+        "--quiet",
+        "--",
+        input_dir.to_str().unwrap(),
+        "--report",
+        "json",
+        "check-links",
+    ];
+    args.extend_from_slice(extra);
+    Command::new("cargo")
+        .args(args)
+        .output()
+        .expect("Failed to execute marmite")
+}
+
+#[test]
+fn test_check_links_reports_and_caches() {
+    let (base, requests) = start_server();
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    fs::create_dir_all(input_dir.join("content")).unwrap();
+    fs::write(
+        input_dir.join("marmite.yaml"),
+        "name: Site\nlink_checker:\n  domain_delay: 0\n  ignore:\n    - /ignored$\n",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("2024-01-01-post.md"),
+        format!(
+            "# Post\n\n[fine]({base}/ok)\n\n[gone]({base}/missing)\n\n[skip]({base}/ignored)\n"
+        ),
+    )
+    .unwrap();
+
+    let output = check_links(&input_dir, &[]);
+    assert!(!output.status.success(), "Broken links should fail");
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let diagnostics = report["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["code"], "broken-external-link");
+    assert_eq!(diagnostics[0]["source"], "post");
+    assert_eq!(diagnostics[0]["target"], format!("{base}/missing"));
+    assert_eq!(diagnostics[0]["status"], 404);
+    assert_eq!(diagnostics[0]["line"], 5);
+    // HEAD for /ok, HEAD then GET for /missing
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    let cache: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(input_dir.join(".marmite-link-cache.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(cache["links"].as_object().unwrap().len(), 2);
+
+    // The second run uses the cache, --deny marks the broken links as errors
+    let output = Command::new("cargo")
+        .args(["run", "--quiet", "--", input_dir.to_str().unwrap()])
+        .args(["--report", "json", "--deny", "broken-external-link"])
+        .arg("check-links")
+        .output()
+        .expect("Failed to execute marmite");
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["diagnostics"][0]["severity"], "error");
+    assert_eq!(report["errors"], 1);
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    // --force checks everything again
+    let output = check_links(&input_dir, &["--force", "--ignore", "/missing$"]);
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+}
@@ -727,6 +727,85 @@ Check out [this page](nonexistent-page.html).
     );
 }
 
+#[test]
+fn test_report_json_and_deny() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+
+    fs::create_dir_all(input_dir.join("content")).unwrap();
+    fs::write(
+        input_dir.join("marmite.yaml"),
+        "name: Site\ncheck_internal_links: true",
+    )
+    .unwrap();
+
+    let post = r"---
+title: Post With Problems
+date: 2024-01-01
+translations: missing-translation
+---
+# Post
+
+Check out [this page](nonexistent-page.html).
+
+<!-- .nonexistent_shortcode -->
+";
+    fs::write(input_dir.join("content").join("post.md"), post).unwrap();
+
+    let run = |extra: &[&str]| {
+        let mut args = vec![
+            "run",
+            "--quiet",
+            "--",
+            input_dir.to_str().unwrap(),
+            output_dir.to_str().unwrap(),
+            "--force",
+        ];
+        args.extend_from_slice(extra);
+        Command::new("cargo")
+            .args(&args)
+            .output()
+            .expect("Failed to execute marmite")
+    };
+
+    let output = run(&["--report", "json"]);
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let diagnostics = report["diagnostics"].as_array().unwrap();
+    let find = |code: &str| {
+        diagnostics
+            .iter()
+            .find(|d| d["code"] == code)
+            .unwrap_or_else(|| panic!("{code} not reported: {report}"))
+    };
+
+    let link = find("broken-internal-link");
+    assert_eq!(link["severity"], "warning");
+    assert_eq!(link["target"], "nonexistent-page");
+    assert!(link["file"].as_str().unwrap().ends_with("post.md"));
+    assert_eq!(link["line"], 8);
+    assert_eq!(find("missing-translation")["line"], 4);
+    let shortcode = find("broken-shortcode");
+    assert_eq!(shortcode["name"], "nonexistent_shortcode");
+    assert_eq!(shortcode["line"], 10);
+    assert_eq!(report["warnings"], diagnostics.len());
+    assert_eq!(report["errors"], 0);
+
+    // denying a code that was not found passes
+    let output = run(&["--deny", "slug-collision"]);
+    assert!(output.status.success());
+
+    let output = run(&["--report", "json", "--deny", "broken-internal-link"]);
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["errors"], 1);
+
+    let output = run(&["--deny", "warnings"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("denied diagnostic"));
+}
+
 #[test]
 fn test_check_internal_links_passes_with_valid_links() {
     let temp_dir = TempDir::new().unwrap();
@@ -1224,3 +1303,445 @@ fn test_native_mermaid_render_disabled_preserves_js_flow() {
         "Should not contain build-time rendered diagram"
     );
 }
+
+#[test]
+fn test_search_index_generation() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+
+    fs::create_dir_all(input_dir.join("content")).unwrap();
+    fs::write(
+        input_dir.join("marmite.yaml"),
+        "name: Site\nenable_search: true",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("post.md"),
+        "---\ndate: 2024-01-01\n---\n# Searching Marmite\n\nFull text search.",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("post-pt.md"),
+        "---\ndate: 2024-01-02\nlanguage: pt\n---\n# Pesquisando\n\nBusca completa.",
+    )
+    .unwrap();
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--quiet",
+            "--",
+            input_dir.to_str().unwrap(),
+            output_dir.to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute marmite");
+
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let search_dir = output_dir.join("static").join("search");
+    let manifest = fs::read_to_string(search_dir.join("index.json")).unwrap();
+    assert!(manifest.contains("\"default_language\":\"en\""));
+    assert!(search_dir.join("en.json").exists());
+    assert!(search_dir.join("pt.json").exists());
+
+    let english = fs::read_to_string(search_dir.join("en.json")).unwrap();
+    assert!(english.contains("\"search\":"));
+    assert!(!english.contains("pesquis"));
+    assert!(!output_dir.join("static").join("search_index.json").exists());
+}
+
+#[test]
+fn test_atom_feed_generation() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+
+    fs::create_dir_all(input_dir.join("content")).unwrap();
+    fs::write(
+        input_dir.join("marmite.yaml"),
+        "name: Site\nurl: https://example.com\natom_feed: true",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("post.md"),
+        "---\ndate: 2024-01-01\ntags: rust\nauthors: alice\nseries: learning\n---\n# Atom Post\n\nBody.",
+    )
+    .unwrap();
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--quiet",
+            "--",
+            input_dir.to_str().unwrap(),
+            output_dir.to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute marmite");
+
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    for feed in [
+        "index.atom",
+        "tag-rust.atom",
+        "author-alice.atom",
+        "series-learning.atom",
+        "archive-2024.atom",
+    ] {
+        assert!(output_dir.join(feed).exists(), "{feed} was not generated");
+    }
+
+    let atom = fs::read_to_string(output_dir.join("index.atom")).unwrap();
+    assert!(atom.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\""));
+    assert!(atom.contains("<id>https://example.com/post.html</id>"));
+
+    let index = fs::read_to_string(output_dir.join("index.html")).unwrap();
+    assert!(index.contains("application/atom+xml"));
+}
+
+#[test]
+fn test_feed_options() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+
+    fs::create_dir_all(input_dir.join("content")).unwrap();
+    fs::write(
+        input_dir.join("marmite.yaml"),
+        "name: Site\nurl: https://example.com\njson_feed: true\nfeed:\n  items: 1\n  exclude_tags: [private]\n  tags:\n    rust:\n      content: summary\n",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("2024-01-01-first.md"),
+        "---\ntags: rust\ndescription: First summary\n---\n# First\n\nFirst body.",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("2024-01-02-second.md"),
+        "---\ntags: rust\ndescription: Second summary\n---\n# Second\n\nSecond body.",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("2024-01-03-secret.md"),
+        "---\ntags: private\n---\n# Secret\n\nSecret body.",
+    )
+    .unwrap();
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--quiet",
+            "--",
+            input_dir.to_str().unwrap(),
+            output_dir.to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute marmite");
+
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // the newest post is excluded by its tag and only one item is kept
+    let index = fs::read_to_string(output_dir.join("index.rss")).unwrap();
+    assert!(!index.contains("Secret"));
+    assert!(index.contains("<title>Second</title>"));
+    assert!(!index.contains("<title>First</title>"));
+    assert!(index.contains("Second body."));
+
+    // the rust tag feed carries summaries instead of the full content
+    let tag: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(output_dir.join("tag-rust.json")).unwrap())
+            .unwrap();
+    let item = &tag["items"][0];
+    assert!(item["content_text"]
+        .as_str()
+        .unwrap()
+        .contains("Second summary"));
+    assert!(item.get("content_html").is_none());
+}
+
+#[test]
+fn test_scheduled_publishing() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+
+    fs::create_dir_all(input_dir.join("content")).unwrap();
+    fs::write(
+        input_dir.join("marmite.yaml"),
+        "name: Site\nurl: https://example.com\nenable_search: true",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("published.md"),
+        "---\ndate: 2024-01-01\ntags: news\n---\n# Published Post\n\nBody.",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("scheduled.md"),
+        "---\ndate: 2999-01-01\ntags: news\n---\n# Scheduled Post\n\nBody.",
+    )
+    .unwrap();
+
+    let run = |extra_args: &[&str]| {
+        let mut args = vec![
+            "run",
+            "--quiet",
+            "--",
+            input_dir.to_str().unwrap(),
+            output_dir.to_str().unwrap(),
+        ];
+        args.extend_from_slice(extra_args);
+        let output = Command::new("cargo")
+            .args(&args)
+            .output()
+            .expect("Failed to execute marmite");
+        assert!(
+            output.status.success(),
+            "Command failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    run(&[]);
+    assert!(output_dir.join("published.html").exists());
+    assert!(!output_dir.join("scheduled.html").exists());
+    for file in [
+        "index.html",
+        "index.rss",
+        "tag-news.html",
+        "sitemap.xml",
+        "urls.json",
+        "static/search/en.json",
+    ] {
+        let text = fs::read_to_string(output_dir.join(file)).unwrap();
+        assert!(
+            !text.contains("scheduled"),
+            "{file} lists scheduled content"
+        );
+    }
+
+    let report: serde_json::Value = serde_json::from_str(&run(&["--show-scheduled"])).unwrap();
+    assert_eq!(
+        report["scheduled"][0]["url"],
+        "https://example.com/scheduled.html"
+    );
+
+    run(&["--include-future"]);
+    assert!(output_dir.join("scheduled.html").exists());
+    assert!(fs::read_to_string(output_dir.join("index.rss"))
+        .unwrap()
+        .contains("Scheduled Post"));
+
+    // building without previews removes the scheduled page again
+    run(&[]);
+    assert!(!output_dir.join("scheduled.html").exists());
+}
+
+#[test]
+fn test_json_api_generation() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+    fs::create_dir_all(input_dir.join("content")).unwrap();
+    fs::write(
+        input_dir.join("marmite.yaml"),
+        "name: Site\nurl: https://example.com\njson_api:\n  per_page: 1\n",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("2024-01-01-first.md"),
+        "---\ntags: Rust, web\nauthors: alice\n---\n# First Post\n\nHello **world**.\n",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("2024-01-02-second.md"),
+        "---\ntags: Rust\n---\n# Second Post\n\nBody.\n",
+    )
+    .unwrap();
+    fs::write(input_dir.join("content").join("about.md"), "# About\n").unwrap();
+
+    let build = |extra_args: &[&str]| {
+        let mut args = vec![
+            "run",
+            "--quiet",
+            "--",
+            input_dir.to_str().unwrap(),
+            output_dir.to_str().unwrap(),
+        ];
+        args.extend_from_slice(extra_args);
+        let output = Command::new("cargo")
+            .args(&args)
+            .output()
+            .expect("Failed to execute marmite");
+        assert!(
+            output.status.success(),
+            "Command failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+    let read = |path: &str| -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(output_dir.join("api").join(path)).unwrap())
+            .unwrap()
+    };
+
+    // Disabled by default
+    build(&[]);
+    assert!(!output_dir.join("api").exists());
+
+    build(&["--json-api", "true"]);
+    let page = read("posts/page/1.json");
+    assert_eq!(page["total_pages"], 2);
+    assert_eq!(page["items"][0]["slug"], "second");
+    assert_eq!(page["next"], "https://example.com/api/posts/page/2.json");
+
+    let post = read("posts/first.json");
+    assert_eq!(post["title"], "First Post");
+    assert!(post["html"]
+        .as_str()
+        .unwrap()
+        .contains("<strong>world</strong>"));
+    assert_eq!(post["markdown"], "# First Post\n\nHello **world**.\n");
+    assert_eq!(post["authors"][0], "alice");
+    assert_eq!(post["next"], "second");
+
+    assert_eq!(read("pages/about.json")["kind"], "page");
+    assert_eq!(read("tags/rust.json")["count"], 2);
+    assert_eq!(read("streams.json")["items"][0]["slug"], "index");
+    assert_eq!(read("authors/alice.json")["items"][0]["slug"], "first");
+    assert_eq!(read("index.json")["counts"]["pages"], 1);
+}
+
+#[test]
+fn test_export_series_book() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let content_dir = input_dir.join("content");
+    fs::create_dir_all(content_dir.join("media")).unwrap();
+    fs::write(input_dir.join("marmite.yaml"), "name: Site\n").unwrap();
+    fs::write(content_dir.join("media").join("diagram.png"), "png").unwrap();
+    fs::write(
+        content_dir.join("2024-01-02-part-two.md"),
+        "---\nseries: guide\n---\n# Part Two\n\n## Details\n\nBack to [part one](part-one.html).\n",
+    )
+    .unwrap();
+    fs::write(
+        content_dir.join("2024-01-01-part-one.md"),
+        "---\nseries: guide\n---\n# Part One\n\n![diagram](media/diagram.png)\n",
+    )
+    .unwrap();
+
+    let export = |args: &[&str]| {
+        let output = Command::new("cargo")
+            .args([
+                "run",
+                "--quiet",
+                "--",
+                input_dir.to_str().unwrap(),
+                "export",
+            ])
+            .args(args)
+            .output()
+            .expect("Failed to execute marmite");
+        assert!(
+            output.status.success(),
+            "Command failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+
+    let epub = temp_dir.path().join("guide.epub");
+    export(&["epub", "--series", "guide", "-o", epub.to_str().unwrap()]);
+    let mut archive = zip::ZipArchive::new(fs::File::open(&epub).unwrap()).unwrap();
+    let mut mimetype = String::new();
+    std::io::Read::read_to_string(&mut archive.by_index(0).unwrap(), &mut mimetype).unwrap();
+    assert_eq!(mimetype, "application/epub+zip");
+    let mut first = String::new();
+    std::io::Read::read_to_string(
+        &mut archive.by_name("OEBPS/chapter-1.xhtml").unwrap(),
+        &mut first,
+    )
+    .unwrap();
+    assert!(first.contains("Part One"));
+    assert!(first.contains("src=\"images/diagram.png\""));
+    assert!(archive.by_name("OEBPS/images/diagram.png").is_ok());
+
+    let print = temp_dir.path().join("guide.html");
+    export(&["print", "--series", "guide", "-o", print.to_str().unwrap()]);
+    let html = fs::read_to_string(&print).unwrap();
+    assert!(html.find("id=\"part-one\"") < html.find("id=\"part-two\""));
+    assert!(html.contains("href=\"#part-one\""));
+    assert!(html.contains("src=\"data:image/png;base64,"));
+}
+
+#[test]
+fn test_lint_fails_on_error_rules() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let content_dir = input_dir.join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(
+        content_dir.join("post.md"),
+        "---\ntitle: Post\ndescription: A post\ntags: Rust\n---\n\n![](media/photo.png)\n",
+    )
+    .unwrap();
+    fs::write(
+        content_dir.join("other.md"),
+        "---\ntitle: Other\ntags: rust, Rust\n---\n\nText\n",
+    )
+    .unwrap();
+
+    let lint = |config: &str, args: &[&str]| {
+        fs::write(input_dir.join("marmite.yaml"), config).unwrap();
+        Command::new("cargo")
+            .args(["run", "--quiet", "--", input_dir.to_str().unwrap()])
+            .args(args)
+            .arg("lint")
+            .output()
+            .expect("Failed to execute marmite")
+    };
+
+    let output = lint("name: Site\n", &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Lint failed: {stdout}");
+    assert!(stdout.contains(
+        "warning: content/post.md:7: Image \"media/photo.png\" has no alt text [image-missing-alt]"
+    ));
+    assert!(stdout.contains("warning: content/other.md: Missing description [missing-description]"));
+    assert!(stdout.contains("inconsistent-tag-case"));
+
+    let output = lint(
+        "name: Site\nlint:\n  rules:\n    image-missing-alt: error\n",
+        &["--report", "json"],
+    );
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["errors"], 1);
+    let denied = report["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["severity"] == "error")
+        .unwrap();
+    assert_eq!(denied["rule"], "image-missing-alt");
+    assert_eq!(denied["line"], 7);
+
+    let output = lint("name: Site\n", &["--deny", "warnings"]);
+    assert!(!output.status.success());
+    let output = lint("name: Site\n", &["--deny", "lint"]);
+    assert!(!output.status.success());
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Serves a tiny PNG for every request under `/wp-content/uploads/`
+fn start_media_server() -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = if request.url().starts_with("/wp-content/uploads/") {
+                tiny_http::Response::from_data(b"\x89PNG fake".to_vec())
+            } else {
+                tiny_http::Response::from_data(Vec::new()).with_status_code(404)
+            };
+            let _ = request.respond(response);
+        }
+    });
+    base
+}
+
+fn marmite(args: &[&str]) -> Output {
+    let output = Command::new("cargo")
+        .args(["run", "--quiet", "--"])
+        .args(args)
+        .output()
+        .expect("Failed to execute marmite");
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn path(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+#[test]
+fn test_import_wordpress_export() {
+    let base = start_media_server();
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("marmite.yaml"), "name: Site\n").unwrap();
+
+    let export = temp_dir.path().join("export.xml");
+    fs::write(
+        &export,
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+<link>{base}</link>
+<item>
+    <title>Hello World</title>
+    <link>{base}/2019/05/03/hello-world/</link>
+    <dc:creator>admin</dc:creator>
+    <content:encoded><![CDATA[<p>Look at <img src="{base}/wp-content/uploads/2019/05/cat.png" alt="cat"></p>
+<p><a href="{base}/about/">About</a></p>]]></content:encoded>
+    <wp:post_id>1</wp:post_id>
+    <wp:post_date>2019-05-03 10:00:00</wp:post_date>
+    <wp:post_name>hello-world</wp:post_name>
+    <wp:status>publish</wp:status>
+    <wp:post_type>post</wp:post_type>
+    <category domain="post_tag" nicename="intro"><![CDATA[Intro]]></category>
+</item>
+<item>
+    <title>About</title>
+    <link>{base}/about/</link>
+    <content:encoded><![CDATA[About this blog]]></content:encoded>
+    <wp:post_id>2</wp:post_id>
+    <wp:post_name>about</wp:post_name>
+    <wp:status>publish</wp:status>
+    <wp:post_type>page</wp:post_type>
+</item>
+</channel>
+</rss>"#
+        ),
+    )
+    .unwrap();
+
+    let output = marmite(&[path(&input_dir), "import", "wordpress", path(&export)]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Imported 1 post(s), 1 page(s) and 1 media file(s)"),
+        "{stdout}"
+    );
+
+    let content_dir = input_dir.join("content");
+    let post = fs::read_to_string(content_dir.join("hello-world.md")).unwrap();
+    assert!(post.contains("date: 2019-05-03 10:00:00"));
+    assert!(post.contains("- Intro"));
+    assert!(post.contains("- /2019/05/03/hello-world/"));
+    assert!(post.contains("src=\"media/hello-world/cat.png\""));
+    assert!(post.contains("href=\"about.html\""));
+    assert!(content_dir.join("media/hello-world/cat.png").is_file());
+
+    // Running again keeps the existing files
+    let output = marmite(&[path(&input_dir), "import", "wordpress", path(&export)]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Skipped 2 existing file(s)"));
+
+    marmite(&[path(&input_dir), path(&output_dir)]);
+    assert!(output_dir.join("hello-world.html").exists());
+    let redirect =
+        fs::read_to_string(output_dir.join("2019/05/03/hello-world/index.html")).unwrap();
+    assert!(redirect.contains("/hello-world.html"));
+    assert!(output_dir.join("about/index.html").exists());
+    assert!(output_dir.join("media/hello-world/cat.png").exists());
+}
+
+#[test]
+fn test_import_jekyll_site() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+    let jekyll_dir = temp_dir.path().join("jekyll");
+    fs::create_dir_all(jekyll_dir.join("_posts")).unwrap();
+    fs::create_dir_all(jekyll_dir.join("assets")).unwrap();
+    fs::write(jekyll_dir.join("assets/chart.png"), "png").unwrap();
+    fs::write(
+        jekyll_dir.join("_posts/2021-04-05-first-steps.md"),
+        "---\ntitle: First Steps\ncategories: blog\n---\n\
+        ![chart]({{ site.baseurl }}/assets/chart.png)\n",
+    )
+    .unwrap();
+
+    marmite(&[
+        path(&input_dir),
+        "import",
+        "jekyll",
+        path(&jekyll_dir),
+        "--dry-run",
+    ]);
+    assert!(!input_dir.join("content").exists());
+
+    marmite(&[path(&input_dir), "import", "jekyll", path(&jekyll_dir)]);
+    let post = fs::read_to_string(input_dir.join("content/first-steps.md")).unwrap();
+    assert!(post.contains("![chart](media/first-steps/chart.png)"));
+
+    marmite(&[path(&input_dir), path(&output_dir)]);
+    assert!(output_dir.join("first-steps.html").exists());
+    assert!(output_dir.join("blog/2021/04/05/first-steps.html").exists());
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+fn run_marmite(input_dir: &Path, output_dir: &Path, extra_args: &[&str]) {
+    let mut args = vec![
+        "run",
+        "--quiet",
+        "--",
+        input_dir.to_str().unwrap(),
+        output_dir.to_str().unwrap(),
+    ];
+    args.extend_from_slice(extra_args);
+    let output = Command::new("cargo")
+        .args(&args)
+        .output()
+        .expect("Failed to execute marmite");
+
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Every generated file except `marmite.json`, with the RSS `lastBuildDate`
+/// removed as both record the time of the build
+fn read_output(output_dir: &Path) -> BTreeMap<String, String> {
+    walkdir::WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && e.file_name() != "marmite.json")
+        .map(|e| {
+            let relative = e.path().strip_prefix(output_dir).unwrap();
+            let mut text = String::from_utf8_lossy(&fs::read(e.path()).unwrap()).to_string();
+            if let (Some(start), Some(end)) =
+                (text.find("<lastBuildDate>"), text.find("</lastBuildDate>"))
+            {
+                text.replace_range(start..end, "");
+            }
+            (relative.to_string_lossy().to_string(), text)
+        })
+        .collect()
+}
+
+fn create_site(input_dir: &Path) {
+    fs::create_dir_all(input_dir.join("content")).unwrap();
+    fs::write(input_dir.join("marmite.yaml"), "name: Incremental").unwrap();
+    fs::write(
+        input_dir.join("content").join("first-post.md"),
+        "---\ndate: 2024-01-01\ntags: rust\n---\n# First Post\n\nOriginal body.",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("second-post.md"),
+        "---\ndate: 2024-01-02\ntags: python\n---\n# Second Post\n\nSee [[First Post]].",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("about.md"),
+        "# About\n\nAbout page.",
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_incremental_build_matches_full_build() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let incremental_dir = temp_dir.path().join("incremental");
+    let full_dir = temp_dir.path().join("full");
+    create_site(&input_dir);
+
+    run_marmite(&input_dir, &incremental_dir, &["--incremental"]);
+    assert!(input_dir.join(".marmite-build-graph.json").exists());
+
+    // Body only change renders the affected outputs
+    fs::write(
+        input_dir.join("content").join("first-post.md"),
+        "---\ndate: 2024-01-01\ntags: rust\n---\n# First Post\n\nChanged body.",
+    )
+    .unwrap();
+    run_marmite(&input_dir, &incremental_dir, &["--incremental"]);
+    run_marmite(&input_dir, &full_dir, &[]);
+    assert!(fs::read_to_string(incremental_dir.join("first-post.html"))
+        .unwrap()
+        .contains("Changed body."));
+    assert_eq!(read_output(&incremental_dir), read_output(&full_dir));
+
+    // Metadata change renders the whole site
+    fs::write(
+        input_dir.join("content").join("first-post.md"),
+        "---\ndate: 2024-01-01\ntags: rust, go\n---\n# First Post Renamed\n\nChanged body.",
+    )
+    .unwrap();
+    run_marmite(&input_dir, &incremental_dir, &["--incremental"]);
+    fs::remove_dir_all(&full_dir).unwrap();
+    run_marmite(&input_dir, &full_dir, &[]);
+    assert!(incremental_dir.join("tag-go.html").exists());
+    assert_eq!(read_output(&incremental_dir), read_output(&full_dir));
+}
+
+#[test]
+fn test_watch_rebuilds_once_per_change() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    create_site(&input_dir);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_marmite"))
+        .args([
+            input_dir.to_str().unwrap(),
+            temp_dir.path().join("output").to_str().unwrap(),
+            "--watch",
+            "--incremental",
+        ])
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start marmite");
+    let (sender, receiver) = mpsc::channel();
+    let stderr = child.stderr.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let wait_for = |text: &str| {
+        while let Ok(line) = receiver.recv_timeout(Duration::from_secs(60)) {
+            if line.contains(text) {
+                return true;
+            }
+        }
+        false
+    };
+
+    let watching = wait_for("Watching for changes");
+    if watching {
+        fs::write(
+            input_dir.join("content").join("first-post.md"),
+            "---\ndate: 2024-01-01\ntags: rust\n---\n# First Post\n\nEdited body.",
+        )
+        .unwrap();
+    }
+    // Saving the build graph must not be seen as another change
+    let mut rebuilds = 0;
+    let deadline = Instant::now() + Duration::from_secs(15);
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(left) {
+            Ok(line) if line.contains("Change detected") => rebuilds += 1,
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+
+    assert!(watching, "marmite did not start watching");
+    assert_eq!(rebuilds, 1);
+}
@@ -0,0 +1,66 @@
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+#[test]
+fn test_responsive_images() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+    let media_dir = input_dir.join("content").join("media");
+    fs::create_dir_all(&media_dir).unwrap();
+
+    let config = r"name: Blog
+extra:
+  max_image_width: 60
+  responsive_image_widths: [20, 40]
+  responsive_image_formats: [webp]
+";
+    fs::write(input_dir.join("marmite.yaml"), config).unwrap();
+
+    image::RgbImage::new(80, 40)
+        .save(media_dir.join("photo.png"))
+        .unwrap();
+    fs::write(
+        input_dir.join("content").join("2024-01-01-post.md"),
+        "# Post\n\n![A photo](media/photo.png)\n",
+    )
+    .unwrap();
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--quiet",
+            "--",
+            input_dir.to_str().unwrap(),
+            output_dir.to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute marmite");
+
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let post = fs::read_to_string(output_dir.join("post.html")).unwrap();
+    assert!(post.contains(r#"<source type="image/webp" srcset="media/_responsive/photo-20w.webp 20w, media/_responsive/photo-40w.webp 40w, media/_responsive/photo-60w.webp 60w""#));
+    assert!(post.contains(r#"srcset="media/_responsive/photo-20w.png 20w, media/_responsive/photo-40w.png 40w, media/photo.png 60w""#));
+    assert!(post.contains(r#"width="60" height="30""#));
+
+    let responsive_dir = output_dir.join("media").join("_responsive");
+    for variant in [
+        "photo-20w.png",
+        "photo-40w.png",
+        "photo-20w.webp",
+        "photo-40w.webp",
+        "photo-60w.webp",
+    ] {
+        assert!(responsive_dir.join(variant).exists(), "missing {variant}");
+    }
+    assert_eq!(
+        image::image_dimensions(output_dir.join("media").join("photo.png")).unwrap(),
+        (60, 30)
+    );
+}
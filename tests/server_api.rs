@@ -0,0 +1,608 @@
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// Kills the server when the test ends, even on failure
+struct Server {
+    child: Child,
+    base: String,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn start_server(input_dir: &Path, extra: &[&str]) -> Server {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let bind = format!("127.0.0.1:{port}");
+    let mut args = vec![
+        input_dir.to_str().unwrap(),
+        "--serve",
+        "--bind",
+        bind.as_str(),
+    ];
+    args.extend_from_slice(extra);
+    let child = Command::new(env!("CARGO_BIN_EXE_marmite"))
+        .args(args)
+        .spawn()
+        .expect("Failed to start marmite");
+    let server = Server {
+        child,
+        base: format!("http://{bind}"),
+    };
+
+    let start = Instant::now();
+    while TcpStream::connect(&bind).is_err() {
+        assert!(
+            start.elapsed() < Duration::from_secs(60),
+            "server did not start"
+        );
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    server
+}
+
+fn agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .max_redirects(0)
+        .build()
+        .into()
+}
+
+fn create_site(config: &str) -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let content_dir = temp_dir.path().join("content");
+    fs::create_dir_all(&content_dir).unwrap();
+    fs::write(temp_dir.path().join("marmite.yaml"), config).unwrap();
+    fs::write(content_dir.join("2024-01-01-post.md"), "# Post\n\nHello").unwrap();
+    temp_dir
+}
+
+fn audit_entries(input_dir: &Path) -> Vec<serde_json::Value> {
+    fs::read_to_string(input_dir.join(".marmite-audit.log"))
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_api_token_origin_check_and_audit_log() {
+    let site = create_site(
+        "name: Site\nserver:\n  api_token: s3cret\n  allowed_origins: [https://editor.example.com]\n",
+    );
+    let server = start_server(site.path(), &[]);
+    let agent = agent();
+    let data_url = format!("{}/__marmite__/data", server.base);
+    let post_url = format!("{}/__marmite__/content/post", server.base);
+
+    // The site itself needs no token, the API does
+    let page = agent
+        .get(format!("{}/post.html", server.base))
+        .call()
+        .unwrap();
+    assert_eq!(page.status(), 200);
+    assert_eq!(agent.get(&data_url).call().unwrap().status(), 401);
+    let with_token = agent
+        .get(&data_url)
+        .header("Authorization", "Bearer s3cret")
+        .call()
+        .unwrap();
+    assert_eq!(with_token.status(), 200);
+    let with_wrong_token = agent
+        .get(&data_url)
+        .header("X-Marmite-Token", "guess")
+        .call()
+        .unwrap();
+    assert_eq!(with_wrong_token.status(), 401);
+
+    // Login stores the token posted by its form in a cookie for the browser
+    let login_url = format!("{}/__marmite__/login", server.base);
+    let form = agent.get(&login_url).call().unwrap();
+    assert_eq!(form.status(), 200);
+    assert!(!form.headers().contains_key("set-cookie"));
+    let login = agent
+        .post(&login_url)
+        .header("Origin", &server.base)
+        .content_type("application/x-www-form-urlencoded")
+        .send("token=s3cret")
+        .unwrap();
+    assert_eq!(login.status(), 303);
+    let cookie = login.headers()["set-cookie"].to_str().unwrap();
+    assert!(cookie.starts_with("marmite_token=s3cret;"));
+    assert!(cookie.contains("HttpOnly"));
+    // The token is never taken from the URL, nor posted by another site
+    let in_query = agent
+        .get(format!("{login_url}?token=s3cret"))
+        .call()
+        .unwrap();
+    assert!(!in_query.headers().contains_key("set-cookie"));
+    let cross_site_login = agent
+        .post(&login_url)
+        .header("Origin", "https://evil.example.com")
+        .content_type("application/x-www-form-urlencoded")
+        .send("token=s3cret")
+        .unwrap();
+    assert_eq!(cross_site_login.status(), 403);
+    assert!(!cross_site_login.headers().contains_key("set-cookie"));
+    let other_path = agent
+        .post(format!("{}/__marmite__/loginanything", server.base))
+        .content_type("application/x-www-form-urlencoded")
+        .send("token=s3cret")
+        .unwrap();
+    assert!(!other_path.headers().contains_key("set-cookie"));
+    let with_cookie = agent
+        .get(&data_url)
+        .header("Cookie", "theme=dark; marmite_token=s3cret")
+        .call()
+        .unwrap();
+    assert_eq!(with_cookie.status(), 200);
+
+    // Cross-origin changes are rejected
+    let cross_origin = agent
+        .delete(&post_url)
+        .header("Authorization", "Bearer s3cret")
+        .header("Origin", "https://evil.example.com")
+        .call()
+        .unwrap();
+    assert_eq!(cross_origin.status(), 403);
+    let browser_without_origin = agent
+        .delete(&post_url)
+        .header("Authorization", "Bearer s3cret")
+        .header("Sec-Fetch-Site", "cross-site")
+        .call()
+        .unwrap();
+    assert_eq!(browser_without_origin.status(), 403);
+    assert!(site.path().join("content/2024-01-01-post.md").exists());
+
+    let allowed_origin = agent
+        .delete(&post_url)
+        .header("Authorization", "Bearer s3cret")
+        .header("Origin", "https://editor.example.com")
+        .call()
+        .unwrap();
+    assert_eq!(allowed_origin.status(), 200);
+    assert!(!site.path().join("content/2024-01-01-post.md").exists());
+
+    let entries = audit_entries(site.path());
+    assert_eq!(entries.len(), 3, "only mutations are audited");
+    assert_eq!(entries[0]["method"], "DELETE");
+    assert_eq!(entries[0]["path"], "/__marmite__/content/post");
+    assert_eq!(entries[0]["status"], 403);
+    assert_eq!(entries[1]["status"], 403);
+    assert_eq!(entries[2]["status"], 200);
+    assert_eq!(entries[2]["user"], "token");
+    assert!(entries[2]["remote"]
+        .as_str()
+        .unwrap()
+        .starts_with("127.0.0.1:"));
+}
+
+#[test]
+fn test_basic_auth_and_read_only_mode() {
+    let site = create_site("name: Site\nserver:\n  basic_auth: admin:pa55\n");
+    let server = start_server(site.path(), &["--read-only", "true"]);
+    let agent = agent();
+    // admin:pa55
+    let credentials = "Basic YWRtaW46cGE1NQ==";
+
+    let anonymous = agent.get(format!("{}/", server.base)).call().unwrap();
+    assert_eq!(anonymous.status(), 401);
+    assert!(anonymous.headers().contains_key("www-authenticate"));
+    let authorized = agent
+        .get(format!("{}/", server.base))
+        .header("Authorization", credentials)
+        .call()
+        .unwrap();
+    assert_eq!(authorized.status(), 200);
+
+    let config_url = format!("{}/__marmite__/config", server.base);
+    let read = agent
+        .get(&config_url)
+        .header("Authorization", credentials)
+        .call()
+        .unwrap();
+    assert_eq!(read.status(), 200);
+    let write = agent
+        .patch(&config_url)
+        .header("Authorization", credentials)
+        .header("Content-Type", "application/json")
+        .send(r#"{"name": "Changed"}"#)
+        .unwrap();
+    assert_eq!(write.status(), 403);
+    assert!(fs::read_to_string(site.path().join("marmite.yaml"))
+        .unwrap()
+        .contains("name: Site"));
+
+    let entries = audit_entries(site.path());
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["method"], "PATCH");
+    assert_eq!(entries[0]["user"], "admin");
+    assert_eq!(entries[0]["status"], 403);
+}
+
+/// Waits for the next live reload message with the given `event`
+fn next_event(
+    socket: &mut tungstenite::WebSocket<tungstenite::stream::MaybeTlsStream<TcpStream>>,
+    event: &str,
+) -> serde_json::Value {
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while Instant::now() < deadline {
+        match socket.read() {
+            Ok(tungstenite::Message::Text(text)) => {
+                let payload: serde_json::Value = serde_json::from_str(&text).unwrap();
+                if payload["event"] == event {
+                    return payload;
+                }
+            }
+            Ok(_) | Err(tungstenite::Error::Io(_)) => {}
+            Err(err) => panic!("live reload socket failed: {err}"),
+        }
+    }
+    panic!("no {event} event received");
+}
+
+#[test]
+fn test_build_errors_are_sent_over_live_reload() {
+    let site = create_site("name: Site\n");
+    let server = start_server(site.path(), &["--watch"]);
+    let ws_url = format!(
+        "{}/__marmite__/livereload",
+        server.base.replace("http://", "ws://")
+    );
+    let (mut socket, _) = tungstenite::connect(ws_url).unwrap();
+    if let tungstenite::stream::MaybeTlsStream::Plain(stream) = socket.get_mut() {
+        stream
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+    }
+
+    let post = site.path().join("content/2024-01-01-post.md");
+    fs::write(&post, "---\ntitle: Post\ntags: [a, b\n---\n# Post\n").unwrap();
+    let payload = next_event(&mut socket, "build-error");
+    let error = &payload["errors"][0];
+    assert_eq!(error["code"], "invalid-frontmatter");
+    assert!(error["file"]
+        .as_str()
+        .unwrap()
+        .ends_with("2024-01-01-post.md"));
+    assert_eq!(error["line"], 3);
+
+    // A browser connecting later gets the errors of the last build
+    let ws_url = format!(
+        "{}/__marmite__/livereload",
+        server.base.replace("http://", "ws://")
+    );
+    let (mut late, _) = tungstenite::connect(ws_url).unwrap();
+    assert_eq!(
+        next_event(&mut late, "build-error")["errors"][0]["code"],
+        "invalid-frontmatter"
+    );
+    drop(late);
+
+    fs::write(&post, "# Post\n\nHello").unwrap();
+    next_event(&mut socket, "build-ok");
+
+    let templates = site.path().join("templates");
+    fs::create_dir_all(&templates).unwrap();
+    fs::write(
+        templates.join("content.html"),
+        "<p>\n{{ missing.value }}\n</p>",
+    )
+    .unwrap();
+    let payload = next_event(&mut socket, "build-error");
+    let error = &payload["errors"][0];
+    assert_eq!(error["code"], "template-error");
+    assert!(error["file"].as_str().unwrap().ends_with("content.html"));
+    assert_eq!(error["line"], 2);
+
+    fs::remove_file(templates.join("content.html")).unwrap();
+    next_event(&mut socket, "build-ok");
+}
+
+#[test]
+fn test_content_history_diff_restore_and_git_commits() {
+    let site = create_site("name: Site\nserver:\n  git_commit: true\n");
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(site.path())
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.name", "Marmite"]);
+    git(&["config", "user.email", "marmite@example.com"]);
+    git(&["add", "-A"]);
+    git(&["commit", "-q", "-m", "Initial"]);
+
+    let server = start_server(site.path(), &[]);
+    let agent = agent();
+    let post_url = format!("{}/__marmite__/content/post", server.base);
+    let file = site.path().join("content/2024-01-01-post.md");
+
+    let edit = agent
+        .put(format!("{post_url}/body"))
+        .header("Content-Type", "application/json")
+        .send(r#"{"body": "Goodbye"}"#)
+        .unwrap();
+    assert_eq!(edit.status(), 200);
+    assert!(fs::read_to_string(&file).unwrap().contains("Goodbye"));
+
+    let history: serde_json::Value = agent
+        .get(format!("{post_url}/history"))
+        .call()
+        .unwrap()
+        .body_mut()
+        .read_json()
+        .unwrap();
+    let revisions = history["revisions"].as_array().unwrap();
+    assert_eq!(revisions.len(), 1);
+    assert_eq!(revisions[0]["action"], "edit");
+    assert_eq!(revisions[0]["path"], "content/2024-01-01-post.md");
+    let id = revisions[0]["id"].as_str().unwrap();
+
+    let revision: serde_json::Value = agent
+        .get(format!("{post_url}/history/{id}"))
+        .call()
+        .unwrap()
+        .body_mut()
+        .read_json()
+        .unwrap();
+    assert_eq!(revision["content"], "# Post\n\nHello");
+    let diff: serde_json::Value = agent
+        .get(format!("{post_url}/history/{id}/diff"))
+        .call()
+        .unwrap()
+        .body_mut()
+        .read_json()
+        .unwrap();
+    let diff = diff["diff"].as_str().unwrap();
+    assert!(diff.contains("-Hello\n"), "{diff}");
+    assert!(diff.contains("+Goodbye\n"), "{diff}");
+
+    let missing = agent
+        .get(format!("{post_url}/history/nope/diff"))
+        .call()
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+
+    // A deleted content comes back from its history
+    assert_eq!(agent.delete(&post_url).call().unwrap().status(), 200);
+    assert!(!file.exists());
+    let restore = agent
+        .post(format!("{post_url}/history/{id}/restore"))
+        .send_empty()
+        .unwrap();
+    assert_eq!(restore.status(), 200);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "# Post\n\nHello");
+
+    let log = git(&["log", "--format=%s"]);
+    let subjects: Vec<&str> = log.lines().collect();
+    assert_eq!(
+        subjects,
+        vec![
+            format!("Restore post to revision {id}").as_str(),
+            "Delete post",
+            "Edit post",
+            "Initial",
+        ]
+    );
+    assert!(git(&["status", "--porcelain", "content"]).is_empty());
+}
+
+#[test]
+fn test_etags_and_conflicting_writes() {
+    let site = create_site("name: Site\n");
+    let server = start_server(site.path(), &[]);
+    let agent = agent();
+    let body_url = format!("{}/__marmite__/content/post/body", server.base);
+    let file_url = format!(
+        "{}/__marmite__/file/content/2024-01-01-post.md",
+        server.base
+    );
+
+    let mut loaded = agent.get(&body_url).call().unwrap();
+    let etag = loaded.headers()["etag"].to_str().unwrap().to_string();
+    let loaded: serde_json::Value = loaded.body_mut().read_json().unwrap();
+    assert_eq!(loaded["etag"], etag.as_str());
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+
+    // The raw file API describes the same file with the same ETag
+    let file: serde_json::Value = agent
+        .get(&file_url)
+        .call()
+        .unwrap()
+        .body_mut()
+        .read_json()
+        .unwrap();
+    assert_eq!(file["etag"], etag.as_str());
+
+    let mut saved = agent
+        .put(&body_url)
+        .header("If-Match", &etag)
+        .send_json(serde_json::json!({"body": "First writer"}))
+        .unwrap();
+    assert_eq!(saved.status(), 200);
+    let new_etag = saved.headers()["etag"].to_str().unwrap().to_string();
+    assert_ne!(new_etag, etag);
+    let saved: serde_json::Value = saved.body_mut().read_json().unwrap();
+    assert_eq!(saved["etag"], new_etag.as_str());
+
+    // A second writer still holding the old ETag gets the current version
+    let mut conflict = agent
+        .put(&body_url)
+        .header("If-Match", &etag)
+        .send_json(serde_json::json!({"body": "Second writer"}))
+        .unwrap();
+    assert_eq!(conflict.status(), 409);
+    let current: serde_json::Value = conflict.body_mut().read_json().unwrap();
+    assert_eq!(current["etag"], new_etag.as_str());
+    assert!(current["body"].as_str().unwrap().contains("First writer"));
+    let content = fs::read_to_string(site.path().join("content/2024-01-01-post.md")).unwrap();
+    assert!(!content.contains("Second writer"));
+
+    // If-Match uses the strong comparison, a weak tag never matches
+    let weak = agent
+        .put(&body_url)
+        .header("If-Match", &format!("W/{new_etag}"))
+        .send_json(serde_json::json!({"body": "Weak writer"}))
+        .unwrap();
+    assert_eq!(weak.status(), 412);
+    let weak_file = agent
+        .put(&file_url)
+        .header("If-Match", &format!("W/{new_etag}"))
+        .send_json(serde_json::json!({"content": "weak"}))
+        .unwrap();
+    assert_eq!(weak_file.status(), 412);
+    let content = fs::read_to_string(site.path().join("content/2024-01-01-post.md")).unwrap();
+    assert!(!content.contains("Weak writer") && !content.contains("weak"));
+
+    let patch = agent
+        .patch(format!("{}/__marmite__/content/post", server.base))
+        .header("If-Match", &etag)
+        .send_json(serde_json::json!({"title": "Stale"}))
+        .unwrap();
+    assert_eq!(patch.status(), 409);
+
+    let mut file_conflict = agent
+        .put(&file_url)
+        .header("If-Match", &etag)
+        .send_json(serde_json::json!({"content": "stale"}))
+        .unwrap();
+    assert_eq!(file_conflict.status(), 409);
+    let current: serde_json::Value = file_conflict.body_mut().read_json().unwrap();
+    assert!(current["content"]
+        .as_str()
+        .unwrap()
+        .contains("First writer"));
+
+    // Writes without If-Match keep working as before
+    let forced = agent
+        .put(&file_url)
+        .send_json(serde_json::json!({"content": "# Post\n\nForced"}))
+        .unwrap();
+    assert_eq!(forced.status(), 200);
+    let matching = agent
+        .put(&body_url)
+        .header("If-Match", "*")
+        .send_json(serde_json::json!({"body": "Any version"}))
+        .unwrap();
+    assert_eq!(matching.status(), 200);
+}
+
+fn multipart(fields: &[(&str, Option<&str>, &[u8])]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (name, filename, data) in fields {
+        body.extend_from_slice(b"--marmite-test\r\n");
+        let disposition = match filename {
+            Some(filename) => {
+                format!("Content-Disposition: form-data; name=\"{name}\"; filename=\"{filename}\"")
+            }
+            None => format!("Content-Disposition: form-data; name=\"{name}\""),
+        };
+        body.extend_from_slice(disposition.as_bytes());
+        body.extend_from_slice(b"\r\n\r\n");
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(b"--marmite-test--\r\n");
+    body
+}
+
+#[test]
+fn test_media_upload() {
+    let site = create_site("name: Site\nserver:\n  max_upload_size: 20000\n");
+    let server = start_server(site.path(), &[]);
+    let agent = agent();
+    let media_url = format!("{}/__marmite__/media", server.base);
+    let content_type = "multipart/form-data; boundary=marmite-test";
+
+    let mut png = Vec::new();
+    image::RgbImage::new(16, 8)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+
+    let mut uploaded = agent
+        .post(&media_url)
+        .header("Content-Type", content_type)
+        .send(&multipart(&[
+            ("slug", None, b"post"),
+            ("file", Some("My Cat.PNG"), &png),
+        ]))
+        .unwrap();
+    assert_eq!(uploaded.status(), 201);
+    let uploaded: serde_json::Value = uploaded.body_mut().read_json().unwrap();
+    let file = &uploaded["files"][0];
+    assert_eq!(file["url"], "media/post/my-cat.png");
+    assert_eq!(file["markdown"], "![my cat](media/post/my-cat.png)");
+    assert_eq!(file["width"], 16);
+    assert!(site.path().join("content/media/post/my-cat.png").is_file());
+
+    let not_an_image = agent
+        .post(&media_url)
+        .header("Content-Type", content_type)
+        .send(&multipart(&[("file", Some("notes.png"), b"just text")]))
+        .unwrap();
+    assert_eq!(not_an_image.status(), 415);
+
+    let too_large = agent
+        .post(&media_url)
+        .header("Content-Type", content_type)
+        .send(&multipart(&[("file", Some("big.png"), &vec![0u8; 30000])]))
+        .unwrap();
+    assert_eq!(too_large.status(), 413);
+
+    let not_multipart = agent
+        .post(&media_url)
+        .header("Content-Type", "application/json")
+        .send("{}")
+        .unwrap();
+    assert_eq!(not_multipart.status(), 400);
+    assert!(!site.path().join("content/media/big.png").exists());
+    assert!(!site.path().join("content/media/notes.png").exists());
+}
+
+#[test]
+fn test_config_errors_are_api_errors() {
+    let site = create_site("name: Site\n");
+    let server = start_server(site.path(), &[]);
+    let agent = agent();
+
+    fs::write(site.path().join("marmite.yaml"), "name: [Site\n").unwrap();
+    let mut files = agent
+        .get(format!("{}/__marmite__/files", server.base))
+        .call()
+        .unwrap();
+    assert_eq!(files.status(), 500);
+    let error: serde_json::Value = files.body_mut().read_json().unwrap();
+    assert!(error["error"].as_str().unwrap().contains("marmite.yaml"));
+
+    let body = agent
+        .get(format!("{}/__marmite__/content/post/body", server.base))
+        .call()
+        .unwrap();
+    assert_eq!(body.status(), 500);
+
+    // The server is still running and recovers once the config is fixed
+    fs::write(site.path().join("marmite.yaml"), "name: Site\n").unwrap();
+    let body = agent
+        .get(format!("{}/__marmite__/content/post/body", server.base))
+        .call()
+        .unwrap();
+    assert_eq!(body.status(), 200);
+}
@@ -0,0 +1,149 @@
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+#[test]
+fn test_custom_taxonomies() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+
+    // Create directory structure
+    fs::create_dir_all(input_dir.join("content")).unwrap();
+
+    // Create config
+    let config = r"name: Blog
+url: https://example.com
+taxonomies:
+  category:
+    title: Categories
+    content_title: 'Posts in $term'
+  difficulty:
+    prefix: level
+    index: levels
+    feeds: false
+";
+    fs::write(input_dir.join("marmite.yaml"), config).unwrap();
+
+    let parser_post = r"---
+date: 2024-01-01
+category: Rust Tips
+difficulty: [advanced]
+---
+# Writing a parser";
+    fs::write(input_dir.join("content").join("parser.md"), parser_post).unwrap();
+
+    let cli_post = r"---
+date: 2024-01-02
+category: Rust Tips, CLI
+---
+# Building a CLI";
+    fs::write(input_dir.join("content").join("cli.md"), cli_post).unwrap();
+
+    // Generate site
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--quiet",
+            "--",
+            input_dir.to_str().unwrap(),
+            output_dir.to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute marmite");
+
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Term pages, pagination and feeds
+    let rust_tips = fs::read_to_string(output_dir.join("category-rust-tips.html")).unwrap();
+    assert!(rust_tips.contains("Posts in Rust Tips"));
+    assert!(rust_tips.contains("parser.html"));
+    assert!(rust_tips.contains("cli.html"));
+    assert!(output_dir.join("category-rust-tips-1.html").exists());
+    assert!(output_dir.join("category-rust-tips.rss").exists());
+    assert!(output_dir.join("category-cli.html").exists());
+    assert!(output_dir.join("level-advanced.html").exists());
+    assert!(!output_dir.join("level-advanced.rss").exists());
+
+    // Index pages link to the term pages
+    let categories = fs::read_to_string(output_dir.join("category-index.html")).unwrap();
+    assert!(categories.contains("Categories"));
+    assert!(categories.contains("category-rust-tips.html"));
+    let levels = fs::read_to_string(output_dir.join("levels.html")).unwrap();
+    assert!(levels.contains("level-advanced.html"));
+
+    // urls.json and sitemap
+    let urls: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(output_dir.join("urls.json")).unwrap()).unwrap();
+    let taxonomies: Vec<&str> = urls["taxonomies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|url| url.as_str().unwrap())
+        .collect();
+    assert!(taxonomies.contains(&"https://example.com/category-rust-tips.html"));
+    assert!(taxonomies.contains(&"https://example.com/levels.html"));
+    assert_eq!(urls["summary"]["taxonomies"], taxonomies.len());
+    let feeds = urls["feeds"].as_array().unwrap();
+    assert!(feeds.contains(&serde_json::json!(
+        "https://example.com/category-rust-tips.rss"
+    )));
+
+    let sitemap = fs::read_to_string(output_dir.join("sitemap.xml")).unwrap();
+    assert!(sitemap.contains("https://example.com/category-cli.html"));
+    assert!(sitemap.contains("https://example.com/category-index.html"));
+}
+
+#[test]
+fn test_taxonomy_terms_differing_in_case_share_pages() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_dir = temp_dir.path().join("input");
+    let output_dir = temp_dir.path().join("output");
+    fs::create_dir_all(input_dir.join("content")).unwrap();
+    fs::write(
+        input_dir.join("marmite.yaml"),
+        "name: Blog\ntaxonomies:\n  difficulty: {}\n",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("hello.md"),
+        "---\ndate: 2024-01-01\ndifficulty: Beginner\n---\n# Hello",
+    )
+    .unwrap();
+    fs::write(
+        input_dir.join("content").join("two.md"),
+        "---\ndate: 2024-01-02\ndifficulty: beginner\n---\n# Two",
+    )
+    .unwrap();
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--quiet",
+            "--",
+            input_dir.to_str().unwrap(),
+            output_dir.to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute marmite");
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let beginner = fs::read_to_string(output_dir.join("difficulty-beginner.html")).unwrap();
+    assert!(beginner.contains("hello.html"));
+    assert!(beginner.contains("two.html"));
+    let feed = fs::read_to_string(output_dir.join("difficulty-beginner.rss")).unwrap();
+    assert!(feed.contains("hello.html"));
+    assert!(feed.contains("two.html"));
+
+    let index = fs::read_to_string(output_dir.join("difficulty-index.html")).unwrap();
+    assert_eq!(index.matches("difficulty-group-title").count(), 1);
+    assert_eq!(index.matches("difficulty-beginner.html\"").count(), 1);
+}
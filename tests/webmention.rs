@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+/// Serves linked pages, a Webmention endpoint recording the (source, target)
+/// it receives and a JF2 feed of received mentions
+fn start_server(received: Arc<Mutex<Vec<(String, String)>>>) -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let feed = serde_json::json!({"type": "feed", "children": [
+        {
+            "author": {"name": "Ann", "url": "https://ann.test", "photo": "https://ann.test/ann.png"},
+            "wm-source": "https://ann.test/likes/1",
+            "wm-target": "https://blog.test/first.html",
+            "wm-property": "like-of",
+            "wm-received": "2024-01-03T00:00:00Z"
+        },
+        {
+            "author": {"name": "Bob"},
+            "url": "https://bob.test/reply",
+            "wm-source": "https://bob.test/reply",
+            "wm-target": "https://blog.test/first.html",
+            "wm-property": "in-reply-to",
+            "published": "2024-01-02T00:00:00Z",
+            "content": {"text": "Nice <post>"}
+        }
+    ]});
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let html = |body: &str| {
+                tiny_http::Response::from_string(body).with_header(
+                    "Content-Type: text/html"
+                        .parse::<tiny_http::Header>()
+                        .unwrap(),
+                )
+            };
+            let response = match request.url() {
+                "/linked" => {
+                    html("<html><head><link rel=\"webmention\" href=\"/endpoint\"></head></html>")
+                }
+                "/header" => html("<html></html>").with_header(
+                    tiny_http::Header::from_bytes("Link", "</endpoint>; rel=\"webmention\"")
+                        .unwrap(),
+                ),
+                "/plain" => html("<html><p>No endpoint here</p></html>"),
+                "/endpoint" => {
+                    let mut body = String::new();
+                    let _ = request.as_reader().read_to_string(&mut body);
+                    let form: HashMap<String, String> =
+                        url::form_urlencoded::parse(body.as_bytes())
+                            .into_owned()
+                            .collect();
+                    received
+                        .lock()
+                        .unwrap()
+                        .push((form["source"].clone(), form["target"].clone()));
+                    tiny_http::Response::from_string("Accepted").with_status_code(202)
+                }
+                "/mentions.jf2" => tiny_http::Response::from_string(feed.to_string()),
+                _ => tiny_http::Response::from_string("Not found").with_status_code(404),
+            };
+            let _ = request.respond(response);
+        }
+    });
+    base
+}
+
+fn marmite(input: &Path, args: &[&str]) -> Output {
+    let output = Command::new("cargo")
+        .args(["run", "--quiet", "--", input.to_str().unwrap()])
+        .args(args)
+        .output()
+        .expect("Failed to execute marmite");
+    assert!(
+        output.status.success(),
+        "Command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+#[test]
+fn test_send_and_receive_webmentions() {
+    let temp = TempDir::new().unwrap();
+    let input = temp.path().join("input");
+    fs::create_dir_all(input.join("content")).unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let base = start_server(Arc::clone(&received));
+    fs::write(
+        input.join("marmite.yaml"),
+        format!(
+            "name: Blog\nurl: https://blog.test\nwebmention:\n  \
+             endpoint: https://webmention.io/blog.test/webmention\n  \
+             source: {base}/mentions.jf2\n"
+        ),
+    )
+    .unwrap();
+    let post = input.join("content/2024-01-01-first.md");
+    fs::write(
+        &post,
+        format!(
+            "# First\n\nSee [linked]({base}/linked), [header]({base}/header), \
+             [plain]({base}/plain) and [mine](https://blog.test/other.html)."
+        ),
+    )
+    .unwrap();
+    let source = "https://blog.test/first.html".to_string();
+
+    let output = marmite(&input, &["webmention", "send", "--dry-run"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Plan: 3 link(s) to notify from 1 post(s)"),
+        "{stderr}"
+    );
+    assert!(received.lock().unwrap().is_empty());
+
+    let output = marmite(&input, &["webmention", "send"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Sent 2 mention(s), 1 link(s) without endpoint, 0 failed"),
+        "{stderr}"
+    );
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![
+            (source.clone(), format!("{base}/linked")),
+            (source.clone(), format!("{base}/header")),
+        ]
+    );
+
+    // Nothing changed, nothing is sent again
+    let output = marmite(&input, &["webmention", "send"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Plan: 0 link(s)"));
+    assert_eq!(received.lock().unwrap().len(), 2);
+
+    // The changed post notifies its links and the link it no longer has
+    fs::write(
+        &post,
+        format!("# First\n\nOnly [linked]({base}/linked) now."),
+    )
+    .unwrap();
+    marmite(&input, &["webmention", "send"]);
+    assert_eq!(
+        received.lock().unwrap()[2..],
+        [
+            (source.clone(), format!("{base}/linked")),
+            (source.clone(), format!("{base}/header")),
+        ]
+    );
+    let state: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(input.join(".marmite-webmention-state.json")).unwrap(),
+    )
+    .unwrap();
+    let links: Vec<&String> = state["posts"]["first"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .collect();
+    assert_eq!(links, vec![&format!("{base}/linked")]);
+
+    marmite(&input, &["webmention", "fetch"]);
+    let output_dir = temp.path().join("output");
+    marmite(&input, &[output_dir.to_str().unwrap()]);
+    let html = fs::read_to_string(output_dir.join("first.html")).unwrap();
+    assert!(html
+        .contains("<link rel=\"webmention\" href=\"https://webmention.io/blog.test/webmention\">"));
+    assert!(html.contains("1 likes"));
+    assert!(html.contains("src=\"https://ann.test/ann.png\""));
+    assert!(html.contains("Nice &lt;post&gt;"));
+    assert!(html.contains("href=\"https://bob.test/reply\""));
+}